@@ -0,0 +1,107 @@
+use crate::executor::dispatch::{execute_sql, ExecuteResult};
+pub use crate::executor::dispatch::ResultSet;
+use crate::prepared::statement::{Statement, Step};
+use crate::sql::ast::Stmt;
+use crate::sql::parser::parse;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::{Error, Write};
+
+// An open database file. Everything done through it is safe: the pages of
+// the file stay behind it and are written back when it is closed, or
+// dropped.
+pub struct Connection {
+    table: Table,
+}
+
+impl Connection {
+    // Opens the database file at path, creating it when it does not exist.
+    pub fn open(path: &str) -> Result<Connection, Error> {
+        let table = unsafe { Table::open(path)? };
+        Ok(Connection { table })
+    }
+
+    // Writes the pages back to the file and closes it. Unlike dropping the
+    // connection, it reports a write that failed.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.write_back()
+    }
+
+    fn write_back(&mut self) -> Result<(), Error> {
+        unsafe {
+            let result = self.table.flush_pages();
+            self.table.free_pages();
+            result
+        }
+    }
+
+    // Runs a statement that does not return rows, returns the number of rows
+    // it inserted, updated or deleted.
+    pub fn execute(&mut self, sql: &str) -> Result<usize, Error> {
+        let stmt = parse(sql)?;
+        let changes_rows = matches!(stmt, Stmt::Insert(_) | Stmt::Update(_) | Stmt::Delete(_));
+        match unsafe { execute_sql(&mut self.table, stmt, sql.trim()) }? {
+            ExecuteResult::Executed { changes } => Ok(changes),
+            // RETURNING returns a row for each row changed.
+            ExecuteResult::Rows(result_set) if changes_rows => Ok(result_set.rows.len()),
+            ExecuteResult::Rows(_) => Err(Error::other("statement returns rows, use query")),
+        }
+    }
+
+    // Runs a statement, returns the rows it returns. Statements that return
+    // none have no columns and no rows.
+    pub fn query(&mut self, sql: &str) -> Result<ResultSet, Error> {
+        let stmt = parse(sql)?;
+        Ok(match unsafe { execute_sql(&mut self.table, stmt, sql.trim()) }? {
+            ExecuteResult::Rows(result_set) => result_set,
+            ExecuteResult::Executed { .. } => ResultSet {
+                columns: Vec::new(),
+                rows: Vec::new(),
+            },
+        })
+    }
+
+    // Parses a statement to run with step, see prepared/statement.rs.
+    pub fn prepare(&self, sql: &str) -> Result<Statement, Error> {
+        Statement::prepare(sql)
+    }
+
+    pub fn step(&mut self, statement: &mut Statement) -> Result<Step, Error> {
+        statement.step(&mut self.table)
+    }
+
+    // Runs a command the way the shell does, legacy and dot commands
+    // included, writing what it prints to writer.
+    pub fn run(&mut self, command: &str, writer: impl Write) {
+        crate::run(command.to_string(), &mut self.table, writer);
+    }
+
+    // See Table::create_function.
+    pub fn create_function(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        self.table.create_function(name, num_args, function);
+    }
+
+    // See Table::create_aggregate.
+    pub fn create_aggregate<S: 'static>(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        init: impl Fn() -> S + 'static,
+        step: impl Fn(&mut S, &[Value]) -> Result<(), Error> + 'static,
+        finalize: impl Fn(S) -> Result<Value, Error> + 'static,
+    ) {
+        self.table.create_aggregate(name, num_args, init, step, finalize);
+    }
+}
+
+impl Drop for Connection {
+    // Writes back what close did not, a failed write goes unreported.
+    fn drop(&mut self) {
+        let _ = self.write_back();
+    }
+}
@@ -24,6 +24,11 @@ impl<'a> Cursor<'a> {
     //return the position where it should be inserted.
     pub unsafe fn find_key(table: &'a mut Table, key: u32) -> Cursor<'a> {
         let root_page_num = table.root_page_num;
+        Cursor::find_key_in_tree(table, root_page_num, key)
+    }
+
+    //Same as find_key but for the tree rooted at root_page_num.
+    pub unsafe fn find_key_in_tree(table: &'a mut Table, root_page_num: u32, key: u32) -> Cursor<'a> {
         let root_node = table.pager.get_page(root_page_num).unwrap();
         let node_type = Node::get_node_type(root_node);
         return match node_type {
@@ -40,6 +45,15 @@ impl<'a> Cursor<'a> {
         value_ptr
     }
 
+    //Returns the key at the cursor, None if the cursor is past the last cell of its leaf.
+    pub unsafe fn cursor_key(&mut self) -> Option<u32> {
+        let page_ptr = self.table.pager.get_page(self.page_num).unwrap();
+        if self.cell_num >= Node::get_leaf_node_num_cells(page_ptr) {
+            return None;
+        }
+        Some(Node::get_leaf_node_key(page_ptr, self.cell_num))
+    }
+
     pub unsafe fn advance_cursor(&mut self) {
         let page_num = self.page_num;
         let node_ptr = self.table.pager.get_page(page_num).unwrap();
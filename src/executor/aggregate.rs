@@ -0,0 +1,460 @@
+use crate::executor::compound::row_key;
+use crate::executor::expr::{eval, Scope};
+use crate::executor::function::{AggregateState, Functions, UserAggregate};
+use crate::sql::ast::{Expr, Select, SelectItem};
+use crate::table::record::encode_value;
+use crate::table::value::{parse_number, Value};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+use std::mem;
+
+const AGGREGATE_FUNCTIONS: [&str; 5] = ["count", "sum", "avg", "min", "max"];
+
+pub fn is_aggregate_function(name: &str) -> bool {
+    AGGREGATE_FUNCTIONS.iter().any(|function| function.eq_ignore_ascii_case(name))
+}
+
+// Whether a call is to a built-in or registered aggregate. min and max with
+// several arguments are the scalar functions, and a registered scalar
+// function hides a built-in aggregate of the same name.
+pub fn is_aggregate_call(expr: &Expr, functions: Option<&Functions>) -> bool {
+    let Expr::Function { name, args, .. } = expr else { return false };
+    if let Some(functions) = functions {
+        if functions.aggregate(name).is_some() {
+            return true;
+        }
+        if functions.scalar(name).is_some() {
+            return false;
+        }
+    }
+    is_aggregate_function(name) && (args.len() <= 1 || !matches!(name.to_ascii_lowercase().as_str(), "min" | "max"))
+}
+
+pub fn contains_aggregate(expr: &Expr, functions: &Functions) -> bool {
+    let mut found = false;
+    expr.walk(&mut |expr| found |= is_aggregate_call(expr, Some(functions)));
+    found
+}
+
+// A select is an aggregate query when it groups rows or any of its result
+// columns, HAVING or ORDER BY terms calls an aggregate function.
+pub fn is_aggregate_query(select: &Select, functions: &Functions) -> bool {
+    let in_columns = select.columns.iter().any(|item| match item {
+        SelectItem::Expr { expr, .. } => contains_aggregate(expr, functions),
+        SelectItem::Wildcard | SelectItem::TableWildcard(_) => false,
+    });
+    let in_order_by = select.order_by.iter().any(|term| contains_aggregate(&term.expr, functions));
+    !select.group_by.is_empty() || select.having.is_some() || in_columns || in_order_by
+}
+
+// Rows of one group: the values of the GROUP BY expressions, the last row
+// added, which bare columns are read from, and a state per aggregate call.
+pub struct Group {
+    pub key: Vec<Value>,
+    pub row: Vec<Value>,
+    accumulators: Vec<Accumulator>,
+}
+
+// Hash aggregation. Rows are put into groups by a hash of their encoded GROUP
+// BY values and every aggregate call of the query is accumulated per group.
+pub struct Aggregator {
+    calls: Vec<Expr>,
+    // The registered aggregate of each call, None for built-ins.
+    user: Vec<Option<UserAggregate>>,
+    group_by: Vec<Expr>,
+    groups: Vec<Group>,
+    lookup: HashMap<Vec<u8>, usize>,
+}
+
+impl Aggregator {
+    pub fn new(select: &Select, functions: &Functions) -> Result<Aggregator, Error> {
+        if let Some(expr) = select.where_clause.as_ref().filter(|expr| contains_aggregate(expr, functions)) {
+            return Err(misuse(expr, functions));
+        }
+        if let Some(expr) = select.group_by.iter().find(|expr| contains_aggregate(expr, functions)) {
+            return Err(misuse(expr, functions));
+        }
+        let mut calls: Vec<Expr> = Vec::new();
+        let mut exprs: Vec<&Expr> = Vec::new();
+        for item in &select.columns {
+            if let SelectItem::Expr { expr, .. } = item {
+                exprs.push(expr);
+            }
+        }
+        exprs.extend(select.having.iter());
+        exprs.extend(select.order_by.iter().map(|term| &term.expr));
+        for expr in exprs {
+            let mut error = None;
+            expr.walk(&mut |expr| {
+                if !is_aggregate_call(expr, Some(functions)) || calls.contains(expr) {
+                    return;
+                }
+                if let Expr::Function { args, .. } = expr {
+                    if args.iter().any(|arg| contains_aggregate(arg, functions)) {
+                        error.get_or_insert_with(|| misuse(expr, functions));
+                    }
+                }
+                calls.push(expr.clone());
+            });
+            if let Some(error) = error {
+                return Err(error);
+            }
+        }
+        let mut user = Vec::with_capacity(calls.len());
+        for call in &calls {
+            let Expr::Function { name, .. } = call else { unreachable!() };
+            user.push(functions.aggregate(name).cloned());
+            Accumulator::new(call, user.last().unwrap().as_ref())?;
+        }
+        Ok(Aggregator {
+            calls,
+            user,
+            group_by: select.group_by.clone(),
+            groups: Vec::new(),
+            lookup: HashMap::new(),
+        })
+    }
+
+    pub fn add(&mut self, scope: &Scope) -> Result<(), Error> {
+        let key = self
+            .group_by
+            .iter()
+            .map(|expr| eval(expr, scope))
+            .collect::<Result<Vec<Value>, Error>>()?;
+        let mut hash_key = Vec::new();
+        for value in &key {
+            encode_value(&value.clone().normalized(), &mut hash_key);
+        }
+        let index = match self.lookup.get(&hash_key) {
+            Some(index) => *index,
+            None => {
+                self.groups.push(Group {
+                    key,
+                    row: Vec::new(),
+                    accumulators: self.new_accumulators(),
+                });
+                self.lookup.insert(hash_key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        let group = &mut self.groups[index];
+        group.row = scope.values.to_vec();
+        for (call, accumulator) in self.calls.iter().zip(&mut group.accumulators) {
+            let Expr::Function { args, .. } = call else { unreachable!() };
+            let args = args.iter().map(|arg| eval(arg, scope)).collect::<Result<Vec<Value>, Error>>()?;
+            accumulator.add(args)?;
+        }
+        Ok(())
+    }
+
+    fn new_accumulators(&self) -> Vec<Accumulator> {
+        self.calls
+            .iter()
+            .zip(&self.user)
+            .map(|(call, user)| Accumulator::new(call, user.as_ref()).unwrap())
+            .collect()
+    }
+
+    // The groups ordered by their GROUP BY values, each with the expression
+    // substitutions for its aggregate results. Without GROUP BY there is
+    // always exactly one group, even when no row was added; its bare
+    // columns are NULL.
+    #[allow(clippy::type_complexity)]
+    pub fn finish(mut self, num_columns: usize) -> Result<Vec<(Group, Vec<(Expr, Value)>)>, Error> {
+        if self.groups.is_empty() && self.group_by.is_empty() {
+            let accumulators = self.new_accumulators();
+            self.groups.push(Group {
+                key: Vec::new(),
+                row: vec![Value::Null; num_columns],
+                accumulators,
+            });
+        }
+        self.groups.sort_by(|a, b| {
+            a.key
+                .iter()
+                .zip(&b.key)
+                .map(|(a, b)| a.compare(b))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        let calls = self.calls;
+        self.groups
+            .into_iter()
+            .map(|mut group| {
+                let results = calls
+                    .iter()
+                    .zip(mem::take(&mut group.accumulators))
+                    .map(|(call, accumulator)| Ok((call.clone(), accumulator.result()?)))
+                    .collect::<Result<Vec<(Expr, Value)>, Error>>()?;
+                Ok((group, results))
+            })
+            .collect()
+    }
+}
+
+// Replaces the aggregate calls in an expression by their results.
+pub fn substitute(expr: &Expr, results: &[(Expr, Value)]) -> Expr {
+    if let Some((_, value)) = results.iter().find(|(call, _)| call == expr) {
+        return Expr::Literal(value.clone());
+    }
+    match expr {
+        Expr::Unary { op, expr } => Expr::Unary {
+            op: *op,
+            expr: Box::new(substitute(expr, results)),
+        },
+        Expr::Binary { left, op, right } => Expr::Binary {
+            left: Box::new(substitute(left, results)),
+            op: *op,
+            right: Box::new(substitute(right, results)),
+        },
+        Expr::IsNull { expr, negated } => Expr::IsNull {
+            expr: Box::new(substitute(expr, results)),
+            negated: *negated,
+        },
+        Expr::InList { expr, list, negated } => Expr::InList {
+            expr: Box::new(substitute(expr, results)),
+            list: list.iter().map(|item| substitute(item, results)).collect(),
+            negated: *negated,
+        },
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => Expr::Between {
+            expr: Box::new(substitute(expr, results)),
+            low: Box::new(substitute(low, results)),
+            high: Box::new(substitute(high, results)),
+            negated: *negated,
+        },
+        Expr::Like {
+            expr,
+            pattern,
+            escape,
+            glob,
+            negated,
+        } => Expr::Like {
+            expr: Box::new(substitute(expr, results)),
+            pattern: Box::new(substitute(pattern, results)),
+            escape: escape.as_ref().map(|escape| Box::new(substitute(escape, results))),
+            glob: *glob,
+            negated: *negated,
+        },
+        Expr::Case {
+            operand,
+            branches,
+            else_expr,
+        } => Expr::Case {
+            operand: operand.as_ref().map(|operand| Box::new(substitute(operand, results))),
+            branches: branches
+                .iter()
+                .map(|(when, then)| (substitute(when, results), substitute(then, results)))
+                .collect(),
+            else_expr: else_expr.as_ref().map(|else_expr| Box::new(substitute(else_expr, results))),
+        },
+        Expr::Function {
+            name,
+            args,
+            wildcard,
+            distinct,
+        } => Expr::Function {
+            name: name.clone(),
+            args: args.iter().map(|arg| substitute(arg, results)).collect(),
+            wildcard: *wildcard,
+            distinct: *distinct,
+        },
+        Expr::InSubquery { expr, select, negated } => Expr::InSubquery {
+            expr: Box::new(substitute(expr, results)),
+            select: select.clone(),
+            negated: *negated,
+        },
+        Expr::Literal(_)
+        | Expr::Parameter { .. }
+        | Expr::Column { .. }
+        | Expr::Window { .. }
+        | Expr::Subquery(_)
+        | Expr::Exists(_) => expr.clone(),
+    }
+}
+
+// The result of an aggregate call over the argument values of some rows,
+// used for aggregates called as window functions over a frame.
+pub fn aggregate_rows<'a>(
+    call: &Expr,
+    functions: &Functions,
+    rows: impl Iterator<Item = &'a Vec<Value>>,
+) -> Result<Value, Error> {
+    let Expr::Function { name, .. } = call else { unreachable!() };
+    let mut accumulator = Accumulator::new(call, functions.aggregate(name))?;
+    for args in rows {
+        accumulator.add(args.clone())?;
+    }
+    accumulator.result()
+}
+
+enum Accumulator {
+    CountRows(i64),
+    Count(i64),
+    Sum { integer: i64, real: f64, is_real: bool, any: bool },
+    Avg { sum: f64, count: i64 },
+    Min(Option<Value>),
+    Max(Option<Value>),
+    User(Box<dyn AggregateState>),
+    // Passes the first of each distinct argument value on.
+    Distinct { seen: HashSet<Vec<u8>>, inner: Box<Accumulator> },
+}
+
+impl Accumulator {
+    fn new(call: &Expr, user: Option<&UserAggregate>) -> Result<Accumulator, Error> {
+        let Expr::Function {
+            name,
+            args,
+            wildcard,
+            distinct,
+        } = call
+        else {
+            unreachable!()
+        };
+        if *distinct {
+            if args.len() != 1 {
+                return Err(Error::other("DISTINCT aggregates must have exactly one argument"));
+            }
+            let inner = Expr::Function {
+                name: name.clone(),
+                args: args.clone(),
+                wildcard: false,
+                distinct: false,
+            };
+            return Ok(Accumulator::Distinct {
+                seen: HashSet::new(),
+                inner: Box::new(Accumulator::new(&inner, user)?),
+            });
+        }
+        if let Some(user) = user {
+            if *wildcard {
+                return Err(Error::other(format!("wrong number of arguments to function {}()", name)));
+            }
+            user.check_arity(name, args.len())?;
+            return Ok(Accumulator::User(user.init()));
+        }
+        let name = name.to_ascii_lowercase();
+        let accumulator = match (name.as_str(), args.len(), wildcard) {
+            ("count", 0, true) => Accumulator::CountRows(0),
+            ("count", 1, false) => Accumulator::Count(0),
+            ("sum", 1, false) => Accumulator::Sum {
+                integer: 0,
+                real: 0.0,
+                is_real: false,
+                any: false,
+            },
+            ("avg", 1, false) => Accumulator::Avg { sum: 0.0, count: 0 },
+            ("min", 1, false) => Accumulator::Min(None),
+            ("max", 1, false) => Accumulator::Max(None),
+            _ => {
+                return Err(Error::other(format!("wrong number of arguments to function {}()", name)))
+            }
+        };
+        Ok(accumulator)
+    }
+
+    fn add(&mut self, args: Vec<Value>) -> Result<(), Error> {
+        match self {
+            Accumulator::CountRows(count) => {
+                *count += 1;
+                return Ok(());
+            }
+            Accumulator::User(state) => return state.step(&args),
+            Accumulator::Distinct { seen, inner } => {
+                if seen.insert(row_key(&args)) {
+                    inner.add(args)?;
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        let value = match args.into_iter().next() {
+            None | Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+        };
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum {
+                integer,
+                real,
+                is_real,
+                any,
+            } => {
+                *any = true;
+                match numeric(value) {
+                    Value::Integer(i) if !*is_real => {
+                        *integer = integer
+                            .checked_add(i)
+                            .ok_or_else(|| Error::other("integer overflow"))?;
+                    }
+                    number => {
+                        if !*is_real {
+                            *is_real = true;
+                            *real = *integer as f64;
+                        }
+                        *real += number.as_f64().unwrap_or(0.0);
+                    }
+                }
+            }
+            Accumulator::Avg { sum, count } => {
+                *sum += numeric(value).as_f64().unwrap_or(0.0);
+                *count += 1;
+            }
+            Accumulator::Min(min) => {
+                if min.as_ref().is_none_or(|min| value.compare(min) == Ordering::Less) {
+                    *min = Some(value);
+                }
+            }
+            Accumulator::Max(max) => {
+                if max.as_ref().is_none_or(|max| value.compare(max) == Ordering::Greater) {
+                    *max = Some(value);
+                }
+            }
+            Accumulator::CountRows(_) | Accumulator::User(_) | Accumulator::Distinct { .. } => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn result(self) -> Result<Value, Error> {
+        Ok(match self {
+            Accumulator::CountRows(count) | Accumulator::Count(count) => Value::Integer(count),
+            Accumulator::Sum { any: false, .. } => Value::Null,
+            Accumulator::Sum { real, is_real: true, .. } => Value::Real(real),
+            Accumulator::Sum { integer, .. } => Value::Integer(integer),
+            Accumulator::Avg { count: 0, .. } => Value::Null,
+            Accumulator::Avg { sum, count } => Value::Real(sum / count as f64),
+            Accumulator::Min(value) | Accumulator::Max(value) => value.unwrap_or(Value::Null),
+            Accumulator::User(state) => return state.finalize(),
+            Accumulator::Distinct { inner, .. } => return inner.result(),
+        })
+    }
+}
+
+// Numeric value summed for a value, text that is not a number counts as 0.
+fn numeric(value: Value) -> Value {
+    match value {
+        Value::Text(text) => parse_number(&text).unwrap_or(Value::Integer(0)),
+        value => value,
+    }
+}
+
+fn misuse(expr: &Expr, functions: &Functions) -> Error {
+    let name = match expr {
+        Expr::Function { name, .. } => name.clone(),
+        _ => {
+            let mut name = String::new();
+            expr.walk(&mut |expr| {
+                if let (true, Expr::Function { name: call, .. }) = (name.is_empty() && is_aggregate_call(expr, Some(functions)), expr) {
+                    name = call.clone();
+                }
+            });
+            name
+        }
+    };
+    Error::other(format!("misuse of aggregate function {}()", name))
+}
@@ -0,0 +1,416 @@
+use crate::executor::analyze::forget_statistics;
+use crate::executor::dispatch::ExecuteResult;
+use crate::executor::expr::{eval, Scope};
+use crate::executor::references::{Change, References};
+use crate::schema::definition::{IndexSchema, TableSchema, TriggerSchema, ViewSchema};
+use crate::sql::ast::{
+    AlterAction, AlterTable, ColumnConstraint, ColumnDef, CreateIndex, CreateTable, CreateTrigger, Expr,
+    ForeignKeyClause, Stmt, TableConstraint, TriggerEvent,
+};
+use crate::sql::parser::parse;
+use crate::table::btree::BTree;
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::Error;
+
+// ALTER TABLE edits the CREATE statements stored in the catalog: the one of
+// the table is parsed, changed and printed back, and so are the ones of its
+// indexes and triggers and of the tables with a foreign key to it when the
+// change concerns them. Only DROP COLUMN rewrites the rows of the table. A
+// column added by ADD COLUMN is missing from the rows stored before it, which
+// read it as its default. The references of views and triggers to a renamed
+// table or column are rewritten, a column they use can not be dropped, see
+// references.rs.
+
+pub unsafe fn execute_alter(table: &mut Table, alter: &AlterTable) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.view(&alter.table).is_some() {
+        return Err(Error::other(format!("cannot alter {} because it is a view", alter.table)));
+    }
+    let schema = table.catalog.get_table(&alter.table)?.clone();
+    let mut create = parse_create_table(&schema.sql)?;
+    match &alter.action {
+        AlterAction::AddColumn(column) => add_column(table, &schema, &mut create, column)?,
+        AlterAction::RenameColumn { old, new } => rename_column(table, &schema, &mut create, old, new)?,
+        AlterAction::RenameTo(name) => rename_table(table, &schema, &mut create, name)?,
+        AlterAction::DropColumn(name) => drop_column(table, &schema, &mut create, name)?,
+    }
+    replace_table(table, &schema.name, &create)?;
+    table.catalog.save(&mut table.pager)?;
+    // The statistics name the table and its automatic indexes by name.
+    if let AlterAction::RenameTo(_) = &alter.action {
+        forget_statistics(table, &schema.name)?;
+    }
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+// Appends the values of the columns added after a row was stored: their
+// defaults, or NULL.
+pub fn complete_row(schema: &TableSchema, row: &mut Vec<Value>) -> Result<(), Error> {
+    for column in schema.columns.iter().skip(row.len()) {
+        let value = match &column.default {
+            Some(default) => eval(default, &Scope::empty())?,
+            None => Value::Null,
+        };
+        row.push(column.affinity.apply(value));
+    }
+    Ok(())
+}
+
+// The row stored under a rowid, completed.
+pub unsafe fn stored_row(table: &mut Table, schema: &TableSchema, rowid: u32) -> Result<Option<Vec<Value>>, Error> {
+    let Some(record) = BTree::new(schema.root_page_num).get(table, rowid) else { return Ok(None) };
+    let mut values = record.values;
+    complete_row(schema, &mut values)?;
+    Ok(Some(values))
+}
+
+fn add_column(table: &Table, schema: &TableSchema, create: &mut CreateTable, column: &ColumnDef) -> Result<(), Error> {
+    if schema.column_index(&column.name).is_ok() {
+        return Err(alter_error(format!("duplicate column name: {}", column.name)));
+    }
+    let default = column.constraints.iter().find_map(|constraint| match constraint {
+        ColumnConstraint::Default(expr) => Some(expr),
+        _ => None,
+    });
+    if let Some(default) = default {
+        if !is_constant(default) {
+            return Err(alter_error("Cannot add a column with non-constant default".to_string()));
+        }
+    }
+    let default_value = match default {
+        Some(default) => eval(default, &Scope::empty())?,
+        None => Value::Null,
+    };
+    for constraint in &column.constraints {
+        match constraint {
+            ColumnConstraint::PrimaryKey => {
+                return Err(alter_error("Cannot add a PRIMARY KEY column".to_string()))
+            }
+            ColumnConstraint::Unique => return Err(alter_error("Cannot add a UNIQUE column".to_string())),
+            ColumnConstraint::NotNull if default_value.is_null() => {
+                return Err(alter_error("Cannot add a NOT NULL column with default value NULL".to_string()))
+            }
+            ColumnConstraint::References(_) if table.pragmas.foreign_keys && !default_value.is_null() => {
+                return Err(alter_error(
+                    "Cannot add a REFERENCES column with non-NULL default value".to_string(),
+                ))
+            }
+            _ => {}
+        }
+    }
+    create.columns.push(column.clone());
+    Ok(())
+}
+
+// Whether an expression reads neither columns, functions nor subqueries, so
+// that every row stored before ADD COLUMN gets the same value.
+fn is_constant(expr: &Expr) -> bool {
+    let mut constant = true;
+    expr.walk(&mut |expr| {
+        constant &= !matches!(
+            expr,
+            Expr::Column { .. } | Expr::Function { .. } | Expr::Subquery(_) | Expr::Exists(_) | Expr::InSubquery { .. }
+        )
+    });
+    constant
+}
+
+unsafe fn rename_column(
+    table: &mut Table,
+    schema: &TableSchema,
+    create: &mut CreateTable,
+    old: &str,
+    new: &str,
+) -> Result<(), Error> {
+    let index = schema.column_index(old)?;
+    if schema.column_index(new).is_ok() {
+        return Err(alter_error(format!("duplicate column name: {}", new)));
+    }
+    let rename = |name: &mut String| {
+        if name.eq_ignore_ascii_case(old) {
+            *name = new.to_string();
+        }
+    };
+    rename_references(table, schema, Change::RenameColumn { old, new })?;
+    create.columns[index].name = new.to_string();
+    for constraint in create.columns.iter_mut().flat_map(|column| column.constraints.iter_mut()) {
+        if let ColumnConstraint::Check(expr) = constraint {
+            *expr = rename_in_expr(expr, old, new)?;
+        }
+    }
+    for constraint in &mut create.constraints {
+        match constraint {
+            TableConstraint::PrimaryKey(columns) | TableConstraint::Unique(columns) => columns.iter_mut().for_each(rename),
+            TableConstraint::Check(expr) => *expr = rename_in_expr(expr, old, new)?,
+            TableConstraint::ForeignKey { columns, .. } => columns.iter_mut().for_each(rename),
+        }
+    }
+    for clause in foreign_key_clauses(create) {
+        if clause.table.eq_ignore_ascii_case(&schema.name) {
+            clause.columns.iter_mut().for_each(rename);
+        }
+    }
+    for_each_child_table(table, &schema.name, |clause| clause.columns.iter_mut().for_each(rename))?;
+    for_each_index(table, &schema.name, |index| index.columns.iter_mut().for_each(rename))?;
+    for_each_trigger(table, &schema.name, |trigger| {
+        if let TriggerEvent::Update(columns) = &mut trigger.event {
+            columns.iter_mut().for_each(rename);
+        }
+        Ok(())
+    })
+}
+
+unsafe fn rename_table(table: &mut Table, schema: &TableSchema, create: &mut CreateTable, name: &str) -> Result<(), Error> {
+    let taken = table.catalog.view(name).is_some()
+        || table.catalog.index(name).is_some()
+        || table
+            .catalog
+            .table(name)
+            .is_some_and(|other| !other.name.eq_ignore_ascii_case(&schema.name));
+    if taken {
+        return Err(alter_error(format!(
+            "there is already another table or index with this name: {}",
+            name
+        )));
+    }
+    rename_references(table, schema, Change::RenameTable(name))?;
+    create.name = name.to_string();
+    for clause in foreign_key_clauses(create) {
+        if clause.table.eq_ignore_ascii_case(&schema.name) {
+            clause.table = name.to_string();
+        }
+    }
+    for_each_child_table(table, &schema.name, |clause| clause.table = name.to_string())?;
+    let auto_index_prefix = format!("autoindex_{}_", schema.name);
+    for_each_index(table, &schema.name, |index| {
+        index.table = name.to_string();
+        if let Some(n) = strip_prefix_ignore_case(&index.name, &auto_index_prefix) {
+            index.name = format!("autoindex_{}_{}", name, n);
+        }
+    })?;
+    for_each_trigger(table, &schema.name, |trigger| {
+        trigger.table = name.to_string();
+        Ok(())
+    })
+}
+
+unsafe fn drop_column(table: &mut Table, schema: &TableSchema, create: &mut CreateTable, name: &str) -> Result<(), Error> {
+    let index = schema.column_index(name)?;
+    let column = &schema.columns[index];
+    let reason = if schema.columns.len() == 1 {
+        Some("no other columns exist")
+    } else if column.primary_key {
+        Some("it is part of the PRIMARY KEY")
+    } else if schema.unique_keys.iter().flatten().any(|key| key.eq_ignore_ascii_case(name)) {
+        Some("it is UNIQUE")
+    } else if table
+        .catalog
+        .table_indexes(&schema.name)
+        .iter()
+        .any(|index| contains_name(&index.columns, name))
+    {
+        Some("it is indexed")
+    } else if is_foreign_key_column(table, schema, name) {
+        Some("it is used by a foreign key")
+    } else if schema.checks.iter().any(|check| contains_name(&check.referenced_columns(), name))
+        || schema
+            .columns
+            .iter()
+            .filter(|other| !other.name.eq_ignore_ascii_case(name))
+            .filter_map(|other| other.check.as_ref())
+            .any(|check| contains_name(&check.referenced_columns(), name))
+    {
+        Some("it is used by a CHECK constraint")
+    } else if table.catalog.triggers.iter().any(|trigger| {
+        trigger.table_name.eq_ignore_ascii_case(&schema.name)
+            && matches!(&trigger.event, TriggerEvent::Update(columns) if contains_name(columns, name))
+    }) {
+        Some("it is used by a trigger")
+    } else {
+        column_user(table, schema, name)?
+    };
+    if let Some(reason) = reason {
+        return Err(alter_error(format!("cannot drop column \"{}\": {}", column.name, reason)));
+    }
+    let tree = BTree::new(schema.root_page_num);
+    let mut rows = Vec::new();
+    let mut scan = tree.scan(table);
+    while let Some((rowid, record)) = scan.next(table) {
+        rows.push((rowid, record.values));
+    }
+    for (rowid, mut values) in rows {
+        complete_row(schema, &mut values)?;
+        values.remove(index);
+        tree.update(table, rowid, &Record::new(values))?;
+    }
+    create.columns.remove(index);
+    Ok(())
+}
+
+// Whether a column is a child column of one of the foreign keys of its table
+// or a parent column of a foreign key of any table.
+fn is_foreign_key_column(table: &Table, schema: &TableSchema, name: &str) -> bool {
+    let is_child = schema.foreign_keys.iter().any(|key| contains_name(&key.columns, name));
+    let is_parent = table.catalog.tables.iter().flat_map(|other| &other.foreign_keys).any(|key| {
+        key.references(&schema.name) && contains_name(&key.parent_columns, name)
+    });
+    is_child || is_parent
+}
+
+// Rewrites the views and triggers referring to a renamed table or column.
+fn rename_references(table: &mut Table, schema: &TableSchema, change: Change) -> Result<(), Error> {
+    let catalog = table.catalog.clone();
+    for view in &mut table.catalog.views {
+        let Stmt::CreateView(mut create) = parse(&view.sql)? else { unreachable!() };
+        let mut references = References::new(&catalog, schema, change);
+        references.view(&mut create)?;
+        if references.found {
+            *view = ViewSchema::from_create(&create, &create.to_string());
+        }
+    }
+    for trigger in &mut table.catalog.triggers {
+        let Stmt::CreateTrigger(mut create) = parse(&trigger.sql)? else { unreachable!() };
+        let mut references = References::new(&catalog, schema, change);
+        references.trigger(&mut create)?;
+        if references.found {
+            *trigger = TriggerSchema::from_create(&create, &create.to_string());
+        }
+    }
+    Ok(())
+}
+
+// Why a column can not be dropped when a view or a trigger uses it.
+fn column_user(table: &Table, schema: &TableSchema, name: &str) -> Result<Option<&'static str>, Error> {
+    let change = Change::DropColumn(name);
+    for view in &table.catalog.views {
+        let Stmt::CreateView(mut create) = parse(&view.sql)? else { unreachable!() };
+        let mut references = References::new(&table.catalog, schema, change);
+        references.view(&mut create)?;
+        if references.found {
+            return Ok(Some("it is used by a view"));
+        }
+    }
+    for trigger in &table.catalog.triggers {
+        let Stmt::CreateTrigger(mut create) = parse(&trigger.sql)? else { unreachable!() };
+        let mut references = References::new(&table.catalog, schema, change);
+        references.trigger(&mut create)?;
+        if references.found {
+            return Ok(Some("it is used by a trigger"));
+        }
+    }
+    Ok(None)
+}
+
+// Replaces the schema of a table with the one of its edited statement.
+fn replace_table(table: &mut Table, name: &str, create: &CreateTable) -> Result<(), Error> {
+    let position = table
+        .catalog
+        .tables
+        .iter()
+        .position(|schema| schema.name.eq_ignore_ascii_case(name))
+        .unwrap();
+    let root_page_num = table.catalog.tables[position].root_page_num;
+    table.catalog.tables[position] = TableSchema::from_create(create, root_page_num, &create.to_string())?;
+    Ok(())
+}
+
+// Edits the REFERENCES clauses pointing at a table in the other tables.
+fn for_each_child_table(table: &mut Table, parent: &str, mut edit: impl FnMut(&mut ForeignKeyClause)) -> Result<(), Error> {
+    let children: Vec<TableSchema> = table
+        .catalog
+        .tables
+        .iter()
+        .filter(|other| !other.name.eq_ignore_ascii_case(parent) && other.foreign_keys.iter().any(|key| key.references(parent)))
+        .cloned()
+        .collect();
+    for child in children {
+        let mut create = parse_create_table(&child.sql)?;
+        for clause in foreign_key_clauses(&mut create) {
+            if clause.table.eq_ignore_ascii_case(parent) {
+                edit(clause);
+            }
+        }
+        replace_table(table, &child.name, &create)?;
+    }
+    Ok(())
+}
+
+fn for_each_index(table: &mut Table, table_name: &str, mut edit: impl FnMut(&mut CreateIndex)) -> Result<(), Error> {
+    for index in &mut table.catalog.indexes {
+        if !index.table_name.eq_ignore_ascii_case(table_name) {
+            continue;
+        }
+        let Stmt::CreateIndex(mut create) = parse(&index.sql)? else { unreachable!() };
+        edit(&mut create);
+        *index = IndexSchema::from_create(&create, index.root_page_num, &create.to_string());
+    }
+    Ok(())
+}
+
+fn for_each_trigger(
+    table: &mut Table,
+    table_name: &str,
+    mut edit: impl FnMut(&mut CreateTrigger) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for trigger in &mut table.catalog.triggers {
+        if !trigger.table_name.eq_ignore_ascii_case(table_name) {
+            continue;
+        }
+        let Stmt::CreateTrigger(mut create) = parse(&trigger.sql)? else { unreachable!() };
+        edit(&mut create)?;
+        *trigger = TriggerSchema::from_create(&create, &create.to_string());
+    }
+    Ok(())
+}
+
+fn foreign_key_clauses(create: &mut CreateTable) -> Vec<&mut ForeignKeyClause> {
+    let column_clauses = create
+        .columns
+        .iter_mut()
+        .flat_map(|column| column.constraints.iter_mut())
+        .filter_map(|constraint| match constraint {
+            ColumnConstraint::References(clause) => Some(clause),
+            _ => None,
+        });
+    let table_clauses = create.constraints.iter_mut().filter_map(|constraint| match constraint {
+        TableConstraint::ForeignKey { clause, .. } => Some(clause),
+        _ => None,
+    });
+    column_clauses.chain(table_clauses).collect()
+}
+
+// Renames the unqualified references to a column.
+fn rename_in_expr(expr: &Expr, old: &str, new: &str) -> Result<Expr, Error> {
+    expr.rewrite(&mut |node| {
+        let Expr::Column { table, name } = node else { return Ok(None) };
+        if table.is_some() || !name.eq_ignore_ascii_case(old) {
+            return Ok(None);
+        }
+        Ok(Some(Expr::Column {
+            table: table.clone(),
+            name: new.to_string(),
+        }))
+    })
+}
+
+fn parse_create_table(sql: &str) -> Result<CreateTable, Error> {
+    match parse(sql)? {
+        Stmt::CreateTable(create) => Ok(create),
+        _ => unreachable!("table schemas are CREATE TABLE statements"),
+    }
+}
+
+fn contains_name(names: &[String], name: &str) -> bool {
+    names.iter().any(|other| other.eq_ignore_ascii_case(name))
+}
+
+fn strip_prefix_ignore_case<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = name.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &name[prefix.len()..])
+}
+
+fn alter_error(message: String) -> Error {
+    Error::other(message)
+}
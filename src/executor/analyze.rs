@@ -0,0 +1,254 @@
+use crate::executor::alter::complete_row;
+use crate::executor::dispatch::{execute_sql, ExecuteResult, ResultSet};
+use crate::executor::dml::{execute_delete, execute_insert};
+use crate::executor::index::index_key_values;
+use crate::executor::query::execute_select;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::schema::statistics::{IndexStats, Sample, TableStats, HISTOGRAM_TABLE, STAT_TABLE};
+use crate::sql::ast::{BinaryOp, ConflictResolution, Delete, Expr, Insert, InsertSource, Stmt};
+use crate::sql::parser::parse;
+use crate::table::btree::BTree;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::io::Error;
+
+// ANALYZE reads every row of a table to count them and the distinct keys of
+// its indexes, and samples the first column of each index for a histogram.
+// The results replace the ones stored for the table, in tables created by
+// the first ANALYZE. ANALYZE alone analyzes every table, ANALYZE name the
+// table of that name or the table of the index of that name.
+
+// Samples taken of each index.
+const HISTOGRAM_SAMPLES: usize = 16;
+
+// Runs ANALYZE, storing the statistics of the tables it names.
+pub unsafe fn execute_analyze(table: &mut Table, name: Option<&str>) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    let schemas: Vec<TableSchema> = match name {
+        Some(name) => {
+            let table_name = match table.catalog.index(name) {
+                Some(index) => index.table_name.clone(),
+                None => name.to_string(),
+            };
+            vec![table.catalog.get_table(&table_name)?.clone()]
+        }
+        None => table
+            .catalog
+            .tables
+            .iter()
+            .filter(|schema| !is_statistics_table(&schema.name))
+            .cloned()
+            .collect(),
+    };
+    for (name, sql) in [
+        (STAT_TABLE, "CREATE TABLE rustql_stat (tbl TEXT, idx TEXT, stat TEXT)"),
+        (HISTOGRAM_TABLE, "CREATE TABLE rustql_histogram (tbl TEXT, idx TEXT, sample, entries INTEGER)"),
+    ] {
+        if table.catalog.table(name).is_none() {
+            execute_sql(table, parse(sql)?, sql)?;
+        }
+    }
+    for schema in schemas {
+        let stats = analyze_table(table, &schema)?;
+        store(table, &stats)?;
+        table.catalog.statistics.retain(|other| !other.table_name.eq_ignore_ascii_case(&schema.name));
+        table.catalog.statistics.push(stats);
+    }
+    // Statements planned with the old statistics are planned again.
+    table.catalog.version += 1;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+// Reads the stored statistics into the catalog.
+pub unsafe fn load_statistics(table: &mut Table) -> Result<(), Error> {
+    table.catalog.statistics.clear();
+    if table.catalog.table(STAT_TABLE).is_none() {
+        return Ok(());
+    }
+    let mut statistics: Vec<TableStats> = Vec::new();
+    let stats = query(table, "SELECT tbl, idx, stat FROM rustql_stat WHERE idx IS NULL")?;
+    for row in stats.rows {
+        if let [Value::Text(table_name), _, Value::Text(stat)] = row.as_slice() {
+            statistics.extend(TableStats::parse(table_name, stat));
+        }
+    }
+    let stats = query(table, "SELECT tbl, idx, stat FROM rustql_stat WHERE idx IS NOT NULL")?;
+    for row in stats.rows {
+        if let [Value::Text(table_name), Value::Text(name), Value::Text(stat)] = row.as_slice() {
+            let table_stats = statistics.iter_mut().find(|stats| stats.table_name.eq_ignore_ascii_case(table_name));
+            if let (Some(table_stats), Some(index_stats)) = (table_stats, IndexStats::parse(name, stat)) {
+                table_stats.indexes.push(index_stats);
+            }
+        }
+    }
+    if table.catalog.table(HISTOGRAM_TABLE).is_some() {
+        let samples = query(table, "SELECT tbl, idx, sample, entries FROM rustql_histogram")?;
+        for row in samples.rows {
+            let [Value::Text(table_name), Value::Text(name), value, Value::Integer(entries)] = row.as_slice() else { continue };
+            let index_stats = statistics
+                .iter_mut()
+                .find(|stats| stats.table_name.eq_ignore_ascii_case(table_name))
+                .and_then(|stats| stats.indexes.iter_mut().find(|index| index.name.eq_ignore_ascii_case(name)));
+            if let Some(index_stats) = index_stats {
+                index_stats.histogram.push(Sample {
+                    value: value.clone(),
+                    entries: *entries as u64,
+                });
+            }
+        }
+    }
+    table.catalog.statistics = statistics;
+    table.catalog.version += 1;
+    Ok(())
+}
+
+// Deletes the statistics of a table that is dropped or renamed.
+pub unsafe fn forget_statistics(table: &mut Table, table_name: &str) -> Result<(), Error> {
+    if is_statistics_table(table_name) {
+        return load_statistics(table);
+    }
+    for name in [STAT_TABLE, HISTOGRAM_TABLE] {
+        if table.catalog.table(name).is_some() {
+            execute_delete(table, &delete_rows(name, table_name))?;
+        }
+    }
+    table.catalog.statistics.retain(|stats| !stats.table_name.eq_ignore_ascii_case(table_name));
+    table.catalog.version += 1;
+    Ok(())
+}
+
+fn is_statistics_table(name: &str) -> bool {
+    name.eq_ignore_ascii_case(STAT_TABLE) || name.eq_ignore_ascii_case(HISTOGRAM_TABLE)
+}
+
+unsafe fn query(table: &mut Table, sql: &str) -> Result<ResultSet, Error> {
+    match parse(sql)? {
+        Stmt::Select(select) => execute_select(table, &select),
+        _ => unreachable!(),
+    }
+}
+
+unsafe fn analyze_table(table: &mut Table, schema: &TableSchema) -> Result<TableStats, Error> {
+    let indexes = table.catalog.table_indexes(&schema.name);
+    // The keys of every index, without the ones having a NULL, which are
+    // not indexed.
+    let mut keys: Vec<Vec<Vec<Value>>> = vec![Vec::new(); indexes.len()];
+    let mut rows = 0;
+    let tree = BTree::new(schema.root_page_num);
+    let mut scan = tree.scan(table);
+    while let Some((_, record)) = scan.next(table) {
+        let mut values = record.values;
+        complete_row(schema, &mut values)?;
+        rows += 1;
+        for (index, keys) in indexes.iter().zip(&mut keys) {
+            let key = index_key_values(index, schema, &values)?;
+            if !key.iter().any(Value::is_null) {
+                keys.push(key);
+            }
+        }
+    }
+    let mut index_stats = Vec::new();
+    for (index, keys) in indexes.iter().zip(keys) {
+        index_stats.push(analyze_index(table, index, keys));
+    }
+    Ok(TableStats {
+        table_name: schema.name.clone(),
+        rows,
+        depth: tree.depth(table),
+        indexes: index_stats,
+    })
+}
+
+unsafe fn analyze_index(table: &mut Table, index: &IndexSchema, mut keys: Vec<Vec<Value>>) -> IndexStats {
+    keys.sort_by(|a, b| compare_keys(a, b));
+    let entries = keys.len();
+    // Sorted keys sharing the first n columns are next to each other.
+    let rows_per_key = (1..=index.columns.len())
+        .map(|n| {
+            let distinct = 1 + keys
+                .windows(2)
+                .filter(|pair| compare_keys(&pair[0][..n], &pair[1][..n]) != Ordering::Equal)
+                .count();
+            entries as f64 / distinct as f64
+        })
+        .collect();
+    let mut histogram: Vec<Sample> = Vec::new();
+    for i in 0..HISTOGRAM_SAMPLES.min(entries) {
+        let value = &keys[(2 * i + 1) * entries / (2 * HISTOGRAM_SAMPLES.min(entries))][0];
+        if histogram.last().is_some_and(|sample| sample.value.compare(value) == Ordering::Equal) {
+            continue;
+        }
+        let start = keys.partition_point(|key| key[0].compare(value) == Ordering::Less);
+        let end = keys.partition_point(|key| key[0].compare(value) != Ordering::Greater);
+        histogram.push(Sample {
+            value: value.clone(),
+            entries: (end - start) as u64,
+        });
+    }
+    IndexStats {
+        name: index.name.clone(),
+        entries: entries as u64,
+        depth: BTree::new(index.root_page_num).depth(table),
+        rows_per_key,
+        histogram,
+    }
+}
+
+fn compare_keys(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.compare(b))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+// Replaces the stored statistics of a table.
+unsafe fn store(table: &mut Table, stats: &TableStats) -> Result<(), Error> {
+    let text = |text: &str| Expr::Literal(Value::Text(text.to_string()));
+    let mut stat_rows = vec![vec![text(&stats.table_name), Expr::Literal(Value::Null), text(&stats.stat())]];
+    let mut sample_rows = Vec::new();
+    for index in &stats.indexes {
+        stat_rows.push(vec![text(&stats.table_name), text(&index.name), text(&index.stat())]);
+        for sample in &index.histogram {
+            sample_rows.push(vec![
+                text(&stats.table_name),
+                text(&index.name),
+                Expr::Literal(sample.value.clone()),
+                Expr::Literal(Value::Integer(sample.entries as i64)),
+            ]);
+        }
+    }
+    for (name, rows) in [(STAT_TABLE, stat_rows), (HISTOGRAM_TABLE, sample_rows)] {
+        execute_delete(table, &delete_rows(name, &stats.table_name))?;
+        if rows.is_empty() {
+            continue;
+        }
+        let insert = Insert {
+            table: name.to_string(),
+            columns: None,
+            source: InsertSource::Values(rows),
+            or_conflict: ConflictResolution::Abort,
+            upsert: None,
+            returning: Vec::new(),
+        };
+        execute_insert(table, &insert)?;
+    }
+    Ok(())
+}
+
+// DELETE FROM name WHERE tbl = table_name.
+fn delete_rows(name: &str, table_name: &str) -> Delete {
+    Delete {
+        table: name.to_string(),
+        where_clause: Some(Expr::Binary {
+            left: Box::new(Expr::Column {
+                table: None,
+                name: "tbl".to_string(),
+            }),
+            op: BinaryOp::Eq,
+            right: Box::new(Expr::Literal(Value::Text(table_name.to_string()))),
+        }),
+        returning: Vec::new(),
+    }
+}
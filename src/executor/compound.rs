@@ -0,0 +1,45 @@
+use crate::sql::ast::CompoundOp;
+use crate::table::record::encode_value;
+use crate::table::value::Value;
+use std::collections::HashSet;
+use std::io::Error;
+
+// Combines the rows of a compound select so far with the rows of its next
+// select. Duplicates are found by hashing the encoded rows. Rows keep the
+// order they come in, the ones on the left first.
+pub fn combine(op: CompoundOp, left: Vec<Vec<Value>>, right: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    match op {
+        CompoundOp::UnionAll => left.into_iter().chain(right).collect(),
+        CompoundOp::Union => distinct(left.into_iter().chain(right)),
+        CompoundOp::Intersect | CompoundOp::Except => {
+            let right: HashSet<Vec<u8>> = right.iter().map(|row| row_key(row)).collect();
+            let keep = op == CompoundOp::Intersect;
+            distinct(left.into_iter().filter(|row| right.contains(&row_key(row)) == keep))
+        }
+    }
+}
+
+// The rows without the ones equal to a row before them.
+pub fn distinct(rows: impl IntoIterator<Item = Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut seen = HashSet::new();
+    rows.into_iter().filter(|row| seen.insert(row_key(row))).collect()
+}
+
+// The encoding of a row that is the same for equal rows: NULLs are equal to
+// each other, 1 and 1.0 are equal.
+pub fn row_key(row: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    for value in row {
+        encode_value(&value.clone().normalized(), &mut key);
+    }
+    key
+}
+
+pub fn check_column_counts(op: CompoundOp, left: usize, right: usize) -> Result<(), Error> {
+    if left != right {
+        return Err(Error::other(
+            format!("SELECTs to the left and right of {} do not have the same number of result columns", op),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::executor::compound::{check_column_counts, row_key};
+use crate::executor::dispatch::ResultSet;
+use crate::executor::aggregate::is_aggregate_query;
+use crate::executor::query::{execute_select, limit_and_offset};
+use crate::executor::window::Windows;
+use crate::sql::ast::{CommonTableExpr, CompoundOp, Expr, Select, SelectItem};
+use crate::table::table::Table;
+use crate::table::temp_tree::TempTree;
+use crate::table::value::Value;
+use std::collections::HashSet;
+use std::io::Error;
+use std::ops::Range;
+
+// The tables of a WITH clause are materialized into temp trees before the
+// select declaring them runs, then read by name like stored tables. A
+// recursive table starts with the rows of the selects before its recursive
+// one. Each of its rows is then fed back to the recursive select in the
+// order they were added, the select seeing the table as that single row,
+// until every row has been fed back. With UNION rather than UNION ALL a row
+// equal to one already in the table is not added again. A LIMIT stops the
+// feeding once the table has that many rows, past the OFFSET first ones
+// which are fed back but left out of the table. When the select declaring
+// the table reads nothing but its rows, in order and up to a LIMIT of its
+// own, the feeding also stops once there are enough rows for that LIMIT.
+
+pub struct CommonTable {
+    pub name: String,
+    pub columns: Vec<String>,
+    rows: TempTree,
+    // Numbers of the rows readers see. While a recursive table is built it
+    // is the row being fed back.
+    visible: Range<u32>,
+}
+
+impl CommonTable {
+    pub unsafe fn rows(&mut self) -> Vec<Vec<Value>> {
+        self.rows.rows(self.visible.clone())
+    }
+}
+
+// The innermost common table in scope with this name.
+pub fn find_common_table<'a>(table: &'a mut Table, name: &str) -> Option<&'a mut CommonTable> {
+    table
+        .common_tables
+        .iter_mut()
+        .rev()
+        .find(|common_table| common_table.name.eq_ignore_ascii_case(name))
+}
+
+// Materializes the tables of the WITH clause of a select and brings them in
+// scope. The caller takes them out of scope once its select is done.
+pub unsafe fn declare(table: &mut Table, select: &Select) -> Result<(), Error> {
+    let Some(with) = &select.with else { return Ok(()) };
+    let rows_read = rows_read(table, select);
+    for cte in &with.tables {
+        let wanted = rows_read.filter(|(name, _)| name.eq_ignore_ascii_case(&cte.name)).map(|(_, wanted)| wanted);
+        materialize(table, cte, with.recursive, wanted)?;
+    }
+    Ok(())
+}
+
+// The table a select reads its first rows of, and how many, when it reads
+// nothing else and its result only depends on those rows.
+fn rows_read<'a>(table: &Table, select: &'a Select) -> Option<(&'a str, usize)> {
+    let from = select.from.as_ref()?;
+    let plain = from.subquery.is_none()
+        && select.joins.is_empty()
+        && select.where_clause.is_none()
+        && select.group_by.is_empty()
+        && select.having.is_none()
+        && !select.distinct
+        && select.compound.is_empty()
+        && select.order_by.is_empty()
+        && !is_aggregate_query(select, &table.functions)
+        && matches!(Windows::plan(select), Ok(None))
+        && !select.columns.iter().any(|item| matches!(item, SelectItem::Expr { expr, .. } if has_subquery(expr)));
+    if !plain {
+        return None;
+    }
+    let (limit, offset) = limit_and_offset(&table.functions, &select.limit, &select.offset).ok()?;
+    Some((&from.name, offset.saturating_add(limit?)))
+}
+
+// A subquery of the result columns could read the whole table.
+fn has_subquery(expr: &Expr) -> bool {
+    let mut found = false;
+    expr.walk(&mut |node| {
+        found |= matches!(node, Expr::Subquery(_) | Expr::Exists(_) | Expr::InSubquery { .. });
+    });
+    found
+}
+
+// wanted is the number of rows the select declaring the table reads, when
+// it reads no more than its first ones.
+unsafe fn materialize(table: &mut Table, cte: &CommonTableExpr, recursive: bool, wanted: Option<usize>) -> Result<(), Error> {
+    let recursive_select = cte.select.compound.last().filter(|last| {
+        recursive
+            && matches!(last.op, CompoundOp::Union | CompoundOp::UnionAll)
+            && reads_table(&last.select, &cte.name)
+    });
+    let Some(recursive_select) = recursive_select else {
+        let result = execute_select(table, &cte.select)?;
+        let columns = column_names(cte, &result)?;
+        let mut rows = TempTree::default();
+        add_rows(&mut rows, &mut None, result)?;
+        bring_in_scope(table, cte, columns, rows);
+        return Ok(());
+    };
+    if !cte.select.order_by.is_empty() {
+        return Err(Error::other(format!("ORDER BY is not supported in recursive table {}", cte.name)));
+    }
+    let (limit, offset) = limit_and_offset(&table.functions, &cte.select.limit, &cte.select.offset)?;
+    let limit = match (limit, wanted) {
+        (Some(limit), Some(wanted)) => Some(limit.min(wanted)),
+        (limit, wanted) => limit.or(wanted),
+    };
+    let last = limit.map(|limit| offset.saturating_add(limit).min(u32::MAX as usize) as u32);
+    let initial_select = Select {
+        compound: cte.select.compound[..cte.select.compound.len() - 1].to_vec(),
+        limit: None,
+        offset: None,
+        ..cte.select.clone()
+    };
+    let initial = execute_select(table, &initial_select)?;
+    let columns = column_names(cte, &initial)?;
+    // Encoded rows already added, when duplicates are discarded.
+    let mut seen = match recursive_select.op {
+        CompoundOp::Union => Some(HashSet::new()),
+        _ => None,
+    };
+    let mut rows = TempTree::default();
+    add_rows(&mut rows, &mut seen, initial)?;
+    let index = table.common_tables.len();
+    bring_in_scope(table, cte, columns, rows);
+    let mut number = 1;
+    while number <= table.common_tables[index].rows.num_rows() && last.is_none_or(|last| number <= last) {
+        table.common_tables[index].visible = number..number + 1;
+        let result = execute_select(table, &recursive_select.select)?;
+        let common_table = &mut table.common_tables[index];
+        check_column_counts(recursive_select.op, common_table.columns.len(), result.columns.len())?;
+        add_rows(&mut common_table.rows, &mut seen, result)?;
+        number += 1;
+    }
+    table.common_tables[index].visible = (offset as u32).saturating_add(1)..number;
+    Ok(())
+}
+
+// The names of the columns of a table, the listed ones or the ones of the
+// result of its select.
+fn column_names(cte: &CommonTableExpr, result: &ResultSet) -> Result<Vec<String>, Error> {
+    if cte.columns.is_empty() {
+        return Ok(result.columns.clone());
+    }
+    if cte.columns.len() != result.columns.len() {
+        return Err(Error::other(format!(
+            "table {} has {} values for {} columns",
+            cte.name,
+            result.columns.len(),
+            cte.columns.len()
+        )));
+    }
+    Ok(cte.columns.clone())
+}
+
+fn bring_in_scope(table: &mut Table, cte: &CommonTableExpr, columns: Vec<String>, rows: TempTree) {
+    let visible = 1..rows.num_rows() + 1;
+    table.common_tables.push(CommonTable {
+        name: cte.name.clone(),
+        columns,
+        rows,
+        visible,
+    });
+}
+
+unsafe fn add_rows(rows: &mut TempTree, seen: &mut Option<HashSet<Vec<u8>>>, result: ResultSet) -> Result<(), Error> {
+    for row in result.rows {
+        if let Some(seen) = seen {
+            if !seen.insert(row_key(&row)) {
+                continue;
+            }
+        }
+        rows.push(row)?;
+    }
+    Ok(())
+}
+
+// Whether a select reads a table by name in its FROM clause. Only a
+// recursive select that does is run once per row.
+pub fn reads_table(select: &Select, name: &str) -> bool {
+    select
+        .from
+        .iter()
+        .chain(select.joins.iter().map(|join| &join.table))
+        .any(|table_ref| table_ref.subquery.is_none() && table_ref.name.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,314 @@
+use crate::table::value::Value;
+use std::io::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Date and time functions. A time value is ISO-8601 text (YYYY-MM-DD,
+// HH:MM[:SS[.SSS]] or both separated by a space or T, optionally followed
+// by Z or a +HH:MM offset), 'now', or a number, which is a Julian day number
+// unless the unixepoch modifier follows it. Modifiers then shift the moment:
+// '+N days' (also hours, minutes, seconds, months and years), 'start of
+// day', 'start of month', 'start of year' and 'weekday N', which moves to the
+// next day that is weekday N, 0 being Sunday, unless the day already is. A
+// value or modifier that is not understood makes the result NULL, and so
+// does a moment outside the years -4713 to 9999, like in SQLite. Times are
+// UTC.
+
+const MS_PER_DAY: i64 = 86_400_000;
+// Julian day number of 1970-01-01 00:00:00.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+// The moments of Julian day 0, noon of -4713-11-24, and of the end of 9999.
+const MIN_MS: i64 = -210_866_760_000_000;
+const MAX_MS: i64 = 253_402_300_799_999;
+
+pub fn date(args: &[Value]) -> Result<Value, Error> {
+    Ok(format_moment(args, "%Y-%m-%d"))
+}
+
+pub fn time(args: &[Value]) -> Result<Value, Error> {
+    Ok(format_moment(args, "%H:%M:%S"))
+}
+
+pub fn datetime(args: &[Value]) -> Result<Value, Error> {
+    Ok(format_moment(args, "%Y-%m-%d %H:%M:%S"))
+}
+
+pub fn strftime(args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Null => Ok(Value::Null),
+        format => Ok(format_moment(&args[1..], &format.to_text())),
+    }
+}
+
+pub fn unixepoch(args: &[Value]) -> Result<Value, Error> {
+    Ok(match moment(args) {
+        Some(ms) => Value::Integer(ms.div_euclid(1000)),
+        None => Value::Null,
+    })
+}
+
+pub fn julianday(args: &[Value]) -> Result<Value, Error> {
+    Ok(match moment(args) {
+        Some(ms) => Value::Real(julian_day(ms)),
+        None => Value::Null,
+    })
+}
+
+fn format_moment(args: &[Value], format: &str) -> Value {
+    match moment(args).and_then(|ms| format_time(ms, format)) {
+        Some(text) => Value::Text(text),
+        None => Value::Null,
+    }
+}
+
+// Milliseconds since the unix epoch of a time value and its modifiers, now
+// when there are no arguments.
+fn moment(args: &[Value]) -> Option<i64> {
+    let Some(value) = args.first() else { return Some(now()) };
+    let number = match value {
+        Value::Integer(_) | Value::Real(_) => value.as_f64(),
+        _ => None,
+    };
+    // Only the first modifier can be unixepoch, and only after a number.
+    let unixepoch = number.is_some()
+        && args
+            .get(1)
+            .is_some_and(|modifier| !modifier.is_null() && modifier.to_text().trim().eq_ignore_ascii_case("unixepoch"));
+    let mut ms = match number {
+        Some(seconds) if unixepoch => (seconds * 1000.0).round() as i64,
+        Some(day) => ((day - UNIX_EPOCH_JULIAN_DAY) * MS_PER_DAY as f64).round() as i64,
+        None => parse_time(&value.to_text())?,
+    };
+    ms = in_range(ms)?;
+    for modifier in &args[1 + unixepoch as usize..] {
+        let modifier = match modifier {
+            Value::Null => return None,
+            modifier => modifier.to_text().trim().to_ascii_lowercase(),
+        };
+        ms = match modifier.as_str() {
+            "start of day" => ms - ms.rem_euclid(MS_PER_DAY),
+            "start of month" => {
+                let (year, month, _) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+                days_from_civil(year, month, 1) * MS_PER_DAY
+            }
+            "start of year" => {
+                let (year, _, _) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+                days_from_civil(year, 1, 1) * MS_PER_DAY
+            }
+            _ => match modifier.strip_prefix("weekday ") {
+                Some(weekday) => next_weekday(ms, weekday)?,
+                None => shift(ms, &modifier)?,
+            },
+        };
+        ms = in_range(ms)?;
+    }
+    Some(ms)
+}
+
+fn in_range(ms: i64) -> Option<i64> {
+    (MIN_MS..=MAX_MS).contains(&ms).then_some(ms)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// Applies a '+N unit' modifier.
+fn shift(ms: i64, modifier: &str) -> Option<i64> {
+    let (amount, unit) = modifier.split_once(' ')?;
+    let amount: f64 = amount.parse().ok()?;
+    let unit = unit.trim();
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let scale = match unit {
+        "day" => MS_PER_DAY,
+        "hour" => 3_600_000,
+        "minute" => 60_000,
+        "second" => 1000,
+        "month" | "year" => {
+            let months = if unit == "year" { amount * 12.0 } else { amount } as i64;
+            let days = ms.div_euclid(MS_PER_DAY);
+            let (year, month, day) = civil_from_days(days);
+            let total = (year * 12 + (month - 1)).checked_add(months)?;
+            // The year is checked before its days are counted, those of a
+            // far away one do not fit.
+            let year = total.div_euclid(12);
+            if !(-4713..=9999).contains(&year) {
+                return None;
+            }
+            // Days past the end of the new month overflow into the next one.
+            let shifted = days_from_civil(year, total.rem_euclid(12) + 1, day);
+            return Some(shifted * MS_PER_DAY + ms.rem_euclid(MS_PER_DAY));
+        }
+        _ => return None,
+    };
+    // The float to integer conversion saturates, the sum may not fit.
+    ms.checked_add((amount * scale as f64).round() as i64)
+}
+
+// Applies a 'weekday N' modifier, the time of day is kept.
+fn next_weekday(ms: i64, weekday: &str) -> Option<i64> {
+    let weekday: i64 = weekday.trim().parse().ok().filter(|weekday| (0..7).contains(weekday))?;
+    let days = ms.div_euclid(MS_PER_DAY);
+    Some(ms + (weekday - day_of_week(days)).rem_euclid(7) * MS_PER_DAY)
+}
+
+// 0 for Sunday to 6 for Saturday, 1970-01-01 was a Thursday.
+fn day_of_week(days: i64) -> i64 {
+    (days + 4).rem_euclid(7)
+}
+
+// Parses ISO-8601 text or 'now'. A time without a date is on 2000-01-01.
+fn parse_time(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("now") {
+        return Some(now());
+    }
+    let (days, rest) = match parse_date(text) {
+        Some((days, rest)) => (days, rest.strip_prefix(['T', ' ']).unwrap_or(rest)),
+        None => (days_from_civil(2000, 1, 1), text),
+    };
+    if rest.is_empty() {
+        return Some(days * MS_PER_DAY);
+    }
+    let (time_ms, rest) = parse_clock(rest)?;
+    let offset_ms = parse_offset(rest.trim())?;
+    Some(days * MS_PER_DAY + time_ms - offset_ms)
+}
+
+fn parse_date(text: &str) -> Option<(i64, &str)> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year = digits(&text[0..4])?;
+    let month = digits(&text[5..7])?;
+    let day = digits(&text[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((days_from_civil(year, month, day), &text[10..]))
+}
+
+// Parses HH:MM[:SS[.SSS]], returning milliseconds since midnight and the
+// rest of the text.
+fn parse_clock(text: &str) -> Option<(i64, &str)> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 5 || bytes[2] != b':' {
+        return None;
+    }
+    let hour = digits(&text[0..2])?;
+    let minute = digits(&text[3..5])?;
+    let mut rest = &text[5..];
+    let mut ms = 0;
+    if rest.starts_with(':') && rest.len() >= 3 {
+        ms = digits(&rest[1..3])? * 1000;
+        rest = &rest[3..];
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let end = fraction.find(|c: char| !c.is_ascii_digit()).unwrap_or(fraction.len());
+            if end == 0 {
+                return None;
+            }
+            ms += (format!("0.{}", &fraction[..end]).parse::<f64>().ok()? * 1000.0).round() as i64;
+            rest = &fraction[end..];
+        }
+    }
+    if hour > 24 || minute > 59 || ms >= 60_000 {
+        return None;
+    }
+    Some(((hour * 60 + minute) * 60_000 + ms, rest))
+}
+
+// Parses a trailing Z or [+-]HH:MM timezone, returning its offset from UTC.
+fn parse_offset(text: &str) -> Option<i64> {
+    if text.is_empty() || text.eq_ignore_ascii_case("z") {
+        return Some(0);
+    }
+    let sign = match text.as_bytes()[0] {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let (clock, rest) = parse_clock(&text[1..])?;
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(sign * clock)
+}
+
+fn digits(text: &str) -> Option<i64> {
+    if text.bytes().all(|byte| byte.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn julian_day(ms: i64) -> f64 {
+    ms as f64 / MS_PER_DAY as f64 + UNIX_EPOCH_JULIAN_DAY
+}
+
+// Formats with the strftime substitutions %d %f %H %j %J %m %M %s %S %w %W
+// %Y and %%. None for other substitutions and for years outside 0000-9999.
+fn format_time(ms: i64, format: &str) -> Option<String> {
+    let days = ms.div_euclid(MS_PER_DAY);
+    let time_ms = ms.rem_euclid(MS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+    let (hour, minute, second) = (time_ms / 3_600_000, time_ms / 60_000 % 60, time_ms / 1000 % 60);
+    let day_of_year = days - days_from_civil(year, 1, 1);
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'd' => text.push_str(&format!("{:02}", day)),
+            'f' => text.push_str(&format!("{:06.3}", (time_ms % 60_000) as f64 / 1000.0)),
+            'H' => text.push_str(&format!("{:02}", hour)),
+            'j' => text.push_str(&format!("{:03}", day_of_year + 1)),
+            'J' => text.push_str(&Value::Real(julian_day(ms)).to_string()),
+            'm' => text.push_str(&format!("{:02}", month)),
+            'M' => text.push_str(&format!("{:02}", minute)),
+            's' => text.push_str(&ms.div_euclid(1000).to_string()),
+            'S' => text.push_str(&format!("{:02}", second)),
+            'w' => text.push_str(&day_of_week(days).to_string()),
+            // Weeks start on Monday, the days before the first one are in
+            // week 00.
+            'W' => text.push_str(&format!("{:02}", (day_of_year + 7 - (day_of_week(days) + 6) % 7) / 7)),
+            'Y' => text.push_str(&format!("{:04}", year)),
+            '%' => text.push('%'),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date. The day may be past
+// the end of the month.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
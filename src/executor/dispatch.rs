@@ -0,0 +1,288 @@
+use crate::executor::alter::{complete_row, execute_alter};
+use crate::executor::analyze::{execute_analyze, forget_statistics};
+use crate::executor::dml::{execute_delete, execute_insert, execute_update};
+use crate::executor::explain::{explain_program, explain_query_plan};
+use crate::executor::foreign_key::check_child_rows;
+use crate::executor::index::{index_insert, index_key_values, index_lookup};
+use crate::executor::pragma::execute_pragma;
+use crate::executor::query::execute_select;
+use crate::executor::view::execute_view;
+use crate::schema::constraint::ConstraintViolation;
+use crate::schema::definition::{IndexSchema, TableSchema, TriggerSchema, ViewSchema};
+use crate::sql::ast::{
+    CreateIndex, CreateTable, CreateTrigger, CreateView, Delete, DropTable, DropTrigger, DropView, Stmt, TriggerEvent,
+};
+use crate::table::btree::BTree;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use crate::vm::compiler::compile;
+use crate::vm::machine::execute_program;
+use std::io::{Error, Write};
+use std::mem;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn print(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        for row in &self.rows {
+            let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(writer, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecuteResult {
+    Executed { changes: usize },
+    Rows(ResultSet),
+}
+
+// Statements are compiled into a program for the virtual machine and run by
+// it, see vm/compiler.rs.
+pub unsafe fn execute_sql(table: &mut Table, stmt: Stmt, sql: &str) -> Result<ExecuteResult, Error> {
+    match stmt {
+        Stmt::Explain(stmt) => explain_program(table, *stmt, sql),
+        stmt => {
+            let program = compile(table, stmt, sql);
+            execute_program(table, &program, &[])
+        }
+    }
+}
+
+// Runs a statement the compiler leaves to the tree-walking executor.
+pub unsafe fn execute_stmt(table: &mut Table, stmt: &Stmt, sql: &str) -> Result<ExecuteResult, Error> {
+    match stmt {
+        Stmt::CreateTable(create) => create_table(table, create, sql),
+        Stmt::DropTable(drop) => drop_table(table, drop),
+        Stmt::CreateIndex(create) => create_index(table, create, sql),
+        Stmt::AlterTable(alter) => execute_alter(table, alter),
+        Stmt::CreateView(create) => create_view(table, create, sql),
+        Stmt::DropView(drop) => drop_view(table, drop),
+        Stmt::CreateTrigger(create) => create_trigger(table, create, sql),
+        Stmt::DropTrigger(drop) => drop_trigger(table, drop),
+        Stmt::Insert(insert) => execute_insert(table, insert),
+        Stmt::Update(update) => execute_update(table, update),
+        Stmt::Delete(delete) => execute_delete(table, delete),
+        Stmt::Select(select) => execute_select(table, select).map(ExecuteResult::Rows),
+        Stmt::Pragma(pragma) => execute_pragma(table, pragma),
+        Stmt::Analyze(name) => execute_analyze(table, name.as_deref()),
+        Stmt::ExplainQueryPlan(stmt) => explain_query_plan(table, stmt),
+        Stmt::Explain(stmt) => explain_program(table, (**stmt).clone(), sql),
+    }
+}
+
+unsafe fn create_table(table: &mut Table, create: &CreateTable, sql: &str) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.table(&create.name).is_some() {
+        if create.if_not_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("table {} already exists", create.name)));
+    }
+    if table.catalog.view(&create.name).is_some() {
+        return Err(Error::other(format!("view {} already exists", create.name)));
+    }
+    let mut schema = TableSchema::from_create(create, 0, sql)?;
+    schema.root_page_num = BTree::create(table)?.root_page_num;
+    for n in 0..schema.unique_keys.len() {
+        let (name, sql) = IndexSchema::auto_index_sql(&schema, n);
+        let root_page_num = BTree::create(table)?.root_page_num;
+        table.catalog.indexes.push(IndexSchema {
+            name,
+            table_name: schema.name.clone(),
+            columns: schema.unique_keys[n].clone(),
+            unique: true,
+            root_page_num,
+            sql,
+        });
+    }
+    table.catalog.tables.push(schema);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+// The pages of the table and of its indexes go to the free list. When foreign
+// keys are enforced and other tables refer to the table, its rows are deleted
+// first so that the foreign key actions run, once none of them turns out to
+// be still referenced. Its triggers are dropped before, they do not fire,
+// and its statistics after.
+unsafe fn drop_table(table: &mut Table, drop: &DropTable) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.view(&drop.name).is_some() {
+        return Err(Error::other(format!("use DROP VIEW to delete view {}", drop.name)));
+    }
+    let Some(schema) = table.catalog.table(&drop.name).cloned() else {
+        if drop.if_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("no such table: {}", drop.name)));
+    };
+    let is_table = |name: &str| name.eq_ignore_ascii_case(&schema.name);
+    let referenced = table.pragmas.foreign_keys
+        && table
+            .catalog
+            .tables
+            .iter()
+            .filter(|other| !is_table(&other.name))
+            .any(|other| other.foreign_keys.iter().any(|key| key.references(&schema.name)));
+    if referenced {
+        let mut rows = Vec::new();
+        let mut scan = BTree::new(schema.root_page_num).scan(table);
+        while let Some((rowid, record)) = scan.next(table) {
+            rows.push((rowid, record.values));
+        }
+        for (rowid, mut row) in rows {
+            complete_row(&schema, &mut row)?;
+            check_child_rows(table, &schema, rowid, &row, None)?;
+        }
+    }
+    let (triggers, others) = mem::take(&mut table.catalog.triggers)
+        .into_iter()
+        .partition(|trigger| is_table(&trigger.table_name));
+    table.catalog.triggers = others;
+    if referenced {
+        let delete = Delete {
+            table: schema.name.clone(),
+            where_clause: None,
+            returning: Vec::new(),
+        };
+        if let Err(error) = execute_delete(table, &delete) {
+            table.catalog.triggers.extend(triggers);
+            return Err(error);
+        }
+    }
+    BTree::new(schema.root_page_num).destroy(table)?;
+    for index in table.catalog.table_indexes(&schema.name) {
+        BTree::new(index.root_page_num).destroy(table)?;
+    }
+    table.catalog.indexes.retain(|index| !is_table(&index.table_name));
+    table.catalog.tables.retain(|other| !is_table(&other.name));
+    table.catalog.save(&mut table.pager)?;
+    forget_statistics(table, &schema.name)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+unsafe fn create_index(table: &mut Table, create: &CreateIndex, sql: &str) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.index(&create.name).is_some() {
+        if create.if_not_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("index {} already exists", create.name)));
+    }
+    if table.catalog.view(&create.table).is_some() {
+        return Err(Error::other("views may not be indexed"));
+    }
+    let schema = table.catalog.get_table(&create.table)?.clone();
+    for column in &create.columns {
+        schema.column_index(column)?;
+    }
+    let mut index = IndexSchema::from_create(create, 0, sql);
+    index.table_name = schema.name.clone();
+    index.root_page_num = BTree::create(table)?.root_page_num;
+    let mut scan = BTree::new(schema.root_page_num).scan(table);
+    while let Some((rowid, record)) = scan.next(table) {
+        let mut row = record.values;
+        complete_row(&schema, &mut row)?;
+        let key_values = index_key_values(&index, &schema, &row)?;
+        if index.unique && !index_lookup(table, &index, &key_values).is_empty() {
+            return Err(ConstraintViolation::Unique {
+                table: schema.name.clone(),
+                columns: index.columns.clone(),
+            }
+            .into());
+        }
+        index_insert(table, &index, &key_values, rowid)?;
+    }
+    table.catalog.indexes.push(index);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+// The view's select is run once so that a definition reading a missing table
+// or column, or the view itself, is rejected up front.
+unsafe fn create_view(table: &mut Table, create: &CreateView, sql: &str) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.view(&create.name).is_some() {
+        if create.if_not_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("view {} already exists", create.name)));
+    }
+    if table.catalog.table(&create.name).is_some() {
+        return Err(Error::other(format!("table {} already exists", create.name)));
+    }
+    let view = ViewSchema::from_create(create, sql);
+    execute_view(table, &view)?;
+    table.catalog.views.push(view);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+unsafe fn drop_view(table: &mut Table, drop: &DropView) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    let position = table
+        .catalog
+        .views
+        .iter()
+        .position(|view| view.name.eq_ignore_ascii_case(&drop.name));
+    let Some(position) = position else {
+        if drop.if_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        if table.catalog.table(&drop.name).is_some() {
+            return Err(Error::other(format!("use DROP TABLE to delete table {}", drop.name)));
+        }
+        return Err(Error::other(format!("no such view: {}", drop.name)));
+    };
+    table.catalog.views.remove(position);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+unsafe fn create_trigger(table: &mut Table, create: &CreateTrigger, sql: &str) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    if table.catalog.trigger(&create.name).is_some() {
+        if create.if_not_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("trigger {} already exists", create.name)));
+    }
+    if table.catalog.view(&create.table).is_some() {
+        return Err(Error::other(format!("cannot create trigger on view: {}", create.table)));
+    }
+    let schema = table.catalog.get_table(&create.table)?;
+    if let TriggerEvent::Update(columns) = &create.event {
+        for column in columns {
+            schema.column_index(column)?;
+        }
+    }
+    let mut trigger = TriggerSchema::from_create(create, sql);
+    trigger.table_name = schema.name.clone();
+    table.catalog.triggers.push(trigger);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
+
+unsafe fn drop_trigger(table: &mut Table, drop: &DropTrigger) -> Result<ExecuteResult, Error> {
+    table.catalog.ensure_available()?;
+    let position = table
+        .catalog
+        .triggers
+        .iter()
+        .position(|trigger| trigger.name.eq_ignore_ascii_case(&drop.name));
+    let Some(position) = position else {
+        if drop.if_exists {
+            return Ok(ExecuteResult::Executed { changes: 0 });
+        }
+        return Err(Error::other(format!("no such trigger: {}", drop.name)));
+    };
+    table.catalog.triggers.remove(position);
+    table.catalog.save(&mut table.pager)?;
+    Ok(ExecuteResult::Executed { changes: 0 })
+}
@@ -0,0 +1,564 @@
+use crate::executor::alter::{complete_row, stored_row};
+use crate::executor::aggregate::contains_aggregate;
+use crate::executor::dispatch::{ExecuteResult, ResultSet};
+use crate::executor::expr::{eval, is_true, ColumnBinding, Scope};
+use crate::executor::foreign_key::{apply_child_actions, check_child_rows, check_parent_rows, same_columns};
+use crate::executor::function::Functions;
+use crate::executor::index::{index_insert, index_key_values, index_lookup, index_remove};
+use crate::executor::planner::{choose_access, conjuncts, Access, PlanTable};
+use crate::executor::query::{column_name, execute_select, project, stored_bindings, table_bindings};
+use crate::executor::subquery::Correlated;
+use crate::executor::trigger::Triggers;
+use crate::schema::constraint::ConstraintViolation;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::sql::ast::{
+    ConflictResolution, Delete, Expr, Insert, InsertSource, SelectItem, TriggerEvent, TriggerTiming, Update, Upsert,
+    UpsertAction,
+};
+use crate::table::btree::{BTree, InsertCursor};
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::Error;
+
+pub unsafe fn execute_insert(table: &mut Table, insert: &Insert) -> Result<ExecuteResult, Error> {
+    let schema = table.catalog.get_writable_table(&insert.table)?.clone();
+    let indexes = table.catalog.table_indexes(&schema.name);
+    let positions = match &insert.columns {
+        Some(columns) => columns
+            .iter()
+            .map(|column| schema.column_index(column))
+            .collect::<Result<Vec<usize>, Error>>()?,
+        None => (0..schema.columns.len()).collect(),
+    };
+    // All rows are computed before the first one is stored, a query reading
+    // the table it inserts into does not see its own rows.
+    let rows = match &insert.source {
+        InsertSource::Values(rows) => {
+            for values in rows {
+                check_value_count(values.len(), positions.len())?;
+            }
+            let mut evaluated = Vec::with_capacity(rows.len());
+            for values in rows {
+                let mut row = Vec::with_capacity(values.len());
+                for expr in values {
+                    let expr = Correlated::plan_uncorrelated(table, expr)?;
+                    row.push(eval(&expr, &Scope::new(&table.functions, &[], &[]))?);
+                }
+                evaluated.push(row);
+            }
+            evaluated
+        }
+        InsertSource::Select(select) => {
+            let result = execute_select(table, select)?;
+            check_value_count(result.columns.len(), positions.len())?;
+            result.rows
+        }
+    };
+    if let Some(upsert) = &insert.upsert {
+        check_upsert(&schema, &indexes, upsert)?;
+    }
+    let conflicts = Conflicts {
+        resolution: insert.or_conflict,
+        upsert: insert.upsert.as_ref(),
+    };
+    let mut returning = Returning::new(table, &schema, &insert.returning)?;
+    let triggers = Triggers::new(table, &schema, &TriggerEvent::Insert);
+    let mut cursor = InsertCursor::new(BTree::new(schema.root_page_num));
+    let mut changes = 0;
+    for values in rows {
+        let mut provided = vec![None; schema.columns.len()];
+        for (position, value) in positions.iter().zip(values) {
+            provided[*position] = Some(value);
+        }
+        let row = build_row(&table.functions, &schema, provided)?;
+        triggers.fire(table, TriggerTiming::Before, None, Some(&row))?;
+        if let Some((rowid, row)) = insert_row(table, &schema, &indexes, &mut cursor, &conflicts, row)? {
+            triggers.fire(table, TriggerTiming::After, None, Some(&row))?;
+            returning.push(table, rowid, &row)?;
+            changes += 1;
+        }
+    }
+    Ok(returning.finish(changes))
+}
+
+// How an INSERT handles a row that collides with existing rows.
+pub struct Conflicts<'a> {
+    pub resolution: ConflictResolution,
+    pub upsert: Option<&'a Upsert>,
+}
+
+impl Conflicts<'_> {
+    // Whether the upsert handles a collision on the given unique columns.
+    fn upsert_applies(&self, columns: &[String]) -> bool {
+        match self.upsert {
+            Some(Upsert { target: Some(target), .. }) => same_columns(target, columns),
+            Some(Upsert { target: None, .. }) => true,
+            None => false,
+        }
+    }
+}
+
+// An ON CONFLICT target has to name the columns of the primary key or of a
+// unique constraint.
+fn check_upsert(schema: &TableSchema, indexes: &[IndexSchema], upsert: &Upsert) -> Result<(), Error> {
+    let Some(target) = &upsert.target else { return Ok(()) };
+    for column in target {
+        schema.column_index(column)?;
+    }
+    let rowid_key = schema.rowid_column.map(|i| vec![schema.columns[i].name.clone()]);
+    let matches = rowid_key.iter().any(|key| same_columns(key, target))
+        || indexes.iter().any(|index| index.unique && same_columns(&index.columns, target));
+    if !matches {
+        return Err(Error::other("ON CONFLICT clause does not match any PRIMARY KEY or UNIQUE constraint"));
+    }
+    Ok(())
+}
+
+fn check_value_count(values: usize, columns: usize) -> Result<(), Error> {
+    if values != columns {
+        return Err(Error::other(format!("{} values for {} columns", values, columns)));
+    }
+    Ok(())
+}
+
+// Fills the columns that were not given a value from their DEFAULT and
+// applies the column affinities.
+pub fn build_row(functions: &Functions, schema: &TableSchema, provided: Vec<Option<Value>>) -> Result<Vec<Value>, Error> {
+    let mut row = Vec::with_capacity(provided.len());
+    for (column, value) in schema.columns.iter().zip(provided) {
+        let value = match (value, &column.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => eval(default, &Scope::new(functions, &[], &[]))?,
+            (None, None) => Value::Null,
+        };
+        row.push(column.affinity.apply(value));
+    }
+    Ok(row)
+}
+
+// Validates a complete row against the table constraints and stores it.
+// Returns the rowid and the row inserted, or updated by an upsert, as stored
+// and None when a conflict left the table unchanged. The cursor is shared by the rows
+// of one statement.
+pub unsafe fn insert_row(
+    table: &mut Table,
+    schema: &TableSchema,
+    indexes: &[IndexSchema],
+    cursor: &mut InsertCursor,
+    conflicts: &Conflicts,
+    mut row: Vec<Value>,
+) -> Result<Option<(u32, Vec<Value>)>, Error> {
+    let rowid = assign_rowid(table, cursor, schema.rowid_column.map(|i| &row[i]))?;
+    if let Some(i) = schema.rowid_column {
+        row[i] = Value::Integer(rowid as i64);
+    }
+    if let Err(error) = check_row(&table.functions, schema, &row) {
+        if conflicts.resolution == ConflictResolution::Ignore && ConstraintViolation::from_error(&error).is_some() {
+            return Ok(None);
+        }
+        return Err(error);
+    }
+    // Existing rows the new one collides with, with the unique columns and
+    // the error each collision raises by default.
+    let tree = BTree::new(schema.root_page_num);
+    let mut collisions = Vec::new();
+    let existing_row = cursor.get(table, rowid);
+    if existing_row.is_some() {
+        let columns = schema.rowid_column.map(|i| vec![schema.columns[i].name.clone()]);
+        collisions.push((rowid, columns, primary_key_violation(schema)));
+    }
+    let mut index_keys = Vec::with_capacity(indexes.len());
+    for index in indexes {
+        let key_values = index_key_values(index, schema, &row)?;
+        if index.unique {
+            if let Some(existing) = index_lookup(table, index, &key_values).first() {
+                collisions.push((*existing, Some(index.columns.clone()), unique_violation(schema, index)));
+            }
+        }
+        index_keys.push(key_values);
+    }
+    let upserted = collisions
+        .iter()
+        .find(|(_, columns, _)| columns.as_ref().is_some_and(|columns| conflicts.upsert_applies(columns)));
+    if let (Some((existing, _, _)), Some(upsert)) = (upserted, conflicts.upsert) {
+        let UpsertAction::Update {
+            assignments,
+            where_clause,
+        } = &upsert.action
+        else {
+            return Ok(None);
+        };
+        // The row under the new rowid was already read by the cursor.
+        let mut old_row = match existing_row {
+            Some(record) if *existing == rowid => record.values,
+            _ => tree.get(table, *existing).unwrap().values,
+        };
+        complete_row(schema, &mut old_row)?;
+        return upsert_row(table, schema, indexes, *existing, old_row, &row, assignments, where_clause);
+    }
+    let mut replaced: Vec<u32> = Vec::new();
+    for (existing, _, error) in collisions {
+        match conflicts.resolution {
+            ConflictResolution::Abort => return Err(error),
+            ConflictResolution::Ignore => return Ok(None),
+            ConflictResolution::Replace if !replaced.contains(&existing) => replaced.push(existing),
+            ConflictResolution::Replace => {}
+        }
+    }
+    check_parent_rows(table, schema, None, &row)?;
+    for existing in replaced {
+        // Foreign key actions of an earlier replaced row may have removed it.
+        if let Some(row) = stored_row(table, schema, existing)? {
+            delete_row(table, schema, indexes, existing, &row)?;
+        }
+    }
+    cursor.insert(table, rowid, &Record::new(row.clone()))?;
+    for (index, key_values) in indexes.iter().zip(index_keys) {
+        index_insert(table, index, &key_values, rowid)?;
+    }
+    Ok(Some((rowid, row)))
+}
+
+// DO UPDATE of an upsert: applies the assignments to the existing row, with
+// the row that failed to insert visible as excluded.
+#[allow(clippy::too_many_arguments)]
+unsafe fn upsert_row(
+    table: &mut Table,
+    schema: &TableSchema,
+    indexes: &[IndexSchema],
+    rowid: u32,
+    old_row: Vec<Value>,
+    excluded: &[Value],
+    assignments: &[(String, Expr)],
+    where_clause: &Option<Expr>,
+) -> Result<Option<(u32, Vec<Value>)>, Error> {
+    let mut bindings = stored_bindings(schema, &schema.name);
+    bindings.extend(schema.columns.iter().map(|column| ColumnBinding::qualified("excluded", &column.name)));
+    let values = [old_row.as_slice(), &[Value::Integer(rowid as i64)], excluded].concat();
+    if !matches_where(&table.functions, where_clause, &bindings, &values)? {
+        return Ok(None);
+    }
+    let scope = Scope::new(&table.functions, &bindings, &values);
+    let mut new_row = old_row.clone();
+    for (column, expr) in assignments {
+        let i = schema.column_index(column)?;
+        new_row[i] = schema.columns[i].affinity.apply(eval(expr, &scope)?);
+    }
+    update_row(table, schema, indexes, rowid, &old_row, new_row).map(Some)
+}
+
+// The rowid of a new row: the value of its INTEGER PRIMARY KEY if given,
+// otherwise one more than the largest rowid in use.
+unsafe fn assign_rowid(table: &mut Table, cursor: &mut InsertCursor, value: Option<&Value>) -> Result<u32, Error> {
+    match value {
+        None | Some(Value::Null) => Ok(cursor.max_key(table).map_or(1, |key| key + 1)),
+        Some(Value::Integer(key)) if *key >= 0 && *key <= u32::MAX as i64 => Ok(*key as u32),
+        Some(_) => Err(Error::other("datatype mismatch")),
+    }
+}
+
+pub unsafe fn execute_update(table: &mut Table, update: &Update) -> Result<ExecuteResult, Error> {
+    let schema = table.catalog.get_writable_table(&update.table)?.clone();
+    let indexes = table.catalog.table_indexes(&schema.name);
+    let mut correlated = Correlated::default();
+    let mut assignments = Vec::with_capacity(update.assignments.len());
+    for (column, expr) in &update.assignments {
+        assignments.push((schema.column_index(column)?, correlated.plan(table, expr)?));
+    }
+    let mut bindings = stored_bindings(&schema, &schema.name);
+    bindings.extend(correlated.bindings());
+    let tree = BTree::new(schema.root_page_num);
+    let targets = matching_rowids(table, &schema, &update.where_clause)?;
+    let mut returning = Returning::new(table, &schema, &update.returning)?;
+    let assigned = update.assignments.iter().map(|(column, _)| column.clone()).collect();
+    let triggers = Triggers::new(table, &schema, &TriggerEvent::Update(assigned));
+    let mut changes = 0;
+    for rowid in targets {
+        // Foreign key actions of earlier rows may have changed this one.
+        let old_row = match stored_row(table, &schema, rowid)? {
+            Some(row) => row,
+            None => continue,
+        };
+        let mut values = old_row.clone();
+        values.push(Value::Integer(rowid as i64));
+        correlated.append_values(table, &bindings, &mut values)?;
+        let scope = Scope::new(&table.functions, &bindings, &values);
+        let mut new_row = old_row.clone();
+        for (i, expr) in &assignments {
+            new_row[*i] = schema.columns[*i].affinity.apply(eval(expr, &scope)?);
+        }
+        triggers.fire(table, TriggerTiming::Before, Some(&old_row), Some(&new_row))?;
+        // A BEFORE trigger may have deleted the row.
+        if !tree.contains(table, rowid) {
+            continue;
+        }
+        let (new_rowid, new_row) = update_row(table, &schema, &indexes, rowid, &old_row, new_row)?;
+        triggers.fire(table, TriggerTiming::After, Some(&old_row), Some(&new_row))?;
+        returning.push(table, new_rowid, &new_row)?;
+        changes += 1;
+    }
+    Ok(returning.finish(changes))
+}
+
+pub unsafe fn execute_delete(table: &mut Table, delete: &Delete) -> Result<ExecuteResult, Error> {
+    let schema = table.catalog.get_writable_table(&delete.table)?.clone();
+    let indexes = table.catalog.table_indexes(&schema.name);
+    let tree = BTree::new(schema.root_page_num);
+    let targets = matching_rowids(table, &schema, &delete.where_clause)?;
+    let mut returning = Returning::new(table, &schema, &delete.returning)?;
+    let triggers = Triggers::new(table, &schema, &TriggerEvent::Delete);
+    let mut changes = 0;
+    for rowid in targets {
+        // Cascading deletes of earlier rows may have removed this one.
+        let row = match stored_row(table, &schema, rowid)? {
+            Some(row) => row,
+            None => continue,
+        };
+        triggers.fire(table, TriggerTiming::Before, Some(&row), None)?;
+        // So may a BEFORE trigger.
+        if !tree.contains(table, rowid) {
+            continue;
+        }
+        delete_row(table, &schema, &indexes, rowid, &row)?;
+        triggers.fire(table, TriggerTiming::After, Some(&row), None)?;
+        returning.push(table, rowid, &row)?;
+        changes += 1;
+    }
+    Ok(returning.finish(changes))
+}
+
+// Rowids of the rows matching a WHERE clause, collected before any of them
+// is modified.
+unsafe fn matching_rowids(table: &mut Table, schema: &TableSchema, where_clause: &Option<Expr>) -> Result<Vec<u32>, Error> {
+    let mut correlated = Correlated::default();
+    let where_clause = match where_clause {
+        Some(expr) => Some(correlated.plan(table, expr)?),
+        None => None,
+    };
+    let relation = PlanTable::stored(table, schema.name.clone(), schema, stored_bindings(schema, &schema.name));
+    let conditions = where_clause.as_ref().map(conjuncts).unwrap_or_default();
+    let access = choose_access(&table.functions, &relation, &[], &conditions, 1.0, false).access;
+    let mut bindings = relation.bindings;
+    bindings.extend(correlated.bindings());
+    let matches = |table: &mut Table, mut values: Vec<Value>| -> Result<bool, Error> {
+        correlated.append_values(table, &bindings, &mut values)?;
+        matches_where(&table.functions, &where_clause, &bindings, &values)
+    };
+    let mut rowids = Vec::new();
+    if let Access::Scan = access {
+        let mut scan = BTree::new(schema.root_page_num).scan(table);
+        while let Some((rowid, record)) = scan.next(table) {
+            let mut values = record.values;
+            complete_row(schema, &mut values)?;
+            values.push(Value::Integer(rowid as i64));
+            if matches(table, values)? {
+                rowids.push(rowid);
+            }
+        }
+    } else {
+        for rowid in access.rowids(table, &[], &[])? {
+            if let Some(mut values) = stored_row(table, schema, rowid)? {
+                values.push(Value::Integer(rowid as i64));
+                if matches(table, values)? {
+                    rowids.push(rowid);
+                }
+            }
+        }
+    }
+    Ok(rowids)
+}
+
+pub unsafe fn delete_row(table: &mut Table, schema: &TableSchema, indexes: &[IndexSchema], rowid: u32, row: &[Value]) -> Result<(), Error> {
+    check_child_rows(table, schema, rowid, row, None)?;
+    BTree::new(schema.root_page_num).delete(table, rowid);
+    for index in indexes {
+        let key_values = index_key_values(index, schema, row)?;
+        index_remove(table, index, &key_values, rowid)?;
+    }
+    apply_child_actions(table, schema, row, None)
+}
+
+// Replaces a row, returning the new rowid and row as stored.
+pub unsafe fn update_row(
+    table: &mut Table,
+    schema: &TableSchema,
+    indexes: &[IndexSchema],
+    rowid: u32,
+    old_row: &[Value],
+    mut new_row: Vec<Value>,
+) -> Result<(u32, Vec<Value>), Error> {
+    let tree = BTree::new(schema.root_page_num);
+    let mut new_rowid = rowid;
+    if let Some(i) = schema.rowid_column {
+        if new_row[i] != old_row[i] {
+            new_rowid = assign_rowid(table, &mut InsertCursor::new(tree), Some(&new_row[i]))?;
+            if tree.contains(table, new_rowid) {
+                return Err(primary_key_violation(schema));
+            }
+            new_row[i] = Value::Integer(new_rowid as i64);
+        }
+    }
+    check_row(&table.functions, schema, &new_row)?;
+    let mut changed = Vec::new();
+    for index in indexes {
+        let old_keys = index_key_values(index, schema, old_row)?;
+        let new_keys = index_key_values(index, schema, &new_row)?;
+        if old_keys == new_keys && new_rowid == rowid {
+            continue;
+        }
+        if index.unique && index_lookup(table, index, &new_keys).iter().any(|other| *other != rowid) {
+            return Err(unique_violation(schema, index));
+        }
+        changed.push((index, old_keys, new_keys));
+    }
+    check_parent_rows(table, schema, Some(old_row), &new_row)?;
+    check_child_rows(table, schema, rowid, old_row, Some(&new_row))?;
+    if new_rowid == rowid {
+        tree.update(table, rowid, &Record::new(new_row.clone()))?;
+    } else {
+        tree.delete(table, rowid);
+        tree.insert(table, new_rowid, &Record::new(new_row.clone()))?;
+    }
+    for (index, old_keys, new_keys) in changed {
+        index_remove(table, index, &old_keys, rowid)?;
+        index_insert(table, index, &new_keys, new_rowid)?;
+    }
+    apply_child_actions(table, schema, old_row, Some(&new_row))?;
+    Ok((new_rowid, new_row))
+}
+
+// Rows of a RETURNING clause, evaluated against each row as it was stored,
+// or deleted.
+struct Returning {
+    items: Vec<SelectItem>,
+    correlated: Correlated,
+    bindings: Vec<ColumnBinding>,
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Returning {
+    unsafe fn new(table: &mut Table, schema: &TableSchema, items: &[SelectItem]) -> Result<Returning, Error> {
+        let mut correlated = Correlated::default();
+        let mut planned = Vec::with_capacity(items.len());
+        let mut columns = Vec::new();
+        for item in items {
+            match item {
+                SelectItem::Wildcard => {
+                    columns.extend(schema.columns.iter().map(|column| column.name.clone()));
+                    planned.push(SelectItem::Wildcard);
+                }
+                SelectItem::TableWildcard(name) if name.eq_ignore_ascii_case(&schema.name) => {
+                    columns.extend(schema.columns.iter().map(|column| column.name.clone()));
+                    planned.push(SelectItem::Wildcard);
+                }
+                SelectItem::TableWildcard(name) => return Err(Error::other(format!("no such table: {}", name))),
+                SelectItem::Expr { expr, alias } => {
+                    if contains_aggregate(expr, &table.functions) {
+                        return Err(Error::other("aggregate functions are not allowed in RETURNING"));
+                    }
+                    columns.push(column_name(expr, alias));
+                    planned.push(SelectItem::Expr {
+                        expr: correlated.plan(table, expr)?,
+                        alias: alias.clone(),
+                    });
+                }
+            }
+        }
+        let mut bindings = stored_bindings(schema, &schema.name);
+        bindings.extend(correlated.bindings());
+        Ok(Returning {
+            items: planned,
+            correlated,
+            bindings,
+            columns,
+            rows: Vec::new(),
+        })
+    }
+
+    unsafe fn push(&mut self, table: &mut Table, rowid: u32, row: &[Value]) -> Result<(), Error> {
+        if !self.items.is_empty() {
+            let mut values = row.to_vec();
+            values.push(Value::Integer(rowid as i64));
+            self.correlated.append_values(table, &self.bindings, &mut values)?;
+            self.rows.push(project(&table.functions, &self.items, &self.bindings, &values)?);
+        }
+        Ok(())
+    }
+
+    // The statement result: the rows when there is a RETURNING clause,
+    // otherwise the number of changed rows.
+    fn finish(self, changes: usize) -> ExecuteResult {
+        if self.items.is_empty() {
+            return ExecuteResult::Executed { changes };
+        }
+        ExecuteResult::Rows(ResultSet {
+            columns: self.columns,
+            rows: self.rows,
+        })
+    }
+}
+
+// Checks the NOT NULL and CHECK constraints of a row.
+pub fn check_row(functions: &Functions, schema: &TableSchema, row: &[Value]) -> Result<(), Error> {
+    for (column, value) in schema.columns.iter().zip(row) {
+        if column.not_null && value.is_null() {
+            return Err(ConstraintViolation::NotNull {
+                table: schema.name.clone(),
+                column: column.name.clone(),
+            }
+            .into());
+        }
+    }
+    let bindings = table_bindings(schema, &schema.name);
+    let scope = Scope::new(functions, &bindings, row);
+    let column_checks = schema
+        .columns
+        .iter()
+        .filter_map(|column| column.check.as_ref().map(|check| (Some(column.name.clone()), check)));
+    let table_checks = schema.checks.iter().map(|check| {
+        let columns = check.referenced_columns();
+        (if columns.len() == 1 { columns.into_iter().next() } else { None }, check)
+    });
+    for (column, check) in column_checks.chain(table_checks) {
+        if eval(check, &scope)?.truthy() == Some(false) {
+            return Err(ConstraintViolation::Check {
+                table: schema.name.clone(),
+                column,
+                expr: check.to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+pub fn matches_where(
+    functions: &Functions,
+    where_clause: &Option<Expr>,
+    bindings: &[ColumnBinding],
+    row: &[Value],
+) -> Result<bool, Error> {
+    match where_clause {
+        Some(expr) => Ok(is_true(&eval(expr, &Scope::new(functions, bindings, row))?)),
+        None => Ok(true),
+    }
+}
+
+fn primary_key_violation(schema: &TableSchema) -> Error {
+    let column = schema.rowid_column.map_or("rowid".to_string(), |i| schema.columns[i].name.clone());
+    ConstraintViolation::PrimaryKey {
+        table: schema.name.clone(),
+        column,
+    }
+    .into()
+}
+
+fn unique_violation(schema: &TableSchema, index: &IndexSchema) -> Error {
+    ConstraintViolation::Unique {
+        table: schema.name.clone(),
+        columns: index.columns.clone(),
+    }
+    .into()
+}
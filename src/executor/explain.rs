@@ -0,0 +1,395 @@
+use crate::executor::aggregate::is_aggregate_query;
+use crate::executor::cte::reads_table;
+use crate::executor::dispatch::{ExecuteResult, ResultSet};
+use crate::executor::expr::ColumnBinding;
+use crate::executor::planner::{choose_access, conjuncts, plan_joins, table_label, PlanTable};
+use crate::executor::query::{column_name, distinct_index, rowid_order, selects_unique_key, stored_bindings};
+use crate::executor::view::check_nesting;
+use crate::sql::ast::{CommonTableExpr, CompoundOp, CompoundSelect, Expr, InsertSource, Select, SelectItem, Stmt, TableRef};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use crate::vm::compiler::compile;
+use std::io::Error;
+use std::iter;
+use std::mem;
+
+// EXPLAIN QUERY PLAN shows how a statement would read its tables, as a tree
+// printed one node per line the way the sqlite3 shell prints it below its
+// QUERY PLAN header, which is the name of the result column. Nothing is
+// run: the tables of WITH clauses, views and subqueries in FROM are shown as
+// materialized, with their own plan below them, and the subqueries of
+// expressions are shown after the tables of the query they are part of.
+// Statements reading no table have an empty plan.
+
+struct Node {
+    detail: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(detail: impl Into<String>) -> Node {
+        Node::with(detail, Vec::new())
+    }
+
+    fn with(detail: impl Into<String>, children: Vec<Node>) -> Node {
+        Node {
+            detail: detail.into(),
+            children,
+        }
+    }
+}
+
+pub fn explain_query_plan(table: &Table, stmt: &Stmt) -> Result<ExecuteResult, Error> {
+    let mut explainer = Explainer {
+        table,
+        subqueries: 0,
+        common_tables: Vec::new(),
+        view_depth: 0,
+    };
+    let nodes = explainer.statement(stmt)?;
+    let mut lines = Vec::new();
+    render(&nodes, "", &mut lines);
+    Ok(ExecuteResult::Rows(ResultSet {
+        columns: vec!["QUERY PLAN".to_string()],
+        rows: lines.into_iter().map(|line| vec![Value::Text(line)]).collect(),
+    }))
+}
+
+// EXPLAIN lists the instructions of the program a statement compiles to, see
+// vm/program.rs, one row each. The statement is not run.
+pub unsafe fn explain_program(table: &mut Table, stmt: Stmt, sql: &str) -> Result<ExecuteResult, Error> {
+    // The statement text without the EXPLAIN keyword.
+    let trimmed = sql.trim_start();
+    let sql = match trimmed.get(..7) {
+        Some(keyword) if keyword.eq_ignore_ascii_case("EXPLAIN") => trimmed[7..].trim_start(),
+        _ => sql,
+    };
+    let program = compile(table, stmt, sql);
+    let columns = ["addr", "opcode", "p1", "p2", "p3", "p4", "p5"];
+    let rows = program
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(addr, instruction)| {
+            vec![
+                Value::Integer(addr as i64),
+                Value::Text(format!("{:?}", instruction.opcode)),
+                Value::Integer(instruction.p1),
+                Value::Integer(instruction.p2),
+                Value::Integer(instruction.p3),
+                instruction.p4.describe().map_or(Value::Null, Value::Text),
+                Value::Integer(instruction.p5),
+            ]
+        })
+        .collect();
+    Ok(ExecuteResult::Rows(ResultSet {
+        columns: columns.iter().map(|column| column.to_string()).collect(),
+        rows,
+    }))
+}
+
+fn render(nodes: &[Node], prefix: &str, lines: &mut Vec<String>) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        lines.push(format!("{}{}{}", prefix, if last { "`--" } else { "|--" }, node.detail));
+        render(&node.children, &format!("{}{}", prefix, if last { "   " } else { "|  " }), lines);
+    }
+}
+
+struct Explainer<'a> {
+    table: &'a Table,
+    // Subqueries numbered so far.
+    subqueries: usize,
+    // The common tables in scope with their columns, innermost last.
+    common_tables: Vec<(String, Vec<String>)>,
+    // How many views are being expanded, each read by the one before.
+    view_depth: usize,
+}
+
+impl Explainer<'_> {
+    fn statement(&mut self, stmt: &Stmt) -> Result<Vec<Node>, Error> {
+        match stmt {
+            Stmt::Select(select) => Ok(self.select(select, &[])?.0),
+            Stmt::Insert(insert) => match &insert.source {
+                InsertSource::Select(select) => Ok(self.select(select, &[])?.0),
+                InsertSource::Values(rows) => {
+                    let mut nodes = Vec::new();
+                    for expr in rows.iter().flatten() {
+                        self.subqueries(expr, &[], &mut nodes)?;
+                    }
+                    Ok(nodes)
+                }
+            },
+            Stmt::Update(update) => {
+                let exprs = update.assignments.iter().map(|(_, expr)| expr);
+                self.target(&update.table, update.where_clause.as_ref(), exprs)
+            }
+            Stmt::Delete(delete) => self.target(&delete.table, delete.where_clause.as_ref(), iter::empty()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    // The table an UPDATE or DELETE changes, then the subqueries of its
+    // expressions.
+    fn target<'e>(&mut self, name: &str, where_clause: Option<&'e Expr>, exprs: impl Iterator<Item = &'e Expr>) -> Result<Vec<Node>, Error> {
+        let schema = self.table.catalog.get_writable_table(name)?.clone();
+        let bindings = stored_bindings(&schema, &schema.name);
+        let relation = PlanTable::stored(self.table, schema.name.clone(), &schema, bindings.clone());
+        let conditions = where_clause.map(conjuncts).unwrap_or_default();
+        let access = choose_access(&self.table.functions, &relation, &[], &conditions, 1.0, false).access;
+        let mut nodes = vec![Node::new(access.describe(&relation))];
+        for expr in exprs.chain(where_clause) {
+            self.subqueries(expr, &bindings, &mut nodes)?;
+        }
+        Ok(nodes)
+    }
+
+    // The plan of a select and the names of its columns. outer are the
+    // columns of the enclosing query of a correlated subquery.
+    fn select(&mut self, select: &Select, outer: &[ColumnBinding]) -> Result<(Vec<Node>, Vec<String>), Error> {
+        let scope = self.common_tables.len();
+        let result = self.with_query(select, outer);
+        self.common_tables.truncate(scope);
+        result
+    }
+
+    fn with_query(&mut self, select: &Select, outer: &[ColumnBinding]) -> Result<(Vec<Node>, Vec<String>), Error> {
+        let mut nodes = Vec::new();
+        if let Some(with) = &select.with {
+            for cte in &with.tables {
+                nodes.push(self.common_table(cte, with.recursive)?);
+            }
+        }
+        let (query_nodes, columns) = self.query(select, outer)?;
+        nodes.extend(query_nodes);
+        Ok((nodes, columns))
+    }
+
+    fn common_table(&mut self, cte: &CommonTableExpr, recursive: bool) -> Result<Node, Error> {
+        let detail = format!("MATERIALIZE {}", cte.name);
+        let recursive_select = cte.select.compound.last().filter(|last| {
+            recursive && matches!(last.op, CompoundOp::Union | CompoundOp::UnionAll) && reads_table(&last.select, &cte.name)
+        });
+        let Some(recursive_select) = recursive_select else {
+            let (children, columns) = self.select(&cte.select, &[])?;
+            self.declare(cte, columns);
+            return Ok(Node::with(detail, children));
+        };
+        let initial_select = Select {
+            compound: cte.select.compound[..cte.select.compound.len() - 1].to_vec(),
+            ..cte.select.clone()
+        };
+        let (setup, columns) = self.select(&initial_select, &[])?;
+        self.declare(cte, columns);
+        let (step, _) = self.select(&recursive_select.select, &[])?;
+        Ok(Node::with(detail, vec![Node::with("SETUP", setup), Node::with("RECURSIVE STEP", step)]))
+    }
+
+    fn declare(&mut self, cte: &CommonTableExpr, columns: Vec<String>) {
+        let columns = if cte.columns.is_empty() { columns } else { cte.columns.clone() };
+        self.common_tables.push((cte.name.clone(), columns));
+    }
+
+    fn query(&mut self, select: &Select, outer: &[ColumnBinding]) -> Result<(Vec<Node>, Vec<String>), Error> {
+        if !select.compound.is_empty() {
+            return self.compound(select, outer);
+        }
+        let table = self.table;
+        let mut nodes = Vec::new();
+        let mut relations = Vec::new();
+        if let Some(from) = &select.from {
+            for table_ref in iter::once(from).chain(select.joins.iter().map(|join| &join.table)) {
+                relations.push(self.relation(table_ref, &mut nodes)?);
+            }
+        }
+        let functions = &table.functions;
+        let conditions = select.where_clause.as_ref().map(conjuncts).unwrap_or_default();
+        // The table of a select reading a single stored table, with its alias.
+        let mut single = None;
+        // Whether the rows of a SELECT DISTINCT are read through an index.
+        let mut distinct_keys = false;
+        match relations.as_slice() {
+            [] => nodes.push(Node::new("SCAN CONSTANT ROW")),
+            [relation @ PlanTable { schema: Some(schema), .. }] => {
+                let from = select.from.as_ref().expect("a table without FROM");
+                let alias = from.alias.clone().unwrap_or_else(|| schema.name.clone());
+                let unique = selects_unique_key(schema, &alias, select, functions);
+                match distinct_index(table, schema, &alias, select).filter(|_| !unique) {
+                    Some(index) => {
+                        nodes.push(Node::new(format!("SCAN {} USING INDEX {}", relation.label, index.name)));
+                        distinct_keys = true;
+                    }
+                    None => {
+                        let known: Vec<ColumnBinding> = outer.iter().map(ColumnBinding::outer).collect();
+                        let access = choose_access(functions, relation, &known, &conditions, 1.0, false).access;
+                        nodes.push(Node::new(access.describe(relation)));
+                    }
+                }
+                single = Some((schema, alias));
+            }
+            _ => {
+                for step in plan_joins(functions, &relations, &select.joins, select.where_clause.as_ref()) {
+                    nodes.push(Node::new(step.access.describe(&relations[step.relation])));
+                }
+            }
+        }
+        let mut bindings: Vec<ColumnBinding> = relations.iter().flat_map(|relation| relation.bindings.iter().cloned()).collect();
+        let mut columns = Vec::new();
+        for item in &select.columns {
+            match item {
+                SelectItem::Wildcard if select.from.is_none() => {
+                    return Err(Error::other("no tables specified"))
+                }
+                SelectItem::Wildcard => columns.extend(
+                    bindings
+                        .iter()
+                        .filter(|binding| binding.in_wildcard(None))
+                        .map(|binding| binding.name.clone()),
+                ),
+                SelectItem::TableWildcard(name) => columns.extend(
+                    bindings
+                        .iter()
+                        .filter(|binding| binding.in_wildcard(Some(name)))
+                        .map(|binding| binding.name.clone()),
+                ),
+                SelectItem::Expr { expr, alias } => columns.push(column_name(expr, alias)),
+            }
+        }
+        let aggregate = is_aggregate_query(select, functions);
+        let distinct = select.distinct
+            && !distinct_keys
+            && !single
+                .as_ref()
+                .is_some_and(|(schema, alias)| selects_unique_key(schema, alias, select, functions));
+        let sorted = !select.order_by.is_empty()
+            && (aggregate
+                || single
+                    .as_ref()
+                    .and_then(|(schema, alias)| rowid_order(schema, alias, &select.order_by))
+                    .is_none());
+        // The subqueries of the select's expressions see its columns, then
+        // the ones of the queries around it.
+        bindings.extend(outer.iter().map(ColumnBinding::outer));
+        let exprs = select
+            .columns
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Expr { expr, .. } => Some(expr),
+                SelectItem::Wildcard | SelectItem::TableWildcard(_) => None,
+            })
+            .chain(select.joins.iter().filter_map(|join| join.constraint.as_ref()))
+            .chain(&select.where_clause)
+            .chain(&select.group_by)
+            .chain(&select.having)
+            .chain(select.order_by.iter().map(|term| &term.expr));
+        for expr in exprs {
+            self.subqueries(expr, &bindings, &mut nodes)?;
+        }
+        if aggregate && !select.group_by.is_empty() {
+            nodes.push(Node::new("USE HASH TABLE FOR GROUP BY"));
+        }
+        if distinct {
+            nodes.push(Node::new("USE HASH TABLE FOR DISTINCT"));
+        }
+        if sorted {
+            nodes.push(Node::new("USE TEMP B-TREE FOR ORDER BY"));
+        }
+        Ok((nodes, columns))
+    }
+
+    fn compound(&mut self, select: &Select, outer: &[ColumnBinding]) -> Result<(Vec<Node>, Vec<String>), Error> {
+        let first = Select {
+            with: None,
+            compound: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+            ..select.clone()
+        };
+        let (left, columns) = self.query(&first, outer)?;
+        let mut children = vec![Node::with("LEFT-MOST SUBQUERY", left)];
+        for CompoundSelect { op, select } in &select.compound {
+            let (nodes, _) = self.query(select, outer)?;
+            let detail = match op {
+                CompoundOp::Union => "UNION USING HASH TABLE",
+                CompoundOp::UnionAll => "UNION ALL",
+                CompoundOp::Intersect => "INTERSECT USING HASH TABLE",
+                CompoundOp::Except => "EXCEPT USING HASH TABLE",
+            };
+            children.push(Node::with(detail, nodes));
+        }
+        let mut nodes = vec![Node::with("COMPOUND QUERY", children)];
+        if !select.order_by.is_empty() {
+            nodes.push(Node::new("USE TEMP B-TREE FOR ORDER BY"));
+        }
+        Ok((nodes, columns))
+    }
+
+    // A table of a FROM clause. The rows of a subquery or view are computed
+    // before the join, their plan comes first.
+    fn relation(&mut self, table_ref: &TableRef, nodes: &mut Vec<Node>) -> Result<PlanTable, Error> {
+        let table = self.table;
+        let label = table_label(table_ref);
+        let alias = table_ref.alias.as_deref();
+        if let Some(select) = &table_ref.subquery {
+            let label = match alias {
+                Some(alias) => alias.to_string(),
+                None => format!("(subquery-{})", self.next_subquery()),
+            };
+            let (children, columns) = self.select(select, &[])?;
+            nodes.push(Node::with(format!("MATERIALIZE {}", label), children));
+            let bindings = columns.iter().map(|name| ColumnBinding::new(alias, name)).collect();
+            return Ok(PlanTable::derived(label, bindings));
+        }
+        let common_table = self
+            .common_tables
+            .iter()
+            .rev()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&table_ref.name));
+        if let Some((name, columns)) = common_table {
+            let alias = alias.unwrap_or(name);
+            let bindings = columns.iter().map(|column| ColumnBinding::new(Some(alias), column)).collect();
+            return Ok(PlanTable::derived(label, bindings));
+        }
+        if let Some(view) = table.catalog.view(&table_ref.name) {
+            // A view does not see the common tables of the query reading it.
+            check_nesting(table, self.view_depth)?;
+            let common_tables = mem::take(&mut self.common_tables);
+            self.view_depth += 1;
+            let result = self.select(&view.select, &[]);
+            self.view_depth -= 1;
+            self.common_tables = common_tables;
+            let (children, columns) = result?;
+            let columns = if view.columns.is_empty() { columns } else { view.columns.clone() };
+            nodes.push(Node::with(format!("MATERIALIZE {}", view.name), children));
+            let alias = alias.unwrap_or(&view.name);
+            let bindings = columns.iter().map(|column| ColumnBinding::new(Some(alias), column)).collect();
+            return Ok(PlanTable::derived(label, bindings));
+        }
+        let schema = self.table.catalog.get_table(&table_ref.name)?.clone();
+        let bindings = stored_bindings(&schema, alias.unwrap_or(&schema.name));
+        Ok(PlanTable::stored(table, label, &schema, bindings))
+    }
+
+    // Adds the plans of the subqueries of an expression, which see the
+    // given columns of the query they are part of.
+    fn subqueries(&mut self, expr: &Expr, bindings: &[ColumnBinding], nodes: &mut Vec<Node>) -> Result<(), Error> {
+        let mut found = Vec::new();
+        expr.walk(&mut |expr| match expr {
+            Expr::Subquery(select) | Expr::Exists(select) => found.push(("SCALAR", select.clone())),
+            Expr::InSubquery { select, .. } => found.push(("LIST", select.clone())),
+            _ => {}
+        });
+        for (kind, select) in found {
+            let n = self.next_subquery();
+            let (children, _) = self.select(&select, bindings)?;
+            nodes.push(Node::with(format!("{} SUBQUERY {}", kind, n), children));
+        }
+        Ok(())
+    }
+
+    fn next_subquery(&mut self) -> usize {
+        self.subqueries += 1;
+        self.subqueries
+    }
+}
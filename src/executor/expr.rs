@@ -0,0 +1,579 @@
+use crate::executor::aggregate::is_aggregate_call;
+use crate::executor::function::{find_function, Functions};
+use crate::sql::ast::{BinaryOp, Expr, UnaryOp};
+use crate::table::value::{Affinity, Value};
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::Error;
+
+// Name of a column visible to expressions, optionally qualified by the table
+// name or alias it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnBinding {
+    pub table: Option<String>,
+    pub name: String,
+    // Only visible as table.name, like the excluded row of an upsert.
+    pub qualified_only: bool,
+    // How many queries out the column comes from, 0 for the query's own
+    // tables. A name resolves to the nearest column having it.
+    pub depth: usize,
+    // Affinity of a stored table column, None for computed ones like the
+    // columns of a subquery.
+    pub affinity: Option<Affinity>,
+    // The rowid of a stored table, read as rowid, _rowid_ or oid unless a
+    // declared column has the name. * does not expand to it.
+    pub rowid: bool,
+}
+
+impl ColumnBinding {
+    pub fn new(table: Option<&str>, name: &str) -> ColumnBinding {
+        ColumnBinding {
+            table: table.map(|table| table.to_string()),
+            name: name.to_string(),
+            qualified_only: false,
+            depth: 0,
+            affinity: None,
+            rowid: false,
+        }
+    }
+
+    pub fn rowid(table: &str) -> ColumnBinding {
+        ColumnBinding {
+            affinity: Some(Affinity::Integer),
+            rowid: true,
+            ..ColumnBinding::new(Some(table), "rowid")
+        }
+    }
+
+    // The same column seen from a subquery.
+    pub fn outer(&self) -> ColumnBinding {
+        ColumnBinding {
+            depth: self.depth + 1,
+            ..self.clone()
+        }
+    }
+
+    // Whether * expands to the column, or table.* when table is given.
+    pub fn in_wildcard(&self, table: Option<&str>) -> bool {
+        let in_table = match (table, &self.table) {
+            (None, _) => true,
+            (Some(wanted), Some(table)) => wanted.eq_ignore_ascii_case(table),
+            (Some(_), None) => false,
+        };
+        self.depth == 0 && !self.qualified_only && !self.rowid && in_table
+    }
+
+    // Of the columns a name matches the reference reads the one ranked
+    // lowest: the nearest, and a declared column before the rowid.
+    pub fn rank(&self) -> (usize, bool) {
+        (self.depth, self.rowid)
+    }
+
+    pub fn qualified(table: &str, name: &str) -> ColumnBinding {
+        ColumnBinding {
+            qualified_only: true,
+            ..ColumnBinding::new(Some(table), name)
+        }
+    }
+
+    pub fn matches(&self, table: Option<&str>, name: &str) -> bool {
+        let named = match self.rowid {
+            true => is_rowid_name(name),
+            false => self.name.eq_ignore_ascii_case(name),
+        };
+        if !named {
+            return false;
+        }
+        match (table, &self.table) {
+            (None, _) => !self.qualified_only,
+            (Some(wanted), Some(table)) => wanted.eq_ignore_ascii_case(table),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+pub fn is_rowid_name(name: &str) -> bool {
+    ["rowid", "_rowid_", "oid"].iter().any(|rowid| rowid.eq_ignore_ascii_case(name))
+}
+
+// A column name that does not resolve. A subquery failing with it reads
+// columns of an enclosing query, see subquery.rs.
+#[derive(Debug)]
+pub struct NoSuchColumn(pub String);
+
+impl NoSuchColumn {
+    pub fn from_error(error: &Error) -> Option<&NoSuchColumn> {
+        error.get_ref()?.downcast_ref::<NoSuchColumn>()
+    }
+}
+
+impl fmt::Display for NoSuchColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no such column: {}", self.0)
+    }
+}
+
+impl std::error::Error for NoSuchColumn {}
+
+impl From<NoSuchColumn> for Error {
+    fn from(error: NoSuchColumn) -> Self {
+        Error::other(error)
+    }
+}
+
+// The row an expression is evaluated against, and the registered functions
+// it may call. A scope without functions only knows the built-ins.
+pub struct Scope<'a> {
+    pub functions: Option<&'a Functions>,
+    pub columns: &'a [ColumnBinding],
+    pub values: &'a [Value],
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(functions: &'a Functions, columns: &'a [ColumnBinding], values: &'a [Value]) -> Scope<'a> {
+        Scope {
+            functions: Some(functions),
+            columns,
+            values,
+        }
+    }
+
+    pub fn empty() -> Scope<'static> {
+        Scope {
+            functions: None,
+            columns: &[],
+            values: &[],
+        }
+    }
+
+    fn lookup(&self, table: Option<&str>, name: &str) -> Result<Value, Error> {
+        Ok(self.values[self.position(table, name)?].clone())
+    }
+
+    // Affinity of an operand, only columns of stored tables have one.
+    pub fn affinity(&self, expr: &Expr) -> Option<Affinity> {
+        match expr {
+            Expr::Column { table, name } => {
+                let i = self.position(table.as_deref(), name).ok()?;
+                self.columns[i].affinity
+            }
+            _ => None,
+        }
+    }
+
+    fn position(&self, table: Option<&str>, name: &str) -> Result<usize, Error> {
+        let mut found: Option<usize> = None;
+        for (i, column) in self.columns.iter().enumerate() {
+            if !column.matches(table, name) {
+                continue;
+            }
+            match found {
+                Some(f) if self.columns[f].rank() < column.rank() => {}
+                Some(f) if self.columns[f].rank() == column.rank() => {
+                    return Err(Error::other(format!("ambiguous column name: {}", name)));
+                }
+                _ => found = Some(i),
+            }
+        }
+        match found {
+            Some(i) => Ok(i),
+            None => {
+                let name = match table {
+                    Some(table) => format!("{}.{}", table, name),
+                    None => name.to_string(),
+                };
+                Err(NoSuchColumn(name).into())
+            }
+        }
+    }
+}
+
+pub fn eval(expr: &Expr, scope: &Scope) -> Result<Value, Error> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Parameter { index, .. } => Ok(scope.functions.map_or(Value::Null, |functions| functions.parameter(*index))),
+        Expr::Column { table, name } => scope.lookup(table.as_deref(), name),
+        Expr::Unary { op, expr } => {
+            let value = eval(expr, scope)?;
+            Ok(match op {
+                UnaryOp::Not => match value.truthy() {
+                    Some(truth) => Value::from_bool(!truth),
+                    None => Value::Null,
+                },
+                UnaryOp::Minus => match value.to_numeric() {
+                    Value::Integer(i) => i.checked_neg().map_or(Value::Real(-(i as f64)), Value::Integer),
+                    Value::Real(r) => Value::Real(-r),
+                    value => value,
+                },
+                UnaryOp::Plus => value,
+            })
+        }
+        Expr::Binary {
+            left,
+            op: BinaryOp::And,
+            right,
+        } => {
+            let left = eval(left, scope)?.truthy();
+            if left == Some(false) {
+                return Ok(Value::from_bool(false));
+            }
+            match (left, eval(right, scope)?.truthy()) {
+                (_, Some(false)) => Ok(Value::from_bool(false)),
+                (Some(true), Some(true)) => Ok(Value::from_bool(true)),
+                _ => Ok(Value::Null),
+            }
+        }
+        Expr::Binary {
+            left,
+            op: BinaryOp::Or,
+            right,
+        } => {
+            let left = eval(left, scope)?.truthy();
+            if left == Some(true) {
+                return Ok(Value::from_bool(true));
+            }
+            match (left, eval(right, scope)?.truthy()) {
+                (_, Some(true)) => Ok(Value::from_bool(true)),
+                (Some(false), Some(false)) => Ok(Value::from_bool(false)),
+                _ => Ok(Value::Null),
+            }
+        }
+        Expr::Binary { left, op, right } if op.is_comparison() => {
+            let (left, right) = eval_operands(left, right, scope)?;
+            Ok(compare(*op, &left, &right))
+        }
+        Expr::Binary { left, op, right } => {
+            let left = eval(left, scope)?;
+            let right = eval(right, scope)?;
+            if left.is_null() || right.is_null() {
+                return Ok(Value::Null);
+            }
+            match op {
+                BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => {
+                    Ok(arithmetic(*op, left.to_numeric(), right.to_numeric()))
+                }
+                BinaryOp::Concat => Ok(Value::Text(left.to_text() + &right.to_text())),
+                _ => unreachable!(),
+            }
+        }
+        Expr::InList { expr, list, negated } => {
+            let value = eval(expr, scope)?;
+            if list.is_empty() {
+                return Ok(Value::from_bool(*negated));
+            }
+            if value.is_null() {
+                return Ok(Value::Null);
+            }
+            let mut saw_null = false;
+            for item in list {
+                let (value, item) = match comparison_affinity(scope.affinity(expr), scope.affinity(item)) {
+                    (Some(affinity), _) => (affinity.apply(value.clone()), eval(item, scope)?),
+                    (None, Some(affinity)) => (value.clone(), affinity.apply(eval(item, scope)?)),
+                    (None, None) => (value.clone(), eval(item, scope)?),
+                };
+                if item.is_null() {
+                    saw_null = true;
+                } else if value.compare(&item) == Ordering::Equal {
+                    return Ok(Value::from_bool(!*negated));
+                }
+            }
+            if saw_null {
+                Ok(Value::Null)
+            } else {
+                Ok(Value::from_bool(*negated))
+            }
+        }
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => {
+            let (value, low) = eval_operands(expr, low, scope)?;
+            let above = compare(BinaryOp::GtEq, &value, &low).truthy();
+            let (value, high) = eval_operands(expr, high, scope)?;
+            let below = compare(BinaryOp::LtEq, &value, &high).truthy();
+            let between = match (above, below) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            };
+            Ok(match between {
+                Some(between) => Value::from_bool(between != *negated),
+                None => Value::Null,
+            })
+        }
+        Expr::Like {
+            expr,
+            pattern,
+            escape,
+            glob,
+            negated,
+        } => {
+            let value = eval(expr, scope)?;
+            let pattern = eval(pattern, scope)?;
+            let escape = match escape {
+                Some(escape) => match eval(escape, scope)? {
+                    Value::Null => return Ok(Value::Null),
+                    escape => {
+                        let escape = escape.to_text();
+                        let mut chars = escape.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Some(c),
+                            _ => {
+                                return Err(Error::other("ESCAPE expression must be a single character"))
+                            }
+                        }
+                    }
+                },
+                None => None,
+            };
+            if value.is_null() || pattern.is_null() {
+                return Ok(Value::Null);
+            }
+            let pattern: Vec<char> = pattern.to_text().chars().collect();
+            let text: Vec<char> = value.to_text().chars().collect();
+            let matched = if *glob {
+                glob_match(&pattern, &text)
+            } else {
+                like_match(&pattern, &text, escape)
+            };
+            Ok(Value::from_bool(matched != *negated))
+        }
+        Expr::Case {
+            operand,
+            branches,
+            else_expr,
+        } => {
+            let operand = match operand {
+                Some(operand) => Some(eval(operand, scope)?),
+                None => None,
+            };
+            for (when, then) in branches {
+                let when = eval(when, scope)?;
+                let matched = match &operand {
+                    Some(operand) => is_true(&compare(BinaryOp::Eq, operand, &when)),
+                    None => is_true(&when),
+                };
+                if matched {
+                    return eval(then, scope);
+                }
+            }
+            match else_expr {
+                Some(else_expr) => eval(else_expr, scope),
+                None => Ok(Value::Null),
+            }
+        }
+        Expr::IsNull { expr, negated } => {
+            let is_null = eval(expr, scope)?.is_null();
+            Ok(Value::from_bool(is_null != *negated))
+        }
+        // Aggregate calls are replaced by their result before the
+        // expressions of an aggregate query are evaluated.
+        Expr::Function { name, .. } if is_aggregate_call(expr, scope.functions) => Err(Error::other(
+            format!("misuse of aggregate function {}()", name),
+        )),
+        Expr::Function {
+            name,
+            args,
+            wildcard,
+            distinct,
+        } => {
+            let user = scope.functions.and_then(|functions| functions.scalar(name));
+            let builtin = find_function(name);
+            if user.is_none() && builtin.is_none() {
+                return Err(Error::other(format!("no such function: {}", name)));
+            }
+            if *distinct {
+                return Err(Error::other(
+                    format!("DISTINCT is not allowed in call to non-aggregate function {}()", name),
+                ));
+            }
+            if *wildcard {
+                return Err(Error::other(format!("wrong number of arguments to function {}()", name)));
+            }
+            let args = args.iter().map(|arg| eval(arg, scope)).collect::<Result<Vec<Value>, Error>>()?;
+            match (user, builtin) {
+                (Some(user), _) => user.call(name, &args),
+                (None, Some(builtin)) => builtin.call(name, &args),
+                (None, None) => unreachable!(),
+            }
+        }
+        // Window calls of result columns and ORDER BY terms are replaced by
+        // their results before the rows are projected.
+        Expr::Window { function, .. } => {
+            let name = match function.as_ref() {
+                Expr::Function { name, .. } => name.as_str(),
+                _ => "",
+            };
+            Err(Error::other(format!("misuse of window function {}()", name)))
+        }
+        // The executor replaces subqueries by their results before a
+        // statement is evaluated, the ones left are in places it does not
+        // run them, like CHECK constraints and defaults.
+        Expr::Subquery(_) | Expr::Exists(_) | Expr::InSubquery { .. } => {
+            Err(Error::other("subqueries are not allowed here"))
+        }
+    }
+}
+
+// The affinities applied to the left and right operands of a comparison,
+// following sqlite: a numeric column makes a text or untyped operand
+// numeric, a text column makes an operand that is no column text.
+pub fn comparison_affinity(left: Option<Affinity>, right: Option<Affinity>) -> (Option<Affinity>, Option<Affinity>) {
+    let numeric = |affinity| matches!(affinity, Some(Affinity::Integer | Affinity::Real | Affinity::Numeric));
+    if numeric(left) && !numeric(right) {
+        (None, Some(Affinity::Numeric))
+    } else if numeric(right) && !numeric(left) {
+        (Some(Affinity::Numeric), None)
+    } else if left == Some(Affinity::Text) && right.is_none() {
+        (None, Some(Affinity::Text))
+    } else if right == Some(Affinity::Text) && left.is_none() {
+        (Some(Affinity::Text), None)
+    } else {
+        (None, None)
+    }
+}
+
+// Both operands of a comparison with their comparison affinity applied.
+fn eval_operands(left: &Expr, right: &Expr, scope: &Scope) -> Result<(Value, Value), Error> {
+    let (left_affinity, right_affinity) = comparison_affinity(scope.affinity(left), scope.affinity(right));
+    let apply = |affinity: Option<Affinity>, value: Value| match affinity {
+        Some(affinity) => affinity.apply(value),
+        None => value,
+    };
+    Ok((
+        apply(left_affinity, eval(left, scope)?),
+        apply(right_affinity, eval(right, scope)?),
+    ))
+}
+
+// Comparison operators, NULL when either side is NULL.
+fn compare(op: BinaryOp, left: &Value, right: &Value) -> Value {
+    if left.is_null() || right.is_null() {
+        return Value::Null;
+    }
+    let ordering = left.compare(right);
+    let result = match op {
+        BinaryOp::Eq => ordering == Ordering::Equal,
+        BinaryOp::NotEq => ordering != Ordering::Equal,
+        BinaryOp::Lt => ordering == Ordering::Less,
+        BinaryOp::LtEq => ordering != Ordering::Greater,
+        BinaryOp::Gt => ordering == Ordering::Greater,
+        BinaryOp::GtEq => ordering != Ordering::Less,
+        _ => unreachable!(),
+    };
+    Value::from_bool(result)
+}
+
+// Arithmetic on numeric operands. Integer operations that overflow are done
+// in floating point instead, division and remainder by zero give NULL.
+fn arithmetic(op: BinaryOp, left: Value, right: Value) -> Value {
+    if let (Value::Integer(a), Value::Integer(b)) = (&left, &right) {
+        let (a, b) = (*a, *b);
+        let result = match op {
+            BinaryOp::Add => a.checked_add(b),
+            BinaryOp::Subtract => a.checked_sub(b),
+            BinaryOp::Multiply => a.checked_mul(b),
+            BinaryOp::Divide | BinaryOp::Remainder if b == 0 => return Value::Null,
+            BinaryOp::Divide => a.checked_div(b),
+            BinaryOp::Remainder => Some(a.checked_rem(b).unwrap_or(0)),
+            _ => unreachable!(),
+        };
+        if let Some(result) = result {
+            return Value::Integer(result);
+        }
+    }
+    let a = left.as_f64().unwrap_or(0.0);
+    let b = right.as_f64().unwrap_or(0.0);
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide if b == 0.0 => return Value::Null,
+        BinaryOp::Divide => a / b,
+        // The remainder of reals is the remainder of their integer parts.
+        BinaryOp::Remainder => {
+            let (a, b) = (a as i64, b as i64);
+            if b == 0 {
+                return Value::Null;
+            }
+            a.checked_rem(b).unwrap_or(0) as f64
+        }
+        _ => unreachable!(),
+    };
+    if result.is_nan() {
+        Value::Null
+    } else {
+        Value::Real(result)
+    }
+}
+
+// LIKE matching: % matches any run of characters, _ any single character,
+// ASCII letters match regardless of case. The escape character makes the
+// next character match itself.
+fn like_match(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(&c) if Some(c) == escape => match (pattern.get(1), text.first()) {
+            (Some(p), Some(t)) => p.eq_ignore_ascii_case(t) && like_match(&pattern[2..], &text[1..], escape),
+            _ => false,
+        },
+        Some('%') => (0..=text.len()).any(|skip| like_match(&pattern[1..], &text[skip..], escape)),
+        Some('_') => !text.is_empty() && like_match(&pattern[1..], &text[1..], escape),
+        Some(p) => match text.first() {
+            Some(t) => p.eq_ignore_ascii_case(t) && like_match(&pattern[1..], &text[1..], escape),
+            None => false,
+        },
+    }
+}
+
+// GLOB matching: * matches any run of characters, ? any single character
+// and [...] any character of a set, [^...] any character not in it. Case
+// matters.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| glob_match(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && glob_match(&pattern[1..], &text[1..]),
+        Some('[') => {
+            let Some(&t) = text.first() else { return false };
+            match glob_set(&pattern[1..], t) {
+                Some((matched, rest)) => matched && glob_match(rest, &text[1..]),
+                None => false,
+            }
+        }
+        Some(p) => text.first() == Some(p) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+// Matches a character against the set after a [, returning whether it
+// matched and the pattern after the closing ], None if the set is not closed.
+fn glob_set(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut i) = match pattern.first() {
+        Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let p = pattern[i];
+        if p == ']' && !first {
+            return Some((matched != negated, &pattern[i + 1..]));
+        }
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|end| *end != ']') {
+            matched |= p <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            matched |= p == c;
+            i += 1;
+        }
+        first = false;
+    }
+    None
+}
+
+// WHERE and CHECK treat NULL as not true.
+pub fn is_true(value: &Value) -> bool {
+    value.truthy() == Some(true)
+}
@@ -0,0 +1,285 @@
+use crate::executor::alter::{complete_row, stored_row};
+use crate::executor::dispatch::ResultSet;
+use crate::executor::dml::{delete_row, update_row};
+use crate::executor::expr::{eval, Scope};
+use crate::executor::index::index_lookup;
+use crate::schema::constraint::ConstraintViolation;
+use crate::schema::definition::{ForeignKey, TableSchema};
+use crate::sql::ast::ForeignKeyAction;
+use crate::table::btree::BTree;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::io::Error;
+
+// Foreign keys are checked from both sides. A child row needs a parent row
+// with the same key when it is inserted or its key changes, and deleting a
+// parent row or changing its key applies the ON DELETE / ON UPDATE action of
+// every foreign key referencing it. Both are skipped while the foreign_keys
+// pragma is off.
+
+// Fails if a foreign key of the row has no matching parent row. old_row is
+// the previous version of the row on UPDATE, keys that did not change are
+// not checked again.
+pub unsafe fn check_parent_rows(
+    table: &mut Table,
+    schema: &TableSchema,
+    old_row: Option<&[Value]>,
+    row: &[Value],
+) -> Result<(), Error> {
+    if !table.pragmas.foreign_keys {
+        return Ok(());
+    }
+    for key in &schema.foreign_keys {
+        let values = row_values(schema, &key.columns, row)?;
+        if values.iter().any(Value::is_null) {
+            continue;
+        }
+        if let Some(old_row) = old_row {
+            if values_equal(&row_values(schema, &key.columns, old_row)?, &values) {
+                continue;
+            }
+        }
+        // A row referencing itself is its own parent.
+        if key.references(&schema.name) {
+            let parent_columns = parent_columns(schema, key, &schema.name)?;
+            if values_equal(&row_values(schema, &parent_columns, row)?, &values) {
+                continue;
+            }
+        }
+        if !parent_row_exists(table, &schema.name, key, &values)? {
+            return Err(ConstraintViolation::ForeignKey.into());
+        }
+    }
+    Ok(())
+}
+
+// Fails if a parent row that is about to be deleted (new_row None) or to get
+// a new key still has children under a NO ACTION or RESTRICT foreign key.
+pub unsafe fn check_child_rows(
+    table: &mut Table,
+    parent: &TableSchema,
+    rowid: u32,
+    old_row: &[Value],
+    new_row: Option<&[Value]>,
+) -> Result<(), Error> {
+    for (child, key, old_values, _) in referencing_keys(table, parent, old_row, new_row)? {
+        let action = if new_row.is_some() { key.on_update } else { key.on_delete };
+        if action != ForeignKeyAction::NoAction && action != ForeignKeyAction::Restrict {
+            continue;
+        }
+        let children = child_rows(table, &child, &key, &old_values)?;
+        if children.iter().any(|(child_rowid, _)| !is_same_row(parent, &child, rowid, *child_rowid)) {
+            return Err(ConstraintViolation::ForeignKey.into());
+        }
+    }
+    Ok(())
+}
+
+// Applies the CASCADE, SET NULL and SET DEFAULT actions to the children of a
+// parent row that was just deleted (new_row None) or got a new key.
+pub unsafe fn apply_child_actions(
+    table: &mut Table,
+    parent: &TableSchema,
+    old_row: &[Value],
+    new_row: Option<&[Value]>,
+) -> Result<(), Error> {
+    for (child, key, old_values, new_values) in referencing_keys(table, parent, old_row, new_row)? {
+        let action = if new_row.is_some() { key.on_update } else { key.on_delete };
+        let indexes = table.catalog.table_indexes(&child.name);
+        for (child_rowid, _) in child_rows(table, &child, &key, &old_values)? {
+            // An earlier action may have changed or removed the row.
+            let child_row = match stored_row(table, &child, child_rowid)? {
+                Some(row) => row,
+                None => continue,
+            };
+            let replacement = match (action, &new_values) {
+                (ForeignKeyAction::Cascade, None) => {
+                    delete_row(table, &child, &indexes, child_rowid, &child_row)?;
+                    continue;
+                }
+                (ForeignKeyAction::Cascade, Some(new_values)) => new_values.clone(),
+                (ForeignKeyAction::SetNull, _) => vec![Value::Null; key.columns.len()],
+                (ForeignKeyAction::SetDefault, _) => key
+                    .columns
+                    .iter()
+                    .map(|name| {
+                        let column = &child.columns[child.column_index(name)?];
+                        match &column.default {
+                            Some(default) => Ok(column.affinity.apply(eval(default, &Scope::new(&table.functions, &[], &[]))?)),
+                            None => Ok(Value::Null),
+                        }
+                    })
+                    .collect::<Result<Vec<Value>, Error>>()?,
+                (ForeignKeyAction::NoAction, _) | (ForeignKeyAction::Restrict, _) => continue,
+            };
+            let mut updated_row = child_row.clone();
+            for (name, value) in key.columns.iter().zip(replacement) {
+                updated_row[child.column_index(name)?] = value;
+            }
+            update_row(table, &child, &indexes, child_rowid, &child_row, updated_row)?;
+        }
+    }
+    Ok(())
+}
+
+// Lists the rows whose foreign keys have no parent row, as PRAGMA
+// foreign_key_check does: the child table, the rowid, the parent table and
+// the position of the foreign key in the child table.
+pub unsafe fn foreign_key_check(table: &mut Table, table_name: Option<&str>) -> Result<ResultSet, Error> {
+    let schemas = match table_name {
+        Some(name) => vec![table.catalog.get_table(name)?.clone()],
+        None => table.catalog.tables.clone(),
+    };
+    let mut rows = Vec::new();
+    for schema in &schemas {
+        let mut scan = BTree::new(schema.root_page_num).scan(table);
+        while let Some((rowid, record)) = scan.next(table) {
+            let mut row = record.values;
+            complete_row(schema, &mut row)?;
+            for (fkid, key) in schema.foreign_keys.iter().enumerate() {
+                let values = row_values(schema, &key.columns, &row)?;
+                if values.iter().any(Value::is_null) || parent_row_exists(table, &schema.name, key, &values)? {
+                    continue;
+                }
+                rows.push(vec![
+                    Value::Text(schema.name.clone()),
+                    Value::Integer(rowid as i64),
+                    Value::Text(key.parent_table.clone()),
+                    Value::Integer(fkid as i64),
+                ]);
+            }
+        }
+    }
+    Ok(ResultSet {
+        columns: vec!["table".to_string(), "rowid".to_string(), "parent".to_string(), "fkid".to_string()],
+        rows,
+    })
+}
+
+// Foreign keys referencing a parent row whose key is affected by the change,
+// with the old and new parent key values. Nothing when the pragma is off.
+#[allow(clippy::type_complexity)]
+fn referencing_keys(
+    table: &Table,
+    parent: &TableSchema,
+    old_row: &[Value],
+    new_row: Option<&[Value]>,
+) -> Result<Vec<(TableSchema, ForeignKey, Vec<Value>, Option<Vec<Value>>)>, Error> {
+    let mut keys = Vec::new();
+    if !table.pragmas.foreign_keys {
+        return Ok(keys);
+    }
+    for child in &table.catalog.tables {
+        for key in child.foreign_keys.iter().filter(|key| key.references(&parent.name)) {
+            let columns = parent_columns(parent, key, &child.name)?;
+            let old_values = row_values(parent, &columns, old_row)?;
+            if old_values.iter().any(Value::is_null) {
+                continue;
+            }
+            let new_values = match new_row {
+                Some(new_row) => {
+                    let new_values = row_values(parent, &columns, new_row)?;
+                    if values_equal(&old_values, &new_values) {
+                        continue;
+                    }
+                    Some(new_values)
+                }
+                None => None,
+            };
+            keys.push((child.clone(), key.clone(), old_values, new_values));
+        }
+    }
+    Ok(keys)
+}
+
+unsafe fn parent_row_exists(table: &mut Table, child_name: &str, key: &ForeignKey, values: &[Value]) -> Result<bool, Error> {
+    let parent = match table.catalog.table(&key.parent_table) {
+        Some(parent) => parent.clone(),
+        None => return Ok(false),
+    };
+    let columns = parent_columns(&parent, key, child_name)?;
+    let values: Vec<Value> = columns
+        .iter()
+        .zip(values)
+        .map(|(name, value)| Ok(parent.columns[parent.column_index(name)?].affinity.apply(value.clone())))
+        .collect::<Result<_, Error>>()?;
+    if let (Some(i), [name]) = (parent.rowid_column, columns.as_slice()) {
+        if parent.columns[i].name.eq_ignore_ascii_case(name) {
+            return Ok(match values[0] {
+                Value::Integer(rowid) if rowid >= 0 && rowid <= u32::MAX as i64 => {
+                    BTree::new(parent.root_page_num).contains(table, rowid as u32)
+                }
+                _ => false,
+            });
+        }
+    }
+    let index = table
+        .catalog
+        .table_indexes(&parent.name)
+        .into_iter()
+        .find(|index| index.unique && same_columns(&index.columns, &columns))
+        .ok_or_else(|| mismatch(child_name, &parent.name))?;
+    let mut key_values = Vec::with_capacity(values.len());
+    for name in &index.columns {
+        let position = columns.iter().position(|column| column.eq_ignore_ascii_case(name)).unwrap();
+        key_values.push(values[position].clone());
+    }
+    Ok(!index_lookup(table, &index, &key_values).is_empty())
+}
+
+// Rows of the child table whose foreign key equals the parent key values.
+unsafe fn child_rows(
+    table: &mut Table,
+    child: &TableSchema,
+    key: &ForeignKey,
+    parent_values: &[Value],
+) -> Result<Vec<(u32, Vec<Value>)>, Error> {
+    let mut rows = Vec::new();
+    let mut scan = BTree::new(child.root_page_num).scan(table);
+    while let Some((rowid, record)) = scan.next(table) {
+        let mut row = record.values;
+        complete_row(child, &mut row)?;
+        if values_equal(&row_values(child, &key.columns, &row)?, parent_values) {
+            rows.push((rowid, row));
+        }
+    }
+    Ok(rows)
+}
+
+// The parent columns a foreign key refers to, its primary key when the
+// REFERENCES clause does not name them.
+fn parent_columns(parent: &TableSchema, key: &ForeignKey, child_name: &str) -> Result<Vec<String>, Error> {
+    let columns = if key.parent_columns.is_empty() {
+        parent.primary_key_columns()
+    } else {
+        key.parent_columns.clone()
+    };
+    if columns.len() != key.columns.len() || columns.iter().any(|name| parent.column_index(name).is_err()) {
+        return Err(mismatch(child_name, &parent.name));
+    }
+    Ok(columns)
+}
+
+fn row_values(schema: &TableSchema, columns: &[String], row: &[Value]) -> Result<Vec<Value>, Error> {
+    columns
+        .iter()
+        .map(|name| Ok(row[schema.column_index(name)?].clone()))
+        .collect()
+}
+
+fn values_equal(a: &[Value], b: &[Value]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.compare(b) == Ordering::Equal)
+}
+
+pub fn same_columns(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().all(|name| b.iter().any(|other| other.eq_ignore_ascii_case(name)))
+}
+
+fn is_same_row(parent: &TableSchema, child: &TableSchema, rowid: u32, child_rowid: u32) -> bool {
+    parent.name.eq_ignore_ascii_case(&child.name) && rowid == child_rowid
+}
+
+fn mismatch(child_name: &str, parent_name: &str) -> Error {
+    Error::other(format!("foreign key mismatch - \"{}\" referencing \"{}\"", child_name, parent_name))
+}
@@ -0,0 +1,387 @@
+use crate::executor::datetime::{date, datetime, julianday, strftime, time, unixepoch};
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Error;
+use std::rc::Rc;
+
+// A built-in scalar function. Arguments are evaluated before the call, so
+// a function sees plain values. max_args is None for variadic functions.
+pub struct ScalarFunction {
+    pub name: &'static str,
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+    pub call: fn(&[Value]) -> Result<Value, Error>,
+}
+
+const fn function(
+    name: &'static str,
+    min_args: usize,
+    max_args: Option<usize>,
+    call: fn(&[Value]) -> Result<Value, Error>,
+) -> ScalarFunction {
+    ScalarFunction {
+        name,
+        min_args,
+        max_args,
+        call,
+    }
+}
+
+static BUILTINS: [ScalarFunction; 24] = [
+    function("lower", 1, Some(1), lower),
+    function("upper", 1, Some(1), upper),
+    function("length", 1, Some(1), length),
+    function("substr", 2, Some(3), substr),
+    function("substring", 2, Some(3), substr),
+    function("trim", 1, Some(2), trim),
+    function("ltrim", 1, Some(2), ltrim),
+    function("rtrim", 1, Some(2), rtrim),
+    function("replace", 3, Some(3), replace),
+    function("instr", 2, Some(2), instr),
+    function("abs", 1, Some(1), abs),
+    function("round", 1, Some(2), round),
+    // min and max with a single argument are the aggregates.
+    function("min", 2, None, min),
+    function("max", 2, None, max),
+    function("coalesce", 2, None, coalesce),
+    function("ifnull", 2, Some(2), coalesce),
+    function("nullif", 2, Some(2), nullif),
+    function("typeof", 1, Some(1), type_of),
+    function("date", 0, None, date),
+    function("time", 0, None, time),
+    function("datetime", 0, None, datetime),
+    function("strftime", 1, None, strftime),
+    function("unixepoch", 0, None, unixepoch),
+    function("julianday", 0, None, julianday),
+];
+
+pub fn find_function(name: &str) -> Option<&'static ScalarFunction> {
+    BUILTINS.iter().find(|function| function.name.eq_ignore_ascii_case(name))
+}
+
+impl ScalarFunction {
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        check_arity(name, args.len(), self.min_args, self.max_args)?;
+        (self.call)(args)
+    }
+}
+
+pub fn check_arity(name: &str, num_args: usize, min_args: usize, max_args: Option<usize>) -> Result<(), Error> {
+    if num_args < min_args || max_args.is_some_and(|max| num_args > max) {
+        return Err(Error::other(format!("wrong number of arguments to function {}()", name)));
+    }
+    Ok(())
+}
+
+// Functions registered by the embedding application on an open database.
+// They are looked up before the built-ins, so they can replace them. The
+// argument count of a call is checked against num_args, None accepting any
+// number of arguments. The values bound to the parameters of the statement
+// running are kept here too, every scope expressions are evaluated in
+// reaching them.
+#[derive(Clone, Default)]
+pub struct Functions {
+    scalars: HashMap<String, UserScalar>,
+    aggregates: HashMap<String, UserAggregate>,
+    parameters: Vec<Value>,
+}
+
+type ScalarCall = dyn Fn(&[Value]) -> Result<Value, Error>;
+type StepCall<S> = dyn Fn(&mut S, &[Value]) -> Result<(), Error>;
+type FinalizeCall<S> = dyn Fn(S) -> Result<Value, Error>;
+
+#[derive(Clone)]
+pub struct UserScalar {
+    num_args: Option<usize>,
+    call: Rc<ScalarCall>,
+}
+
+#[derive(Clone)]
+pub struct UserAggregate {
+    num_args: Option<usize>,
+    init: Rc<dyn Fn() -> Box<dyn AggregateState>>,
+}
+
+// The running state of a user aggregate for one group.
+pub trait AggregateState {
+    fn step(&mut self, args: &[Value]) -> Result<(), Error>;
+    fn finalize(self: Box<Self>) -> Result<Value, Error>;
+}
+
+// Aggregate state built from the init, step and finalize closures.
+struct ClosureState<S> {
+    state: S,
+    step: Rc<StepCall<S>>,
+    finalize: Rc<FinalizeCall<S>>,
+}
+
+impl<S> AggregateState for ClosureState<S> {
+    fn step(&mut self, args: &[Value]) -> Result<(), Error> {
+        (self.step)(&mut self.state, args)
+    }
+
+    fn finalize(self: Box<Self>) -> Result<Value, Error> {
+        (self.finalize)(self.state)
+    }
+}
+
+impl Functions {
+    pub fn register_scalar(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        call: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        let name = name.to_ascii_lowercase();
+        self.aggregates.remove(&name);
+        self.scalars.insert(
+            name,
+            UserScalar {
+                num_args,
+                call: Rc::new(call),
+            },
+        );
+    }
+
+    pub fn register_aggregate<S: 'static>(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        init: impl Fn() -> S + 'static,
+        step: impl Fn(&mut S, &[Value]) -> Result<(), Error> + 'static,
+        finalize: impl Fn(S) -> Result<Value, Error> + 'static,
+    ) {
+        let name = name.to_ascii_lowercase();
+        let step: Rc<StepCall<S>> = Rc::new(step);
+        let finalize: Rc<FinalizeCall<S>> = Rc::new(finalize);
+        let init = move || -> Box<dyn AggregateState> {
+            Box::new(ClosureState {
+                state: init(),
+                step: step.clone(),
+                finalize: finalize.clone(),
+            })
+        };
+        self.scalars.remove(&name);
+        self.aggregates.insert(
+            name,
+            UserAggregate {
+                num_args,
+                init: Rc::new(init),
+            },
+        );
+    }
+
+    pub fn scalar(&self, name: &str) -> Option<&UserScalar> {
+        self.scalars.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn aggregate(&self, name: &str) -> Option<&UserAggregate> {
+        self.aggregates.get(&name.to_ascii_lowercase())
+    }
+
+    // Binds values to the parameters, the first one to parameter 1. Returns
+    // the values bound before.
+    pub fn bind_parameters(&mut self, values: Vec<Value>) -> Vec<Value> {
+        std::mem::replace(&mut self.parameters, values)
+    }
+
+    // The value bound to parameter index, NULL when there is none.
+    pub fn parameter(&self, index: usize) -> Value {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.parameters.get(i))
+            .cloned()
+            .unwrap_or(Value::Null)
+    }
+}
+
+impl UserScalar {
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        check_arity(name, args.len(), self.num_args.unwrap_or(0), self.num_args)?;
+        (self.call)(args)
+    }
+}
+
+impl UserAggregate {
+    pub fn check_arity(&self, name: &str, num_args: usize) -> Result<(), Error> {
+        check_arity(name, num_args, self.num_args.unwrap_or(0), self.num_args)
+    }
+
+    pub fn init(&self) -> Box<dyn AggregateState> {
+        (self.init)()
+    }
+}
+
+fn lower(args: &[Value]) -> Result<Value, Error> {
+    Ok(map_text(&args[0], |text| text.to_ascii_lowercase()))
+}
+
+fn upper(args: &[Value]) -> Result<Value, Error> {
+    Ok(map_text(&args[0], |text| text.to_ascii_uppercase()))
+}
+
+fn length(args: &[Value]) -> Result<Value, Error> {
+    Ok(match &args[0] {
+        Value::Null => Value::Null,
+        value => Value::Integer(value.to_text().chars().count() as i64),
+    })
+}
+
+// substr(X, Y, Z) as in sqlite: Y counts characters from 1, or from the end
+// when negative, and a negative Z takes the characters before Y.
+fn substr(args: &[Value]) -> Result<Value, Error> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let chars: Vec<char> = args[0].to_text().chars().collect();
+    let len = chars.len() as i64;
+    let mut start = args[1].to_numeric().as_i64().unwrap_or(0);
+    let (mut count, negative) = match args.get(2) {
+        Some(count) => {
+            let count = count.to_numeric().as_i64().unwrap_or(0);
+            (count.saturating_abs(), count < 0)
+        }
+        None => (i64::MAX / 2, false),
+    };
+    if start < 0 {
+        start += len;
+        if start < 0 {
+            count = (count + start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if count > 0 {
+        count -= 1;
+    }
+    if negative {
+        start -= count;
+        if start < 0 {
+            count += start;
+            start = 0;
+        }
+    }
+    let start = start.min(len);
+    let end = start.saturating_add(count.max(0)).min(len);
+    Ok(Value::Text(chars[start as usize..end as usize].iter().collect()))
+}
+
+fn trim(args: &[Value]) -> Result<Value, Error> {
+    trim_with(args, |text, set| text.trim_matches(|c| set.contains(&c)).to_string())
+}
+
+fn ltrim(args: &[Value]) -> Result<Value, Error> {
+    trim_with(args, |text, set| text.trim_start_matches(|c| set.contains(&c)).to_string())
+}
+
+fn rtrim(args: &[Value]) -> Result<Value, Error> {
+    trim_with(args, |text, set| text.trim_end_matches(|c| set.contains(&c)).to_string())
+}
+
+// Removes the characters of the second argument, spaces by default.
+fn trim_with(args: &[Value], f: fn(&str, &[char]) -> String) -> Result<Value, Error> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let set: Vec<char> = match args.get(1) {
+        Some(set) => set.to_text().chars().collect(),
+        None => vec![' '],
+    };
+    Ok(Value::Text(f(&args[0].to_text(), &set)))
+}
+
+fn replace(args: &[Value]) -> Result<Value, Error> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let (text, from, to) = (args[0].to_text(), args[1].to_text(), args[2].to_text());
+    if from.is_empty() {
+        return Ok(Value::Text(text));
+    }
+    Ok(Value::Text(text.replace(&from, &to)))
+}
+
+// Position of the first occurrence of Y in X counted in characters from 1, 0
+// when there is none.
+fn instr(args: &[Value]) -> Result<Value, Error> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let (text, needle) = (args[0].to_text(), args[1].to_text());
+    Ok(Value::Integer(match text.find(&needle) {
+        Some(byte) => text[..byte].chars().count() as i64 + 1,
+        None => 0,
+    }))
+}
+
+fn abs(args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Null => Ok(Value::Null),
+        Value::Integer(i) => i
+            .checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| Error::other("integer overflow")),
+        value => Ok(Value::Real(value.as_f64().unwrap_or(0.0).abs())),
+    }
+}
+
+// Rounds half away from zero to the given number of digits after the point,
+// always giving a real.
+fn round(args: &[Value]) -> Result<Value, Error> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let number = args[0].to_numeric().as_f64().unwrap_or(0.0);
+    let digits = match args.get(1) {
+        Some(digits) => digits.to_numeric().as_i64().unwrap_or(0).clamp(0, 30) as i32,
+        None => 0,
+    };
+    let scale = 10f64.powi(digits);
+    let rounded = (number * scale).round() / scale;
+    Ok(Value::Real(if rounded.is_finite() { rounded } else { number }))
+}
+
+fn min(args: &[Value]) -> Result<Value, Error> {
+    Ok(extreme(args, Ordering::Less))
+}
+
+fn max(args: &[Value]) -> Result<Value, Error> {
+    Ok(extreme(args, Ordering::Greater))
+}
+
+// The smallest or largest argument, NULL if any of them is NULL.
+fn extreme(args: &[Value], wanted: Ordering) -> Value {
+    if args.iter().any(Value::is_null) {
+        return Value::Null;
+    }
+    let mut best = &args[0];
+    for value in &args[1..] {
+        if value.compare(best) == wanted {
+            best = value;
+        }
+    }
+    best.clone()
+}
+
+fn coalesce(args: &[Value]) -> Result<Value, Error> {
+    Ok(args.iter().find(|value| !value.is_null()).cloned().unwrap_or(Value::Null))
+}
+
+fn nullif(args: &[Value]) -> Result<Value, Error> {
+    if !args[0].is_null() && args[0].compare(&args[1]) == Ordering::Equal {
+        return Ok(Value::Null);
+    }
+    Ok(args[0].clone())
+}
+
+fn type_of(args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Text(args[0].type_name().to_string()))
+}
+
+fn map_text(value: &Value, f: impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        value => Value::Text(f(&value.to_text())),
+    }
+}
@@ -0,0 +1,145 @@
+use crate::executor::compound::row_key;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::table::btree::BTree;
+use crate::table::record::{encode_value, Record};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io::Error;
+
+// Indexes are trees holding one entry per indexed row: its key values
+// followed by its rowid. An entry is stored under a hash of its key values,
+// or when that key is taken under the first free key after it, so the
+// entries of a key are found by reading the keys from its hash on until one
+// is missing. A removed entry followed by others is left as an empty record
+// to keep them reachable. Rows with a NULL in the key are not indexed, so
+// UNIQUE allows any number of NULLs.
+
+pub fn index_key_values(index: &IndexSchema, schema: &TableSchema, row: &[Value]) -> Result<Vec<Value>, Error> {
+    index
+        .columns
+        .iter()
+        .map(|name| Ok(row[schema.column_index(name)?].clone()))
+        .collect()
+}
+
+pub unsafe fn index_lookup(table: &mut Table, index: &IndexSchema, key_values: &[Value]) -> Vec<u32> {
+    let Some(hash) = index_hash(key_values) else { return Vec::new() };
+    let mut rowids = Vec::new();
+    for (_, record) in probe(table, index, hash) {
+        if let Some((values, rowid)) = entry(&record, key_values.len()) {
+            if keys_equal(values, key_values) {
+                rowids.push(rowid);
+            }
+        }
+    }
+    rowids
+}
+
+pub unsafe fn index_insert(table: &mut Table, index: &IndexSchema, key_values: &[Value], rowid: u32) -> Result<(), Error> {
+    let Some(hash) = index_hash(key_values) else { return Ok(()) };
+    let mut values = key_values.to_vec();
+    values.push(Value::Integer(rowid as i64));
+    let record = Record::new(values);
+    let run = probe(table, index, hash);
+    let tree = BTree::new(index.root_page_num);
+    match run.iter().find(|(_, record)| record.values.is_empty()) {
+        Some((key, _)) => tree.update(table, *key, &record),
+        None => {
+            let key = hash
+                .checked_add(run.len() as u32)
+                .ok_or_else(|| Error::other("Index is full"))?;
+            tree.insert(table, key, &record)
+        }
+    }
+}
+
+pub unsafe fn index_remove(table: &mut Table, index: &IndexSchema, key_values: &[Value], rowid: u32) -> Result<(), Error> {
+    let Some(hash) = index_hash(key_values) else { return Ok(()) };
+    let run = probe(table, index, hash);
+    let position = run.iter().position(|(_, record)| {
+        entry(record, key_values.len()).is_some_and(|(values, entry_rowid)| entry_rowid == rowid && keys_equal(values, key_values))
+    });
+    let Some(position) = position else { return Ok(()) };
+    let tree = BTree::new(index.root_page_num);
+    if position + 1 < run.len() {
+        return tree.update(table, run[position].0, &Record::new(Vec::new()));
+    }
+    // The last entry of the run, it goes with the empty records before it.
+    tree.delete(table, run[position].0);
+    for (key, record) in run[..position].iter().rev() {
+        if !record.values.is_empty() {
+            break;
+        }
+        tree.delete(table, *key);
+    }
+    Ok(())
+}
+
+// The rowid of one entry of every key in the index, in the order of the
+// index tree. The entries of a key are all in the run of consecutive tree
+// keys holding its hash, so only the keys of the run being read are kept to
+// tell whether an entry's key was seen.
+pub unsafe fn index_distinct_rowids(table: &mut Table, index: &IndexSchema) -> Vec<u32> {
+    let mut rowids = Vec::new();
+    let mut run_keys = HashSet::new();
+    let mut next_key = None;
+    let mut scan = BTree::new(index.root_page_num).scan(table);
+    while let Some((key, record)) = scan.next(table) {
+        if next_key != Some(key) {
+            run_keys.clear();
+        }
+        next_key = key.checked_add(1);
+        if let Some((values, rowid)) = entry(&record, index.columns.len()) {
+            if run_keys.insert(row_key(values)) {
+                rowids.push(rowid);
+            }
+        }
+    }
+    rowids
+}
+
+// The records under the consecutive keys starting at hash.
+unsafe fn probe(table: &mut Table, index: &IndexSchema, hash: u32) -> Vec<(u32, Record)> {
+    let mut run = Vec::new();
+    let mut scan = BTree::new(index.root_page_num).scan_from(table, hash);
+    while let Some((key, record)) = scan.next(table) {
+        if key != hash.wrapping_add(run.len() as u32) {
+            break;
+        }
+        run.push((key, record));
+    }
+    run
+}
+
+// The key values and rowid of an entry, None for a removed one.
+fn entry(record: &Record, key_len: usize) -> Option<(&[Value], u32)> {
+    if record.values.len() != key_len + 1 {
+        return None;
+    }
+    let rowid = record.values[key_len].as_i64().unwrap_or(0) as u32;
+    Some((&record.values[..key_len], rowid))
+}
+
+fn keys_equal(a: &[Value], b: &[Value]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a.compare(b) == Ordering::Equal)
+}
+
+// FNV-1a hash of the encoded key values, None if any of them is NULL. The
+// top bit is cleared to leave room for the keys after it.
+fn index_hash(key_values: &[Value]) -> Option<u32> {
+    let mut bytes = Vec::new();
+    for value in key_values {
+        if value.is_null() {
+            return None;
+        }
+        encode_value(&value.clone().normalized(), &mut bytes);
+    }
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    Some(hash & 0x7fff_ffff)
+}
@@ -0,0 +1,231 @@
+use crate::executor::alter::{complete_row, stored_row};
+use crate::executor::cte::find_common_table;
+use crate::executor::expr::{eval, is_true, ColumnBinding, Scope};
+use crate::executor::function::Functions;
+use crate::executor::planner::{key_value, plan_joins, table_label, Access, PlanTable};
+use crate::executor::query::{execute_select, stored_bindings};
+use crate::executor::view::execute_view;
+use crate::schema::definition::TableSchema;
+use crate::sql::ast::{Expr, Join, TableRef};
+use crate::table::btree::BTree;
+use crate::table::record::encode_value;
+use crate::table::table::Table;
+use crate::table::value::{Affinity, Value};
+use std::collections::HashMap;
+use std::io::Error;
+use std::iter;
+
+// The planner decides the order the tables of a FROM clause are joined in
+// and how the rows of each table matching the rows joined before it are
+// found: by seeking the table tree or an index, by probing a hash table
+// built from the table's rows, or by a nested loop over all of them. The
+// conditions of the step are checked on every candidate either way. The
+// columns of the joined rows are then put back in FROM clause order.
+
+// How the rows of a table for the rows joined before it are found.
+enum Strategy {
+    NestedLoop(Vec<Vec<Value>>),
+    Seek(TableSchema, Access),
+    // The buckets, the value sought and the affinity of the hashed column.
+    HashLookup(HashMap<Vec<u8>, Vec<Vec<Value>>>, Expr, Option<Affinity>),
+}
+
+// A table of the FROM clause: a stored table, or rows computed before the
+// join, those of a subquery or view run once or of a common table.
+enum Relation {
+    Stored(TableSchema),
+    Derived(Vec<Vec<Value>>),
+}
+
+impl Relation {
+    unsafe fn resolve(table: &mut Table, table_ref: &TableRef) -> Result<(Relation, Vec<ColumnBinding>), Error> {
+        match &table_ref.subquery {
+            Some(select) => {
+                let result = execute_select(table, select)?;
+                let bindings = result
+                    .columns
+                    .iter()
+                    .map(|name| ColumnBinding::new(table_ref.alias.as_deref(), name))
+                    .collect();
+                Ok((Relation::Derived(result.rows), bindings))
+            }
+            None => {
+                if let Some(common_table) = find_common_table(table, &table_ref.name) {
+                    let alias = table_ref.alias.as_deref().unwrap_or(&table_ref.name);
+                    let bindings = common_table
+                        .columns
+                        .iter()
+                        .map(|name| ColumnBinding::new(Some(alias), name))
+                        .collect();
+                    return Ok((Relation::Derived(common_table.rows()), bindings));
+                }
+                if let Some(view) = table.catalog.view(&table_ref.name).cloned() {
+                    let result = execute_view(table, &view)?;
+                    let alias = table_ref.alias.as_deref().unwrap_or(&view.name);
+                    let bindings = result
+                        .columns
+                        .iter()
+                        .map(|name| ColumnBinding::new(Some(alias), name))
+                        .collect();
+                    return Ok((Relation::Derived(result.rows), bindings));
+                }
+                let schema = table.catalog.get_table(&table_ref.name)?.clone();
+                let bindings = stored_bindings(&schema, table_ref.alias.as_deref().unwrap_or(&schema.name));
+                Ok((Relation::Stored(schema), bindings))
+            }
+        }
+    }
+
+    unsafe fn into_rows(self, table: &mut Table) -> Result<Vec<Vec<Value>>, Error> {
+        match self {
+            Relation::Stored(schema) => table_rows(table, &schema),
+            Relation::Derived(rows) => Ok(rows),
+        }
+    }
+}
+
+// The rows of the FROM clause with the bindings of their columns, the
+// columns of every table one after the other.
+pub unsafe fn join_rows(
+    table: &mut Table,
+    from: &TableRef,
+    joins: &[Join],
+    where_clause: Option<&Expr>,
+) -> Result<(Vec<ColumnBinding>, Vec<Vec<Value>>), Error> {
+    let mut relations = Vec::new();
+    let mut plan_tables = Vec::new();
+    for table_ref in iter::once(from).chain(joins.iter().map(|join| &join.table)) {
+        let (relation, bindings) = Relation::resolve(table, table_ref)?;
+        plan_tables.push(match &relation {
+            Relation::Stored(schema) => PlanTable::stored(table, table_label(table_ref), schema, bindings),
+            Relation::Derived(_) => PlanTable::derived(table_label(table_ref), bindings),
+        });
+        relations.push(Some(relation));
+    }
+    let steps = plan_joins(&table.functions, &plan_tables, joins, where_clause);
+    let mut bindings = Vec::new();
+    let mut rows = vec![Vec::new()];
+    for step in &steps {
+        let relation = relations[step.relation].take().expect("table joined twice");
+        let strategy = Strategy::new(table, relation, &step.access, &plan_tables[step.relation].bindings)?;
+        let outer_len = bindings.len();
+        bindings.extend(plan_tables[step.relation].bindings.iter().cloned());
+        let mut joined = Vec::new();
+        for outer_row in rows {
+            let candidates = inner_candidates(table, &strategy, &bindings[..outer_len], &outer_row)?;
+            let mut matched = false;
+            for inner_row in candidates {
+                let mut row = outer_row.clone();
+                row.extend(inner_row);
+                if !passes(&table.functions, &step.filters, &bindings, &row)? {
+                    continue;
+                }
+                matched = true;
+                joined.push(row);
+            }
+            if !matched && step.left {
+                let mut row = outer_row;
+                row.resize(bindings.len(), Value::Null);
+                joined.push(row);
+            }
+        }
+        rows = joined;
+    }
+    // The columns of each table start where the tables joined before it end.
+    let mut starts = vec![0; plan_tables.len()];
+    let mut start = 0;
+    for step in &steps {
+        starts[step.relation] = start;
+        start += plan_tables[step.relation].bindings.len();
+    }
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            plan_tables
+                .iter()
+                .zip(&starts)
+                .flat_map(|(plan_table, start)| row[*start..*start + plan_table.bindings.len()].iter().cloned())
+                .collect()
+        })
+        .collect();
+    let bindings = plan_tables.into_iter().flat_map(|plan_table| plan_table.bindings).collect();
+    Ok((bindings, rows))
+}
+
+unsafe fn table_rows(table: &mut Table, schema: &TableSchema) -> Result<Vec<Vec<Value>>, Error> {
+    let mut rows = Vec::new();
+    let mut scan = BTree::new(schema.root_page_num).scan(table);
+    while let Some((rowid, record)) = scan.next(table) {
+        let mut values = record.values;
+        complete_row(schema, &mut values)?;
+        values.push(Value::Integer(rowid as i64));
+        rows.push(values);
+    }
+    Ok(rows)
+}
+
+impl Strategy {
+    unsafe fn new(table: &mut Table, relation: Relation, access: &Access, bindings: &[ColumnBinding]) -> Result<Strategy, Error> {
+        Ok(match (relation, access) {
+            (Relation::Stored(schema), Access::RowidSeek(_) | Access::IndexSeek(..)) => Strategy::Seek(schema, access.clone()),
+            (relation, Access::HashLookup(column, outer)) => {
+                let mut buckets: HashMap<Vec<u8>, Vec<Vec<Value>>> = HashMap::new();
+                for row in relation.into_rows(table)? {
+                    if let Some(key) = hash_key(&row[*column]) {
+                        buckets.entry(key).or_default().push(row);
+                    }
+                }
+                Strategy::HashLookup(buckets, outer.clone(), bindings[*column].affinity)
+            }
+            (relation, _) => Strategy::NestedLoop(relation.into_rows(table)?),
+        })
+    }
+}
+
+fn passes(functions: &Functions, filters: &[Expr], bindings: &[ColumnBinding], row: &[Value]) -> Result<bool, Error> {
+    let scope = Scope::new(functions, bindings, row);
+    for filter in filters {
+        if !is_true(&eval(filter, &scope)?) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+unsafe fn inner_candidates(
+    table: &mut Table,
+    strategy: &Strategy,
+    outer_bindings: &[ColumnBinding],
+    outer_row: &[Value],
+) -> Result<Vec<Vec<Value>>, Error> {
+    Ok(match strategy {
+        Strategy::NestedLoop(rows) => rows.clone(),
+        Strategy::Seek(schema, access) => {
+            let mut rows = Vec::new();
+            for rowid in access.rowids(table, outer_bindings, outer_row)? {
+                if let Some(mut values) = stored_row(table, schema, rowid)? {
+                    values.push(Value::Integer(rowid as i64));
+                    rows.push(values);
+                }
+            }
+            rows
+        }
+        Strategy::HashLookup(buckets, outer, affinity) => {
+            let scope = Scope::new(&table.functions, outer_bindings, outer_row);
+            match hash_key(&key_value(*affinity, outer, &scope)?) {
+                Some(key) => buckets.get(&key).cloned().unwrap_or_default(),
+                None => Vec::new(),
+            }
+        }
+    })
+}
+
+// Values that compare equal have the same key. NULL equals nothing.
+fn hash_key(value: &Value) -> Option<Vec<u8>> {
+    if value.is_null() {
+        return None;
+    }
+    let mut key = Vec::new();
+    encode_value(&value.clone().normalized(), &mut key);
+    Some(key)
+}
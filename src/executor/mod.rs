@@ -0,0 +1,23 @@
+pub mod aggregate;
+pub mod alter;
+pub mod analyze;
+pub mod compound;
+pub mod cte;
+pub mod datetime;
+pub mod dispatch;
+pub mod dml;
+pub mod explain;
+pub mod expr;
+pub mod foreign_key;
+pub mod function;
+pub mod index;
+pub mod join;
+pub mod planner;
+pub mod pragma;
+pub mod query;
+pub mod references;
+pub mod sort;
+pub mod subquery;
+pub mod trigger;
+pub mod view;
+pub mod window;
@@ -0,0 +1,509 @@
+use crate::executor::expr::{comparison_affinity, eval, ColumnBinding, Scope};
+use crate::executor::function::Functions;
+use crate::executor::index::index_lookup;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::schema::statistics::{IndexStats, TableStats};
+use crate::sql::ast::{BinaryOp, Expr, Join, JoinKind, TableRef};
+use crate::table::table::Table;
+use crate::table::value::{Affinity, Value};
+use std::io::Error;
+
+// The planner decides how the rows of each table of a FROM clause are found
+// and in which order the tables are joined. A table is read by a full scan
+// unless a condition of the form column = value, with a value known before
+// the table is read, lets it seek: its INTEGER PRIMARY KEY seeks the table
+// tree, the columns of an index look up the index. Failing those, any other
+// column of a table joined to others probes a hash table of its rows. The
+// conditions are the top level AND terms of the ON and WHERE clauses. Other
+// conditions, ranges like id > 2 and lists like id IN (1, 2) included, never
+// make a table seek: it is scanned and they are checked on each row.
+//
+// Every order of the tables is costed by trying each table first and adding
+// the cheapest next table until all are joined. The costs count the pages
+// and rows read, estimated from the statistics ANALYZE stores. A table never
+// analyzed is assumed to have DEFAULT_ROWS rows and DEFAULT_KEY_ROWS of them
+// to share a value, half as many for each further column of an index sought.
+// With a LEFT JOIN the tables are joined in the order they are written.
+
+const DEFAULT_ROWS: f64 = 1000.0;
+const DEFAULT_KEY_ROWS: f64 = 10.0;
+
+// A table of a FROM clause as the planner sees it.
+pub struct PlanTable {
+    // The table as a plan shows it, t or t AS a.
+    pub label: String,
+    // The stored table, None for rows computed before the join.
+    pub schema: Option<TableSchema>,
+    pub indexes: Vec<IndexSchema>,
+    pub bindings: Vec<ColumnBinding>,
+    pub stats: Option<TableStats>,
+}
+
+impl PlanTable {
+    pub fn stored(table: &Table, label: String, schema: &TableSchema, bindings: Vec<ColumnBinding>) -> PlanTable {
+        PlanTable {
+            label,
+            schema: Some(schema.clone()),
+            indexes: table.catalog.table_indexes(&schema.name),
+            bindings,
+            stats: table.catalog.statistics(&schema.name).cloned(),
+        }
+    }
+
+    pub fn derived(label: String, bindings: Vec<ColumnBinding>) -> PlanTable {
+        PlanTable {
+            label,
+            schema: None,
+            indexes: Vec::new(),
+            bindings,
+            stats: None,
+        }
+    }
+
+    fn rows(&self) -> f64 {
+        self.stats.as_ref().map_or(DEFAULT_ROWS, |stats| stats.rows.max(1) as f64)
+    }
+
+    // The pages read to find a row of the table by its rowid.
+    fn depth(&self) -> f64 {
+        match &self.stats {
+            Some(stats) => stats.depth as f64,
+            None => self.rows().log2().max(1.0),
+        }
+    }
+
+    fn index_stats(&self, name: &str) -> Option<&IndexStats> {
+        self.stats.as_ref().and_then(|stats| stats.index(name))
+    }
+
+    // The rows found by looking up these values in an index.
+    fn index_rows(&self, index: &IndexSchema, values: &[Expr]) -> f64 {
+        if index.unique {
+            return 1.0;
+        }
+        let default_rows = (DEFAULT_KEY_ROWS / 2f64.powi(values.len() as i32 - 1)).max(1.0);
+        let Some(stats) = self.index_stats(&index.name) else { return default_rows };
+        let rows_per_key = stats.rows_per_key.get(values.len() - 1).copied().unwrap_or(default_rows);
+        // The histogram knows how common a sampled value of the first
+        // column is, the rest narrows it down like on average.
+        if let (Expr::Literal(value), Some(first)) = (&values[0], stats.rows_per_key.first()) {
+            if let Some(entries) = stats.sampled_entries(value) {
+                return entries as f64 * rows_per_key / first;
+            }
+        }
+        rows_per_key
+    }
+
+    // The rows sharing a value of a column.
+    fn column_rows(&self, column: usize) -> f64 {
+        let Some(schema) = &self.schema else { return DEFAULT_KEY_ROWS };
+        let Some(name) = schema.columns.get(column).map(|column| &column.name) else { return 1.0 };
+        let leading = |index: &&IndexSchema| index.columns[0].eq_ignore_ascii_case(name);
+        if schema.rowid_column == Some(column) || self.indexes.iter().any(|index| index.unique && index.columns.len() == 1 && leading(&index)) {
+            return 1.0;
+        }
+        self.indexes
+            .iter()
+            .filter(leading)
+            .find_map(|index| self.index_stats(&index.name)?.rows_per_key.first().copied())
+            .unwrap_or(DEFAULT_KEY_ROWS)
+    }
+}
+
+pub fn table_label(table_ref: &TableRef) -> String {
+    match (&table_ref.subquery, &table_ref.alias) {
+        (Some(_), Some(alias)) => alias.clone(),
+        (Some(_), None) => "(subquery)".to_string(),
+        (None, Some(alias)) => format!("{} AS {}", table_ref.name, alias),
+        (None, None) => table_ref.name.clone(),
+    }
+}
+
+// How the rows of a table are found. The expressions are the values sought,
+// known before the table is read.
+#[derive(Debug, Clone)]
+pub enum Access {
+    Scan,
+    RowidSeek(Expr),
+    IndexSeek(IndexSchema, Vec<Expr>),
+    // Probes a hash table of the rows on the column at this position.
+    HashLookup(usize, Expr),
+}
+
+impl Access {
+    pub fn describe(&self, relation: &PlanTable) -> String {
+        match self {
+            Access::Scan => format!("SCAN {}", relation.label),
+            Access::RowidSeek(_) => {
+                let name = relation
+                    .schema
+                    .as_ref()
+                    .and_then(|schema| schema.rowid_column.map(|i| schema.columns[i].name.clone()))
+                    .unwrap_or_else(|| "rowid".to_string());
+                format!("SEARCH {} USING PRIMARY KEY ({}=?)", relation.label, name)
+            }
+            Access::IndexSeek(index, _) => {
+                let terms: Vec<String> = index.columns.iter().map(|column| format!("{}=?", column)).collect();
+                format!("SEARCH {} USING INDEX {} ({})", relation.label, index.name, terms.join(" AND "))
+            }
+            Access::HashLookup(column, _) => format!(
+                "SEARCH {} USING AUTOMATIC HASH INDEX ({}=?)",
+                relation.label, relation.bindings[*column].name
+            ),
+        }
+    }
+
+    // The rowids a seek finds, in ascending order. The values sought are
+    // evaluated with the given columns in scope.
+    pub unsafe fn rowids(&self, table: &mut Table, bindings: &[ColumnBinding], values: &[Value]) -> Result<Vec<u32>, Error> {
+        let keys = {
+            let scope = Scope::new(&table.functions, bindings, values);
+            match self {
+                Access::RowidSeek(expr) => vec![key_value(Some(Affinity::Integer), expr, &scope)?],
+                Access::IndexSeek(index, exprs) => {
+                    let schema = table.catalog.table(&index.table_name);
+                    let affinity = |name: &str| schema.and_then(|schema| Some(schema.columns[schema.column_index(name).ok()?].affinity));
+                    index
+                        .columns
+                        .iter()
+                        .zip(exprs)
+                        .map(|(name, expr)| key_value(affinity(name), expr, &scope))
+                        .collect::<Result<_, _>>()?
+                }
+                Access::Scan | Access::HashLookup(..) => unreachable!(),
+            }
+        };
+        let mut rowids = match self {
+            Access::IndexSeek(index, _) => index_lookup(table, index, &keys),
+            _ => match keys[0].clone().normalized() {
+                Value::Integer(rowid) if rowid >= 0 && rowid <= u32::MAX as i64 => vec![rowid as u32],
+                _ => Vec::new(),
+            },
+        };
+        rowids.sort_unstable();
+        Ok(rowids)
+    }
+}
+
+// The value a seek looks for in a column of this affinity: the key with the
+// affinity comparing them applies to it. key_term leaves out the keys that
+// would convert the column instead.
+pub fn key_value(column: Option<Affinity>, key: &Expr, scope: &Scope) -> Result<Value, Error> {
+    let value = eval(key, scope)?;
+    Ok(match comparison_affinity(column, scope.affinity(key)) {
+        (_, Some(affinity)) => affinity.apply(value),
+        (_, None) => value,
+    })
+}
+
+// The chosen way to read a table, the cost of reading it for every row
+// joined before it and the rows it finds for them.
+pub struct Choice {
+    pub access: Access,
+    pub cost: f64,
+    pub rows: f64,
+}
+
+// Chooses how to read a table when rows_in rows with the known columns are
+// joined before it. A hash table is only built for tables joined to others.
+pub fn choose_access(
+    functions: &Functions,
+    relation: &PlanTable,
+    known: &[ColumnBinding],
+    conditions: &[&Expr],
+    rows_in: f64,
+    hashable: bool,
+) -> Choice {
+    let keys: Vec<(usize, Expr)> = conditions
+        .iter()
+        .filter_map(|condition| key_term(functions, condition, relation, known))
+        .collect();
+    let key = |column: usize| keys.iter().find(|(key, _)| *key == column).map(|(_, expr)| expr.clone());
+    let rows = relation.rows();
+    let depth = relation.depth();
+    let mut best = Choice {
+        access: Access::Scan,
+        cost: rows_in * rows,
+        rows: rows_in * rows,
+    };
+    let mut candidates = Vec::new();
+    if let Some(schema) = &relation.schema {
+        // A key on the rowid, by the INTEGER PRIMARY KEY or one of its names.
+        let rowid = relation.bindings.iter().position(|binding| binding.rowid);
+        if let Some(expr) = schema.rowid_column.and_then(&key).or_else(|| rowid.and_then(&key)) {
+            candidates.push((Access::RowidSeek(expr), rows_in * depth, rows_in));
+        }
+        for index in &relation.indexes {
+            let exprs: Option<Vec<Expr>> = index
+                .columns
+                .iter()
+                .map(|name| schema.column_index(name).ok().and_then(&key))
+                .collect();
+            if let Some(exprs) = exprs {
+                let matches = relation.index_rows(index, &exprs).min(rows);
+                let index_depth = relation.index_stats(&index.name).map_or(depth, |stats| stats.depth as f64);
+                let cost = rows_in * (index_depth + matches * depth);
+                candidates.push((Access::IndexSeek(index.clone(), exprs), cost, rows_in * matches));
+            }
+        }
+    }
+    // A hash table is only built when the table can not seek. Building it
+    // costs about as much as sorting the rows.
+    if let (true, true, Some((column, expr))) = (hashable, candidates.is_empty(), keys.first()) {
+        let matches = relation.column_rows(*column).min(rows);
+        candidates.push((Access::HashLookup(*column, expr.clone()), rows * depth + rows_in, rows_in * matches));
+    }
+    for (access, cost, rows) in candidates {
+        if cost < best.cost {
+            best = Choice { access, cost, rows };
+        }
+    }
+    best
+}
+
+// A table of a join, in the order the tables are joined.
+pub struct Step {
+    // The position of the table in the FROM clause.
+    pub relation: usize,
+    pub access: Access,
+    // Conditions checked on the rows joined so far once the table is joined.
+    pub filters: Vec<Expr>,
+    // Whether rows finding no match are kept, the table's columns NULL.
+    pub left: bool,
+}
+
+// Plans the join of the tables of a FROM clause, the first one followed by
+// the ones of the joins.
+pub fn plan_joins(functions: &Functions, relations: &[PlanTable], joins: &[Join], where_clause: Option<&Expr>) -> Vec<Step> {
+    let where_conditions: Vec<&Expr> = where_clause
+        .map(conjuncts)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|condition| builtin_only(functions, condition))
+        .collect();
+    if joins.iter().any(|join| join.kind == JoinKind::Left) {
+        return written_order(functions, relations, joins, &where_conditions);
+    }
+    // The ON conditions must be checked, WHERE is checked again after the
+    // join and only narrows the rows early.
+    let mut conditions: Vec<(&Expr, bool)> = joins
+        .iter()
+        .filter_map(|join| join.constraint.as_ref())
+        .flat_map(conjuncts)
+        .map(|condition| (condition, true))
+        .collect();
+    conditions.extend(where_conditions.into_iter().map(|condition| (condition, false)));
+    let mut best: Option<(f64, Vec<Step>)> = None;
+    for first in 0..relations.len() {
+        let (cost, steps) = greedy_order(functions, relations, &conditions, first);
+        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+            best = Some((cost, steps));
+        }
+    }
+    let (_, mut steps) = best.unwrap_or_default();
+    // Conditions naming columns of no table still have to fail.
+    let mut applied: Vec<&Expr> = steps.iter().flat_map(|step| step.filters.iter()).collect();
+    let mut leftover = Vec::new();
+    for (condition, required) in &conditions {
+        if *required && !applied.contains(condition) {
+            applied.push(condition);
+            leftover.push((*condition).clone());
+        }
+    }
+    if let Some(last) = steps.last_mut() {
+        last.filters.extend(leftover);
+    }
+    steps
+}
+
+// Joins the tables starting with the first one, then each time the table
+// cheapest to add.
+fn greedy_order(functions: &Functions, relations: &[PlanTable], conditions: &[(&Expr, bool)], first: usize) -> (f64, Vec<Step>) {
+    let mut steps: Vec<Step> = Vec::new();
+    let mut known: Vec<ColumnBinding> = Vec::new();
+    let mut applied = vec![false; conditions.len()];
+    let mut rows_in = 1.0;
+    let mut total = 0.0;
+    while steps.len() < relations.len() {
+        let mut next: Option<(f64, f64, Step, Vec<usize>)> = None;
+        for (i, relation) in relations.iter().enumerate() {
+            let placed = steps.iter().any(|step| step.relation == i);
+            if placed || (steps.is_empty() && i != first) {
+                continue;
+            }
+            let open: Vec<&Expr> = conditions
+                .iter()
+                .zip(&applied)
+                .filter(|(_, applied)| !**applied)
+                .map(|((condition, _), _)| *condition)
+                .collect();
+            let choice = choose_access(functions, relation, &known, &open, rows_in, !steps.is_empty());
+            let mut joined = known.clone();
+            joined.extend(relation.bindings.iter().cloned());
+            let newly: Vec<usize> = (0..conditions.len())
+                .filter(|&c| !applied[c] && resolves(conditions[c].0, &joined))
+                .collect();
+            let cost = choice.cost + choice.rows;
+            if next.as_ref().is_none_or(|(best, ..)| cost < *best) {
+                let step = Step {
+                    relation: i,
+                    access: choice.access,
+                    filters: newly.iter().map(|&c| conditions[c].0.clone()).collect(),
+                    left: false,
+                };
+                next = Some((cost, choice.rows, step, newly));
+            }
+        }
+        let Some((cost, rows_out, step, newly)) = next else { break };
+        for c in newly {
+            applied[c] = true;
+        }
+        known.extend(relations[step.relation].bindings.iter().cloned());
+        total += cost;
+        rows_in = rows_out;
+        steps.push(step);
+    }
+    (total, steps)
+}
+
+// The tables in the order they are written. A joined table only seeks on
+// its own ON conditions, which are checked as a whole so that a LEFT JOIN
+// keeps the rows it finds no match for.
+fn written_order(functions: &Functions, relations: &[PlanTable], joins: &[Join], where_conditions: &[&Expr]) -> Vec<Step> {
+    let mut known: Vec<ColumnBinding> = Vec::new();
+    let mut steps = Vec::new();
+    let mut rows_in = 1.0;
+    for (i, relation) in relations.iter().enumerate() {
+        let (conditions, filters, left) = match i.checked_sub(1).map(|j| &joins[j]) {
+            None => (where_conditions.to_vec(), Vec::new(), false),
+            Some(join) => (
+                join.constraint.as_ref().map(conjuncts).unwrap_or_default(),
+                join.constraint.iter().cloned().collect(),
+                join.kind == JoinKind::Left,
+            ),
+        };
+        let choice = choose_access(functions, relation, &known, &conditions, rows_in, i > 0);
+        known.extend(relation.bindings.iter().cloned());
+        rows_in = choice.rows;
+        steps.push(Step {
+            relation: i,
+            access: choice.access,
+            filters,
+            left,
+        });
+    }
+    steps
+}
+
+// The terms of an AND chain.
+pub fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Binary {
+            left,
+            op: BinaryOp::And,
+            right,
+        } => {
+            let mut terms = conjuncts(left);
+            terms.extend(conjuncts(right));
+            terms
+        }
+        _ => vec![expr],
+    }
+}
+
+// A condition column = value, as the position of the table's column and the
+// value, when the value is known before the table is read.
+fn key_term(functions: &Functions, condition: &Expr, relation: &PlanTable, known: &[ColumnBinding]) -> Option<(usize, Expr)> {
+    let Expr::Binary {
+        left,
+        op: BinaryOp::Eq,
+        right,
+    } = condition
+    else {
+        return None;
+    };
+    for (column, value) in [(left, right), (right, left)] {
+        let Expr::Column { table, name } = column.as_ref() else { continue };
+        let Some(Side::Table(position)) = resolve(table.as_deref(), name, known, &relation.bindings) else { continue };
+        let mut is_known = builtin_only(functions, value);
+        value.walk(&mut |expr| {
+            if let Expr::Column { table, name } = expr {
+                is_known &= resolve(table.as_deref(), name, known, &relation.bindings) == Some(Side::Known);
+            }
+        });
+        // A comparison converting the column's values can not look them up.
+        let known_scope = Scope {
+            functions: None,
+            columns: known,
+            values: &[],
+        };
+        let affinity = comparison_affinity(relation.bindings[position].affinity, known_scope.affinity(value));
+        if is_known && affinity.0.is_none() {
+            return Some((position, (**value).clone()));
+        }
+    }
+    None
+}
+
+#[derive(PartialEq)]
+enum Side {
+    Known,
+    Table(usize),
+}
+
+// What a column reference reads, like Scope::lookup: the nearest column
+// having the name. None when there is no such column or more than one.
+fn resolve(table: Option<&str>, name: &str, known: &[ColumnBinding], bindings: &[ColumnBinding]) -> Option<Side> {
+    let columns = known
+        .iter()
+        .map(|binding| (binding, None))
+        .chain(bindings.iter().enumerate().map(|(i, binding)| (binding, Some(i))));
+    let mut found: Option<((usize, bool), Option<usize>)> = None;
+    let mut ambiguous = false;
+    for (binding, position) in columns {
+        if !binding.matches(table, name) {
+            continue;
+        }
+        match found {
+            Some((rank, _)) if rank < binding.rank() => {}
+            Some((rank, _)) if rank == binding.rank() => ambiguous = true,
+            _ => {
+                found = Some((binding.rank(), position));
+                ambiguous = false;
+            }
+        }
+    }
+    match found {
+        _ if ambiguous => None,
+        Some((_, Some(position))) => Some(Side::Table(position)),
+        Some((_, None)) => Some(Side::Known),
+        None => None,
+    }
+}
+
+// Whether every column the expression reads is one of the bindings.
+fn resolves(expr: &Expr, bindings: &[ColumnBinding]) -> bool {
+    let mut resolved = true;
+    expr.walk(&mut |expr| {
+        if let Expr::Column { table, name } = expr {
+            resolved &= resolve(table.as_deref(), name, bindings, &[]).is_some();
+        }
+    });
+    resolved
+}
+
+// Whether the expression calls no user function. Those may return a
+// different value each call, so the expression can not be evaluated once
+// in place of once per row.
+fn builtin_only(functions: &Functions, expr: &Expr) -> bool {
+    let mut builtin = true;
+    expr.walk(&mut |expr| {
+        if let Expr::Function { name, .. } = expr {
+            builtin &= functions.scalar(name).is_none() && functions.aggregate(name).is_none();
+        }
+        if let Expr::Window { .. } = expr {
+            builtin = false;
+        }
+    });
+    builtin
+}
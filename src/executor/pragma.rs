@@ -0,0 +1,64 @@
+use crate::executor::dispatch::{ExecuteResult, ResultSet};
+use crate::executor::foreign_key::foreign_key_check;
+use crate::sql::ast::Pragma;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::Error;
+
+// Settings of an open database changed with PRAGMA. They are not stored in
+// the file and start from their defaults on every open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pragmas {
+    pub foreign_keys: bool,
+    // Rows an ORDER BY sorts in memory before spilling them to temp pages.
+    pub sort_buffer_rows: usize,
+}
+
+pub const DEFAULT_SORT_BUFFER_ROWS: usize = 1000;
+
+impl Default for Pragmas {
+    fn default() -> Self {
+        Pragmas {
+            foreign_keys: true,
+            sort_buffer_rows: DEFAULT_SORT_BUFFER_ROWS,
+        }
+    }
+}
+
+pub unsafe fn execute_pragma(table: &mut Table, pragma: &Pragma) -> Result<ExecuteResult, Error> {
+    match pragma.name.to_ascii_lowercase().as_str() {
+        "foreign_keys" => match &pragma.value {
+            Some(value) => {
+                table.pragmas.foreign_keys = parse_flag(value)?;
+                Ok(ExecuteResult::Executed { changes: 0 })
+            }
+            None => Ok(ExecuteResult::Rows(ResultSet {
+                columns: vec!["foreign_keys".to_string()],
+                rows: vec![vec![Value::from_bool(table.pragmas.foreign_keys)]],
+            })),
+        },
+        "sort_buffer_rows" => match &pragma.value {
+            Some(value) => {
+                table.pragmas.sort_buffer_rows = match value.parse::<usize>() {
+                    Ok(rows) if rows > 0 => rows,
+                    _ => return Err(Error::other(format!("invalid pragma value: {}", value))),
+                };
+                Ok(ExecuteResult::Executed { changes: 0 })
+            }
+            None => Ok(ExecuteResult::Rows(ResultSet {
+                columns: vec!["sort_buffer_rows".to_string()],
+                rows: vec![vec![Value::Integer(table.pragmas.sort_buffer_rows as i64)]],
+            })),
+        },
+        "foreign_key_check" => foreign_key_check(table, pragma.value.as_deref()).map(ExecuteResult::Rows),
+        _ => Err(Error::other(format!("no such pragma: {}", pragma.name))),
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool, Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Ok(true),
+        "off" | "false" | "no" | "0" => Ok(false),
+        _ => Err(Error::other(format!("invalid pragma value: {}", value))),
+    }
+}
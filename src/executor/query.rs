@@ -0,0 +1,740 @@
+use crate::executor::aggregate::{is_aggregate_query, substitute, Aggregator};
+use crate::executor::alter::{complete_row, stored_row};
+use crate::executor::compound::{check_column_counts, combine, row_key};
+use crate::executor::cte::{declare, find_common_table};
+use crate::executor::dispatch::ResultSet;
+use crate::executor::dml::matches_where;
+use crate::executor::expr::{eval, is_true, ColumnBinding, Scope};
+use crate::executor::function::Functions;
+use crate::executor::index::index_distinct_rowids;
+use crate::executor::join::join_rows;
+use crate::executor::planner::{choose_access, conjuncts, table_label, Access, PlanTable};
+use crate::executor::sort::Sorter;
+use crate::executor::subquery::Correlated;
+use crate::executor::window::Windows;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::sql::ast::{Expr, OrderingTerm, Select, SelectItem};
+use crate::table::btree::{BTree, ReverseScan, TreeScan};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::collections::HashSet;
+use std::mem;
+use std::io::Error;
+
+pub fn table_bindings(schema: &TableSchema, alias: &str) -> Vec<ColumnBinding> {
+    schema
+        .columns
+        .iter()
+        .map(|column| ColumnBinding {
+            affinity: Some(column.affinity),
+            ..ColumnBinding::new(Some(alias), &column.name)
+        })
+        .collect()
+}
+
+// The columns of a row read from the table tree, its rowid after them.
+pub fn stored_bindings(schema: &TableSchema, alias: &str) -> Vec<ColumnBinding> {
+    let mut bindings = table_bindings(schema, alias);
+    bindings.push(ColumnBinding::rowid(alias));
+    bindings
+}
+
+pub unsafe fn execute_select(table: &mut Table, select: &Select) -> Result<ResultSet, Error> {
+    run_select(table, select, &[], &[])
+}
+
+// Runs a correlated subquery for a row of the enclosing query. The columns
+// of that row follow the subquery's own columns, one query further out.
+pub unsafe fn execute_subquery(
+    table: &mut Table,
+    select: &Select,
+    outer_bindings: &[ColumnBinding],
+    outer_values: &[Value],
+) -> Result<ResultSet, Error> {
+    run_select(table, select, outer_bindings, outer_values)
+}
+
+// Deepest selects nest in each other through subqueries, derived tables,
+// common tables and views. A level takes up to about 45 KB of stack in a
+// debug build, so a query at the limit still runs on a 2 MB thread.
+const MAX_SELECT_DEPTH: usize = 32;
+
+unsafe fn run_select(
+    table: &mut Table,
+    select: &Select,
+    outer_bindings: &[ColumnBinding],
+    outer_values: &[Value],
+) -> Result<ResultSet, Error> {
+    if table.select_depth == MAX_SELECT_DEPTH {
+        return Err(Error::other(format!(
+            "too many levels of subquery nesting (maximum depth {})",
+            MAX_SELECT_DEPTH
+        )));
+    }
+    table.select_depth += 1;
+    let result = match &select.with {
+        None => run_query(table, select, outer_bindings, outer_values),
+        Some(_) => {
+            let scope = table.common_tables.len();
+            let result = declare(table, select).and_then(|()| run_query(table, select, outer_bindings, outer_values));
+            table.common_tables.truncate(scope);
+            result
+        }
+    };
+    table.select_depth -= 1;
+    result
+}
+
+// Runs a select once the tables of its WITH clause are in scope.
+unsafe fn run_query(
+    table: &mut Table,
+    select: &Select,
+    outer_bindings: &[ColumnBinding],
+    outer_values: &[Value],
+) -> Result<ResultSet, Error> {
+    if !select.compound.is_empty() {
+        return run_compound(table, select, outer_bindings, outer_values);
+    }
+    // Result columns are named after the expressions as written, before
+    // their subqueries are planned.
+    let written = select;
+    let mut correlated = Correlated::default();
+    let select = &correlated.plan_select(table, select)?;
+    let (schema, alias) = match &select.from {
+        Some(from)
+            if select.joins.is_empty()
+                && from.subquery.is_none()
+                && find_common_table(table, &from.name).is_none()
+                && table.catalog.view(&from.name).is_none() =>
+        {
+            let schema = table.catalog.get_table(&from.name)?.clone();
+            let alias = from.alias.clone().unwrap_or_else(|| schema.name.clone());
+            (Some(schema), alias)
+        }
+        _ => (None, String::new()),
+    };
+    let unique = schema
+        .as_ref()
+        .is_some_and(|schema| selects_unique_key(schema, &alias, select, &table.functions));
+    // The index finding one row of every distinct key, if any.
+    let distinct_keys = match &schema {
+        Some(schema) if !unique => distinct_index(table, schema, &alias, select),
+        _ => None,
+    };
+    let (mut bindings, rows) = match (&select.from, &schema) {
+        (Some(from), Some(schema)) => {
+            // The columns of the enclosing query are known before the table
+            // is read, a correlated subquery can seek on them.
+            let known: Vec<ColumnBinding> = outer_bindings.iter().map(ColumnBinding::outer).collect();
+            let relation = PlanTable::stored(table, table_label(from), schema, stored_bindings(schema, &alias));
+            let conditions = select.where_clause.as_ref().map(conjuncts).unwrap_or_default();
+            let access = choose_access(&table.functions, &relation, &known, &conditions, 1.0, false).access;
+            let rows = match (&distinct_keys, access) {
+                (Some(index), _) => SourceRows::Rowids(index_distinct_rowids(table, index).into_iter(), schema.clone()),
+                (None, Access::Scan) => SourceRows::Scan(BTree::new(schema.root_page_num).scan(table), schema.clone()),
+                (None, access) => SourceRows::Rowids(access.rowids(table, &known, outer_values)?.into_iter(), schema.clone()),
+            };
+            (relation.bindings, rows)
+        }
+        (Some(from), None) => {
+            let (bindings, rows) = join_rows(table, from, &select.joins, select.where_clause.as_ref())?;
+            (bindings, SourceRows::Rows(rows.into_iter()))
+        }
+        // Without FROM the select reads a single row without columns.
+        (None, _) => (Vec::new(), SourceRows::Rows(vec![Vec::new()].into_iter())),
+    };
+    let distinct = select.distinct && !unique && distinct_keys.is_none();
+    let mut output = Output::new(&table.functions, &select.limit, &select.offset, distinct)?;
+    bindings.extend(outer_bindings.iter().map(ColumnBinding::outer));
+    bindings.extend(correlated.bindings());
+    let mut source = Source {
+        rows,
+        outer_values: outer_values.to_vec(),
+        correlated,
+        bindings: bindings.clone(),
+    };
+    let mut columns = Vec::new();
+    let mut aliases = Vec::new();
+    for item in &written.columns {
+        match item {
+            SelectItem::Wildcard if select.from.is_none() => {
+                return Err(Error::other("no tables specified"))
+            }
+            SelectItem::Wildcard => columns.extend(
+                bindings
+                    .iter()
+                    .filter(|binding| binding.in_wildcard(None))
+                    .map(|binding| binding.name.clone()),
+            ),
+            SelectItem::TableWildcard(name) => {
+                let before = columns.len();
+                columns.extend(
+                    bindings
+                        .iter()
+                        .filter(|binding| binding.in_wildcard(Some(name)))
+                        .map(|binding| binding.name.clone()),
+                );
+                if columns.len() == before {
+                    return Err(Error::other(format!("no such table: {}", name)));
+                }
+            }
+            SelectItem::Expr { expr, alias } => {
+                if let Some(alias) = alias {
+                    aliases.push((alias.clone(), columns.len()));
+                }
+                columns.push(column_name(expr, alias));
+            }
+        }
+    }
+    let mut windowed = Windows::plan(select)?;
+    if let Some((windows, planned)) = &mut windowed {
+        if is_aggregate_query(select, &table.functions) {
+            return Err(Error::other("window functions are not supported in aggregate queries"));
+        }
+        // Every matching row is read before the calls are computed. The rows
+        // then are the source of the select, and pass WHERE already.
+        let where_clause = planned.where_clause.take();
+        let mut rows = Vec::new();
+        while let Some(values) = source.next(table)? {
+            if matches_where(&table.functions, &where_clause, &bindings, &values)? {
+                rows.push(values);
+            }
+        }
+        windows.append_values(table, &bindings, &mut rows)?;
+        bindings.extend(windows.bindings());
+        source = Source {
+            rows: SourceRows::Rows(rows.into_iter()),
+            outer_values: Vec::new(),
+            correlated: Correlated::default(),
+            bindings: bindings.clone(),
+        };
+    }
+    let select = windowed.as_ref().map_or(select, |(_, planned)| planned);
+    if is_aggregate_query(select, &table.functions) {
+        // The number of rows of a table is known without reading them.
+        if let (Some(schema), true) = (&schema, is_count_of_table(select)) {
+            let count = BTree::new(schema.root_page_num).count(table);
+            output.push(vec![Value::Integer(count as i64)]);
+            return Ok(ResultSet {
+                columns,
+                rows: output.rows,
+            });
+        }
+        return execute_aggregate(table, select, source, &bindings, columns, &aliases, output);
+    }
+    let order = resolve_order_by(&select.order_by, &columns, &aliases)?;
+    let by_rowid = match (order.as_slice(), &schema) {
+        ([(SortKey::Expr(_), _)], Some(schema)) => rowid_order(schema, &alias, &select.order_by),
+        _ => None,
+    };
+    match by_rowid {
+        // Rows come out of the source in rowid order, nothing to sort.
+        _ if order.is_empty() => scan_rows(table, &mut source, select, &bindings, &mut output)?,
+        Some(false) => scan_rows(table, &mut source, select, &bindings, &mut output)?,
+        Some(true) => {
+            source.reverse(table);
+            scan_rows(table, &mut source, select, &bindings, &mut output)?
+        }
+        None => {
+            let descending = order.iter().map(|(_, descending)| *descending).collect();
+            let mut sorter = Sorter::new(descending, table.pragmas.sort_buffer_rows);
+            while let Some(values) = source.next(table)? {
+                if !matches_where(&table.functions, &select.where_clause, &bindings, &values)? {
+                    continue;
+                }
+                let row = project(&table.functions, &select.columns, &bindings, &values)?;
+                let scope = Scope::new(&table.functions, &bindings, &values);
+                let keys = order
+                    .iter()
+                    .map(|(key, _)| match key {
+                        SortKey::Column(i) => Ok(row[*i].clone()),
+                        SortKey::Expr(expr) => eval(expr, &scope),
+                    })
+                    .collect::<Result<Vec<Value>, Error>>()?;
+                sorter.add(keys, row)?;
+            }
+            let mut sorted = sorter.finish()?;
+            while let Some(row) = sorted.next()? {
+                if !output.push(row) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(ResultSet {
+        columns,
+        rows: output.rows,
+    })
+}
+
+// Runs the selects of a compound select left to right, combining the rows
+// of each one with the rows so far, then sorts and limits the result. The
+// columns are named after the ones of the first select.
+unsafe fn run_compound(
+    table: &mut Table,
+    select: &Select,
+    outer_bindings: &[ColumnBinding],
+    outer_values: &[Value],
+) -> Result<ResultSet, Error> {
+    let first = Select {
+        with: None,
+        compound: Vec::new(),
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+        ..select.clone()
+    };
+    let ResultSet { columns, mut rows } = run_query(table, &first, outer_bindings, outer_values)?;
+    for term in &select.compound {
+        let result = run_query(table, &term.select, outer_bindings, outer_values)?;
+        check_column_counts(term.op, columns.len(), result.columns.len())?;
+        rows = combine(term.op, rows, result.rows);
+    }
+    let order = resolve_compound_order_by(&select.order_by, &columns)?;
+    let limit = match &select.limit {
+        Some(limit) => Some(Correlated::plan_uncorrelated(table, limit)?),
+        None => None,
+    };
+    let offset = match &select.offset {
+        Some(offset) => Some(Correlated::plan_uncorrelated(table, offset)?),
+        None => None,
+    };
+    let mut output = Output::new(&table.functions, &limit, &offset, false)?;
+    if order.is_empty() {
+        for row in rows {
+            if !output.push(row) {
+                break;
+            }
+        }
+    } else {
+        let descending = order.iter().map(|(_, descending)| *descending).collect();
+        let mut sorter = Sorter::new(descending, table.pragmas.sort_buffer_rows);
+        for row in rows {
+            sorter.add(order.iter().map(|(i, _)| row[*i].clone()).collect(), row)?;
+        }
+        let mut sorted = sorter.finish()?;
+        while let Some(row) = sorted.next()? {
+            if !output.push(row) {
+                break;
+            }
+        }
+    }
+    Ok(ResultSet {
+        columns,
+        rows: output.rows,
+    })
+}
+
+// The rows a select reads, each followed by the row of the enclosing query
+// when it is a correlated subquery and by its hidden columns.
+struct Source {
+    rows: SourceRows,
+    outer_values: Vec<Value>,
+    correlated: Correlated,
+    bindings: Vec<ColumnBinding>,
+}
+
+// A table in rowid order, the rows of a table a seek found in rowid order,
+// or rows produced before hand by the joins.
+enum SourceRows {
+    Scan(TreeScan, TableSchema),
+    ReverseScan(ReverseScan, TableSchema),
+    Rowids(std::vec::IntoIter<u32>, TableSchema),
+    Rows(std::vec::IntoIter<Vec<Value>>),
+}
+
+impl Source {
+    // Makes the rows come out in the opposite order, before any is read. A
+    // table is then read from its largest rowid down.
+    unsafe fn reverse(&mut self, table: &mut Table) {
+        self.rows = match mem::replace(&mut self.rows, SourceRows::Rows(Vec::new().into_iter())) {
+            SourceRows::Scan(_, schema) => SourceRows::ReverseScan(BTree::new(schema.root_page_num).scan_reverse(table), schema),
+            SourceRows::ReverseScan(_, schema) => SourceRows::Scan(BTree::new(schema.root_page_num).scan(table), schema),
+            SourceRows::Rowids(rowids, schema) => SourceRows::Rowids(rowids.rev().collect::<Vec<_>>().into_iter(), schema),
+            SourceRows::Rows(rows) => SourceRows::Rows(rows.rev().collect::<Vec<_>>().into_iter()),
+        };
+    }
+
+    unsafe fn next(&mut self, table: &mut Table) -> Result<Option<Vec<Value>>, Error> {
+        let values = match &mut self.rows {
+            SourceRows::Scan(scan, schema) => match scan.next(table) {
+                Some((rowid, record)) => {
+                    let mut values = record.values;
+                    complete_row(schema, &mut values)?;
+                    values.push(Value::Integer(rowid as i64));
+                    Some(values)
+                }
+                None => None,
+            },
+            SourceRows::ReverseScan(scan, schema) => match scan.next(table) {
+                Some((rowid, record)) => {
+                    let mut values = record.values;
+                    complete_row(schema, &mut values)?;
+                    values.push(Value::Integer(rowid as i64));
+                    Some(values)
+                }
+                None => None,
+            },
+            SourceRows::Rowids(rowids, schema) => loop {
+                match rowids.next() {
+                    Some(rowid) => {
+                        if let Some(mut values) = stored_row(table, schema, rowid)? {
+                            values.push(Value::Integer(rowid as i64));
+                            break Some(values);
+                        }
+                    }
+                    None => break None,
+                }
+            },
+            SourceRows::Rows(rows) => rows.next(),
+        };
+        let Some(mut values) = values else { return Ok(None) };
+        values.extend(self.outer_values.iter().cloned());
+        self.correlated.append_values(table, &self.bindings, &mut values)?;
+        Ok(Some(values))
+    }
+}
+
+// Groups the matching rows and produces a row per group that passes HAVING.
+unsafe fn execute_aggregate(
+    table: &mut Table,
+    select: &Select,
+    mut source: Source,
+    bindings: &[ColumnBinding],
+    columns: Vec<String>,
+    aliases: &[(String, usize)],
+    mut output: Output,
+) -> Result<ResultSet, Error> {
+    let mut aggregator = Aggregator::new(select, &table.functions)?;
+    while let Some(values) = source.next(table)? {
+        if matches_where(&table.functions, &select.where_clause, bindings, &values)? {
+            aggregator.add(&Scope::new(&table.functions, bindings, &values))?;
+        }
+    }
+    let order = resolve_order_by(&select.order_by, &columns, aliases)?;
+    let descending = order.iter().map(|(_, descending)| *descending).collect();
+    let mut sorter = Sorter::new(descending, table.pragmas.sort_buffer_rows);
+    for (group, results) in aggregator.finish(bindings.len())? {
+        let scope = Scope::new(&table.functions, bindings, &group.row);
+        if let Some(having) = &select.having {
+            if !is_true(&eval(&substitute(having, &results), &scope)?) {
+                continue;
+            }
+        }
+        let mut row = Vec::with_capacity(columns.len());
+        for item in &select.columns {
+            match item {
+                SelectItem::Wildcard => row.extend(wildcard_values(bindings, &group.row, None)),
+                SelectItem::TableWildcard(name) => row.extend(wildcard_values(bindings, &group.row, Some(name))),
+                SelectItem::Expr { expr, .. } => row.push(eval(&substitute(expr, &results), &scope)?),
+            }
+        }
+        let keys = order
+            .iter()
+            .map(|(key, _)| match key {
+                SortKey::Column(i) => Ok(row[*i].clone()),
+                SortKey::Expr(expr) => eval(&substitute(expr, &results), &scope),
+            })
+            .collect::<Result<Vec<Value>, Error>>()?;
+        sorter.add(keys, row)?;
+    }
+    let mut sorted = sorter.finish()?;
+    while let Some(row) = sorted.next()? {
+        if !output.push(row) {
+            break;
+        }
+    }
+    Ok(ResultSet {
+        columns,
+        rows: output.rows,
+    })
+}
+
+// SELECT COUNT(*) FROM table, without any filter or grouping.
+fn is_count_of_table(select: &Select) -> bool {
+    let count_star = matches!(
+        select.columns.as_slice(),
+        [SelectItem::Expr { expr: Expr::Function { name, wildcard: true, .. }, .. }] if name.eq_ignore_ascii_case("count")
+    );
+    count_star && select.where_clause.is_none() && select.group_by.is_empty() && select.having.is_none()
+}
+
+// Streams the matching rows in source order, stopping at the LIMIT.
+unsafe fn scan_rows(
+    table: &mut Table,
+    source: &mut Source,
+    select: &Select,
+    bindings: &[ColumnBinding],
+    output: &mut Output,
+) -> Result<(), Error> {
+    while let Some(values) = source.next(table)? {
+        if !matches_where(&table.functions, &select.where_clause, bindings, &values)? {
+            continue;
+        }
+        if !output.push(project(&table.functions, &select.columns, bindings, &values)?) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub fn project(
+    functions: &Functions,
+    items: &[SelectItem],
+    bindings: &[ColumnBinding],
+    values: &[Value],
+) -> Result<Vec<Value>, Error> {
+    let scope = Scope::new(functions, bindings, values);
+    let mut row = Vec::with_capacity(values.len());
+    for item in items {
+        match item {
+            SelectItem::Wildcard => row.extend(wildcard_values(bindings, values, None)),
+            SelectItem::TableWildcard(name) => row.extend(wildcard_values(bindings, values, Some(name))),
+            SelectItem::Expr { expr, .. } => row.push(eval(expr, &scope)?),
+        }
+    }
+    Ok(row)
+}
+
+fn wildcard_values<'a>(
+    bindings: &'a [ColumnBinding],
+    values: &'a [Value],
+    table: Option<&'a str>,
+) -> impl Iterator<Item = Value> + 'a {
+    bindings
+        .iter()
+        .zip(values)
+        .filter(move |(binding, _)| binding.in_wildcard(table))
+        .map(|(_, value)| value.clone())
+}
+
+// What an ORDER BY term sorts on: a result column, named by its position or
+// its alias, or an expression over the table row.
+enum SortKey {
+    Column(usize),
+    Expr(Expr),
+}
+
+fn resolve_order_by(
+    order_by: &[OrderingTerm],
+    columns: &[String],
+    aliases: &[(String, usize)],
+) -> Result<Vec<(SortKey, bool)>, Error> {
+    let mut order = Vec::new();
+    for (n, term) in order_by.iter().enumerate() {
+        let key = match &term.expr {
+            Expr::Literal(Value::Integer(position)) => {
+                if *position < 1 || *position as usize > columns.len() {
+                    return Err(Error::other(
+                        format!("{} ORDER BY term out of range - should be between 1 and {}", ordinal(n + 1), columns.len()),
+                    ));
+                }
+                SortKey::Column(*position as usize - 1)
+            }
+            Expr::Column { table: None, name } => match aliases.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(name)) {
+                Some((_, i)) => SortKey::Column(*i),
+                None => SortKey::Expr(term.expr.clone()),
+            },
+            expr => SortKey::Expr(expr.clone()),
+        };
+        order.push((key, term.descending));
+    }
+    Ok(order)
+}
+
+// The result columns the ORDER BY terms of a compound select sort on, named
+// by position or by name.
+fn resolve_compound_order_by(order_by: &[OrderingTerm], columns: &[String]) -> Result<Vec<(usize, bool)>, Error> {
+    let aliases: Vec<(String, usize)> = columns.iter().cloned().zip(0..).collect();
+    let mut order = Vec::new();
+    for (n, (key, descending)) in resolve_order_by(order_by, columns, &aliases)?.into_iter().enumerate() {
+        match key {
+            SortKey::Column(i) => order.push((i, descending)),
+            SortKey::Expr(_) => {
+                return Err(Error::other(
+                    format!("{} ORDER BY term does not match any column in the result set", ordinal(n + 1)),
+                ))
+            }
+        }
+    }
+    Ok(order)
+}
+
+fn ordinal(n: usize) -> String {
+    match n {
+        1 => "1st".to_string(),
+        2 => "2nd".to_string(),
+        3 => "3rd".to_string(),
+        _ => format!("{}th", n),
+    }
+}
+
+// Whether the rows of a select reading a single table are distinct without
+// removing duplicates: it is not an aggregate query and selects every
+// column of a key no two rows share, the INTEGER PRIMARY KEY, any other
+// primary key, or a UNIQUE key whose columns are NOT NULL. Selecting only
+// some columns of a key is not enough.
+pub fn selects_unique_key(schema: &TableSchema, alias: &str, select: &Select, functions: &Functions) -> bool {
+    if is_aggregate_query(select, functions) {
+        return false;
+    }
+    let selected = |column: &String| {
+        select.columns.iter().any(|item| match item {
+            SelectItem::Wildcard => true,
+            SelectItem::TableWildcard(table) => table.eq_ignore_ascii_case(alias),
+            SelectItem::Expr {
+                expr: Expr::Column { table, name },
+                ..
+            } => name.eq_ignore_ascii_case(column) && table.as_deref().is_none_or(|table| table.eq_ignore_ascii_case(alias)),
+            SelectItem::Expr { .. } => false,
+        })
+    };
+    let not_null = |column: &String| schema.column_index(column).is_ok_and(|i| schema.columns[i].not_null);
+    let rowid_key = schema.rowid_column.map(|i| vec![schema.columns[i].name.clone()]);
+    rowid_key.iter().any(|key| key.iter().all(selected))
+        || schema
+            .unique_keys
+            .iter()
+            .any(|key| key.iter().all(|column| not_null(column) && selected(column)))
+}
+
+// The index a SELECT DISTINCT of a single table reads its rows through, one
+// for every key, so they are distinct without Output keeping every row seen,
+// see index_distinct_rowids. The select has no WHERE or ORDER BY and selects
+// the columns of the index and no others, NOT NULL as rows with a NULL key
+// are not indexed. Entries are stored by the hash of the whole key, the ones
+// sharing a prefix of it are spread over the index, so selecting a prefix
+// leaves the duplicates to Output.
+pub fn distinct_index(table: &Table, schema: &TableSchema, alias: &str, select: &Select) -> Option<IndexSchema> {
+    if !select.distinct || select.where_clause.is_some() || !select.order_by.is_empty() || is_aggregate_query(select, &table.functions) {
+        return None;
+    }
+    let mut selected = Vec::new();
+    for item in &select.columns {
+        let SelectItem::Expr {
+            expr: Expr::Column { table, name },
+            ..
+        } = item
+        else {
+            return None;
+        };
+        if table.as_deref().is_some_and(|table| !table.eq_ignore_ascii_case(alias)) {
+            return None;
+        }
+        let column = schema.column_index(name).ok()?;
+        if !schema.columns[column].not_null {
+            return None;
+        }
+        selected.push(column);
+    }
+    selected.sort_unstable();
+    selected.dedup();
+    table.catalog.table_indexes(&schema.name).into_iter().find(|index| {
+        let mut columns: Vec<usize> = index.columns.iter().filter_map(|name| schema.column_index(name).ok()).collect();
+        columns.sort_unstable();
+        columns == selected
+    })
+}
+
+// Some(descending) when the ORDER BY is just the rowid of the table, which
+// is the order of its tree.
+pub fn rowid_order(schema: &TableSchema, alias: &str, order_by: &[OrderingTerm]) -> Option<bool> {
+    let [term] = order_by else { return None };
+    let Expr::Column { table, name } = &term.expr else { return None };
+    if table.as_deref().is_some_and(|table| !table.eq_ignore_ascii_case(alias)) {
+        return None;
+    }
+    let is_rowid = match schema.rowid_column {
+        Some(i) => schema.columns[i].name.eq_ignore_ascii_case(name),
+        None => false,
+    };
+    if is_rowid {
+        Some(term.descending)
+    } else {
+        None
+    }
+}
+
+// Collects result rows, skipping the OFFSET first ones and stopping at the
+// LIMIT. For SELECT DISTINCT rows equal to one pushed before are dropped
+// first, they are found with a hash set of the encoded rows.
+struct Output {
+    offset: usize,
+    limit: Option<usize>,
+    seen: Option<HashSet<Vec<u8>>>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Output {
+    fn new(functions: &Functions, limit: &Option<Expr>, offset: &Option<Expr>, distinct: bool) -> Result<Output, Error> {
+        let (limit, offset) = limit_and_offset(functions, limit, offset)?;
+        Ok(Output {
+            offset,
+            limit,
+            seen: if distinct { Some(HashSet::new()) } else { None },
+            rows: Vec::new(),
+        })
+    }
+
+    // Adds a row, returns false once no more rows are wanted.
+    fn push(&mut self, row: Vec<Value>) -> bool {
+        if self.limit == Some(self.rows.len()) {
+            return false;
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(row_key(&row)) {
+                return true;
+            }
+        }
+        if self.offset > 0 {
+            self.offset -= 1;
+        } else {
+            self.rows.push(row);
+        }
+        self.limit != Some(self.rows.len())
+    }
+}
+
+// The number of rows a LIMIT keeps, None without a limit, and the number an
+// OFFSET skips.
+pub fn limit_and_offset(
+    functions: &Functions,
+    limit: &Option<Expr>,
+    offset: &Option<Expr>,
+) -> Result<(Option<usize>, usize), Error> {
+    let limit = match limit {
+        Some(expr) => {
+            let limit = eval_integer(functions, expr)?;
+            // A negative limit means no limit.
+            if limit < 0 {
+                None
+            } else {
+                Some(limit as usize)
+            }
+        }
+        None => None,
+    };
+    let offset = match offset {
+        Some(expr) => eval_integer(functions, expr)?.max(0) as usize,
+        None => 0,
+    };
+    Ok((limit, offset))
+}
+
+fn eval_integer(functions: &Functions, expr: &Expr) -> Result<i64, Error> {
+    match eval(expr, &Scope::new(functions, &[], &[]))? {
+        Value::Integer(i) => Ok(i),
+        Value::Real(r) if r.fract() == 0.0 => Ok(r as i64),
+        _ => Err(Error::other("datatype mismatch")),
+    }
+}
+
+// Result column name: the alias, the column name for a plain column
+// reference, otherwise the expression text.
+pub fn column_name(expr: &Expr, alias: &Option<String>) -> String {
+    match (alias, expr) {
+        (Some(alias), _) => alias.clone(),
+        (None, Expr::Column { name, .. }) => name.clone(),
+        (None, expr) => expr.to_string(),
+    }
+}
@@ -0,0 +1,384 @@
+use crate::schema::catalog::Catalog;
+use crate::schema::definition::TableSchema;
+use crate::sql::ast::{
+    CreateTrigger, CreateView, Expr, InsertSource, Select, SelectItem, Stmt, TableRef, UpsertAction,
+};
+use std::io::Error;
+
+// ALTER TABLE looks for the references to the table it changes in the
+// selects of views and in the WHEN clauses and statements of triggers. A
+// name is resolved the way a query resolves it: a qualified column through
+// the names of the FROM clauses around it, an unqualified one to the
+// innermost FROM clause with a table having such a column, NEW.x and OLD.x
+// to the table of the trigger. The columns of subqueries, common tables and
+// views without a column list are not known without running them, a column
+// that could be one of theirs is left alone. Renames rewrite the references
+// found, DROP COLUMN is refused when there is one.
+
+#[derive(Debug, Clone, Copy)]
+pub enum Change<'a> {
+    RenameTable(&'a str),
+    RenameColumn { old: &'a str, new: &'a str },
+    DropColumn(&'a str),
+}
+
+// A table of a FROM clause, or the table of a statement, by the name the
+// query gives it.
+#[derive(Debug, Clone)]
+struct Source {
+    name: String,
+    aliased: bool,
+    // None when they are not known.
+    columns: Option<Vec<String>>,
+    // Whether it is the table being altered.
+    altered: bool,
+}
+
+pub struct References<'a> {
+    catalog: &'a Catalog,
+    schema: &'a TableSchema,
+    change: Change<'a>,
+    // The FROM clauses around the expression being looked at, innermost last.
+    scopes: Vec<Vec<Source>>,
+    // The common tables in scope, they hide the tables of the same name.
+    common_tables: Vec<String>,
+    // Whether NEW and OLD are rows of the table being altered.
+    trigger_rows: bool,
+    // Set when a reference to the renamed table or to the changed column
+    // was met.
+    pub found: bool,
+}
+
+impl<'a> References<'a> {
+    // catalog and schema are the ones from before the change.
+    pub fn new(catalog: &'a Catalog, schema: &'a TableSchema, change: Change<'a>) -> References<'a> {
+        References {
+            catalog,
+            schema,
+            change,
+            scopes: Vec::new(),
+            common_tables: Vec::new(),
+            trigger_rows: false,
+            found: false,
+        }
+    }
+
+    pub fn view(&mut self, create: &mut CreateView) -> Result<(), Error> {
+        self.trigger_rows = false;
+        self.select(&mut create.select)
+    }
+
+    pub fn trigger(&mut self, create: &mut CreateTrigger) -> Result<(), Error> {
+        self.trigger_rows = create.table.eq_ignore_ascii_case(&self.schema.name);
+        if let Some(when) = &mut create.when {
+            self.expr(when)?;
+        }
+        for stmt in &mut create.body {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &mut Stmt) -> Result<(), Error> {
+        match stmt {
+            Stmt::Insert(insert) => {
+                let target = self.source(&mut insert.table, None);
+                if target.altered {
+                    match &mut insert.columns {
+                        Some(columns) => columns.iter_mut().for_each(|column| self.column(column)),
+                        // The values no longer line up with the columns.
+                        None => self.found |= matches!(self.change, Change::DropColumn(_)),
+                    }
+                    if let Some(upsert) = &mut insert.upsert {
+                        upsert.target.iter_mut().flatten().for_each(|column| self.column(column));
+                        if let UpsertAction::Update { assignments, .. } = &mut upsert.action {
+                            assignments.iter_mut().for_each(|(column, _)| self.column(column));
+                        }
+                    }
+                }
+                match &mut insert.source {
+                    InsertSource::Values(rows) => {
+                        for expr in rows.iter_mut().flatten() {
+                            self.expr(expr)?;
+                        }
+                    }
+                    InsertSource::Select(select) => self.select(select)?,
+                }
+                let excluded = Source {
+                    name: "excluded".to_string(),
+                    aliased: true,
+                    ..target.clone()
+                };
+                self.scopes.push(vec![target, excluded]);
+                if let Some(UpsertAction::Update {
+                    assignments,
+                    where_clause,
+                }) = insert.upsert.as_mut().map(|upsert| &mut upsert.action)
+                {
+                    for expr in assignments.iter_mut().map(|(_, expr)| expr).chain(where_clause.as_mut()) {
+                        self.expr(expr)?;
+                    }
+                }
+                self.items(&mut insert.returning)?;
+                self.scopes.pop();
+            }
+            Stmt::Update(update) => {
+                let target = self.source(&mut update.table, None);
+                if target.altered {
+                    update.assignments.iter_mut().for_each(|(column, _)| self.column(column));
+                }
+                self.scopes.push(vec![target]);
+                let exprs = update
+                    .assignments
+                    .iter_mut()
+                    .map(|(_, expr)| expr)
+                    .chain(update.where_clause.as_mut());
+                for expr in exprs {
+                    self.expr(expr)?;
+                }
+                self.items(&mut update.returning)?;
+                self.scopes.pop();
+            }
+            Stmt::Delete(delete) => {
+                let target = self.source(&mut delete.table, None);
+                self.scopes.push(vec![target]);
+                if let Some(where_clause) = &mut delete.where_clause {
+                    self.expr(where_clause)?;
+                }
+                self.items(&mut delete.returning)?;
+                self.scopes.pop();
+            }
+            Stmt::Select(select) => self.select(select)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn select(&mut self, select: &mut Select) -> Result<(), Error> {
+        let common_tables = self.common_tables.len();
+        if let Some(with) = &mut select.with {
+            let recursive = with.recursive;
+            for cte in &mut with.tables {
+                if recursive {
+                    self.common_tables.push(cte.name.clone());
+                }
+                self.select(&mut cte.select)?;
+                if !recursive {
+                    self.common_tables.push(cte.name.clone());
+                }
+            }
+        }
+        let mut sources = Vec::new();
+        let table_refs = select.from.iter_mut().chain(select.joins.iter_mut().map(|join| &mut join.table));
+        for table_ref in table_refs {
+            sources.push(self.table_ref(table_ref)?);
+        }
+        self.scopes.push(sources);
+        self.items(&mut select.columns)?;
+        let exprs = select
+            .joins
+            .iter_mut()
+            .filter_map(|join| join.constraint.as_mut())
+            .chain(select.where_clause.as_mut())
+            .chain(select.group_by.iter_mut())
+            .chain(select.having.as_mut())
+            .chain(select.limit.as_mut())
+            .chain(select.offset.as_mut());
+        for expr in exprs {
+            self.expr(expr)?;
+        }
+        // A term naming a result column is not a column of a table.
+        let aliases: Vec<String> = select
+            .columns
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Expr { alias: Some(alias), .. } => Some(alias.clone()),
+                _ => None,
+            })
+            .collect();
+        for term in &mut select.order_by {
+            let is_alias = matches!(
+                &term.expr,
+                Expr::Column { table: None, name } if aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+            );
+            if !is_alias {
+                self.expr(&mut term.expr)?;
+            }
+        }
+        self.scopes.pop();
+        for term in &mut select.compound {
+            self.select(&mut term.select)?;
+        }
+        self.common_tables.truncate(common_tables);
+        Ok(())
+    }
+
+    fn table_ref(&mut self, table_ref: &mut TableRef) -> Result<Source, Error> {
+        match &mut table_ref.subquery {
+            Some(subquery) => {
+                self.select(subquery)?;
+                Ok(Source {
+                    name: table_ref.alias.clone().unwrap_or_default(),
+                    aliased: true,
+                    columns: None,
+                    altered: false,
+                })
+            }
+            None => Ok(self.source(&mut table_ref.name, table_ref.alias.as_deref())),
+        }
+    }
+
+    // The source for a table named by a query, whose name is rewritten when
+    // it is the table renamed.
+    fn source(&mut self, name: &mut String, alias: Option<&str>) -> Source {
+        let common_table = self.common_tables.iter().any(|other| other.eq_ignore_ascii_case(name));
+        let columns = if common_table {
+            None
+        } else if let Some(schema) = self.catalog.table(name) {
+            Some(schema.columns.iter().map(|column| column.name.clone()).collect())
+        } else {
+            self.catalog
+                .view(name)
+                .map(|view| view.columns.clone())
+                .filter(|columns| !columns.is_empty())
+        };
+        let source = Source {
+            name: alias.unwrap_or(name).to_string(),
+            aliased: alias.is_some(),
+            columns,
+            altered: !common_table && name.eq_ignore_ascii_case(&self.schema.name),
+        };
+        if let (true, Change::RenameTable(new)) = (source.altered, self.change) {
+            *name = new.to_string();
+            self.found = true;
+        }
+        source
+    }
+
+    fn items(&mut self, items: &mut [SelectItem]) -> Result<(), Error> {
+        for item in items {
+            match item {
+                SelectItem::Expr { expr, .. } => self.expr(expr)?,
+                SelectItem::TableWildcard(qualifier) => {
+                    if self.resolve_qualifier(qualifier).is_some() {
+                        self.rename_qualifier(qualifier);
+                    }
+                }
+                SelectItem::Wildcard => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &mut Expr) -> Result<(), Error> {
+        *expr = expr.rewrite(&mut |node| self.node(node))?;
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Expr) -> Result<Option<Expr>, Error> {
+        Ok(Some(match node {
+            Expr::Column { table, name } => {
+                let mut table = table.clone();
+                let mut name = name.clone();
+                self.column_ref(&mut table, &mut name);
+                Expr::Column { table, name }
+            }
+            Expr::Subquery(select) => {
+                let mut select = select.clone();
+                self.select(&mut select)?;
+                Expr::Subquery(select)
+            }
+            Expr::Exists(select) => {
+                let mut select = select.clone();
+                self.select(&mut select)?;
+                Expr::Exists(select)
+            }
+            Expr::InSubquery { expr, select, negated } => {
+                let mut expr = expr.clone();
+                self.expr(&mut expr)?;
+                let mut select = select.clone();
+                self.select(&mut select)?;
+                Expr::InSubquery {
+                    expr,
+                    select,
+                    negated: *negated,
+                }
+            }
+            _ => return Ok(None),
+        }))
+    }
+
+    fn column_ref(&mut self, qualifier: &mut Option<String>, name: &mut String) {
+        match qualifier {
+            Some(qualifier) if self.trigger_rows && is_trigger_row(qualifier) => self.column(name),
+            Some(qualifier) => {
+                if self.resolve_qualifier(qualifier).is_some() {
+                    self.rename_qualifier(qualifier);
+                    self.column(name);
+                }
+            }
+            None => {
+                if self.resolve_unqualified(name) {
+                    self.column(name);
+                }
+            }
+        }
+    }
+
+    // The source of the table being altered a qualifier names, if it does.
+    fn resolve_qualifier(&self, qualifier: &str) -> Option<&Source> {
+        self.scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|source| source.name.eq_ignore_ascii_case(qualifier))
+            .filter(|source| source.altered)
+    }
+
+    // Whether an unqualified column is one of the table being altered.
+    fn resolve_unqualified(&self, name: &str) -> bool {
+        for scope in self.scopes.iter().rev() {
+            let owners: Vec<&Source> = scope
+                .iter()
+                .filter(|source| {
+                    source
+                        .columns
+                        .as_ref()
+                        .is_some_and(|columns| columns.iter().any(|column| column.eq_ignore_ascii_case(name)))
+                })
+                .collect();
+            if let [owner] = owners.as_slice() {
+                return owner.altered;
+            }
+            if !owners.is_empty() || scope.iter().any(|source| source.columns.is_none()) {
+                return false;
+            }
+        }
+        false
+    }
+
+    // Renames the qualifier of a column of the renamed table when it is the
+    // name of the table rather than an alias.
+    fn rename_qualifier(&mut self, qualifier: &mut String) {
+        let aliased = self.resolve_qualifier(qualifier).is_some_and(|source| source.aliased);
+        if let (false, Change::RenameTable(new)) = (aliased, self.change) {
+            *qualifier = new.to_string();
+        }
+    }
+
+    // A reference to a column of the table being altered.
+    fn column(&mut self, name: &mut String) {
+        match self.change {
+            Change::RenameColumn { old, new } if name.eq_ignore_ascii_case(old) => {
+                *name = new.to_string();
+                self.found = true;
+            }
+            Change::DropColumn(dropped) if name.eq_ignore_ascii_case(dropped) => self.found = true,
+            _ => {}
+        }
+    }
+}
+
+fn is_trigger_row(qualifier: &str) -> bool {
+    qualifier.eq_ignore_ascii_case("new") || qualifier.eq_ignore_ascii_case("old")
+}
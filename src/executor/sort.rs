@@ -0,0 +1,208 @@
+use crate::pager::temp::TempPager;
+use crate::table::record::{decode_values, encode_values};
+use crate::table::table::PAGE_SIZE;
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::io::Error;
+use std::mem;
+
+// Rows waiting to be sorted, each with the values it is sorted by.
+pub type SortEntry = (Vec<Value>, Vec<Value>);
+
+// External merge sort. Rows are buffered in memory until the buffer is full,
+// then the buffer is sorted and written to temp pages as a run. Finishing
+// merges the runs, or just sorts the buffer when nothing was spilled.
+// Rows with equal keys keep the order they were added in.
+pub struct Sorter {
+    descending: Vec<bool>,
+    buffer_rows: usize,
+    buffer: Vec<SortEntry>,
+    temp: Option<TempPager>,
+    runs: Vec<Run>,
+}
+
+// A sorted run stored in consecutive temp pages as length prefixed entries.
+struct Run {
+    first_page: u32,
+    len: usize,
+}
+
+impl Sorter {
+    // descending holds one flag per sort key, buffer_rows is the number of
+    // rows kept in memory before spilling.
+    pub fn new(descending: Vec<bool>, buffer_rows: usize) -> Sorter {
+        Sorter {
+            descending,
+            buffer_rows: buffer_rows.max(1),
+            buffer: Vec::new(),
+            temp: None,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, keys: Vec<Value>, row: Vec<Value>) -> Result<(), Error> {
+        self.buffer.push((keys, row));
+        if self.buffer.len() >= self.buffer_rows {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<SortedRows, Error> {
+        if self.runs.is_empty() {
+            self.sort_buffer();
+            return Ok(SortedRows::Memory(mem::take(&mut self.buffer).into_iter()));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        let temp = self.temp.take().expect("spilled runs without temp pages");
+        let readers = self.runs.iter().map(RunReader::new).collect();
+        let mut merge = Merge {
+            descending: self.descending,
+            temp,
+            readers,
+            heads: Vec::new(),
+        };
+        for i in 0..merge.readers.len() {
+            let head = merge.readers[i].next(&mut merge.temp)?;
+            merge.heads.push(head);
+        }
+        Ok(SortedRows::Merge(Box::new(merge)))
+    }
+
+    fn sort_buffer(&mut self) {
+        let descending = &self.descending;
+        self.buffer.sort_by(|a, b| compare_keys(descending, &a.0, &b.0));
+    }
+
+    fn spill(&mut self) -> Result<(), Error> {
+        self.sort_buffer();
+        if self.temp.is_none() {
+            self.temp = Some(TempPager::new()?);
+        }
+        let temp = self.temp.as_mut().unwrap();
+        let mut bytes = Vec::new();
+        for (keys, row) in self.buffer.drain(..) {
+            let mut entry = encode_values(&keys);
+            entry.extend(encode_values(&row));
+            bytes.extend_from_slice(&(entry.len() as u32).to_be_bytes());
+            bytes.extend(entry);
+        }
+        let first_page = temp.num_pages;
+        for chunk in bytes.chunks(PAGE_SIZE) {
+            let mut page = [0u8; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            let page_num = temp.allocate_page();
+            temp.write_page(page_num, &page)?;
+        }
+        self.runs.push(Run {
+            first_page,
+            len: bytes.len(),
+        });
+        Ok(())
+    }
+}
+
+pub enum SortedRows {
+    Memory(std::vec::IntoIter<SortEntry>),
+    Merge(Box<Merge>),
+}
+
+impl SortedRows {
+    pub fn next(&mut self) -> Result<Option<Vec<Value>>, Error> {
+        match self {
+            SortedRows::Memory(rows) => Ok(rows.next().map(|(_, row)| row)),
+            SortedRows::Merge(merge) => merge.next(),
+        }
+    }
+}
+
+pub struct Merge {
+    descending: Vec<bool>,
+    temp: TempPager,
+    readers: Vec<RunReader>,
+    heads: Vec<Option<SortEntry>>,
+}
+
+impl Merge {
+    fn next(&mut self) -> Result<Option<Vec<Value>>, Error> {
+        // Earlier runs hold earlier rows, so ties go to the lowest run.
+        let mut smallest: Option<usize> = None;
+        for (i, head) in self.heads.iter().enumerate() {
+            let Some((keys, _)) = head else { continue };
+            let is_smaller = match smallest.and_then(|s| self.heads[s].as_ref()) {
+                Some((smallest_keys, _)) => compare_keys(&self.descending, keys, smallest_keys) == Ordering::Less,
+                None => true,
+            };
+            if is_smaller {
+                smallest = Some(i);
+            }
+        }
+        let Some(i) = smallest else { return Ok(None) };
+        let next_head = self.readers[i].next(&mut self.temp)?;
+        let (_, row) = mem::replace(&mut self.heads[i], next_head).unwrap();
+        Ok(Some(row))
+    }
+}
+
+struct RunReader {
+    next_page: u32,
+    remaining: usize,
+    bytes: Vec<u8>,
+    offset: usize,
+}
+
+impl RunReader {
+    fn new(run: &Run) -> RunReader {
+        RunReader {
+            next_page: run.first_page,
+            remaining: run.len,
+            bytes: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    fn next(&mut self, temp: &mut TempPager) -> Result<Option<SortEntry>, Error> {
+        if !self.fill(temp, 4)? {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(self.bytes[self.offset..self.offset + 4].try_into().unwrap()) as usize;
+        self.fill(temp, 4 + len)?;
+        let mut offset = self.offset + 4;
+        let keys = decode_values(&self.bytes, &mut offset);
+        let row = decode_values(&self.bytes, &mut offset);
+        self.offset += 4 + len;
+        Ok(Some((keys, row)))
+    }
+
+    // Reads pages until at least needed unread bytes are buffered, returns
+    // false at the end of the run.
+    fn fill(&mut self, temp: &mut TempPager, needed: usize) -> Result<bool, Error> {
+        self.bytes.drain(..self.offset);
+        self.offset = 0;
+        while self.bytes.len() < needed && self.remaining > 0 {
+            let mut page = [0u8; PAGE_SIZE];
+            temp.read_page(self.next_page, &mut page)?;
+            let take = self.remaining.min(PAGE_SIZE);
+            self.bytes.extend_from_slice(&page[..take]);
+            self.remaining -= take;
+            self.next_page += 1;
+        }
+        Ok(self.bytes.len() >= needed)
+    }
+}
+
+pub fn compare_keys(descending: &[bool], a: &[Value], b: &[Value]) -> Ordering {
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        let ordering = a.compare(b);
+        if ordering != Ordering::Equal {
+            return if descending.get(i).copied().unwrap_or(false) {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+        }
+    }
+    Ordering::Equal
+}
@@ -0,0 +1,167 @@
+use crate::executor::dispatch::ResultSet;
+use crate::executor::expr::{eval, ColumnBinding, NoSuchColumn, Scope};
+use crate::executor::query::{execute_select, execute_subquery};
+use crate::sql::ast::{Expr, OrderingTerm, Select, SelectItem};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::Error;
+
+// Running a subquery needs the table, so eval does not run them. Before a
+// statement is executed every subquery in it is run once on its own and
+// replaced by its result: a scalar subquery by the value, EXISTS by 0 or 1
+// and IN (SELECT ...) by an IN list. A subquery reading columns of the
+// statement's rows fails to resolve them on its own. It is correlated and
+// replaced by a hidden column instead, computed for every row by running
+// the subquery with that row in scope.
+
+// The correlated subqueries of a statement, one per hidden column.
+#[derive(Default)]
+pub struct Correlated {
+    subqueries: Vec<Expr>,
+    // Why the first subquery could not run on its own.
+    error: Option<Error>,
+}
+
+impl Correlated {
+    // Replaces the subqueries of an expression by their results or by
+    // hidden columns.
+    pub unsafe fn plan(&mut self, table: &mut Table, expr: &Expr) -> Result<Expr, Error> {
+        expr.rewrite(&mut |node| plan_node(table, self, node))
+    }
+
+    // Replaces the subqueries of an expression that has no row to be
+    // correlated with, like a LIMIT or an INSERT value.
+    pub unsafe fn plan_uncorrelated(table: &mut Table, expr: &Expr) -> Result<Expr, Error> {
+        let mut correlated = Correlated::default();
+        let expr = correlated.plan(table, expr)?;
+        match correlated.error {
+            Some(error) => Err(error),
+            None => Ok(expr),
+        }
+    }
+
+    // Plans the expressions of a select. Its FROM clause is run by the
+    // select itself.
+    pub unsafe fn plan_select(&mut self, table: &mut Table, select: &Select) -> Result<Select, Error> {
+        let mut planned = select.clone();
+        for item in &mut planned.columns {
+            if let SelectItem::Expr { expr, .. } = item {
+                *expr = self.plan(table, expr)?;
+            }
+        }
+        for join in &mut planned.joins {
+            if let Some(constraint) = &join.constraint {
+                join.constraint = Some(Correlated::plan_uncorrelated(table, constraint)?);
+            }
+        }
+        if let Some(where_clause) = &select.where_clause {
+            planned.where_clause = Some(self.plan(table, where_clause)?);
+        }
+        for expr in &mut planned.group_by {
+            *expr = self.plan(table, expr)?;
+        }
+        if let Some(having) = &select.having {
+            planned.having = Some(self.plan(table, having)?);
+        }
+        for OrderingTerm { expr, .. } in &mut planned.order_by {
+            *expr = self.plan(table, expr)?;
+        }
+        if let Some(limit) = &select.limit {
+            planned.limit = Some(Correlated::plan_uncorrelated(table, limit)?);
+        }
+        if let Some(offset) = &select.offset {
+            planned.offset = Some(Correlated::plan_uncorrelated(table, offset)?);
+        }
+        Ok(planned)
+    }
+
+    // Bindings of the hidden columns, they follow the bindings of the row.
+    pub fn bindings(&self) -> Vec<ColumnBinding> {
+        (0..self.subqueries.len())
+            .map(|n| ColumnBinding::qualified("", &n.to_string()))
+            .collect()
+    }
+
+    // Appends the hidden columns to a row. bindings are the bindings of the
+    // row followed by the ones of the hidden columns.
+    pub unsafe fn append_values(&self, table: &mut Table, bindings: &[ColumnBinding], row: &mut Vec<Value>) -> Result<(), Error> {
+        for subquery in &self.subqueries {
+            let outer = &bindings[..row.len()];
+            let value = match subquery {
+                Expr::Subquery(select) => scalar(execute_subquery(table, select, outer, row)?)?,
+                Expr::Exists(select) => Value::from_bool(!execute_subquery(table, select, outer, row)?.rows.is_empty()),
+                Expr::InSubquery { expr, select, negated } => {
+                    let list = in_list(execute_subquery(table, select, outer, row)?)?;
+                    let in_list = Expr::InList {
+                        expr: expr.clone(),
+                        list,
+                        negated: *negated,
+                    };
+                    eval(&in_list, &Scope::new(&table.functions, outer, row))?
+                }
+                _ => unreachable!(),
+            };
+            row.push(value);
+        }
+        Ok(())
+    }
+
+    fn hide(&mut self, subquery: Expr, error: Error) -> Expr {
+        self.error.get_or_insert(error);
+        self.subqueries.push(subquery);
+        Expr::Column {
+            table: Some(String::new()),
+            name: (self.subqueries.len() - 1).to_string(),
+        }
+    }
+}
+
+unsafe fn plan_node(table: &mut Table, correlated: &mut Correlated, node: &Expr) -> Result<Option<Expr>, Error> {
+    let select = match node {
+        Expr::Subquery(select) | Expr::Exists(select) | Expr::InSubquery { select, .. } => select,
+        _ => return Ok(None),
+    };
+    let result = match execute_select(table, select) {
+        Ok(result) => result,
+        Err(error) if NoSuchColumn::from_error(&error).is_some() => {
+            let node = match node {
+                Expr::InSubquery { expr, select, negated } => Expr::InSubquery {
+                    expr: Box::new(correlated.plan(table, expr)?),
+                    select: select.clone(),
+                    negated: *negated,
+                },
+                _ => node.clone(),
+            };
+            return Ok(Some(correlated.hide(node, error)));
+        }
+        Err(error) => return Err(error),
+    };
+    Ok(Some(match node {
+        Expr::Subquery(_) => Expr::Literal(scalar(result)?),
+        Expr::Exists(_) => Expr::Literal(Value::from_bool(!result.rows.is_empty())),
+        Expr::InSubquery { expr, negated, .. } => Expr::InList {
+            expr: Box::new(correlated.plan(table, expr)?),
+            list: in_list(result)?,
+            negated: *negated,
+        },
+        _ => unreachable!(),
+    }))
+}
+
+// The value of a scalar subquery: its first row's value, NULL without rows.
+fn scalar(result: ResultSet) -> Result<Value, Error> {
+    check_single_column(&result)?;
+    Ok(result.rows.into_iter().next().map_or(Value::Null, |mut row| row.remove(0)))
+}
+
+fn in_list(result: ResultSet) -> Result<Vec<Expr>, Error> {
+    check_single_column(&result)?;
+    Ok(result.rows.into_iter().map(|mut row| Expr::Literal(row.remove(0))).collect())
+}
+
+fn check_single_column(result: &ResultSet) -> Result<(), Error> {
+    if result.columns.len() != 1 {
+        return Err(Error::other(format!("sub-select returns {} columns - expected 1", result.columns.len())));
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use crate::executor::dispatch::ExecuteResult;
+use crate::executor::dml::{execute_delete, execute_insert, execute_update};
+use crate::executor::expr::{eval, is_true, Scope};
+use crate::executor::query::execute_select;
+use crate::executor::subquery::Correlated;
+use crate::executor::view::check_nesting;
+use crate::schema::definition::{TableSchema, TriggerSchema};
+use crate::sql::ast::{Expr, InsertSource, Select, SelectItem, Stmt, TriggerEvent, TriggerTiming, UpsertAction};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::io::Error;
+
+// Triggers fire once for every row a statement changes. Before the WHEN
+// clause and the statements of a trigger run, their references to NEW and
+// OLD are replaced by the values of the row, in subqueries too. Statements
+// run by a trigger fire triggers of their own, except for the ones already
+// running. A chain of them fails past the nesting limit of view.rs.
+
+// The triggers of a table a statement fires.
+pub struct Triggers {
+    schema: TableSchema,
+    triggers: Vec<TriggerSchema>,
+}
+
+impl Triggers {
+    // event is the kind of statement, for an UPDATE with the columns it
+    // assigns.
+    pub fn new(table: &Table, schema: &TableSchema, event: &TriggerEvent) -> Triggers {
+        let triggers = table
+            .catalog
+            .triggers
+            .iter()
+            .filter(|trigger| trigger.table_name.eq_ignore_ascii_case(&schema.name) && fires_on(&trigger.event, event))
+            .cloned()
+            .collect();
+        Triggers {
+            schema: schema.clone(),
+            triggers,
+        }
+    }
+
+    pub unsafe fn fire(
+        &self,
+        table: &mut Table,
+        timing: TriggerTiming,
+        old: Option<&[Value]>,
+        new: Option<&[Value]>,
+    ) -> Result<(), Error> {
+        let row = TriggerRow {
+            schema: &self.schema,
+            old,
+            new,
+        };
+        for trigger in self.triggers.iter().filter(|trigger| trigger.timing == timing) {
+            if table.running_triggers.iter().any(|name| name.eq_ignore_ascii_case(&trigger.name)) {
+                continue;
+            }
+            if let Some(when) = &trigger.when {
+                let when = Correlated::plan_uncorrelated(table, &row.bind(when)?)?;
+                if !is_true(&eval(&when, &Scope::new(&table.functions, &[], &[]))?) {
+                    continue;
+                }
+            }
+            check_nesting(table, table.view_depth)?;
+            table.running_triggers.push(trigger.name.clone());
+            let result = run_statements(table, trigger, &row);
+            table.running_triggers.pop();
+            result?;
+        }
+        Ok(())
+    }
+}
+
+fn fires_on(trigger_event: &TriggerEvent, event: &TriggerEvent) -> bool {
+    match (trigger_event, event) {
+        (TriggerEvent::Insert, TriggerEvent::Insert) | (TriggerEvent::Delete, TriggerEvent::Delete) => true,
+        (TriggerEvent::Update(columns), TriggerEvent::Update(assigned)) => {
+            columns.is_empty()
+                || columns
+                    .iter()
+                    .any(|column| assigned.iter().any(|name| name.eq_ignore_ascii_case(column)))
+        }
+        _ => false,
+    }
+}
+
+unsafe fn run_statements(table: &mut Table, trigger: &TriggerSchema, row: &TriggerRow) -> Result<(), Error> {
+    for stmt in &trigger.body {
+        match row.bind_statement(stmt)? {
+            Stmt::Insert(insert) => execute_insert(table, &insert)?,
+            Stmt::Update(update) => execute_update(table, &update)?,
+            Stmt::Delete(delete) => execute_delete(table, &delete)?,
+            Stmt::Select(select) => ExecuteResult::Rows(execute_select(table, &select)?),
+            _ => unreachable!("trigger statements are INSERT, UPDATE, DELETE or SELECT"),
+        };
+    }
+    Ok(())
+}
+
+// The row a trigger fires for, as NEW and OLD.
+struct TriggerRow<'a> {
+    schema: &'a TableSchema,
+    old: Option<&'a [Value]>,
+    new: Option<&'a [Value]>,
+}
+
+impl TriggerRow<'_> {
+    fn bind(&self, expr: &Expr) -> Result<Expr, Error> {
+        expr.rewrite(&mut |node| self.bind_node(node))
+    }
+
+    fn bind_node(&self, node: &Expr) -> Result<Option<Expr>, Error> {
+        Ok(Some(match node {
+            Expr::Column {
+                table: Some(qualifier),
+                name,
+            } => {
+                let row = match qualifier.to_ascii_lowercase().as_str() {
+                    "new" => self.new,
+                    "old" => self.old,
+                    _ => return Ok(None),
+                };
+                let value = row.and_then(|row| {
+                    let i = self.schema.column_index(name).ok()?;
+                    Some(row[i].clone())
+                });
+                match value {
+                    Some(value) => Expr::Literal(value),
+                    None => {
+                        return Err(Error::other(format!("no such column: {}.{}", qualifier, name)))
+                    }
+                }
+            }
+            Expr::Subquery(select) => Expr::Subquery(Box::new(self.bind_select(select)?)),
+            Expr::Exists(select) => Expr::Exists(Box::new(self.bind_select(select)?)),
+            Expr::InSubquery { expr, select, negated } => Expr::InSubquery {
+                expr: Box::new(self.bind(expr)?),
+                select: Box::new(self.bind_select(select)?),
+                negated: *negated,
+            },
+            _ => return Ok(None),
+        }))
+    }
+
+    fn bind_in_place(&self, expr: &mut Expr) -> Result<(), Error> {
+        *expr = self.bind(expr)?;
+        Ok(())
+    }
+
+    fn bind_items(&self, items: &mut [SelectItem]) -> Result<(), Error> {
+        for item in items {
+            if let SelectItem::Expr { expr, .. } = item {
+                self.bind_in_place(expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_select(&self, select: &Select) -> Result<Select, Error> {
+        let mut bound = select.clone();
+        if let Some(with) = &mut bound.with {
+            for cte in &mut with.tables {
+                cte.select = self.bind_select(&cte.select)?;
+            }
+        }
+        self.bind_items(&mut bound.columns)?;
+        let table_refs = bound.from.iter_mut().chain(bound.joins.iter_mut().map(|join| &mut join.table));
+        for table_ref in table_refs {
+            if let Some(subquery) = &mut table_ref.subquery {
+                **subquery = self.bind_select(subquery)?;
+            }
+        }
+        let exprs = bound
+            .joins
+            .iter_mut()
+            .filter_map(|join| join.constraint.as_mut())
+            .chain(bound.where_clause.as_mut())
+            .chain(bound.group_by.iter_mut())
+            .chain(bound.having.as_mut())
+            .chain(bound.order_by.iter_mut().map(|term| &mut term.expr))
+            .chain(bound.limit.as_mut())
+            .chain(bound.offset.as_mut());
+        for expr in exprs {
+            self.bind_in_place(expr)?;
+        }
+        for term in &mut bound.compound {
+            term.select = self.bind_select(&term.select)?;
+        }
+        Ok(bound)
+    }
+
+    fn bind_statement(&self, stmt: &Stmt) -> Result<Stmt, Error> {
+        let mut bound = stmt.clone();
+        match &mut bound {
+            Stmt::Insert(insert) => {
+                match &mut insert.source {
+                    InsertSource::Values(rows) => {
+                        for expr in rows.iter_mut().flatten() {
+                            self.bind_in_place(expr)?;
+                        }
+                    }
+                    InsertSource::Select(select) => **select = self.bind_select(select)?,
+                }
+                if let Some(UpsertAction::Update {
+                    assignments,
+                    where_clause,
+                }) = insert.upsert.as_mut().map(|upsert| &mut upsert.action)
+                {
+                    for expr in assignments.iter_mut().map(|(_, expr)| expr).chain(where_clause.as_mut()) {
+                        self.bind_in_place(expr)?;
+                    }
+                }
+                self.bind_items(&mut insert.returning)?;
+            }
+            Stmt::Update(update) => {
+                let exprs = update
+                    .assignments
+                    .iter_mut()
+                    .map(|(_, expr)| expr)
+                    .chain(update.where_clause.as_mut());
+                for expr in exprs {
+                    self.bind_in_place(expr)?;
+                }
+                self.bind_items(&mut update.returning)?;
+            }
+            Stmt::Delete(delete) => {
+                if let Some(where_clause) = &mut delete.where_clause {
+                    self.bind_in_place(where_clause)?;
+                }
+                self.bind_items(&mut delete.returning)?;
+            }
+            Stmt::Select(select) => **select = self.bind_select(select)?,
+            _ => {}
+        }
+        Ok(bound)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::executor::dispatch::ResultSet;
+use crate::executor::query::execute_select;
+use crate::schema::definition::ViewSchema;
+use crate::table::table::Table;
+use std::io::Error;
+use std::mem;
+
+// Deepest triggers and views nest in each other, a trigger firing another one
+// or a view reading another one. Every level nests statements or selects in
+// the ones before, a chain of views is cut off well before MAX_SELECT_DEPTH
+// of query.rs.
+const MAX_NESTING_DEPTH: usize = 16;
+
+// A view is expanded by running its select whenever a query reads it, like a
+// subquery in FROM. The select only sees the tables of the schema, not the
+// common tables of the query reading the view. Views can not be circular:
+// a view's select is run when it is created, before its name exists.
+pub unsafe fn execute_view(table: &mut Table, view: &ViewSchema) -> Result<ResultSet, Error> {
+    check_nesting(table, table.view_depth)?;
+    let common_tables = mem::take(&mut table.common_tables);
+    table.view_depth += 1;
+    let result = execute_select(table, &view.select);
+    table.view_depth -= 1;
+    table.common_tables = common_tables;
+    let mut result = result?;
+    if !view.columns.is_empty() {
+        if view.columns.len() != result.columns.len() {
+            return Err(Error::other(
+                format!(
+                    "expected {} columns for '{}' but got {}",
+                    view.columns.len(),
+                    view.name,
+                    result.columns.len()
+                ),
+            ));
+        }
+        result.columns = view.columns.clone();
+    }
+    Ok(result)
+}
+
+// Fails when another trigger or view would nest past MAX_NESTING_DEPTH, with
+// views_expanded views being expanded.
+pub fn check_nesting(table: &Table, views_expanded: usize) -> Result<(), Error> {
+    if table.running_triggers.len() + views_expanded >= MAX_NESTING_DEPTH {
+        return Err(Error::other("too many levels of trigger/view nesting"));
+    }
+    Ok(())
+}
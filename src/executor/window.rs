@@ -0,0 +1,326 @@
+use crate::executor::aggregate::{aggregate_rows, is_aggregate_call};
+use crate::executor::expr::{eval, ColumnBinding, Scope};
+use crate::executor::function::Functions;
+use crate::executor::sort::{compare_keys, Sorter};
+use crate::sql::ast::{Expr, FrameBound, FrameUnits, Select, SelectItem, WindowSpec};
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::cmp::Ordering;
+use std::io::Error;
+use std::ops::Range;
+
+// Window calls are computed once every matching row of a select is read and
+// before the rows are projected. For each call the rows are sorted by its
+// PARTITION BY values then its ORDER BY values, and the call is computed for
+// every row of a partition from the rows of its frame. The results are
+// appended to the rows as hidden columns, which the calls in the result
+// columns and ORDER BY terms are replaced by.
+
+pub struct Windows {
+    calls: Vec<Expr>,
+}
+
+impl Windows {
+    // Replaces the window calls in the result columns and ORDER BY terms of a
+    // select by hidden columns. None when the select calls none.
+    pub fn plan(select: &Select) -> Result<Option<(Windows, Select)>, Error> {
+        if !calls_window(select) {
+            return Ok(None);
+        }
+        let mut windows = Windows { calls: Vec::new() };
+        let mut planned = select.clone();
+        for item in &mut planned.columns {
+            if let SelectItem::Expr { expr, .. } = item {
+                *expr = windows.replace(expr)?;
+            }
+        }
+        for term in &mut planned.order_by {
+            term.expr = windows.replace(&term.expr)?;
+        }
+        Ok(Some((windows, planned)))
+    }
+
+    fn replace(&mut self, expr: &Expr) -> Result<Expr, Error> {
+        expr.rewrite(&mut |expr| {
+            if !matches!(expr, Expr::Window { .. }) {
+                return Ok(None);
+            }
+            let index = match self.calls.iter().position(|call| call == expr) {
+                Some(index) => index,
+                None => {
+                    self.calls.push(expr.clone());
+                    self.calls.len() - 1
+                }
+            };
+            Ok(Some(Expr::Column {
+                table: Some(String::new()),
+                name: format!("w{}", index),
+            }))
+        })
+    }
+
+    pub fn bindings(&self) -> Vec<ColumnBinding> {
+        (0..self.calls.len())
+            .map(|i| ColumnBinding::qualified("", &format!("w{}", i)))
+            .collect()
+    }
+
+    // Appends the result of every call to each row.
+    pub fn append_values(&self, table: &Table, bindings: &[ColumnBinding], rows: &mut [Vec<Value>]) -> Result<(), Error> {
+        for call in &self.calls {
+            let results = compute(table, call, bindings, rows)?;
+            for (row, value) in rows.iter_mut().zip(results) {
+                row.push(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn calls_window(select: &Select) -> bool {
+    let mut found = false;
+    for item in &select.columns {
+        if let SelectItem::Expr { expr, .. } = item {
+            expr.walk(&mut |expr| found |= matches!(expr, Expr::Window { .. }));
+        }
+    }
+    for term in &select.order_by {
+        term.expr.walk(&mut |expr| found |= matches!(expr, Expr::Window { .. }));
+    }
+    found
+}
+
+enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    Aggregate,
+}
+
+impl WindowFunction {
+    fn new(call: &Expr, functions: &Functions) -> Result<WindowFunction, Error> {
+        let Expr::Function {
+            name,
+            args,
+            wildcard,
+            distinct,
+        } = call
+        else {
+            unreachable!()
+        };
+        if *distinct {
+            return Err(Error::other("DISTINCT is not supported for window functions"));
+        }
+        if is_aggregate_call(call, Some(functions)) {
+            return Ok(WindowFunction::Aggregate);
+        }
+        let (function, arity) = match name.to_ascii_lowercase().as_str() {
+            "row_number" => (WindowFunction::RowNumber, 0..=0),
+            "rank" => (WindowFunction::Rank, 0..=0),
+            "dense_rank" => (WindowFunction::DenseRank, 0..=0),
+            "lag" => (WindowFunction::Lag, 1..=3),
+            "lead" => (WindowFunction::Lead, 1..=3),
+            "first_value" => (WindowFunction::FirstValue, 1..=1),
+            "last_value" => (WindowFunction::LastValue, 1..=1),
+            _ => {
+                return Err(Error::other(format!("{}() may not be used as a window function", name)))
+            }
+        };
+        if *wildcard || !arity.contains(&args.len()) {
+            return Err(Error::other(format!("wrong number of arguments to function {}()", name)));
+        }
+        Ok(function)
+    }
+}
+
+// Where a frame starts or ends: at an end of the partition, a number of rows
+// from the current row, or at the first or past the last peer of the
+// current row.
+#[derive(Clone, Copy)]
+enum Edge {
+    Unbounded,
+    Rows(i64),
+    Peers,
+}
+
+struct Frame {
+    start: Edge,
+    end: Edge,
+}
+
+impl Frame {
+    fn new(window: &WindowSpec) -> Result<Frame, Error> {
+        let Some(frame) = &window.frame else {
+            // Without ORDER BY every row of the partition is a peer.
+            return Ok(Frame {
+                start: Edge::Unbounded,
+                end: Edge::Peers,
+            });
+        };
+        let edge = |bound: &FrameBound, which: &str| -> Result<Edge, Error> {
+            Ok(match (bound, frame.units) {
+                (FrameBound::UnboundedPreceding, _) if which == "starting" => Edge::Unbounded,
+                (FrameBound::UnboundedFollowing, _) if which == "ending" => Edge::Unbounded,
+                (FrameBound::UnboundedPreceding | FrameBound::UnboundedFollowing, _) => {
+                    return Err(Error::other("unsupported frame specification"))
+                }
+                (FrameBound::CurrentRow, FrameUnits::Rows) => Edge::Rows(0),
+                (FrameBound::CurrentRow, FrameUnits::Range) => Edge::Peers,
+                (FrameBound::Preceding(_) | FrameBound::Following(_), FrameUnits::Range) => {
+                    return Err(Error::other("RANGE with offset PRECEDING/FOLLOWING is not supported"))
+                }
+                (FrameBound::Preceding(offset), FrameUnits::Rows) => Edge::Rows(-frame_offset(offset, which)?),
+                (FrameBound::Following(offset), FrameUnits::Rows) => Edge::Rows(frame_offset(offset, which)?),
+            })
+        };
+        Ok(Frame {
+            start: edge(&frame.start, "starting")?,
+            end: edge(&frame.end, "ending")?,
+        })
+    }
+
+    // The positions in its partition of the rows in the frame of the row at
+    // position i.
+    fn rows(&self, i: usize, peers: &Range<usize>, len: usize) -> Range<usize> {
+        let position = |edge: Edge, unbounded: usize, peer: usize, past: i64| match edge {
+            Edge::Unbounded => unbounded,
+            Edge::Peers => peer,
+            Edge::Rows(offset) => (i as i64 + offset + past).clamp(0, len as i64) as usize,
+        };
+        let start = position(self.start, 0, peers.start, 0);
+        let end = position(self.end, len, peers.end, 1);
+        start..end.max(start)
+    }
+}
+
+fn frame_offset(offset: &Expr, which: &str) -> Result<i64, Error> {
+    match eval(offset, &Scope::empty())? {
+        Value::Integer(offset) if offset >= 0 => Ok(offset),
+        _ => Err(Error::other(format!("frame {} offset must be a non-negative integer", which))),
+    }
+}
+
+// A row of a partition: its position in the rows of the select and the
+// values it is sorted by.
+struct Entry {
+    index: usize,
+    partition: Vec<Value>,
+    order: Vec<Value>,
+}
+
+// The result of a window call for each row, in the order of the rows.
+fn compute(table: &Table, call: &Expr, bindings: &[ColumnBinding], rows: &[Vec<Value>]) -> Result<Vec<Value>, Error> {
+    let Expr::Window { function, window } = call else { unreachable!() };
+    let Expr::Function { name, args, .. } = function.as_ref() else { unreachable!() };
+    let kind = WindowFunction::new(function, &table.functions)?;
+    let frame = Frame::new(window)?;
+    let partition_len = window.partition_by.len();
+    let descending: Vec<bool> = (0..partition_len)
+        .map(|_| false)
+        .chain(window.order_by.iter().map(|term| term.descending))
+        .collect();
+    let (partition_descending, order_descending) = (descending[..partition_len].to_vec(), descending[partition_len..].to_vec());
+    let mut sorter = Sorter::new(descending, table.pragmas.sort_buffer_rows);
+    for (index, row) in rows.iter().enumerate() {
+        let scope = Scope::new(&table.functions, bindings, row);
+        let keys = window
+            .partition_by
+            .iter()
+            .chain(window.order_by.iter().map(|term| &term.expr))
+            .map(|expr| eval(expr, &scope))
+            .collect::<Result<Vec<Value>, Error>>()?;
+        let mut sorted_row = vec![Value::Integer(index as i64)];
+        sorted_row.extend(keys.iter().cloned());
+        sorter.add(keys, sorted_row)?;
+    }
+    let mut sorted = sorter.finish()?;
+    let mut entries = Vec::with_capacity(rows.len());
+    while let Some(mut row) = sorted.next()? {
+        let order = row.split_off(1 + partition_len);
+        let partition = row.split_off(1);
+        let Value::Integer(index) = row[0] else { unreachable!() };
+        entries.push(Entry {
+            index: index as usize,
+            partition,
+            order,
+        });
+    }
+    let mut results = vec![Value::Null; rows.len()];
+    let mut start = 0;
+    while start < entries.len() {
+        let len = entries[start..]
+            .iter()
+            .take_while(|entry| {
+                compare_keys(&partition_descending, &entry.partition, &entries[start].partition) == Ordering::Equal
+            })
+            .count();
+        let partition = &entries[start..start + len];
+        let args = partition
+            .iter()
+            .map(|entry| {
+                let scope = Scope::new(&table.functions, bindings, &rows[entry.index]);
+                args.iter().map(|arg| eval(arg, &scope)).collect()
+            })
+            .collect::<Result<Vec<Vec<Value>>, Error>>()?;
+        let peers = peer_groups(partition, &order_descending);
+        let mut group = 0;
+        for (i, entry) in partition.iter().enumerate() {
+            while peers[group].end <= i {
+                group += 1;
+            }
+            let value = match kind {
+                WindowFunction::RowNumber => Value::Integer(i as i64 + 1),
+                WindowFunction::Rank => Value::Integer(peers[group].start as i64 + 1),
+                WindowFunction::DenseRank => Value::Integer(group as i64 + 1),
+                WindowFunction::Lag | WindowFunction::Lead => {
+                    let offset = match args[i].get(1) {
+                        None => 1,
+                        Some(Value::Integer(offset)) if *offset >= 0 => *offset,
+                        Some(_) => {
+                            return Err(Error::other(
+                                format!("second argument to {}() must be a non-negative integer", name),
+                            ))
+                        }
+                    };
+                    let offset = if matches!(kind, WindowFunction::Lag) { -offset } else { offset };
+                    let target = i as i64 + offset;
+                    if target >= 0 && (target as usize) < len {
+                        args[target as usize][0].clone()
+                    } else {
+                        args[i].get(2).cloned().unwrap_or(Value::Null)
+                    }
+                }
+                WindowFunction::FirstValue | WindowFunction::LastValue | WindowFunction::Aggregate => {
+                    let in_frame = &args[frame.rows(i, &peers[group], len)];
+                    match kind {
+                        WindowFunction::FirstValue => in_frame.first().map_or(Value::Null, |args| args[0].clone()),
+                        WindowFunction::LastValue => in_frame.last().map_or(Value::Null, |args| args[0].clone()),
+                        _ => aggregate_rows(function, &table.functions, in_frame.iter())?,
+                    }
+                }
+            };
+            results[entry.index] = value;
+        }
+        start += len;
+    }
+    Ok(results)
+}
+
+// The ranges of positions of the rows of a partition with equal ORDER BY
+// values.
+fn peer_groups(partition: &[Entry], descending: &[bool]) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for (i, entry) in partition.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if compare_keys(descending, &partition[group.start].order, &entry.order) == Ordering::Equal => {
+                group.end = i + 1
+            }
+            _ => groups.push(i..i + 1),
+        }
+    }
+    groups
+}
@@ -1,18 +1,26 @@
+pub mod connection;
 mod cursor;
+mod executor;
 mod node;
 mod pager;
+pub mod prepared;
+pub mod schema;
+mod sql;
 mod statement;
 pub mod table;
+mod vm;
 
-use crate::cursor::cursor::Cursor;
+use crate::executor::dispatch::{execute_sql, ExecuteResult};
+use crate::prepared::statement::Statement as PreparedStatement;
 use crate::statement::{Statement, StatementType};
-use crate::table::row::{Row, ROW_SIZE};
-use crate::table::table::{ROWS_PER_PAGE, Table, TABLE_MAX_ROWS};
-use std::io::{Error, ErrorKind, Write};
+use crate::table::row::Row;
+use crate::table::table::{ROWS_PER_PAGE, Table};
+use crate::vm::compiler::{compile_legacy_insert, compile_legacy_select};
+use crate::vm::machine::execute_program;
+use std::io::Write;
 use std::ops::Deref;
 
 use crate::node::node::Node;
-use std::ptr;
 use crate::pager::pager::TABLE_MAX_PAGES;
 
 pub fn run(command: String, table: &mut Table, writer: impl Write) {
@@ -29,6 +37,11 @@ pub fn run(command: String, table: &mut Table, writer: impl Write) {
     }
 }
 
+// Parses a statement to run with Statement::step, see prepared/statement.rs.
+pub fn prepare(sql: &str) -> Result<PreparedStatement, std::io::Error> {
+    PreparedStatement::prepare(sql)
+}
+
 unsafe fn execute_meta_command(command: &str, table: &mut Table, mut writer: impl Write) {
     if (command == ".btree") {
         writeln!(writer, "Tree:");
@@ -47,22 +60,46 @@ unsafe fn execute_statement(command: &str, table: &mut Table, mut writer: impl W
     match statement_result {
         Ok(statement) => match statement.statement_type {
             StatementType::INSERT => {
-                let insert_resp_result = execute_insert(statement, table);
-                let insert_resp = match insert_resp_result {
-                    Ok(resp) => resp,
-                    Err(e) => e.to_string(),
-                };
-                if insert_resp == "EXECUTE_SUCCESS" {
-                    writeln!(writer, "Executed.");
-                } else {
-                    writeln!(writer, "{}", insert_resp);
+                let program = compile_legacy_insert(table, &statement.row_to_insert);
+                match execute_program(table, &program, &[]) {
+                    Ok(_) => {
+                        let _result = writeln!(writer, "Executed.");
+                    }
+                    Err(error) => {
+                        let _result = writeln!(writer, "{}", error);
+                    }
                 }
             }
             StatementType::UPDATE => {
                 let _result = writeln!(writer, "Update statement will be executed");
             }
             StatementType::SELECT => {
-                execute_select(table, writer);
+                let program = compile_legacy_select(table);
+                match execute_program(table, &program, &[]) {
+                    Ok(ExecuteResult::Rows(result_set)) => {
+                        for row in result_set.rows {
+                            let _result = Row::print_row(Row::from_values(&row), &mut writer);
+                        }
+                    }
+                    Ok(ExecuteResult::Executed { .. }) => {}
+                    Err(error) => {
+                        let _result = writeln!(writer, "{}", error);
+                    }
+                }
+            }
+            StatementType::SQL => {
+                let stmt = statement.sql.expect("SQL statement without a parsed form");
+                match execute_sql(table, stmt, command) {
+                    Ok(ExecuteResult::Rows(result_set)) => {
+                        let _result = result_set.print(&mut writer);
+                    }
+                    Ok(ExecuteResult::Executed { .. }) => {
+                        let _result = writeln!(writer, "Executed.");
+                    }
+                    Err(error) => {
+                        let _result = writeln!(writer, "{}", error);
+                    }
+                }
             }
         },
         Err(error) => {
@@ -71,28 +108,3 @@ unsafe fn execute_statement(command: &str, table: &mut Table, mut writer: impl W
         }
     }
 }
-
-unsafe fn execute_insert(statement: Statement, table: &mut Table) -> Result<String, Error> {
-    let row = statement.row_to_insert;
-    let num_rows = table.num_rows;
-    if num_rows >= TABLE_MAX_ROWS {
-        return Err(Error::new(ErrorKind::Other, "Table is full"));
-    }
-    let cursor = Cursor::find_key(table, row.id);
-    Node::leaf_node_insert(cursor, row.id, row)
-}
-
-unsafe fn execute_select(table: &mut Table, mut writer: impl Write) -> Result<&'static str, Error> {
-    let mut cursor = Cursor::table_start(table);
-    while (!cursor.end_of_table) {
-        let row_ptr = cursor.cursor_value();
-        let mut bytes: [u8; ROW_SIZE] = [0u8; ROW_SIZE];
-        for i in 0..ROW_SIZE {
-            bytes[i] = ptr::read(row_ptr.add(i));
-        }
-        let deserialized_row = Row::deserialize_row(&bytes);
-        let _result = Row::print_row(deserialized_row, &mut writer);
-        cursor.advance_cursor()
-    }
-    Ok("SUCCESS")
-}
@@ -1,5 +1,4 @@
-use rustql::exit_process;
-use rustql::table::table::Table;
+use rustql::connection::handle::Connection;
 use std::io::Write;
 use std::{env, io};
 
@@ -10,16 +9,21 @@ fn main() {
         return;
     }
     let file_name = &args[1];
-    let mut table = unsafe { Table::db_open(file_name) };
+    let mut connection = match Connection::open(file_name) {
+        Ok(connection) => connection,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
     loop {
         print_prompt();
         let mut command = String::new();
         io::stdin().read_line(&mut command).unwrap();
         if (command.trim() == ".exit") {
-            unsafe { exit_process(table) }
             break;
         } else {
-            rustql::run(command, &mut table, &mut io::stdout());
+            connection.run(&command, &mut io::stdout());
         }
     }
 }
@@ -33,7 +33,7 @@ const LEAF_NODE_VALUE_SIZE: u32 = ROW_SIZE as u32;
 const LEAF_NODE_VALUE_OFFSET: u32 = LEAF_NODE_KEY_OFFSET + LEAF_NODE_KEY_SIZE;
 const LEAF_NODE_CELL_SIZE: u32 = LEAF_NODE_KEY_SIZE + LEAF_NODE_VALUE_SIZE;
 const LEAF_NODE_SPACE_FOR_CELLS: u32 = (PAGE_SIZE - LEAF_NODE_HEADER_SIZE as usize) as u32;
-const LEAF_NODE_MAX_CELLS: u32 = 2;
+pub const LEAF_NODE_MAX_CELLS: u32 = 2;
 //LEAF_NODE_SPACE_FOR_CELLS / LEAF_NODE_CELL_SIZE;
 
 
@@ -77,6 +77,17 @@ impl From<NodeType> for u8 {
     }
 }
 
+// Anything that can be written into the value part of a leaf cell.
+pub trait LeafValue {
+    fn serialize_into(&self, destination_ptr: *mut u8);
+}
+
+impl LeafValue for Row {
+    fn serialize_into(&self, destination_ptr: *mut u8) {
+        self.serialize_row(destination_ptr)
+    }
+}
+
 pub struct Node;
 
 impl Node {
@@ -98,6 +109,22 @@ impl Node {
         u32::from_be_bytes(bytes)
     }
 
+    pub unsafe fn get_leaf_node_key(node_ptr: *const u8, cell_num: u32) -> u32 {
+        get_leaf_node_key(node_ptr, cell_num)
+    }
+
+    pub unsafe fn get_internal_node_num_cells(node_ptr: *mut u8) -> u32 {
+        get_internal_node_num_cells(node_ptr)
+    }
+
+    pub unsafe fn get_internal_node_child(node_ptr: *mut u8, cell_num: u32) -> u32 {
+        get_internal_node_child_page_num(node_ptr, cell_num)
+    }
+
+    pub unsafe fn get_internal_node_right_child(node_ptr: *mut u8) -> u32 {
+        get_internal_node_right_child(node_ptr)
+    }
+
     pub unsafe fn get_leaf_node_value_ptr(node_ptr: *const u8, cell_num: u32) -> *mut u8 {
         get_leaf_node_cell_ptr(node_ptr, cell_num).add(LEAF_NODE_KEY_SIZE as usize)
     }
@@ -116,7 +143,7 @@ impl Node {
         set_internal_node_right_child(node_ptr,INVALID_PAGE_NUM);
     }
 
-    pub unsafe fn leaf_node_insert(cursor: Cursor, key: u32, value: Row) -> Result<String, Error> {
+    pub unsafe fn leaf_node_insert(cursor: Cursor, key: u32, value: impl LeafValue) -> Result<String, Error> {
         let node_ptr = cursor.table.pager.get_page(cursor.page_num).unwrap();
         let num_cells = Node::get_leaf_node_num_cells(node_ptr);
         if (num_cells >= LEAF_NODE_MAX_CELLS) {
@@ -124,7 +151,7 @@ impl Node {
             return Ok("EXECUTE_SUCCESS".parse().unwrap());
         }
         let key_at_index = get_leaf_node_key(node_ptr, cursor.cell_num);
-        if (key_at_index == key) {
+        if (cursor.cell_num < num_cells && key_at_index == key) {
             return (Err(Error::new(ErrorKind::Other, "Error:Duplicate key")));
         }
         if (cursor.cell_num < num_cells) {
@@ -143,7 +170,7 @@ impl Node {
             let id_byte = x[i];
             ptr::copy(&id_byte, cell_ptr.add(i), 1);
         }
-        value.serialize_row(value_ptr);
+        value.serialize_into(value_ptr);
         cursor.table.num_rows += 1;
         Ok("EXECUTE_SUCCESS".parse().unwrap())
     }
@@ -190,63 +217,143 @@ impl Node {
         set_internal_node_num_cells(node_ptr, num_cells + 1);
     }
 
+    //Redistributes the children of a full internal node plus the new child
+    //between the node and a new sibling, each child ordered by its max key.
     unsafe fn internal_node_split_and_insert(table:&mut Table, page_num:u32, child_page_num:u32){
         let mut old_page_num = page_num;
         let mut old_node_ptr = table.pager.get_page(old_page_num).unwrap();
         let old_max_key = get_node_max_key(table,old_node_ptr);
-        let child_node_ptr = table.pager.get_page(child_page_num).unwrap();
-        let child_max = get_node_max_key(table,child_node_ptr);
+        let mut children = Vec::new();
+        let num_cells = get_internal_node_num_cells(old_node_ptr);
+        for i in 0..=num_cells {
+            children.push(get_internal_node_child_page_num(old_node_ptr, i));
+        }
+        children.push(child_page_num);
+        let mut children: Vec<(u32, u32)> = children
+            .into_iter()
+            .map(|child| {
+                let child_ptr = table.pager.get_page(child).unwrap();
+                (get_node_max_key(table, child_ptr), child)
+            })
+            .collect();
+        children.sort();
+        let right_half = children.split_off(children.len() / 2);
+
         let new_page_num = table.pager.get_unused_page_num();
         let new_node_ptr = table.pager.get_page(new_page_num).unwrap();
         let splitting_root = is_node_root(old_node_ptr);
-        let mut parent_page_num ;
-        if(splitting_root){
+        let parent_page_num;
+        if splitting_root {
             Node::create_new_root(table,new_page_num);
-             parent_page_num = table.root_page_num;
-            let parent_ptr =  table.pager.get_page(parent_page_num).unwrap();
+            parent_page_num = table.root_page_num;
+            let parent_ptr = table.pager.get_page(parent_page_num).unwrap();
             old_page_num = get_internal_node_child_page_num(parent_ptr,0);
             old_node_ptr = table.pager.get_page(old_page_num).unwrap();
-        }
-        else {
+        } else {
             parent_page_num = Node::get_parent_node(old_node_ptr);
             Node::initialize_internal_node(new_node_ptr);
         }
-        let parent_ptr =  table.pager.get_page(parent_page_num).unwrap();
-        let mut num_cells = get_internal_node_num_cells(old_node_ptr);
-        let right_child = get_internal_node_right_child(old_node_ptr);
-        let right_child_ptr = table.pager.get_page(right_child).unwrap();
-        Node::internal_node_insert(table,new_page_num,right_child);
-        Node::set_parent_node(right_child_ptr,new_page_num);
-        set_internal_node_right_child(old_node_ptr,INVALID_PAGE_NUM);
-        for i in ((INTERNAL_NODE_MAX_CELLS/2)+1..=INTERNAL_NODE_MAX_CELLS - 1).rev(){
-            let cell_child_page_num = get_internal_node_child_page_num(old_node_ptr,i);
-            let cell_child_ptr = table.pager.get_page(cell_child_page_num).unwrap();
-            Node::internal_node_insert(table,new_page_num,cell_child_page_num);
-            Node::set_parent_node(cell_child_ptr,new_page_num);
-            num_cells -= 1
-        }
-        let cell_child_page_num = get_internal_node_child_page_num(old_node_ptr,num_cells-1);
-        set_internal_node_right_child(old_node_ptr,cell_child_page_num);
-        num_cells -= 1;
-        set_internal_node_num_cells(old_node_ptr,num_cells);
-        let max_after_split = get_node_max_key(table,old_node_ptr);
-        let destination_page_num = if child_max < max_after_split {
-            old_page_num
+        Node::set_internal_node_children(table, old_page_num, &children);
+        Node::set_internal_node_children(table, new_page_num, &right_half);
+        let parent_ptr = table.pager.get_page(parent_page_num).unwrap();
+        let old_node_max_key = get_node_max_key(table,old_node_ptr);
+        if splitting_root {
+            set_internal_node_cell(parent_ptr, 0, old_page_num, old_node_max_key);
         } else {
-            new_page_num
-        };
-        Node::internal_node_insert(table,destination_page_num,child_page_num);
-        Node::set_parent_node(child_node_ptr,parent_page_num);
-
-      if(!splitting_root){
-          Node::update_internal_node_key(parent_ptr,old_max_key,max_after_split);
-          Node::internal_node_insert(table,parent_page_num,new_page_num);
-      }
-
+            Node::update_internal_node_key(parent_ptr,old_max_key,old_node_max_key);
+            Node::set_parent_node(new_node_ptr,parent_page_num);
+            Node::internal_node_insert(table,parent_page_num,new_page_num);
+        }
+    }
 
+    //Overwrites the cells of an internal node with children sorted by max key.
+    unsafe fn set_internal_node_children(table: &mut Table, page_num: u32, children: &[(u32, u32)]) {
+        let node_ptr = table.pager.get_page(page_num).unwrap();
+        let (last, cells) = children.split_last().unwrap();
+        for (i, (key, child)) in cells.iter().enumerate() {
+            set_internal_node_cell(node_ptr, i as u32, *child, *key);
+        }
+        set_internal_node_num_cells(node_ptr, cells.len() as u32);
+        set_internal_node_right_child(node_ptr, last.1);
+        for (_, child) in children {
+            let child_ptr = table.pager.get_page(*child).unwrap();
+            Node::set_parent_node(child_ptr, page_num);
+        }
+    }
 
+    //Removes the cell at the cursor. A leaf left empty is unlinked from its
+    //siblings and removed from its parent, unless it is the root.
+    pub unsafe fn leaf_node_delete(cursor: Cursor) {
+        let node_ptr = cursor.table.pager.get_page(cursor.page_num).unwrap();
+        let num_cells = Node::get_leaf_node_num_cells(node_ptr);
+        for i in cursor.cell_num + 1..num_cells {
+            let src_cell_ptr = get_leaf_node_cell_ptr(node_ptr, i);
+            let dest_ptr = get_leaf_node_cell_ptr(node_ptr, i - 1);
+            ptr::copy(src_cell_ptr, dest_ptr, LEAF_NODE_CELL_SIZE as usize);
+        }
+        set_leaf_node_num_cells(node_ptr, num_cells - 1);
+        cursor.table.num_rows = cursor.table.num_rows.saturating_sub(1);
+        if num_cells == 1 && !is_node_root(node_ptr) {
+            Node::unlink_leaf_node(cursor.table, cursor.page_num);
+            let parent_page_num = Node::get_parent_node(node_ptr);
+            Node::internal_node_remove(cursor.table, parent_page_num, cursor.page_num);
+        }
+    }
 
+    //Points the leaf before page_num at the leaf after it.
+    unsafe fn unlink_leaf_node(table: &mut Table, page_num: u32) {
+        let node_ptr = table.pager.get_page(page_num).unwrap();
+        let next_leaf = Node::get_leaf_node_next_leaf(node_ptr);
+        let mut current = table.root_page_num;
+        loop {
+            let current_ptr = table.pager.get_page(current).unwrap();
+            match Node::get_node_type(current_ptr) {
+                NodeType::INTERNAL => current = get_internal_node_child_page_num(current_ptr, 0),
+                NodeType::LEAF => break,
+            }
+        }
+        while current != page_num && current != 0 {
+            let current_ptr = table.pager.get_page(current).unwrap();
+            let current_next = Node::get_leaf_node_next_leaf(current_ptr);
+            if current_next == page_num {
+                set_leaf_node_next_leaf(current_ptr, next_leaf);
+                return;
+            }
+            current = current_next;
+        }
+    }
 
+    //Removes a child from an internal node. A node left without children is
+    //removed from its own parent, an empty root becomes an empty leaf.
+    unsafe fn internal_node_remove(table: &mut Table, page_num: u32, child_page_num: u32) {
+        let node_ptr = table.pager.get_page(page_num).unwrap();
+        let num_cells = get_internal_node_num_cells(node_ptr);
+        let right_child = get_internal_node_right_child(node_ptr);
+        if right_child == child_page_num {
+            if num_cells == 0 {
+                if is_node_root(node_ptr) {
+                    Node::initialize_leaf_node(node_ptr);
+                    Node::set_node_root(node_ptr, true);
+                } else {
+                    let parent_page_num = Node::get_parent_node(node_ptr);
+                    Node::internal_node_remove(table, parent_page_num, page_num);
+                }
+                return;
+            }
+            let last_child = get_internal_node_child_page_num(node_ptr, num_cells - 1);
+            set_internal_node_right_child(node_ptr, last_child);
+        } else {
+            let mut cell_num = 0;
+            while get_internal_node_child_page_num(node_ptr, cell_num) != child_page_num {
+                cell_num += 1;
+            }
+            for i in cell_num + 1..num_cells {
+                let src_cell_ptr = get_internal_node_cell_ptr(node_ptr, i);
+                let dest_cell_ptr = get_internal_node_cell_ptr(node_ptr, i - 1);
+                ptr::copy(src_cell_ptr, dest_cell_ptr, INTERNAL_NODE_CELL_SIZE as usize);
+            }
+        }
+        set_internal_node_num_cells(node_ptr, num_cells - 1);
     }
 
     pub unsafe fn find_key_in_leaf_node<'a>(
@@ -267,7 +374,7 @@ impl Node {
             } else if (key < key_at_index) {
                 one_past_max_index = index
             } else {
-                required_index = index;
+                min_index = index;
                 break;
             }
         }
@@ -383,7 +490,7 @@ impl Node {
         ptr::copy(&(is_root as u8), is_root_ptr, 1);
     }
 
-    pub unsafe fn split_leaf_node_and_insert(cursor: Cursor, key: u32, value: Row) {
+    pub unsafe fn split_leaf_node_and_insert(cursor: Cursor, key: u32, value: impl LeafValue) {
         let old_node_page_num = cursor.page_num;
         let old_node_ptr = cursor.table.pager.get_page(old_node_page_num).unwrap();
         let new_page_num = cursor.table.pager.get_unused_page_num();
@@ -402,13 +509,13 @@ impl Node {
             let destination_ptr = get_leaf_node_cell_ptr(dest_node_ptr, cell_num);
             if (i == cursor.cell_num) {
                 //Inserting new value
-                let value_ptr = Node::get_leaf_node_value_ptr(new_node_ptr, cell_num);
+                let value_ptr = Node::get_leaf_node_value_ptr(dest_node_ptr, cell_num);
                 let x = u32::to_be_bytes(key);
                 for i in 0..4 {
                     let id_byte = x[i];
                     ptr::copy(&id_byte, destination_ptr.add(i), 1);
                 }
-                value.serialize_row(value_ptr);
+                value.serialize_into(value_ptr);
             } else if (i > cursor.cell_num) {
                 let src_ptr = get_leaf_node_cell_ptr(old_node_ptr, i - 1);
                 ptr::copy(src_ptr, destination_ptr, LEAF_NODE_CELL_SIZE as usize);
@@ -1 +1,2 @@
 pub mod pager;
+pub mod temp;
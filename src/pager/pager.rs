@@ -9,11 +9,21 @@ use crate::node::node::Node;
 
 pub const TABLE_MAX_PAGES: u32 = 100;
 
+// Pages freed by DROP TABLE are handed out again before the file grows. They
+// form a list whose first page number is kept in the database header, at
+// FREE_LIST_HEAD_OFFSET of page 1, each free page holding the number of the
+// next one in its first four bytes. 0 ends the list. Files without a header
+// never free pages.
+pub const FREE_LIST_HEAD_PAGE_NUM: u32 = 1;
+pub const FREE_LIST_HEAD_OFFSET: usize = 8;
+
 pub struct Pager {
     pub file: File,
     pub file_length: u32,
     pub pages: [Option<*mut u8>; TABLE_MAX_PAGES as usize],
     pub num_pages: u32,
+    // Set once the header holding the free list is known to exist.
+    pub free_list: bool,
 }
 
 impl Pager {
@@ -36,6 +46,7 @@ impl Pager {
             file_length,
             pages,
             num_pages,
+            free_list: false,
         })
     }
 
@@ -74,16 +85,102 @@ impl Pager {
         Ok(page_ptr.unwrap())
     }
 
-    pub unsafe fn flush_page(&mut self, page_num: usize) -> Result<String, Error> {
-        let page_ptr = self.get_page(page_num as u32).unwrap();
+    // Reads a cached page again from the file, dropping the changes made to
+    // it in memory. The part past the end of the file is zeroed.
+    pub unsafe fn reload_page(&mut self, page_num: u32) -> Result<(), Error> {
+        let Some(page_ptr) = self.pages[page_num as usize] else { return Ok(()) };
+        let page = std::slice::from_raw_parts_mut(page_ptr, PAGE_SIZE);
+        page.fill(0);
+        self.file.seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
+        let mut total_bytes_read = 0;
+        while total_bytes_read < PAGE_SIZE {
+            let bytes_read = self.file.read(&mut page[total_bytes_read..])?;
+            if bytes_read == 0 {
+                break;
+            }
+            total_bytes_read += bytes_read;
+        }
+        Ok(())
+    }
+
+    // Drops a cached page without writing it back, as if it was never
+    // loaded. The buffer is the vector get_page allocated.
+    pub unsafe fn uncache_page(&mut self, page_num: u32) {
+        if let Some(page_ptr) = self.pages[page_num as usize].take() {
+            drop(Vec::from_raw_parts(page_ptr, PAGE_SIZE, PAGE_SIZE));
+        }
+    }
+
+    // Writes a cached page back to the file. A page that is not cached is
+    // the one in the file already.
+    pub unsafe fn flush_page(&mut self, page_num: usize) -> Result<(), Error> {
+        let Some(page_ptr) = self.pages[page_num] else { return Ok(()) };
         let start_offset = page_num * PAGE_SIZE;
-        self.file.seek(SeekFrom::Start(start_offset as u64));
+        self.file.seek(SeekFrom::Start(start_offset as u64))?;
         let data = slice_from_raw_parts(page_ptr, PAGE_SIZE);
-        self.file.write(&*data);
-        Ok("SUCCESS".parse().unwrap())
+        self.file.write_all(&*data)
+    }
+
+    // The page a new node goes to: the first free page, emptied, or a new
+    // page at the end of the file.
+    pub fn get_unused_page_num(&mut self) -> u32 {
+        let head = self.free_list_head().unwrap();
+        if head == 0 {
+            return self.num_pages;
+        }
+        unsafe {
+            let page_ptr = self.get_page(head).unwrap();
+            let next = read_page_num(page_ptr, 0);
+            std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+            self.set_free_list_head(next);
+        }
+        head
     }
 
-    pub fn get_unused_page_num(&self) -> u32 {
-        self.num_pages
+    // Number of pages that can still be allocated, the free ones included.
+    pub fn available_pages(&mut self) -> Result<u32, Error> {
+        let mut available = TABLE_MAX_PAGES.saturating_sub(self.num_pages);
+        let mut page_num = self.free_list_head()?;
+        while page_num != 0 {
+            available += 1;
+            page_num = unsafe { read_page_num(self.get_page(page_num)?, 0) };
+        }
+        Ok(available)
     }
+
+    // Puts a page no longer in use at the front of the free list.
+    pub unsafe fn free_page(&mut self, page_num: u32) -> Result<(), Error> {
+        if !self.free_list {
+            return Err(Error::other("Database file has no free list"));
+        }
+        let head = self.free_list_head()?;
+        let page_ptr = self.get_page(page_num)?;
+        std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+        write_page_num(page_ptr, 0, head);
+        self.set_free_list_head(page_num);
+        Ok(())
+    }
+
+    fn free_list_head(&mut self) -> Result<u32, Error> {
+        if !self.free_list {
+            return Ok(0);
+        }
+        let header_ptr = self.get_page(FREE_LIST_HEAD_PAGE_NUM)?;
+        Ok(unsafe { read_page_num(header_ptr, FREE_LIST_HEAD_OFFSET) })
+    }
+
+    unsafe fn set_free_list_head(&mut self, page_num: u32) {
+        let header_ptr = self.get_page(FREE_LIST_HEAD_PAGE_NUM).unwrap();
+        write_page_num(header_ptr, FREE_LIST_HEAD_OFFSET, page_num);
+    }
+}
+
+unsafe fn read_page_num(page_ptr: *const u8, offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    std::ptr::copy(page_ptr.add(offset), bytes.as_mut_ptr(), 4);
+    u32::from_be_bytes(bytes)
+}
+
+unsafe fn write_page_num(page_ptr: *mut u8, offset: usize, page_num: u32) {
+    std::ptr::copy(page_num.to_be_bytes().as_ptr(), page_ptr.add(offset), 4);
 }
@@ -0,0 +1,61 @@
+use crate::table::table::PAGE_SIZE;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_TEMP_FILE: AtomicUsize = AtomicUsize::new(0);
+
+// Scratch pages in a temporary file for work that does not fit in memory,
+// like the runs of a large sort. Unlike Pager it keeps no pages cached and
+// has no page limit. The file is removed when the pager is dropped.
+pub struct TempPager {
+    file: File,
+    path: PathBuf,
+    pub num_pages: u32,
+}
+
+impl TempPager {
+    pub fn new() -> io::Result<TempPager> {
+        let path = temp_path();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(TempPager {
+            file,
+            path,
+            num_pages: 0,
+        })
+    }
+
+    pub fn allocate_page(&mut self) -> u32 {
+        self.num_pages += 1;
+        self.num_pages - 1
+    }
+
+    pub fn write_page(&mut self, page_num: u32, data: &[u8; PAGE_SIZE]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
+        self.file.write_all(data)
+    }
+
+    pub fn read_page(&mut self, page_num: u32, data: &mut [u8; PAGE_SIZE]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
+        self.file.read_exact(data)
+    }
+}
+
+impl Drop for TempPager {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// A path for a new temporary file, unique within the process.
+pub fn temp_path() -> PathBuf {
+    let id = NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("rustql-{}-{}.tmp", process::id(), id))
+}
@@ -0,0 +1,162 @@
+use crate::executor::dispatch::ExecuteResult;
+use crate::sql::ast::Stmt;
+use crate::sql::parser::parse_with_parameters;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use crate::vm::compiler::compile;
+use crate::vm::machine::execute_program;
+use crate::vm::program::Program;
+use std::io::Error;
+use std::vec;
+
+// A statement parsed once and run any number of times with different values
+// bound to its parameters: ? takes the number after the largest one so far,
+// ?NNN the number NNN, and :name, @name and $name the number of the first
+// parameter with that name or else the next one. Parameters left unbound are
+// NULL.
+//
+// Each run starts at the first step and ends at Step::Done, reset starts
+// the next one. The program the statement compiles to is kept and compiled
+// again only when it is run on another database, or the schema changed
+// since.
+pub struct Statement {
+    stmt: Stmt,
+    sql: String,
+    // The names of the parameters by number, None for the unnamed ones.
+    parameters: Vec<Option<String>>,
+    values: Vec<Value>,
+    // The program and the id of the table and catalog version it was
+    // compiled for.
+    program: Option<((u64, u64), Program)>,
+    // The rows of the current run not stepped through yet, None before it
+    // starts.
+    rows: Option<vec::IntoIter<Vec<Value>>>,
+    columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Row(Vec<Value>),
+    Done,
+}
+
+impl Statement {
+    pub fn prepare(sql: &str) -> Result<Statement, Error> {
+        let (stmt, parameters) = parse_with_parameters(sql)?;
+        Ok(Statement {
+            stmt,
+            sql: sql.trim().to_string(),
+            values: vec![Value::Null; parameters.len()],
+            parameters,
+            program: None,
+            rows: None,
+            columns: Vec::new(),
+        })
+    }
+
+    // The largest parameter number.
+    pub fn parameter_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    // The number of the parameter with this name, prefix included.
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameters
+            .iter()
+            .position(|parameter| parameter.as_deref() == Some(name))
+            .map(|i| i + 1)
+    }
+
+    pub fn parameter_name(&self, index: usize) -> Option<&str> {
+        self.parameters.get(index.checked_sub(1)?)?.as_deref()
+    }
+
+    // Binds a value to parameter index, from 1. It is used from the next run
+    // on.
+    pub fn bind(&mut self, index: usize, value: Value) -> Result<(), Error> {
+        match index.checked_sub(1).and_then(|i| self.values.get_mut(i)) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(Error::other(
+                format!(
+                    "bind index {} is out of range, the statement has {} parameters",
+                    index,
+                    self.values.len()
+                ),
+            )),
+        }
+    }
+
+    pub fn bind_int(&mut self, index: usize, value: i64) -> Result<(), Error> {
+        self.bind(index, Value::Integer(value))
+    }
+
+    pub fn bind_real(&mut self, index: usize, value: f64) -> Result<(), Error> {
+        self.bind(index, Value::Real(value))
+    }
+
+    pub fn bind_text(&mut self, index: usize, value: &str) -> Result<(), Error> {
+        self.bind(index, Value::Text(value.to_string()))
+    }
+
+    pub fn bind_null(&mut self, index: usize) -> Result<(), Error> {
+        self.bind(index, Value::Null)
+    }
+
+    // Binds a value to the parameter with this name, prefix included.
+    pub fn bind_named(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        match self.parameter_index(name) {
+            Some(index) => self.bind(index, value),
+            None => Err(Error::other(format!("no such parameter: {}", name))),
+        }
+    }
+
+    // Sets every parameter back to NULL.
+    pub fn clear_bindings(&mut self) {
+        self.values.fill(Value::Null);
+    }
+
+    // Runs the statement on its first step, returns its rows one step at a
+    // time. Statements that change rows make all their changes on the first
+    // step and return no row.
+    pub fn step(&mut self, table: &mut Table) -> Result<Step, Error> {
+        if self.rows.is_none() {
+            let rows = self.run(table)?;
+            self.rows = Some(rows.into_iter());
+        }
+        Ok(match self.rows.as_mut().and_then(Iterator::next) {
+            Some(row) => Step::Row(row),
+            None => Step::Done,
+        })
+    }
+
+    // Ends the current run, the next step starts another one with the values
+    // bound then.
+    pub fn reset(&mut self) {
+        self.rows = None;
+    }
+
+    // The names of the result columns, known once the statement ran.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn run(&mut self, table: &mut Table) -> Result<Vec<Vec<Value>>, Error> {
+        let version = (table.id, table.catalog.version);
+        let program = match self.program.take() {
+            Some((compiled, program)) if compiled == version => program,
+            _ => unsafe { compile(table, self.stmt.clone(), &self.sql) },
+        };
+        let result = unsafe { execute_program(table, &program, &self.values) };
+        self.program = Some((version, program));
+        Ok(match result? {
+            ExecuteResult::Rows(result_set) => {
+                self.columns = result_set.columns;
+                result_set.rows
+            }
+            ExecuteResult::Executed { .. } => Vec::new(),
+        })
+    }
+}
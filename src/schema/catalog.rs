@@ -0,0 +1,222 @@
+use crate::pager::pager::{Pager, TABLE_MAX_PAGES};
+use crate::schema::definition::{IndexSchema, TableSchema, TriggerSchema, ViewSchema};
+use crate::schema::statistics::TableStats;
+use crate::sql::ast::Stmt;
+use crate::sql::parser::parse;
+use crate::table::record::{decode_values, encode_values};
+use crate::table::table::PAGE_SIZE;
+use crate::table::value::Value;
+use std::io::Error;
+use std::ptr;
+
+// Page 1 holds the database header followed by the start of the schema. The
+// header is the magic string, the head of the pager's free list, the length
+// of the schema and the page it continues on. The
+// schema is the list of CREATE statements of every table, index, view and
+// trigger, each with the root page of its tree (0 for views and triggers),
+// and continues in a chain of pages when it does not fit in the header page.
+pub const SCHEMA_PAGE_NUM: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"rustql\0\x01";
+const MAGIC_OFFSET: usize = 0;
+const SCHEMA_LENGTH_OFFSET: usize = 12;
+const SCHEMA_NEXT_PAGE_OFFSET: usize = 16;
+const SCHEMA_DATA_OFFSET: usize = 20;
+const CHAIN_NEXT_PAGE_OFFSET: usize = 0;
+const CHAIN_DATA_OFFSET: usize = 4;
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    pub tables: Vec<TableSchema>,
+    pub indexes: Vec<IndexSchema>,
+    pub views: Vec<ViewSchema>,
+    pub triggers: Vec<TriggerSchema>,
+    // The statistics of the analyzed tables. They are stored in tables of
+    // their own rather than in the schema.
+    pub statistics: Vec<TableStats>,
+    // Counts the changes to the schema and statistics, prepared statements
+    // compiled for another version compile again.
+    pub version: u64,
+    // False for database files created before the schema page existed,
+    // whose page 1 belongs to the legacy table.
+    available: bool,
+}
+
+impl Catalog {
+    // Writes an empty header to page 1 of a new database.
+    pub(crate) unsafe fn initialize(pager: &mut Pager) -> Result<(), Error> {
+        let page_ptr = pager.get_page(SCHEMA_PAGE_NUM)?;
+        ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+        ptr::copy(MAGIC.as_ptr(), page_ptr.add(MAGIC_OFFSET), MAGIC.len());
+        Ok(())
+    }
+
+    // Reads the schema page and the pages chained to it.
+    pub(crate) unsafe fn load(pager: &mut Pager) -> Result<Catalog, Error> {
+        let mut catalog = Catalog::default();
+        let page_ptr = pager.get_page(SCHEMA_PAGE_NUM)?;
+        let mut magic = [0u8; 8];
+        ptr::copy(page_ptr.add(MAGIC_OFFSET), magic.as_mut_ptr(), magic.len());
+        if &magic != MAGIC {
+            return Ok(catalog);
+        }
+        catalog.available = true;
+        pager.free_list = true;
+        let bytes = read_schema_bytes(pager)?;
+        if bytes.is_empty() {
+            return Ok(catalog);
+        }
+        let mut offset = 0;
+        let count = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        offset += 4;
+        for _ in 0..count {
+            let entry = decode_values(&bytes, &mut offset);
+            let (root_page_num, sql) = match (&entry[0], &entry[1]) {
+                (Value::Integer(root_page_num), Value::Text(sql)) => (*root_page_num as u32, sql.clone()),
+                _ => return Err(corrupt_schema()),
+            };
+            match parse(&sql)? {
+                Stmt::CreateTable(create) => {
+                    catalog.tables.push(TableSchema::from_create(&create, root_page_num, &sql)?)
+                }
+                Stmt::CreateIndex(create) => {
+                    catalog.indexes.push(IndexSchema::from_create(&create, root_page_num, &sql))
+                }
+                Stmt::CreateView(create) => catalog.views.push(ViewSchema::from_create(&create, &sql)),
+                Stmt::CreateTrigger(create) => catalog.triggers.push(TriggerSchema::from_create(&create, &sql)),
+                _ => return Err(corrupt_schema()),
+            }
+        }
+        Ok(catalog)
+    }
+
+    // Writes the schema to page 1 and the pages chained to it.
+    pub(crate) unsafe fn save(&mut self, pager: &mut Pager) -> Result<(), Error> {
+        self.version += 1;
+        let mut bytes = Vec::new();
+        let count = self.tables.len() + self.indexes.len() + self.views.len() + self.triggers.len();
+        bytes.extend_from_slice(&(count as u32).to_be_bytes());
+        let entries = self
+            .tables
+            .iter()
+            .map(|table| (table.root_page_num, &table.sql))
+            .chain(self.indexes.iter().map(|index| (index.root_page_num, &index.sql)))
+            .chain(self.views.iter().map(|view| (0, &view.sql)))
+            .chain(self.triggers.iter().map(|trigger| (0, &trigger.sql)));
+        for (root_page_num, sql) in entries {
+            bytes.extend(encode_values(&[Value::Integer(root_page_num as i64), Value::Text(sql.clone())]));
+        }
+        write_schema_bytes(pager, &bytes)
+    }
+
+    pub fn ensure_available(&self) -> Result<(), Error> {
+        if !self.available {
+            return Err(Error::other("Database file has no schema page, recreate it to use CREATE statements"));
+        }
+        Ok(())
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.iter().find(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get_table(&self, name: &str) -> Result<&TableSchema, Error> {
+        self.table(name)
+            .ok_or_else(|| Error::other(format!("no such table: {}", name)))
+    }
+
+    // The table a statement writes to. Views can only be read.
+    pub fn get_writable_table(&self, name: &str) -> Result<&TableSchema, Error> {
+        if self.view(name).is_some() {
+            return Err(Error::other(format!("cannot modify {} because it is a view", name)));
+        }
+        self.get_table(name)
+    }
+
+    pub fn view(&self, name: &str) -> Option<&ViewSchema> {
+        self.views.iter().find(|view| view.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn trigger(&self, name: &str) -> Option<&TriggerSchema> {
+        self.triggers.iter().find(|trigger| trigger.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn index(&self, name: &str) -> Option<&IndexSchema> {
+        self.indexes.iter().find(|index| index.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn statistics(&self, table_name: &str) -> Option<&TableStats> {
+        self.statistics
+            .iter()
+            .find(|stats| stats.table_name.eq_ignore_ascii_case(table_name))
+    }
+
+    pub fn table_indexes(&self, table_name: &str) -> Vec<IndexSchema> {
+        self.indexes
+            .iter()
+            .filter(|index| index.table_name.eq_ignore_ascii_case(table_name))
+            .cloned()
+            .collect()
+    }
+}
+
+unsafe fn read_schema_bytes(pager: &mut Pager) -> Result<Vec<u8>, Error> {
+    let page_ptr = pager.get_page(SCHEMA_PAGE_NUM)?;
+    let length = read_u32(page_ptr, SCHEMA_LENGTH_OFFSET) as usize;
+    let mut next_page_num = read_u32(page_ptr, SCHEMA_NEXT_PAGE_OFFSET);
+    let mut bytes = Vec::with_capacity(length);
+    let mut chunk = length.min(PAGE_SIZE - SCHEMA_DATA_OFFSET);
+    bytes.extend_from_slice(std::slice::from_raw_parts(page_ptr.add(SCHEMA_DATA_OFFSET), chunk));
+    while bytes.len() < length {
+        if next_page_num == 0 {
+            return Err(corrupt_schema());
+        }
+        let page_ptr = pager.get_page(next_page_num)?;
+        chunk = (length - bytes.len()).min(PAGE_SIZE - CHAIN_DATA_OFFSET);
+        bytes.extend_from_slice(std::slice::from_raw_parts(page_ptr.add(CHAIN_DATA_OFFSET), chunk));
+        next_page_num = read_u32(page_ptr, CHAIN_NEXT_PAGE_OFFSET);
+    }
+    Ok(bytes)
+}
+
+// Writes the schema over the existing chain, allocating pages at the end of
+// the file when it grew.
+unsafe fn write_schema_bytes(pager: &mut Pager, bytes: &[u8]) -> Result<(), Error> {
+    let page_ptr = pager.get_page(SCHEMA_PAGE_NUM)?;
+    write_u32(page_ptr, SCHEMA_LENGTH_OFFSET, bytes.len() as u32);
+    let mut written = bytes.len().min(PAGE_SIZE - SCHEMA_DATA_OFFSET);
+    ptr::copy(bytes.as_ptr(), page_ptr.add(SCHEMA_DATA_OFFSET), written);
+    let mut previous_ptr = page_ptr;
+    let mut next_offset = SCHEMA_NEXT_PAGE_OFFSET;
+    while written < bytes.len() {
+        let mut page_num = read_u32(previous_ptr, next_offset);
+        if page_num == 0 {
+            page_num = pager.get_unused_page_num();
+            if page_num >= TABLE_MAX_PAGES {
+                return Err(Error::other("Table is full"));
+            }
+            write_u32(previous_ptr, next_offset, page_num);
+        }
+        let page_ptr = pager.get_page(page_num)?;
+        let chunk = (bytes.len() - written).min(PAGE_SIZE - CHAIN_DATA_OFFSET);
+        ptr::copy(bytes.as_ptr().add(written), page_ptr.add(CHAIN_DATA_OFFSET), chunk);
+        written += chunk;
+        previous_ptr = page_ptr;
+        next_offset = CHAIN_NEXT_PAGE_OFFSET;
+    }
+    Ok(())
+}
+
+unsafe fn read_u32(page_ptr: *const u8, offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    ptr::copy(page_ptr.add(offset), bytes.as_mut_ptr(), 4);
+    u32::from_be_bytes(bytes)
+}
+
+unsafe fn write_u32(page_ptr: *mut u8, offset: usize, value: u32) {
+    ptr::copy(value.to_be_bytes().as_ptr(), page_ptr.add(offset), 4);
+}
+
+fn corrupt_schema() -> Error {
+    Error::other("Database schema is corrupt")
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use std::io::Error;
+
+// Integrity rule broken by an INSERT, UPDATE or DELETE. Returned wrapped in an
+// io::Error, use ConstraintViolation::from_error to get it back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintViolation {
+    NotNull { table: String, column: String },
+    Unique { table: String, columns: Vec<String> },
+    PrimaryKey { table: String, column: String },
+    Check { table: String, column: Option<String>, expr: String },
+    ForeignKey,
+}
+
+impl ConstraintViolation {
+    pub fn from_error(error: &Error) -> Option<&ConstraintViolation> {
+        error.get_ref()?.downcast_ref::<ConstraintViolation>()
+    }
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::NotNull { table, column } => {
+                write!(f, "NOT NULL constraint failed: {}.{}", table, column)
+            }
+            ConstraintViolation::Unique { table, columns } => {
+                let columns: Vec<String> = columns.iter().map(|column| format!("{}.{}", table, column)).collect();
+                write!(f, "UNIQUE constraint failed: {}", columns.join(", "))
+            }
+            ConstraintViolation::PrimaryKey { table, column } => {
+                write!(f, "PRIMARY KEY constraint failed: {}.{}", table, column)
+            }
+            ConstraintViolation::Check {
+                table,
+                column: Some(column),
+                ..
+            } => write!(f, "CHECK constraint failed: {}.{}", table, column),
+            ConstraintViolation::Check { expr, .. } => write!(f, "CHECK constraint failed: {}", expr),
+            ConstraintViolation::ForeignKey => write!(f, "FOREIGN KEY constraint failed"),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+impl From<ConstraintViolation> for Error {
+    fn from(violation: ConstraintViolation) -> Self {
+        Error::other(violation)
+    }
+}
@@ -0,0 +1,272 @@
+use crate::sql::ast::{
+    ColumnConstraint, CreateIndex, CreateTable, CreateTrigger, CreateView, Expr, ForeignKeyAction, ForeignKeyClause, Select,
+    Stmt, TableConstraint, TriggerEvent, TriggerTiming,
+};
+use crate::table::value::Affinity;
+use std::io::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub affinity: Affinity,
+    pub primary_key: bool,
+    pub not_null: bool,
+    pub default: Option<Expr>,
+    pub check: Option<Expr>,
+}
+
+// A table created with CREATE TABLE. Rows are stored in the tree rooted at
+// root_page_num keyed by rowid. An INTEGER PRIMARY KEY column is an alias of
+// the rowid, other tables get increasing rowids assigned on insert.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    pub name: String,
+    pub root_page_num: u32,
+    pub columns: Vec<Column>,
+    pub rowid_column: Option<usize>,
+    pub checks: Vec<Expr>,
+    // Column sets declared UNIQUE or PRIMARY KEY (other than the rowid
+    // alias). Each of them is enforced through a unique index.
+    pub unique_keys: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub sql: String,
+}
+
+// Columns of a child table that must match a row of the parent table. The
+// parent columns are empty when the key refers to the parent's primary key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub parent_table: String,
+    pub parent_columns: Vec<String>,
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+impl ForeignKey {
+    fn new(columns: Vec<String>, clause: &ForeignKeyClause) -> ForeignKey {
+        ForeignKey {
+            columns,
+            parent_table: clause.table.clone(),
+            parent_columns: clause.columns.clone(),
+            on_delete: clause.on_delete,
+            on_update: clause.on_update,
+        }
+    }
+
+    pub fn references(&self, table_name: &str) -> bool {
+        self.parent_table.eq_ignore_ascii_case(table_name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSchema {
+    pub name: String,
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub root_page_num: u32,
+    pub sql: String,
+}
+
+// A stored select, run whenever a query reads the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewSchema {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub select: Select,
+    pub sql: String,
+}
+
+// Statements run for every row an INSERT, UPDATE or DELETE changes in a
+// table, before or after the change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerSchema {
+    pub name: String,
+    pub table_name: String,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    pub when: Option<Expr>,
+    pub body: Vec<Stmt>,
+    pub sql: String,
+}
+
+impl TableSchema {
+    pub fn from_create(create: &CreateTable, root_page_num: u32, sql: &str) -> Result<TableSchema, Error> {
+        let mut columns: Vec<Column> = Vec::new();
+        let mut unique_keys = Vec::new();
+        let mut primary_key: Option<Vec<String>> = None;
+        let mut foreign_keys = Vec::new();
+        for column_def in &create.columns {
+            if columns.iter().any(|c| c.name.eq_ignore_ascii_case(&column_def.name)) {
+                return Err(schema_error(format!("duplicate column name: {}", column_def.name)));
+            }
+            let mut column = Column {
+                name: column_def.name.clone(),
+                type_name: column_def.type_name.clone(),
+                affinity: Affinity::from_type_name(column_def.type_name.as_deref()),
+                primary_key: false,
+                not_null: false,
+                default: None,
+                check: None,
+            };
+            for constraint in &column_def.constraints {
+                match constraint {
+                    ColumnConstraint::PrimaryKey => {
+                        if primary_key.is_some() {
+                            return Err(more_than_one_primary_key(&create.name));
+                        }
+                        column.primary_key = true;
+                        primary_key = Some(vec![column.name.clone()]);
+                    }
+                    ColumnConstraint::NotNull => column.not_null = true,
+                    ColumnConstraint::Unique => unique_keys.push(vec![column.name.clone()]),
+                    ColumnConstraint::Check(expr) => column.check = Some(expr.clone()),
+                    ColumnConstraint::Default(expr) => column.default = Some(expr.clone()),
+                    ColumnConstraint::References(clause) => {
+                        foreign_keys.push(ForeignKey::new(vec![column.name.clone()], clause))
+                    }
+                }
+            }
+            columns.push(column);
+        }
+        let mut checks = Vec::new();
+        for constraint in &create.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey(key) => {
+                    if primary_key.is_some() {
+                        return Err(more_than_one_primary_key(&create.name));
+                    }
+                    primary_key = Some(key.clone());
+                }
+                TableConstraint::Unique(key) => unique_keys.push(key.clone()),
+                TableConstraint::Check(expr) => checks.push(expr.clone()),
+                TableConstraint::ForeignKey { columns, clause } => {
+                    if !clause.columns.is_empty() && clause.columns.len() != columns.len() {
+                        return Err(schema_error(format!(
+                            "number of columns in foreign key does not match the number of columns in the referenced table {}",
+                            clause.table
+                        )));
+                    }
+                    foreign_keys.push(ForeignKey::new(columns.clone(), clause));
+                }
+            }
+        }
+        let mut schema = TableSchema {
+            name: create.name.clone(),
+            root_page_num,
+            columns,
+            rowid_column: None,
+            checks,
+            unique_keys: Vec::new(),
+            foreign_keys,
+            sql: sql.to_string(),
+        };
+        let foreign_key_columns = schema.foreign_keys.iter().map(|key| &key.columns);
+        for key in unique_keys.iter().chain(primary_key.iter()).chain(foreign_key_columns) {
+            for name in key {
+                schema.column_index(name)?;
+            }
+        }
+        if let Some(key) = primary_key {
+            let index = schema.column_index(&key[0])?;
+            let column = &mut schema.columns[index];
+            column.primary_key = true;
+            let is_integer = column
+                .type_name
+                .as_deref()
+                .is_some_and(|type_name| type_name.eq_ignore_ascii_case("INTEGER"));
+            if key.len() == 1 && is_integer {
+                schema.rowid_column = Some(index);
+            } else {
+                for name in &key {
+                    let index = schema.column_index(name)?;
+                    schema.columns[index].primary_key = true;
+                    schema.columns[index].not_null = true;
+                }
+                unique_keys.insert(0, key);
+            }
+        }
+        schema.unique_keys = unique_keys;
+        Ok(schema)
+    }
+
+    pub fn column_index(&self, name: &str) -> Result<usize, Error> {
+        self.columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| schema_error(format!("no such column: {}", name)))
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|column| column.name.clone()).collect()
+    }
+
+    // Columns of the primary key, the rowid alias included.
+    pub fn primary_key_columns(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|column| column.primary_key)
+            .map(|column| column.name.clone())
+            .collect()
+    }
+}
+
+impl IndexSchema {
+    pub fn from_create(create: &CreateIndex, root_page_num: u32, sql: &str) -> IndexSchema {
+        IndexSchema {
+            name: create.name.clone(),
+            table_name: create.table.clone(),
+            columns: create.columns.clone(),
+            unique: create.unique,
+            root_page_num,
+            sql: sql.to_string(),
+        }
+    }
+
+    // Name and definition of the index backing the n-th unique key of a table.
+    pub fn auto_index_sql(table: &TableSchema, n: usize) -> (String, String) {
+        let name = format!("autoindex_{}_{}", table.name, n + 1);
+        let sql = format!(
+            "CREATE UNIQUE INDEX {} ON {} ({})",
+            name,
+            table.name,
+            table.unique_keys[n].join(", ")
+        );
+        (name, sql)
+    }
+}
+
+impl ViewSchema {
+    pub fn from_create(create: &CreateView, sql: &str) -> ViewSchema {
+        ViewSchema {
+            name: create.name.clone(),
+            columns: create.columns.clone(),
+            select: (*create.select).clone(),
+            sql: sql.to_string(),
+        }
+    }
+}
+
+impl TriggerSchema {
+    pub fn from_create(create: &CreateTrigger, sql: &str) -> TriggerSchema {
+        TriggerSchema {
+            name: create.name.clone(),
+            table_name: create.table.clone(),
+            timing: create.timing,
+            event: create.event.clone(),
+            when: create.when.clone(),
+            body: create.body.clone(),
+            sql: sql.to_string(),
+        }
+    }
+}
+
+fn more_than_one_primary_key(table: &str) -> Error {
+    schema_error(format!("table \"{}\" has more than one primary key", table))
+}
+
+pub fn schema_error(message: String) -> Error {
+    Error::other(message)
+}
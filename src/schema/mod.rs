@@ -0,0 +1,4 @@
+pub mod catalog;
+pub mod constraint;
+pub mod definition;
+pub mod statistics;
@@ -0,0 +1,95 @@
+use crate::table::value::Value;
+
+// What ANALYZE found out about a table and its indexes. It is stored in two
+// tables: rustql_stat has a row per table and index whose stat column lists
+// numbers separated by spaces, and rustql_histogram a row per sampled value
+// of the first column of an index. The catalog keeps them in memory for the
+// planner, loaded when the database is opened and replaced by ANALYZE.
+//
+// The stat of a table is its number of rows and the depth of its tree. The
+// stat of an index is its number of entries, the depth of its tree, then
+// for the first column, the first two columns and so on, the average number
+// of entries sharing the values of those columns.
+
+pub const STAT_TABLE: &str = "rustql_stat";
+pub const HISTOGRAM_TABLE: &str = "rustql_histogram";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub table_name: String,
+    pub rows: u64,
+    pub depth: u32,
+    pub indexes: Vec<IndexStats>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexStats {
+    pub name: String,
+    pub entries: u64,
+    pub depth: u32,
+    pub rows_per_key: Vec<f64>,
+    // Values of the first column taken at even intervals of the entries in
+    // sorted order, each with the number of entries having it. A value
+    // having many entries is likely to be one of them.
+    pub histogram: Vec<Sample>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub value: Value,
+    pub entries: u64,
+}
+
+impl TableStats {
+    pub fn stat(&self) -> String {
+        format!("{} {}", self.rows, self.depth)
+    }
+
+    // The table from its stat, None when it is malformed.
+    pub fn parse(table_name: &str, stat: &str) -> Option<TableStats> {
+        let mut numbers = stat.split_whitespace();
+        let rows = numbers.next()?.parse().ok()?;
+        let depth = numbers.next()?.parse().ok()?;
+        Some(TableStats {
+            table_name: table_name.to_string(),
+            rows,
+            depth,
+            indexes: Vec::new(),
+        })
+    }
+
+    pub fn index(&self, name: &str) -> Option<&IndexStats> {
+        self.indexes.iter().find(|index| index.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl IndexStats {
+    pub fn stat(&self) -> String {
+        let mut numbers = vec![self.entries.to_string(), self.depth.to_string()];
+        numbers.extend(self.rows_per_key.iter().map(|rows| format!("{:.2}", rows)));
+        numbers.join(" ")
+    }
+
+    pub fn parse(name: &str, stat: &str) -> Option<IndexStats> {
+        let mut numbers = stat.split_whitespace();
+        let entries = numbers.next()?.parse().ok()?;
+        let depth = numbers.next()?.parse().ok()?;
+        let rows_per_key = numbers.map(|rows| rows.parse().ok()).collect::<Option<Vec<f64>>>()?;
+        Some(IndexStats {
+            name: name.to_string(),
+            entries,
+            depth,
+            rows_per_key,
+            histogram: Vec::new(),
+        })
+    }
+
+    // The entries having this value in the first column, when it was
+    // sampled.
+    pub fn sampled_entries(&self, value: &Value) -> Option<u64> {
+        self.histogram
+            .iter()
+            .find(|sample| !value.is_null() && sample.value.compare(value).is_eq())
+            .map(|sample| sample.entries)
+    }
+}
@@ -0,0 +1,1089 @@
+use crate::table::value::Value;
+use std::fmt;
+use std::io::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    CreateTable(CreateTable),
+    DropTable(DropTable),
+    CreateIndex(CreateIndex),
+    AlterTable(AlterTable),
+    CreateView(CreateView),
+    DropView(DropView),
+    CreateTrigger(CreateTrigger),
+    DropTrigger(DropTrigger),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    Select(Box<Select>),
+    Pragma(Pragma),
+    // ANALYZE [table or index name].
+    Analyze(Option<String>),
+    // EXPLAIN QUERY PLAN stmt: how the statement would read its tables.
+    ExplainQueryPlan(Box<Stmt>),
+    // EXPLAIN stmt: the program the statement compiles to.
+    Explain(Box<Stmt>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<ColumnDef>,
+    pub constraints: Vec<TableConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConstraint {
+    PrimaryKey,
+    NotNull,
+    Unique,
+    Check(Expr),
+    Default(Expr),
+    References(ForeignKeyClause),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableConstraint {
+    PrimaryKey(Vec<String>),
+    Unique(Vec<String>),
+    Check(Expr),
+    ForeignKey { columns: Vec<String>, clause: ForeignKeyClause },
+}
+
+// REFERENCES part of a foreign key. An empty column list refers to the
+// primary key of the parent table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKeyClause {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForeignKeyAction {
+    NoAction,
+    Restrict,
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub if_not_exists: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+// ALTER TABLE name followed by one change to the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTable {
+    pub table: String,
+    pub action: AlterAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterAction {
+    AddColumn(ColumnDef),
+    RenameColumn { old: String, new: String },
+    RenameTo(String),
+    DropColumn(String),
+}
+
+// CREATE VIEW name (columns) AS select. Without a column list the columns
+// are named after the result columns of the select.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateView {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<String>,
+    pub select: Box<Select>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropView {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+// CREATE TRIGGER name BEFORE|AFTER event ON table [FOR EACH ROW] [WHEN expr]
+// BEGIN statements END. The WHEN clause and the statements read the row
+// being changed as NEW and, for UPDATE and DELETE, as it was before as OLD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTrigger {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    pub table: String,
+    pub when: Option<Expr>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+}
+
+// UPDATE OF columns only fires for updates assigning one of the columns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    Insert,
+    Update(Vec<String>),
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTrigger {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    pub columns: Option<Vec<String>>,
+    pub source: InsertSource,
+    pub or_conflict: ConflictResolution,
+    pub upsert: Option<Upsert>,
+    pub returning: Vec<SelectItem>,
+}
+
+// The rows of an INSERT: a VALUES list with one entry per row, or a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertSource {
+    Values(Vec<Vec<Expr>>),
+    Select(Box<Select>),
+}
+
+// INSERT OR ... : what happens to a row that would break a PRIMARY KEY,
+// UNIQUE, NOT NULL or CHECK constraint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictResolution {
+    Abort,
+    Ignore,
+    Replace,
+}
+
+// ON CONFLICT [(columns)] DO NOTHING | DO UPDATE SET ... [WHERE ...]. Without
+// columns it applies to every PRIMARY KEY and UNIQUE constraint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upsert {
+    pub target: Option<Vec<String>>,
+    pub action: UpsertAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertAction {
+    Nothing,
+    Update {
+        assignments: Vec<(String, Expr)>,
+        where_clause: Option<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expr)>,
+    pub where_clause: Option<Expr>,
+    pub returning: Vec<SelectItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub where_clause: Option<Expr>,
+    pub returning: Vec<SelectItem>,
+}
+
+// PRAGMA name, PRAGMA name = value or PRAGMA name(value).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pragma {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub with: Option<With>,
+    pub distinct: bool,
+    pub columns: Vec<SelectItem>,
+    pub from: Option<TableRef>,
+    pub joins: Vec<Join>,
+    pub where_clause: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    // Selects combined with this one, left to right. ORDER BY, LIMIT and
+    // OFFSET apply to the combined rows when there are any.
+    pub compound: Vec<CompoundSelect>,
+    pub order_by: Vec<OrderingTerm>,
+    pub limit: Option<Expr>,
+    pub offset: Option<Expr>,
+}
+
+// A select of a compound select, without ORDER BY, LIMIT or OFFSET.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompoundSelect {
+    pub op: CompoundOp,
+    pub select: Select,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompoundOp {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+// The common table expressions of a WITH clause, in the order they are
+// declared. Each one can read the ones before it and, in a WITH RECURSIVE
+// clause, itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct With {
+    pub recursive: bool,
+    pub tables: Vec<CommonTableExpr>,
+}
+
+// name [(columns)] AS (select). The columns are named after the result
+// columns of select when none are listed. The table is recursive when the
+// last select of a compound select reads it, combined with UNION [ALL].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpr {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub select: Select,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderingTerm {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard,
+    // table.*, the columns of that table only.
+    TableWildcard(String),
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+// A table of the FROM clause, or a subquery with an optional alias when
+// subquery is set. The name of a subquery is its alias or empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+    pub subquery: Option<Box<Select>>,
+}
+
+// A table joined to the tables before it in the FROM clause. Comma joins are
+// cross joins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableRef,
+    pub constraint: Option<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    // A parameter of a prepared statement, numbered from 1, with its text.
+    Parameter { index: usize, name: String },
+    Column { table: Option<String>, name: String },
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { left: Box<Expr>, op: BinaryOp, right: Box<Expr> },
+    IsNull { expr: Box<Expr>, negated: bool },
+    InList { expr: Box<Expr>, list: Vec<Expr>, negated: bool },
+    Between { expr: Box<Expr>, low: Box<Expr>, high: Box<Expr>, negated: bool },
+    // LIKE, or GLOB when glob is set.
+    Like { expr: Box<Expr>, pattern: Box<Expr>, escape: Option<Box<Expr>>, glob: bool, negated: bool },
+    // CASE [operand] WHEN .. THEN .. [ELSE ..] END.
+    Case { operand: Option<Box<Expr>>, branches: Vec<(Expr, Expr)>, else_expr: Option<Box<Expr>> },
+    // A function call, wildcard is set for name(*).
+    Function { name: String, args: Vec<Expr>, wildcard: bool, distinct: bool },
+    // function OVER (window), function being a Function.
+    Window { function: Box<Expr>, window: Box<WindowSpec> },
+    // A scalar subquery: the first column of its first row, or NULL.
+    Subquery(Box<Select>),
+    Exists(Box<Select>),
+    InSubquery { expr: Box<Expr>, select: Box<Select>, negated: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSpec {
+    pub partition_by: Vec<Expr>,
+    pub order_by: Vec<OrderingTerm>,
+    pub frame: Option<WindowFrame>,
+}
+
+// ROWS or RANGE BETWEEN start AND end. A frame given by its start alone
+// ends at the current row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameUnits {
+    Rows,
+    Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Expr),
+    CurrentRow,
+    Following(Expr),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Not,
+    Minus,
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Concat,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq
+        )
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Remainder => "%",
+            BinaryOp::Concat => "||",
+        }
+    }
+}
+
+impl Expr {
+    // Calls f on this expression and every expression nested in it.
+    pub fn walk(&self, f: &mut impl FnMut(&Expr)) {
+        f(self);
+        match self {
+            Expr::Literal(_) | Expr::Parameter { .. } | Expr::Column { .. } => {}
+            Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => expr.walk(f),
+            Expr::Binary { left, right, .. } => {
+                left.walk(f);
+                right.walk(f);
+            }
+            Expr::InList { expr, list, .. } => {
+                expr.walk(f);
+                list.iter().for_each(|item| item.walk(f));
+            }
+            Expr::Between { expr, low, high, .. } => {
+                expr.walk(f);
+                low.walk(f);
+                high.walk(f);
+            }
+            Expr::Like {
+                expr, pattern, escape, ..
+            } => {
+                expr.walk(f);
+                pattern.walk(f);
+                if let Some(escape) = escape {
+                    escape.walk(f);
+                }
+            }
+            Expr::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                if let Some(operand) = operand {
+                    operand.walk(f);
+                }
+                for (when, then) in branches {
+                    when.walk(f);
+                    then.walk(f);
+                }
+                if let Some(else_expr) = else_expr {
+                    else_expr.walk(f);
+                }
+            }
+            Expr::Function { args, .. } => args.iter().for_each(|arg| arg.walk(f)),
+            // The function itself is not visited, a window call is not an
+            // aggregate call even when its function is an aggregate.
+            Expr::Window { function, window } => {
+                if let Expr::Function { args, .. } = function.as_ref() {
+                    args.iter().for_each(|arg| arg.walk(f));
+                }
+                window.partition_by.iter().for_each(|expr| expr.walk(f));
+                window.order_by.iter().for_each(|term| term.expr.walk(f));
+                if let Some(frame) = &window.frame {
+                    for bound in [&frame.start, &frame.end] {
+                        if let FrameBound::Preceding(expr) | FrameBound::Following(expr) = bound {
+                            expr.walk(f);
+                        }
+                    }
+                }
+            }
+            // The expressions of a subquery belong to it, not to this one.
+            Expr::Subquery(_) | Expr::Exists(_) => {}
+            Expr::InSubquery { expr, .. } => expr.walk(f),
+        }
+    }
+
+    // Rebuilds the expression top down. f returns the replacement of a node,
+    // or None to keep the node and rewrite its children.
+    pub fn rewrite(&self, f: &mut impl FnMut(&Expr) -> Result<Option<Expr>, Error>) -> Result<Expr, Error> {
+        if let Some(replacement) = f(self)? {
+            return Ok(replacement);
+        }
+        let mut rewrite = |expr: &Expr| expr.rewrite(f).map(Box::new);
+        Ok(match self {
+            Expr::Literal(_) | Expr::Parameter { .. } | Expr::Column { .. } | Expr::Subquery(_) | Expr::Exists(_) => {
+                self.clone()
+            }
+            Expr::Unary { op, expr } => Expr::Unary {
+                op: *op,
+                expr: rewrite(expr)?,
+            },
+            Expr::Binary { left, op, right } => Expr::Binary {
+                left: rewrite(left)?,
+                op: *op,
+                right: rewrite(right)?,
+            },
+            Expr::IsNull { expr, negated } => Expr::IsNull {
+                expr: rewrite(expr)?,
+                negated: *negated,
+            },
+            Expr::InList { expr, list, negated } => Expr::InList {
+                expr: rewrite(expr)?,
+                list: list.iter().map(|item| rewrite(item).map(|item| *item)).collect::<Result<_, _>>()?,
+                negated: *negated,
+            },
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => Expr::Between {
+                expr: rewrite(expr)?,
+                low: rewrite(low)?,
+                high: rewrite(high)?,
+                negated: *negated,
+            },
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                glob,
+                negated,
+            } => Expr::Like {
+                expr: rewrite(expr)?,
+                pattern: rewrite(pattern)?,
+                escape: escape.as_deref().map(&mut rewrite).transpose()?,
+                glob: *glob,
+                negated: *negated,
+            },
+            Expr::Case {
+                operand,
+                branches,
+                else_expr,
+            } => Expr::Case {
+                operand: operand.as_deref().map(&mut rewrite).transpose()?,
+                branches: branches
+                    .iter()
+                    .map(|(when, then)| Ok((*rewrite(when)?, *rewrite(then)?)))
+                    .collect::<Result<_, Error>>()?,
+                else_expr: else_expr.as_deref().map(&mut rewrite).transpose()?,
+            },
+            Expr::Function {
+                name,
+                args,
+                wildcard,
+                distinct,
+            } => Expr::Function {
+                name: name.clone(),
+                args: args.iter().map(|arg| rewrite(arg).map(|arg| *arg)).collect::<Result<_, _>>()?,
+                wildcard: *wildcard,
+                distinct: *distinct,
+            },
+            Expr::Window { function, window } => {
+                let mut rewrite_bound = |bound: &FrameBound| -> Result<FrameBound, Error> {
+                    Ok(match bound {
+                        FrameBound::Preceding(expr) => FrameBound::Preceding(*rewrite(expr)?),
+                        FrameBound::Following(expr) => FrameBound::Following(*rewrite(expr)?),
+                        bound => bound.clone(),
+                    })
+                };
+                let frame = match &window.frame {
+                    Some(frame) => Some(WindowFrame {
+                        units: frame.units,
+                        start: rewrite_bound(&frame.start)?,
+                        end: rewrite_bound(&frame.end)?,
+                    }),
+                    None => None,
+                };
+                Expr::Window {
+                    function: rewrite(function)?,
+                    window: Box::new(WindowSpec {
+                        partition_by: window
+                            .partition_by
+                            .iter()
+                            .map(|expr| rewrite(expr).map(|expr| *expr))
+                            .collect::<Result<_, _>>()?,
+                        order_by: window
+                            .order_by
+                            .iter()
+                            .map(|term| {
+                                Ok(OrderingTerm {
+                                    expr: *rewrite(&term.expr)?,
+                                    descending: term.descending,
+                                })
+                            })
+                            .collect::<Result<_, Error>>()?,
+                        frame,
+                    }),
+                }
+            }
+            Expr::InSubquery { expr, select, negated } => Expr::InSubquery {
+                expr: rewrite(expr)?,
+                select: select.clone(),
+                negated: *negated,
+            },
+        })
+    }
+
+    // Names of the columns referenced by the expression, without duplicates.
+    pub fn referenced_columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = Vec::new();
+        self.walk(&mut |expr| {
+            if let Expr::Column { name, .. } = expr {
+                if !columns.iter().any(|column| column.eq_ignore_ascii_case(name)) {
+                    columns.push(name.clone());
+                }
+            }
+        });
+        columns
+    }
+}
+
+impl fmt::Display for CreateTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.to_string())
+            .chain(self.constraints.iter().map(|constraint| constraint.to_string()))
+            .collect();
+        write!(
+            f,
+            "CREATE TABLE {}{} ({})",
+            if self.if_not_exists { "IF NOT EXISTS " } else { "" },
+            self.name,
+            definitions.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for ColumnDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " {}", type_name)?;
+        }
+        for constraint in &self.constraints {
+            write!(f, " {}", constraint)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColumnConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnConstraint::PrimaryKey => write!(f, "PRIMARY KEY"),
+            ColumnConstraint::NotNull => write!(f, "NOT NULL"),
+            ColumnConstraint::Unique => write!(f, "UNIQUE"),
+            ColumnConstraint::Check(expr) => write!(f, "CHECK ({})", expr),
+            ColumnConstraint::Default(expr) => write!(f, "DEFAULT ({})", expr),
+            ColumnConstraint::References(clause) => write!(f, "REFERENCES {}", clause),
+        }
+    }
+}
+
+impl fmt::Display for TableConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableConstraint::PrimaryKey(columns) => write!(f, "PRIMARY KEY ({})", columns.join(", ")),
+            TableConstraint::Unique(columns) => write!(f, "UNIQUE ({})", columns.join(", ")),
+            TableConstraint::Check(expr) => write!(f, "CHECK ({})", expr),
+            TableConstraint::ForeignKey { columns, clause } => {
+                write!(f, "FOREIGN KEY ({}) REFERENCES {}", columns.join(", "), clause)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ForeignKeyClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table)?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", self.columns.join(", "))?;
+        }
+        if self.on_delete != ForeignKeyAction::NoAction {
+            write!(f, " ON DELETE {}", self.on_delete)?;
+        }
+        if self.on_update != ForeignKeyAction::NoAction {
+            write!(f, " ON UPDATE {}", self.on_update)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ForeignKeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ForeignKeyAction::NoAction => "NO ACTION",
+            ForeignKeyAction::Restrict => "RESTRICT",
+            ForeignKeyAction::Cascade => "CASCADE",
+            ForeignKeyAction::SetNull => "SET NULL",
+            ForeignKeyAction::SetDefault => "SET DEFAULT",
+        })
+    }
+}
+
+impl fmt::Display for CreateIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CREATE {}INDEX {}{} ON {} ({})",
+            if self.unique { "UNIQUE " } else { "" },
+            if self.if_not_exists { "IF NOT EXISTS " } else { "" },
+            self.name,
+            self.table,
+            self.columns.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for CreateView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CREATE VIEW {}{}",
+            if self.if_not_exists { "IF NOT EXISTS " } else { "" },
+            self.name
+        )?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", self.columns.join(", "))?;
+        }
+        write!(f, " AS {}", self.select)
+    }
+}
+
+impl fmt::Display for CreateTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timing = match self.timing {
+            TriggerTiming::Before => "BEFORE",
+            TriggerTiming::After => "AFTER",
+        };
+        write!(
+            f,
+            "CREATE TRIGGER {}{} {} ",
+            if self.if_not_exists { "IF NOT EXISTS " } else { "" },
+            self.name,
+            timing
+        )?;
+        match &self.event {
+            TriggerEvent::Insert => write!(f, "INSERT")?,
+            TriggerEvent::Delete => write!(f, "DELETE")?,
+            TriggerEvent::Update(columns) if columns.is_empty() => write!(f, "UPDATE")?,
+            TriggerEvent::Update(columns) => write!(f, "UPDATE OF {}", columns.join(", "))?,
+        }
+        write!(f, " ON {}", self.table)?;
+        if let Some(when) = &self.when {
+            write!(f, " WHEN {}", when)?;
+        }
+        write!(f, " BEGIN")?;
+        for stmt in &self.body {
+            match stmt {
+                Stmt::Insert(insert) => write!(f, " {};", insert)?,
+                Stmt::Update(update) => write!(f, " {};", update)?,
+                Stmt::Delete(delete) => write!(f, " {};", delete)?,
+                Stmt::Select(select) => write!(f, " {};", select)?,
+                _ => unreachable!("trigger statements are INSERT, UPDATE, DELETE or SELECT"),
+            }
+        }
+        write!(f, " END")
+    }
+}
+
+impl fmt::Display for Insert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self.or_conflict {
+            ConflictResolution::Abort => "INSERT",
+            ConflictResolution::Ignore => "INSERT OR IGNORE",
+            ConflictResolution::Replace => "INSERT OR REPLACE",
+        };
+        write!(f, "{} INTO {}", verb, self.table)?;
+        if let Some(columns) = &self.columns {
+            write!(f, " ({})", columns.join(", "))?;
+        }
+        match &self.source {
+            InsertSource::Values(rows) => {
+                let rows: Vec<String> = rows.iter().map(|row| format!("({})", exprs(row))).collect();
+                write!(f, " VALUES {}", rows.join(", "))?;
+            }
+            InsertSource::Select(select) => write!(f, " {}", select)?,
+        }
+        if let Some(upsert) = &self.upsert {
+            write!(f, " ON CONFLICT")?;
+            if let Some(target) = &upsert.target {
+                write!(f, " ({})", target.join(", "))?;
+            }
+            match &upsert.action {
+                UpsertAction::Nothing => write!(f, " DO NOTHING")?,
+                UpsertAction::Update {
+                    assignments,
+                    where_clause,
+                } => {
+                    write!(f, " DO UPDATE SET {}", self::assignments(assignments))?;
+                    if let Some(where_clause) = where_clause {
+                        write!(f, " WHERE {}", where_clause)?;
+                    }
+                }
+            }
+        }
+        returning(f, &self.returning)
+    }
+}
+
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UPDATE {} SET {}", self.table, assignments(&self.assignments))?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        returning(f, &self.returning)
+    }
+}
+
+impl fmt::Display for Delete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DELETE FROM {}", self.table)?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        returning(f, &self.returning)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(Value::Text(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Parameter { name, .. } => write!(f, "{}", name),
+            Expr::Column { table: Some(table), name } => write!(f, "{}.{}", table, name),
+            Expr::Column { table: None, name } => write!(f, "{}", name),
+            Expr::Unary { op: UnaryOp::Not, expr } => write!(f, "NOT {}", expr),
+            Expr::Unary { op: UnaryOp::Minus, expr } => write!(f, "-{}", expr),
+            Expr::Unary { op: UnaryOp::Plus, expr } => write!(f, "+{}", expr),
+            Expr::Binary { left, op, right } => write!(f, "{} {} {}", wrap(left), op.symbol(), wrap(right)),
+            Expr::IsNull { expr, negated } => write!(f, "{} IS {}NULL", wrap(expr), not(*negated)),
+            Expr::InList { expr, list, negated } => {
+                let list: Vec<String> = list.iter().map(|item| item.to_string()).collect();
+                write!(f, "{} {}IN ({})", wrap(expr), not(*negated), list.join(", "))
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => write!(f, "{} {}BETWEEN {} AND {}", wrap(expr), not(*negated), wrap(low), wrap(high)),
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                glob,
+                negated,
+            } => {
+                let op = if *glob { "GLOB" } else { "LIKE" };
+                write!(f, "{} {}{} {}", wrap(expr), not(*negated), op, wrap(pattern))?;
+                match escape {
+                    Some(escape) => write!(f, " ESCAPE {}", wrap(escape)),
+                    None => Ok(()),
+                }
+            }
+            Expr::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", wrap(operand))?;
+                }
+                for (when, then) in branches {
+                    write!(f, " WHEN {} THEN {}", when, then)?;
+                }
+                if let Some(else_expr) = else_expr {
+                    write!(f, " ELSE {}", else_expr)?;
+                }
+                write!(f, " END")
+            }
+            Expr::Function { name, wildcard: true, .. } => write!(f, "{}(*)", name),
+            Expr::Function { name, args, distinct, .. } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({}{})", name, if *distinct { "DISTINCT " } else { "" }, args.join(", "))
+            }
+            Expr::Window { function, window } => write!(f, "{} OVER ({})", function, window),
+            Expr::Subquery(select) => write!(f, "({})", select),
+            Expr::Exists(select) => write!(f, "EXISTS ({})", select),
+            Expr::InSubquery { expr, select, negated } => write!(f, "{} {}IN ({})", wrap(expr), not(*negated), select),
+        }
+    }
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(with) = &self.with {
+            write!(f, "{} ", with)?;
+        }
+        write!(f, "SELECT {}{}", if self.distinct { "DISTINCT " } else { "" }, items(&self.columns))?;
+        if let Some(from) = &self.from {
+            write!(f, " FROM {}", from)?;
+        }
+        for join in &self.joins {
+            let kind = match join.kind {
+                JoinKind::Inner => "JOIN",
+                JoinKind::Left => "LEFT JOIN",
+                JoinKind::Cross => "CROSS JOIN",
+            };
+            write!(f, " {} {}", kind, join.table)?;
+            if let Some(constraint) = &join.constraint {
+                write!(f, " ON {}", constraint)?;
+            }
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        if !self.group_by.is_empty() {
+            let terms: Vec<String> = self.group_by.iter().map(|expr| expr.to_string()).collect();
+            write!(f, " GROUP BY {}", terms.join(", "))?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {}", having)?;
+        }
+        for term in &self.compound {
+            write!(f, " {} {}", term.op, term.select)?;
+        }
+        if !self.order_by.is_empty() {
+            let terms: Vec<String> = self
+                .order_by
+                .iter()
+                .map(|term| format!("{}{}", term.expr, if term.descending { " DESC" } else { "" }))
+                .collect();
+            write!(f, " ORDER BY {}", terms.join(", "))?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WindowSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.partition_by.is_empty() {
+            let terms: Vec<String> = self.partition_by.iter().map(|expr| expr.to_string()).collect();
+            parts.push(format!("PARTITION BY {}", terms.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let terms: Vec<String> = self
+                .order_by
+                .iter()
+                .map(|term| format!("{}{}", term.expr, if term.descending { " DESC" } else { "" }))
+                .collect();
+            parts.push(format!("ORDER BY {}", terms.join(", ")));
+        }
+        if let Some(frame) = &self.frame {
+            let units = match frame.units {
+                FrameUnits::Rows => "ROWS",
+                FrameUnits::Range => "RANGE",
+            };
+            parts.push(format!("{} BETWEEN {} AND {}", units, frame.start, frame.end));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameBound::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            FrameBound::Preceding(expr) => write!(f, "{} PRECEDING", expr),
+            FrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            FrameBound::Following(expr) => write!(f, "{} FOLLOWING", expr),
+            FrameBound::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+impl fmt::Display for CompoundOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompoundOp::Union => "UNION",
+            CompoundOp::UnionAll => "UNION ALL",
+            CompoundOp::Intersect => "INTERSECT",
+            CompoundOp::Except => "EXCEPT",
+        })
+    }
+}
+
+impl fmt::Display for With {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WITH {}", if self.recursive { "RECURSIVE " } else { "" })?;
+        for (i, cte) in self.tables.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", cte.name)?;
+            if !cte.columns.is_empty() {
+                write!(f, "({})", cte.columns.join(", "))?;
+            }
+            write!(f, " AS ({})", cte.select)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subquery {
+            Some(select) => write!(f, "({})", select)?,
+            None => write!(f, "{}", self.name)?,
+        }
+        match &self.alias {
+            Some(alias) => write!(f, " AS {}", alias),
+            None => Ok(()),
+        }
+    }
+}
+
+fn items(items: &[SelectItem]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            SelectItem::Wildcard => "*".to_string(),
+            SelectItem::TableWildcard(table) => format!("{}.*", table),
+            SelectItem::Expr { expr, alias: Some(alias) } => format!("{} AS {}", expr, alias),
+            SelectItem::Expr { expr, alias: None } => expr.to_string(),
+        })
+        .collect();
+    items.join(", ")
+}
+
+fn exprs(exprs: &[Expr]) -> String {
+    let exprs: Vec<String> = exprs.iter().map(|expr| expr.to_string()).collect();
+    exprs.join(", ")
+}
+
+fn assignments(assignments: &[(String, Expr)]) -> String {
+    let assignments: Vec<String> = assignments
+        .iter()
+        .map(|(column, expr)| format!("{} = {}", column, expr))
+        .collect();
+    assignments.join(", ")
+}
+
+fn returning(f: &mut fmt::Formatter<'_>, items: &[SelectItem]) -> fmt::Result {
+    if items.is_empty() {
+        return Ok(());
+    }
+    write!(f, " RETURNING {}", self::items(items))
+}
+
+// Parenthesizes nested binary expressions so the printed text parses back
+// to the same tree.
+fn wrap(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary { .. }
+        | Expr::InList { .. }
+        | Expr::Between { .. }
+        | Expr::Like { .. }
+        | Expr::InSubquery { .. } => format!("({})", expr),
+        _ => expr.to_string(),
+    }
+}
+
+fn not(negated: bool) -> &'static str {
+    if negated {
+        "NOT "
+    } else {
+        ""
+    }
+}
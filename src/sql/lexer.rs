@@ -0,0 +1,122 @@
+use std::io::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    // Bare identifier or keyword, keywords are matched case insensitively.
+    Ident(String),
+    // "name", `name` or [name]; never treated as a keyword.
+    QuotedIdent(String),
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Symbol(&'static str),
+    // A parameter: ?, ?NNN, or :name, @name and $name, with its prefix.
+    Parameter(String),
+}
+
+const SYMBOLS: [&str; 19] = [
+    "==", "!=", "<>", "<=", ">=", "||", "(", ")", ",", ";", "*", "+", "-", "/", "%", "=", "<", ">", ".",
+];
+
+pub fn tokenize(sql: &str) -> Result<Vec<Token>, Error> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            match text.parse::<i64>() {
+                Ok(value) => tokens.push(Token::Integer(value)),
+                Err(_) => match text.parse::<f64>() {
+                    Ok(value) => tokens.push(Token::Real(value)),
+                    Err(_) => return Err(syntax_error(&text)),
+                },
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '?' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            tokens.push(Token::Parameter(chars[start..i].iter().collect()));
+        } else if (c == ':' || c == '@' || c == '$') && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(Token::Parameter(chars[start..i].iter().collect()));
+        } else if c == '\'' {
+            let (text, next) = read_quoted(&chars, i, '\'')?;
+            tokens.push(Token::String(text));
+            i = next;
+        } else if c == '"' || c == '`' {
+            let (text, next) = read_quoted(&chars, i, c)?;
+            tokens.push(Token::QuotedIdent(text));
+            i = next;
+        } else if c == '[' {
+            let (text, next) = read_quoted(&chars, i, ']')?;
+            tokens.push(Token::QuotedIdent(text));
+            i = next;
+        } else {
+            let rest: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| syntax_error(&c.to_string()))?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+// Reads a quoted string starting at the opening quote. A doubled closing
+// quote stands for the quote itself.
+fn read_quoted(chars: &[char], start: usize, close: char) -> Result<(String, usize), Error> {
+    let mut text = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == close {
+            if close != ']' && chars.get(i + 1) == Some(&close) {
+                text.push(close);
+                i += 2;
+                continue;
+            }
+            return Ok((text, i + 1));
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+    Err(Error::other("unrecognized token: unterminated string"))
+}
+
+fn syntax_error(near: &str) -> Error {
+    Error::other(format!("unrecognized token: \"{}\"", near))
+}
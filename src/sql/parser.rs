@@ -0,0 +1,1457 @@
+use crate::sql::ast::*;
+use crate::sql::lexer::{tokenize, Token};
+use crate::table::value::Value;
+use std::io::Error;
+
+// Keywords that can not be used as bare identifiers or aliases.
+const RESERVED: [&str; 61] = [
+    "SELECT", "FROM", "WHERE", "AND", "OR", "NOT", "NULL", "IS", "AS", "SET", "VALUES", "INSERT", "INTO",
+    "UPDATE", "DELETE", "CREATE", "TABLE", "INDEX", "ON", "PRIMARY", "UNIQUE", "CHECK", "DEFAULT",
+    "FOREIGN", "REFERENCES", "PRAGMA", "ORDER", "BY", "LIMIT", "OFFSET", "GROUP", "HAVING",
+    "JOIN", "INNER", "LEFT", "OUTER", "CROSS", "IN", "BETWEEN", "LIKE", "GLOB", "ESCAPE", "CASE", "WHEN", "THEN",
+    "ELSE", "END", "RETURNING", "EXISTS", "WITH", "UNION",
+    "INTERSECT", "EXCEPT", "DISTINCT", "ALL", "VIEW", "DROP", "TRIGGER", "ALTER",
+    "EXPLAIN", "ANALYZE",
+];
+
+pub fn parse(sql: &str) -> Result<Stmt, Error> {
+    parse_with_parameters(sql).map(|(stmt, _)| stmt)
+}
+
+// Parses a statement along with its parameters: the name of each one by
+// number, None for the ones numbered with ? or ?NNN only. Parameters are
+// numbered the way SQLite numbers them: ?NNN is number NNN, a name gets the
+// number it got first, anything else the next number after the largest one
+// so far.
+pub fn parse_with_parameters(sql: &str) -> Result<(Stmt, Vec<Option<String>>), Error> {
+    let mut parser = Parser::new(tokenize(sql)?);
+    let stmt = parser.parse_statement()?;
+    parser.consume_symbol(";");
+    if !parser.at_end() {
+        return Err(parser.error());
+    }
+    let schema_change = matches!(
+        stmt,
+        Stmt::CreateTable(_) | Stmt::CreateIndex(_) | Stmt::CreateView(_) | Stmt::CreateTrigger(_) | Stmt::AlterTable(_)
+    );
+    if schema_change && !parser.parameters.is_empty() {
+        return Err(Error::other("parameters are not allowed in schema definitions"));
+    }
+    Ok((stmt, parser.parameters))
+}
+
+// Largest number a parameter can have.
+const MAX_PARAMETER: usize = 32766;
+
+// Largest height of an expression tree, SQLite's limit.
+const MAX_EXPR_DEPTH: usize = 1000;
+
+// Deepest the parser nests through parentheses, subqueries, calls and prefix
+// operators. Each level takes several kilobytes of stack, so this is far
+// below MAX_EXPR_DEPTH, like the depth of SQLite's parser stack.
+const MAX_PARSER_DEPTH: usize = 100;
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    parameters: Vec<Option<String>>,
+    // How many expressions the one being parsed is nested in.
+    depth: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        Parser {
+            tokens,
+            pos: 0,
+            parameters: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn parameter(&mut self, name: String) -> Result<Expr, Error> {
+        let index = match name.strip_prefix('?') {
+            Some("") => self.parameters.len() + 1,
+            Some(digits) => match digits.parse::<usize>() {
+                Ok(index) if (1..=MAX_PARAMETER).contains(&index) => index,
+                _ => {
+                    return Err(Error::other(format!("variable number must be between ?1 and ?{}", MAX_PARAMETER)))
+                }
+            },
+            None => match self.parameters.iter().position(|other| other.as_deref() == Some(name.as_str())) {
+                Some(i) => i + 1,
+                None => self.parameters.len() + 1,
+            },
+        };
+        if self.parameters.len() < index {
+            self.parameters.resize(index, None);
+        }
+        if !name.starts_with('?') {
+            self.parameters[index - 1] = Some(name.clone());
+        }
+        Ok(Expr::Parameter { index, name })
+    }
+
+    pub fn parse_statement(&mut self) -> Result<Stmt, Error> {
+        if self.consume_keyword("EXPLAIN") {
+            if self.consume_keyword("QUERY") {
+                self.expect_keyword("PLAN")?;
+                return self.parse_statement().map(|stmt| Stmt::ExplainQueryPlan(Box::new(stmt)));
+            }
+            return self.parse_statement().map(|stmt| Stmt::Explain(Box::new(stmt)));
+        }
+        if self.peek_keyword("CREATE") {
+            self.parse_create()
+        } else if self.peek_keyword("INSERT") || self.peek_keyword("REPLACE") {
+            self.parse_insert().map(Stmt::Insert)
+        } else if self.peek_keyword("UPDATE") {
+            self.parse_update().map(Stmt::Update)
+        } else if self.peek_keyword("DELETE") {
+            self.parse_delete().map(Stmt::Delete)
+        } else if self.peek_select_at(0) {
+            self.parse_select().map(|select| Stmt::Select(Box::new(select)))
+        } else if self.peek_keyword("PRAGMA") {
+            self.parse_pragma().map(Stmt::Pragma)
+        } else if self.peek_keyword("DROP") {
+            self.parse_drop()
+        } else if self.peek_keyword("ALTER") {
+            self.parse_alter_table().map(Stmt::AlterTable)
+        } else if self.consume_keyword("ANALYZE") {
+            let name = if self.at_end() || self.peek_symbol(";") { None } else { Some(self.parse_identifier()?) };
+            Ok(Stmt::Analyze(name))
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn parse_create(&mut self) -> Result<Stmt, Error> {
+        self.expect_keyword("CREATE")?;
+        if self.consume_keyword("TABLE") {
+            return self.parse_create_table().map(Stmt::CreateTable);
+        }
+        let unique = self.consume_keyword("UNIQUE");
+        if self.consume_keyword("INDEX") {
+            return self.parse_create_index(unique).map(Stmt::CreateIndex);
+        }
+        if !unique && self.consume_keyword("VIEW") {
+            return self.parse_create_view().map(Stmt::CreateView);
+        }
+        if !unique && self.consume_keyword("TRIGGER") {
+            return self.parse_create_trigger().map(Stmt::CreateTrigger);
+        }
+        Err(self.error())
+    }
+
+    fn parse_drop(&mut self) -> Result<Stmt, Error> {
+        self.expect_keyword("DROP")?;
+        if self.consume_keyword("TABLE") {
+            let if_exists = self.parse_if_exists()?;
+            let name = self.parse_identifier()?;
+            return Ok(Stmt::DropTable(DropTable { name, if_exists }));
+        }
+        if self.consume_keyword("TRIGGER") {
+            let if_exists = self.parse_if_exists()?;
+            let name = self.parse_identifier()?;
+            return Ok(Stmt::DropTrigger(DropTrigger { name, if_exists }));
+        }
+        self.expect_keyword("VIEW")?;
+        let if_exists = self.parse_if_exists()?;
+        let name = self.parse_identifier()?;
+        Ok(Stmt::DropView(DropView { name, if_exists }))
+    }
+
+    // ALTER TABLE t ADD [COLUMN] def | RENAME [COLUMN] a TO b | RENAME TO n
+    // | DROP [COLUMN] c.
+    fn parse_alter_table(&mut self) -> Result<AlterTable, Error> {
+        self.expect_keyword("ALTER")?;
+        self.expect_keyword("TABLE")?;
+        let table = self.parse_identifier()?;
+        let action = if self.consume_keyword("ADD") {
+            self.consume_keyword("COLUMN");
+            AlterAction::AddColumn(self.parse_column_def()?)
+        } else if self.consume_keyword("RENAME") {
+            if self.consume_keyword("TO") {
+                AlterAction::RenameTo(self.parse_identifier()?)
+            } else {
+                self.consume_keyword("COLUMN");
+                let old = self.parse_identifier()?;
+                self.expect_keyword("TO")?;
+                let new = self.parse_identifier()?;
+                AlterAction::RenameColumn { old, new }
+            }
+        } else {
+            self.expect_keyword("DROP")?;
+            self.consume_keyword("COLUMN");
+            AlterAction::DropColumn(self.parse_identifier()?)
+        };
+        Ok(AlterTable { table, action })
+    }
+
+    fn parse_create_table(&mut self) -> Result<CreateTable, Error> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_identifier()?;
+        self.expect_symbol("(")?;
+        let mut columns = Vec::new();
+        let mut constraints = Vec::new();
+        loop {
+            if self.peek_table_constraint() {
+                constraints.push(self.parse_table_constraint()?);
+            } else {
+                columns.push(self.parse_column_def()?);
+            }
+            if !self.consume_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(CreateTable {
+            name,
+            if_not_exists,
+            columns,
+            constraints,
+        })
+    }
+
+    fn parse_if_not_exists(&mut self) -> Result<bool, Error> {
+        if self.consume_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn parse_if_exists(&mut self) -> Result<bool, Error> {
+        if self.consume_keyword("IF") {
+            self.expect_keyword("EXISTS")?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn parse_column_def(&mut self) -> Result<ColumnDef, Error> {
+        let name = self.parse_identifier()?;
+        let type_name = self.parse_type_name()?;
+        let mut constraints = Vec::new();
+        loop {
+            if self.consume_keyword("CONSTRAINT") {
+                self.parse_identifier()?;
+            }
+            if self.consume_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                if !self.consume_keyword("ASC") {
+                    self.consume_keyword("DESC");
+                }
+                self.consume_keyword("AUTOINCREMENT");
+                constraints.push(ColumnConstraint::PrimaryKey);
+            } else if self.consume_keyword("NOT") {
+                self.expect_keyword("NULL")?;
+                constraints.push(ColumnConstraint::NotNull);
+            } else if self.consume_keyword("NULL") {
+                // Explicitly nullable, nothing to record.
+            } else if self.consume_keyword("UNIQUE") {
+                constraints.push(ColumnConstraint::Unique);
+            } else if self.consume_keyword("CHECK") {
+                self.expect_symbol("(")?;
+                let expr = self.parse_expr()?;
+                self.expect_symbol(")")?;
+                constraints.push(ColumnConstraint::Check(expr));
+            } else if self.consume_keyword("DEFAULT") {
+                constraints.push(ColumnConstraint::Default(self.parse_default_value()?));
+            } else if self.consume_keyword("REFERENCES") {
+                constraints.push(ColumnConstraint::References(self.parse_foreign_key_clause()?));
+            } else {
+                break;
+            }
+        }
+        Ok(ColumnDef {
+            name,
+            type_name,
+            constraints,
+        })
+    }
+
+    // Type names are a sequence of words optionally followed by a size, like
+    // VARCHAR(32) or DOUBLE PRECISION.
+    fn parse_type_name(&mut self) -> Result<Option<String>, Error> {
+        let mut words = Vec::new();
+        while let Some(Token::Ident(word)) = self.peek() {
+            if is_column_constraint_start(word) {
+                break;
+            }
+            words.push(word.clone());
+            self.pos += 1;
+        }
+        if words.is_empty() {
+            return Ok(None);
+        }
+        let mut type_name = words.join(" ");
+        if self.consume_symbol("(") {
+            let mut sizes = Vec::new();
+            loop {
+                match self.next() {
+                    Some(Token::Integer(size)) => sizes.push(size.to_string()),
+                    _ => return Err(self.error()),
+                }
+                if !self.consume_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+            type_name = format!("{}({})", type_name, sizes.join(","));
+        }
+        Ok(Some(type_name))
+    }
+
+    fn parse_default_value(&mut self) -> Result<Expr, Error> {
+        if self.consume_symbol("(") {
+            let expr = self.parse_expr()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+        self.parse_unary()
+    }
+
+    fn peek_table_constraint(&self) -> bool {
+        ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+            .iter()
+            .any(|keyword| self.peek_keyword(keyword))
+    }
+
+    fn parse_table_constraint(&mut self) -> Result<TableConstraint, Error> {
+        if self.consume_keyword("CONSTRAINT") {
+            self.parse_identifier()?;
+        }
+        if self.consume_keyword("PRIMARY") {
+            self.expect_keyword("KEY")?;
+            Ok(TableConstraint::PrimaryKey(self.parse_column_list()?))
+        } else if self.consume_keyword("UNIQUE") {
+            Ok(TableConstraint::Unique(self.parse_column_list()?))
+        } else if self.consume_keyword("CHECK") {
+            self.expect_symbol("(")?;
+            let expr = self.parse_expr()?;
+            self.expect_symbol(")")?;
+            Ok(TableConstraint::Check(expr))
+        } else if self.consume_keyword("FOREIGN") {
+            self.expect_keyword("KEY")?;
+            let columns = self.parse_column_list()?;
+            self.expect_keyword("REFERENCES")?;
+            let clause = self.parse_foreign_key_clause()?;
+            Ok(TableConstraint::ForeignKey { columns, clause })
+        } else {
+            Err(self.error())
+        }
+    }
+
+    // Parses what follows REFERENCES: the parent table, its optional column
+    // list and the ON DELETE / ON UPDATE actions.
+    fn parse_foreign_key_clause(&mut self) -> Result<ForeignKeyClause, Error> {
+        let table = self.parse_identifier()?;
+        let columns = if self.peek_symbol("(") {
+            self.parse_column_list()?
+        } else {
+            Vec::new()
+        };
+        let mut on_delete = ForeignKeyAction::NoAction;
+        let mut on_update = ForeignKeyAction::NoAction;
+        while self.consume_keyword("ON") {
+            if self.consume_keyword("DELETE") {
+                on_delete = self.parse_foreign_key_action()?;
+            } else if self.consume_keyword("UPDATE") {
+                on_update = self.parse_foreign_key_action()?;
+            } else {
+                return Err(self.error());
+            }
+        }
+        Ok(ForeignKeyClause {
+            table,
+            columns,
+            on_delete,
+            on_update,
+        })
+    }
+
+    fn parse_foreign_key_action(&mut self) -> Result<ForeignKeyAction, Error> {
+        if self.consume_keyword("CASCADE") {
+            Ok(ForeignKeyAction::Cascade)
+        } else if self.consume_keyword("RESTRICT") {
+            Ok(ForeignKeyAction::Restrict)
+        } else if self.consume_keyword("SET") {
+            if self.consume_keyword("NULL") {
+                return Ok(ForeignKeyAction::SetNull);
+            }
+            self.expect_keyword("DEFAULT")?;
+            Ok(ForeignKeyAction::SetDefault)
+        } else if self.consume_keyword("NO") {
+            self.expect_keyword("ACTION")?;
+            Ok(ForeignKeyAction::NoAction)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn parse_column_list(&mut self) -> Result<Vec<String>, Error> {
+        self.expect_symbol("(")?;
+        let mut columns = vec![self.parse_identifier()?];
+        while self.consume_symbol(",") {
+            columns.push(self.parse_identifier()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(columns)
+    }
+
+    fn parse_create_index(&mut self, unique: bool) -> Result<CreateIndex, Error> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_identifier()?;
+        self.expect_keyword("ON")?;
+        let table = self.parse_identifier()?;
+        let columns = self.parse_column_list()?;
+        Ok(CreateIndex {
+            name,
+            table,
+            columns,
+            unique,
+            if_not_exists,
+        })
+    }
+
+    fn parse_create_view(&mut self) -> Result<CreateView, Error> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_identifier()?;
+        let columns = if self.peek_symbol("(") {
+            self.parse_column_list()?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("AS")?;
+        let select = self.parse_select()?;
+        Ok(CreateView {
+            name,
+            if_not_exists,
+            columns,
+            select: Box::new(select),
+        })
+    }
+
+    fn parse_create_trigger(&mut self) -> Result<CreateTrigger, Error> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_identifier()?;
+        let timing = if self.consume_keyword("AFTER") {
+            TriggerTiming::After
+        } else {
+            self.consume_keyword("BEFORE");
+            TriggerTiming::Before
+        };
+        let event = if self.consume_keyword("INSERT") {
+            TriggerEvent::Insert
+        } else if self.consume_keyword("DELETE") {
+            TriggerEvent::Delete
+        } else {
+            self.expect_keyword("UPDATE")?;
+            let mut columns = Vec::new();
+            if self.consume_keyword("OF") {
+                loop {
+                    columns.push(self.parse_identifier()?);
+                    if !self.consume_symbol(",") {
+                        break;
+                    }
+                }
+            }
+            TriggerEvent::Update(columns)
+        };
+        self.expect_keyword("ON")?;
+        let table = self.parse_identifier()?;
+        if self.consume_keyword("FOR") {
+            self.expect_keyword("EACH")?;
+            self.expect_keyword("ROW")?;
+        }
+        let when = if self.consume_keyword("WHEN") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect_keyword("BEGIN")?;
+        let mut body = Vec::new();
+        loop {
+            let is_dml = ["INSERT", "REPLACE", "UPDATE", "DELETE"]
+                .iter()
+                .any(|keyword| self.peek_keyword(keyword));
+            if !is_dml && !self.peek_select_at(0) {
+                return Err(self.error());
+            }
+            body.push(self.parse_statement()?);
+            self.expect_symbol(";")?;
+            if self.consume_keyword("END") {
+                break;
+            }
+        }
+        Ok(CreateTrigger {
+            name,
+            if_not_exists,
+            timing,
+            event,
+            table,
+            when,
+            body,
+        })
+    }
+
+    fn parse_insert(&mut self) -> Result<Insert, Error> {
+        let or_conflict = if self.consume_keyword("REPLACE") {
+            ConflictResolution::Replace
+        } else {
+            self.expect_keyword("INSERT")?;
+            self.parse_or_conflict()?
+        };
+        self.expect_keyword("INTO")?;
+        let table = self.parse_identifier()?;
+        let columns = if self.peek_symbol("(") {
+            Some(self.parse_column_list()?)
+        } else {
+            None
+        };
+        let source = if self.peek_select_at(0) {
+            InsertSource::Select(Box::new(self.parse_select()?))
+        } else {
+            self.expect_keyword("VALUES")?;
+            let mut rows = Vec::new();
+            loop {
+                self.expect_symbol("(")?;
+                rows.push(self.parse_expr_list()?);
+                self.expect_symbol(")")?;
+                if !self.consume_symbol(",") {
+                    break;
+                }
+            }
+            InsertSource::Values(rows)
+        };
+        let upsert = if self.consume_keyword("ON") {
+            Some(self.parse_upsert()?)
+        } else {
+            None
+        };
+        let returning = self.parse_returning()?;
+        Ok(Insert {
+            table,
+            columns,
+            source,
+            or_conflict,
+            upsert,
+            returning,
+        })
+    }
+
+    fn parse_or_conflict(&mut self) -> Result<ConflictResolution, Error> {
+        if !self.consume_keyword("OR") {
+            return Ok(ConflictResolution::Abort);
+        }
+        if self.consume_keyword("ABORT") {
+            Ok(ConflictResolution::Abort)
+        } else if self.consume_keyword("IGNORE") {
+            Ok(ConflictResolution::Ignore)
+        } else if self.consume_keyword("REPLACE") {
+            Ok(ConflictResolution::Replace)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    // The part of an ON CONFLICT clause after ON.
+    fn parse_upsert(&mut self) -> Result<Upsert, Error> {
+        self.expect_keyword("CONFLICT")?;
+        let target = if self.peek_symbol("(") {
+            Some(self.parse_column_list()?)
+        } else {
+            None
+        };
+        self.expect_keyword("DO")?;
+        let action = if self.consume_keyword("NOTHING") {
+            UpsertAction::Nothing
+        } else {
+            self.expect_keyword("UPDATE")?;
+            self.expect_keyword("SET")?;
+            let assignments = self.parse_assignments()?;
+            let where_clause = self.parse_where()?;
+            UpsertAction::Update {
+                assignments,
+                where_clause,
+            }
+        };
+        Ok(Upsert { target, action })
+    }
+
+    fn parse_update(&mut self) -> Result<Update, Error> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_identifier()?;
+        self.expect_keyword("SET")?;
+        let assignments = self.parse_assignments()?;
+        let where_clause = self.parse_where()?;
+        let returning = self.parse_returning()?;
+        Ok(Update {
+            table,
+            assignments,
+            where_clause,
+            returning,
+        })
+    }
+
+    // column = expr, ... of an UPDATE or DO UPDATE SET.
+    fn parse_assignments(&mut self) -> Result<Vec<(String, Expr)>, Error> {
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.parse_identifier()?;
+            self.expect_symbol("=")?;
+            assignments.push((column, self.parse_expr()?));
+            if !self.consume_symbol(",") {
+                break;
+            }
+        }
+        Ok(assignments)
+    }
+
+    fn parse_delete(&mut self) -> Result<Delete, Error> {
+        self.expect_keyword("DELETE")?;
+        self.expect_keyword("FROM")?;
+        let table = self.parse_identifier()?;
+        let where_clause = self.parse_where()?;
+        let returning = self.parse_returning()?;
+        Ok(Delete {
+            table,
+            where_clause,
+            returning,
+        })
+    }
+
+    fn parse_pragma(&mut self) -> Result<Pragma, Error> {
+        self.expect_keyword("PRAGMA")?;
+        let name = self.parse_identifier()?;
+        let value = if self.consume_symbol("=") {
+            Some(self.parse_pragma_value()?)
+        } else if self.consume_symbol("(") {
+            let value = self.parse_pragma_value()?;
+            self.expect_symbol(")")?;
+            Some(value)
+        } else {
+            None
+        };
+        Ok(Pragma { name, value })
+    }
+
+    // Pragma values are keywords like ON, names or numbers.
+    fn parse_pragma_value(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some(Token::Ident(word)) | Some(Token::QuotedIdent(word)) | Some(Token::String(word)) => Ok(word),
+            Some(Token::Integer(i)) => Ok(i.to_string()),
+            _ => {
+                self.pos -= 1;
+                Err(self.error())
+            }
+        }
+    }
+
+    fn parse_select(&mut self) -> Result<Select, Error> {
+        let with = if self.peek_keyword("WITH") {
+            Some(self.parse_with()?)
+        } else {
+            None
+        };
+        let mut select = self.parse_select_core()?;
+        while let Some(op) = self.parse_compound_op() {
+            let core = self.parse_select_core()?;
+            select.compound.push(CompoundSelect { op, select: core });
+        }
+        select.with = with;
+        select.order_by = self.parse_order_by()?;
+        (select.limit, select.offset) = self.parse_limit()?;
+        Ok(select)
+    }
+
+    // A select without WITH, ORDER BY, LIMIT or OFFSET.
+    fn parse_select_core(&mut self) -> Result<Select, Error> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.consume_keyword("DISTINCT");
+        if !distinct {
+            self.consume_keyword("ALL");
+        }
+        let columns = self.parse_select_items()?;
+        let (from, joins) = if self.consume_keyword("FROM") {
+            let from = self.parse_table_ref()?;
+            (Some(from), self.parse_joins()?)
+        } else {
+            (None, Vec::new())
+        };
+        let where_clause = self.parse_where()?;
+        let group_by = if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            self.parse_expr_list()?
+        } else {
+            Vec::new()
+        };
+        let having = if self.consume_keyword("HAVING") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(Select {
+            with: None,
+            distinct,
+            columns,
+            from,
+            joins,
+            where_clause,
+            group_by,
+            having,
+            compound: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        })
+    }
+
+    fn parse_compound_op(&mut self) -> Option<CompoundOp> {
+        if self.consume_keyword("UNION") {
+            Some(if self.consume_keyword("ALL") {
+                CompoundOp::UnionAll
+            } else {
+                CompoundOp::Union
+            })
+        } else if self.consume_keyword("INTERSECT") {
+            Some(CompoundOp::Intersect)
+        } else if self.consume_keyword("EXCEPT") {
+            Some(CompoundOp::Except)
+        } else {
+            None
+        }
+    }
+
+
+    fn parse_with(&mut self) -> Result<With, Error> {
+        self.expect_keyword("WITH")?;
+        let recursive = self.consume_keyword("RECURSIVE");
+        let mut tables = Vec::new();
+        loop {
+            let name = self.parse_identifier()?;
+            let columns = if self.peek_symbol("(") {
+                self.parse_column_list()?
+            } else {
+                Vec::new()
+            };
+            self.expect_keyword("AS")?;
+            self.expect_symbol("(")?;
+            let select = self.parse_select()?;
+            self.expect_symbol(")")?;
+            tables.push(CommonTableExpr { name, columns, select });
+            if !self.consume_symbol(",") {
+                break;
+            }
+        }
+        Ok(With { recursive, tables })
+    }
+
+    // Result columns of a SELECT or a RETURNING clause.
+    fn parse_select_items(&mut self) -> Result<Vec<SelectItem>, Error> {
+        let mut items = Vec::new();
+        loop {
+            if self.consume_symbol("*") {
+                items.push(SelectItem::Wildcard);
+            } else if let (Some(Token::Ident(table)), Some(Token::Symbol(".")), Some(Token::Symbol("*"))) =
+                (self.tokens.get(self.pos), self.tokens.get(self.pos + 1), self.tokens.get(self.pos + 2))
+            {
+                items.push(SelectItem::TableWildcard(table.clone()));
+                self.pos += 3;
+            } else {
+                let expr = self.parse_expr()?;
+                let alias = self.parse_alias()?;
+                items.push(SelectItem::Expr { expr, alias });
+            }
+            if !self.consume_symbol(",") {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn parse_returning(&mut self) -> Result<Vec<SelectItem>, Error> {
+        if !self.consume_keyword("RETURNING") {
+            return Ok(Vec::new());
+        }
+        self.parse_select_items()
+    }
+    fn parse_table_ref(&mut self) -> Result<TableRef, Error> {
+        if self.consume_symbol("(") {
+            let select = self.parse_select()?;
+            self.expect_symbol(")")?;
+            let alias = self.parse_alias()?;
+            return Ok(TableRef {
+                name: alias.clone().unwrap_or_default(),
+                alias,
+                subquery: Some(Box::new(select)),
+            });
+        }
+        let name = self.parse_identifier()?;
+        let alias = self.parse_alias()?;
+        Ok(TableRef {
+            name,
+            alias,
+            subquery: None,
+        })
+    }
+
+    fn parse_joins(&mut self) -> Result<Vec<Join>, Error> {
+        let mut joins = Vec::new();
+        loop {
+            let kind = if self.consume_symbol(",") {
+                JoinKind::Cross
+            } else if self.consume_keyword("CROSS") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Cross
+            } else if self.consume_keyword("LEFT") {
+                self.consume_keyword("OUTER");
+                self.expect_keyword("JOIN")?;
+                JoinKind::Left
+            } else if self.consume_keyword("INNER") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Inner
+            } else if self.consume_keyword("JOIN") {
+                JoinKind::Inner
+            } else {
+                return Ok(joins);
+            };
+            let table = self.parse_table_ref()?;
+            let constraint = if self.consume_keyword("ON") {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            joins.push(Join { kind, table, constraint });
+        }
+    }
+
+    fn parse_order_by(&mut self) -> Result<Vec<OrderingTerm>, Error> {
+        let mut terms = Vec::new();
+        if !self.consume_keyword("ORDER") {
+            return Ok(terms);
+        }
+        self.expect_keyword("BY")?;
+        loop {
+            let expr = self.parse_expr()?;
+            let descending = if self.consume_keyword("DESC") {
+                true
+            } else {
+                self.consume_keyword("ASC");
+                false
+            };
+            terms.push(OrderingTerm { expr, descending });
+            if !self.consume_symbol(",") {
+                break;
+            }
+        }
+        Ok(terms)
+    }
+
+    // LIMIT count [OFFSET skip], or LIMIT skip, count.
+    fn parse_limit(&mut self) -> Result<(Option<Expr>, Option<Expr>), Error> {
+        if !self.consume_keyword("LIMIT") {
+            return Ok((None, None));
+        }
+        let first = self.parse_expr()?;
+        if self.consume_keyword("OFFSET") {
+            return Ok((Some(first), Some(self.parse_expr()?)));
+        }
+        if self.consume_symbol(",") {
+            return Ok((Some(self.parse_expr()?), Some(first)));
+        }
+        Ok((Some(first), None))
+    }
+
+    fn parse_alias(&mut self) -> Result<Option<String>, Error> {
+        if self.consume_keyword("AS") {
+            return self.parse_identifier().map(Some);
+        }
+        match self.peek() {
+            Some(Token::Ident(word)) if !is_reserved(word) => self.parse_identifier().map(Some),
+            Some(Token::QuotedIdent(_)) => self.parse_identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_where(&mut self) -> Result<Option<Expr>, Error> {
+        if self.consume_keyword("WHERE") {
+            return self.parse_expr().map(Some);
+        }
+        Ok(None)
+    }
+
+    fn parse_expr_list(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut exprs = vec![self.parse_expr()?];
+        while self.consume_symbol(",") {
+            exprs.push(self.parse_expr()?);
+        }
+        Ok(exprs)
+    }
+
+    pub fn parse_expr(&mut self) -> Result<Expr, Error> {
+        let expr = self.nested(Parser::parse_or)?;
+        check_height(&expr)?;
+        Ok(expr)
+    }
+
+    // Parses with parse one level deeper, failing past MAX_PARSER_DEPTH
+    // before the recursion can overflow the stack.
+    fn nested(&mut self, parse: fn(&mut Parser) -> Result<Expr, Error>) -> Result<Expr, Error> {
+        if self.depth >= MAX_PARSER_DEPTH {
+            return Err(Error::other("parser stack overflow"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_and()?;
+        while self.consume_keyword("OR") {
+            let right = self.parse_and()?;
+            left = binary(left, BinaryOp::Or, right);
+            check_height(&left)?;
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_not()?;
+        while self.consume_keyword("AND") {
+            let right = self.parse_not()?;
+            left = binary(left, BinaryOp::And, right);
+            check_height(&left)?;
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, Error> {
+        if self.consume_keyword("NOT") {
+            let expr = self.nested(Parser::parse_not)?;
+            return Ok(Expr::Unary {
+                op: UnaryOp::Not,
+                expr: Box::new(expr),
+            });
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_additive()?;
+        loop {
+            check_height(&left)?;
+            let op = match self.peek() {
+                Some(Token::Symbol("=")) | Some(Token::Symbol("==")) => BinaryOp::Eq,
+                Some(Token::Symbol("!=")) | Some(Token::Symbol("<>")) => BinaryOp::NotEq,
+                Some(Token::Symbol("<")) => BinaryOp::Lt,
+                Some(Token::Symbol("<=")) => BinaryOp::LtEq,
+                Some(Token::Symbol(">")) => BinaryOp::Gt,
+                Some(Token::Symbol(">=")) => BinaryOp::GtEq,
+                _ => {
+                    if self.consume_keyword("IS") {
+                        let negated = self.consume_keyword("NOT");
+                        self.expect_keyword("NULL")?;
+                        left = Expr::IsNull {
+                            expr: Box::new(left),
+                            negated,
+                        };
+                        continue;
+                    }
+                    if self.consume_keyword("ISNULL") {
+                        left = Expr::IsNull {
+                            expr: Box::new(left),
+                            negated: false,
+                        };
+                        continue;
+                    }
+                    if self.consume_keyword("NOTNULL") {
+                        left = Expr::IsNull {
+                            expr: Box::new(left),
+                            negated: true,
+                        };
+                        continue;
+                    }
+                    let negated = self.peek_keyword("NOT")
+                        && ["IN", "BETWEEN", "LIKE", "GLOB"]
+                            .iter()
+                            .any(|keyword| self.peek_keyword_at(1, keyword));
+                    if negated {
+                        self.pos += 1;
+                    }
+                    if self.consume_keyword("IN") {
+                        left = self.parse_in_list(left, negated)?;
+                        continue;
+                    }
+                    if self.consume_keyword("BETWEEN") {
+                        let low = self.parse_additive()?;
+                        self.expect_keyword("AND")?;
+                        let high = self.parse_additive()?;
+                        left = Expr::Between {
+                            expr: Box::new(left),
+                            low: Box::new(low),
+                            high: Box::new(high),
+                            negated,
+                        };
+                        continue;
+                    }
+                    let glob = self.peek_keyword("GLOB");
+                    if self.consume_keyword("LIKE") || self.consume_keyword("GLOB") {
+                        let pattern = self.parse_additive()?;
+                        let escape = if self.consume_keyword("ESCAPE") {
+                            Some(Box::new(self.parse_additive()?))
+                        } else {
+                            None
+                        };
+                        left = Expr::Like {
+                            expr: Box::new(left),
+                            pattern: Box::new(pattern),
+                            escape,
+                            glob,
+                            negated,
+                        };
+                        continue;
+                    }
+                    return Ok(left);
+                }
+            };
+            self.pos += 1;
+            let right = self.parse_additive()?;
+            left = binary(left, op, right);
+        }
+    }
+
+    // Parses the parenthesized list after IN.
+    fn parse_in_list(&mut self, expr: Expr, negated: bool) -> Result<Expr, Error> {
+        self.expect_symbol("(")?;
+        if self.peek_select_at(0) {
+            let select = self.parse_select()?;
+            self.expect_symbol(")")?;
+            return Ok(Expr::InSubquery {
+                expr: Box::new(expr),
+                select: Box::new(select),
+                negated,
+            });
+        }
+        let list = if self.peek_symbol(")") {
+            Vec::new()
+        } else {
+            self.parse_expr_list()?
+        };
+        self.expect_symbol(")")?;
+        Ok(Expr::InList {
+            expr: Box::new(expr),
+            list,
+            negated,
+        })
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            left = binary(left, op, right);
+            check_height(&left)?;
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_concat()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOp::Multiply,
+                Some(Token::Symbol("/")) => BinaryOp::Divide,
+                Some(Token::Symbol("%")) => BinaryOp::Remainder,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_concat()?;
+            left = binary(left, op, right);
+            check_height(&left)?;
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_unary()?;
+        while self.consume_symbol("||") {
+            let right = self.parse_unary()?;
+            left = binary(left, BinaryOp::Concat, right);
+            check_height(&left)?;
+        }
+        Ok(left)
+    }
+
+    fn parse_case(&mut self) -> Result<Expr, Error> {
+        let operand = if self.peek_keyword("WHEN") {
+            None
+        } else {
+            Some(Box::new(self.parse_expr()?))
+        };
+        let mut branches = Vec::new();
+        while self.consume_keyword("WHEN") {
+            let when = self.parse_expr()?;
+            self.expect_keyword("THEN")?;
+            branches.push((when, self.parse_expr()?));
+        }
+        if branches.is_empty() {
+            return Err(self.error());
+        }
+        let else_expr = if self.consume_keyword("ELSE") {
+            Some(Box::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+        Ok(Expr::Case {
+            operand,
+            branches,
+            else_expr,
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        if self.consume_symbol("-") {
+            return Ok(match self.nested(Parser::parse_unary)? {
+                Expr::Literal(Value::Integer(i)) => Expr::Literal(Value::Integer(-i)),
+                Expr::Literal(Value::Real(r)) => Expr::Literal(Value::Real(-r)),
+                expr => Expr::Unary {
+                    op: UnaryOp::Minus,
+                    expr: Box::new(expr),
+                },
+            });
+        }
+        if self.consume_symbol("+") {
+            let expr = self.nested(Parser::parse_unary)?;
+            return Ok(Expr::Unary {
+                op: UnaryOp::Plus,
+                expr: Box::new(expr),
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        match self.peek().cloned() {
+            Some(Token::Integer(i)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Integer(i)))
+            }
+            Some(Token::Real(r)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Real(r)))
+            }
+            Some(Token::String(s)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Text(s)))
+            }
+            Some(Token::Parameter(name)) => {
+                self.pos += 1;
+                self.parameter(name)
+            }
+            Some(Token::Symbol("(")) if self.peek_select_at(1) => {
+                self.pos += 1;
+                let select = self.parse_select()?;
+                self.expect_symbol(")")?;
+                Ok(Expr::Subquery(Box::new(select)))
+            }
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("EXISTS") => {
+                self.pos += 1;
+                self.expect_symbol("(")?;
+                let select = self.parse_select()?;
+                self.expect_symbol(")")?;
+                Ok(Expr::Exists(Box::new(select)))
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("NULL") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Null))
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("CASE") => {
+                self.pos += 1;
+                self.parse_case()
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("TRUE") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Integer(1)))
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("FALSE") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Integer(0)))
+            }
+            Some(Token::Ident(_)) | Some(Token::QuotedIdent(_)) => {
+                let name = self.parse_identifier()?;
+                if self.consume_symbol("(") {
+                    let function = self.parse_function_call(name)?;
+                    if self.peek_keyword("OVER") && matches!(self.tokens.get(self.pos + 1), Some(Token::Symbol("("))) {
+                        self.pos += 2;
+                        let window = self.parse_window_spec()?;
+                        return Ok(Expr::Window {
+                            function: Box::new(function),
+                            window: Box::new(window),
+                        });
+                    }
+                    return Ok(function);
+                }
+                if self.consume_symbol(".") {
+                    let column = self.parse_identifier()?;
+                    return Ok(Expr::Column {
+                        table: Some(name),
+                        name: column,
+                    });
+                }
+                Ok(Expr::Column { table: None, name })
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    // Parses the arguments of a call after the opening parenthesis.
+    fn parse_function_call(&mut self, name: String) -> Result<Expr, Error> {
+        let mut args = Vec::new();
+        let mut wildcard = false;
+        let distinct = self.consume_keyword("DISTINCT");
+        if !distinct && self.consume_symbol("*") {
+            wildcard = true;
+        } else if distinct || !self.peek_symbol(")") {
+            args = self.parse_expr_list()?;
+        }
+        self.expect_symbol(")")?;
+        Ok(Expr::Function {
+            name,
+            args,
+            wildcard,
+            distinct,
+        })
+    }
+
+    // The window of a window call, after OVER (.
+    fn parse_window_spec(&mut self) -> Result<WindowSpec, Error> {
+        let partition_by = if self.consume_keyword("PARTITION") {
+            self.expect_keyword("BY")?;
+            self.parse_expr_list()?
+        } else {
+            Vec::new()
+        };
+        let order_by = self.parse_order_by()?;
+        let units = if self.consume_keyword("ROWS") {
+            Some(FrameUnits::Rows)
+        } else if self.consume_keyword("RANGE") {
+            Some(FrameUnits::Range)
+        } else {
+            None
+        };
+        let frame = match units {
+            Some(units) if self.consume_keyword("BETWEEN") => {
+                let start = self.parse_frame_bound()?;
+                self.expect_keyword("AND")?;
+                let end = self.parse_frame_bound()?;
+                Some(WindowFrame { units, start, end })
+            }
+            Some(units) => Some(WindowFrame {
+                units,
+                start: self.parse_frame_bound()?,
+                end: FrameBound::CurrentRow,
+            }),
+            None => None,
+        };
+        self.expect_symbol(")")?;
+        Ok(WindowSpec {
+            partition_by,
+            order_by,
+            frame,
+        })
+    }
+
+    fn parse_frame_bound(&mut self) -> Result<FrameBound, Error> {
+        if self.consume_keyword("UNBOUNDED") {
+            if self.consume_keyword("PRECEDING") {
+                return Ok(FrameBound::UnboundedPreceding);
+            }
+            self.expect_keyword("FOLLOWING")?;
+            return Ok(FrameBound::UnboundedFollowing);
+        }
+        if self.consume_keyword("CURRENT") {
+            self.expect_keyword("ROW")?;
+            return Ok(FrameBound::CurrentRow);
+        }
+        let offset = self.parse_additive()?;
+        if self.consume_keyword("PRECEDING") {
+            return Ok(FrameBound::Preceding(offset));
+        }
+        self.expect_keyword("FOLLOWING")?;
+        Ok(FrameBound::Following(offset))
+    }
+
+    pub fn parse_identifier(&mut self) -> Result<String, Error> {
+        match self.peek().cloned() {
+            Some(Token::Ident(word)) if !is_reserved(&word) => {
+                self.pos += 1;
+                Ok(word)
+            }
+            Some(Token::QuotedIdent(word)) => {
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn peek_keyword_at(&self, offset: usize, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos + offset), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    // Whether the token at offset starts a select, with or without a WITH
+    // clause.
+    fn peek_select_at(&self, offset: usize) -> bool {
+        self.peek_keyword_at(offset, "SELECT") || self.peek_keyword_at(offset, "WITH")
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.consume_keyword(keyword) {
+            return Ok(());
+        }
+        Err(self.error())
+    }
+
+    fn peek_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    pub fn consume_symbol(&mut self, symbol: &str) -> bool {
+        if self.peek_symbol(symbol) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.consume_symbol(symbol) {
+            return Ok(());
+        }
+        Err(self.error())
+    }
+
+    fn error(&self) -> Error {
+        let message = match self.peek() {
+            Some(token) => format!("near \"{}\": syntax error", token_text(token)),
+            None => "incomplete input".to_string(),
+        };
+        Error::other(message)
+    }
+}
+
+fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
+    Expr::Binary {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    }
+}
+
+// Fails on an expression too deep for the evaluator to recurse through.
+fn check_height(expr: &Expr) -> Result<(), Error> {
+    if deeper_than(expr, MAX_EXPR_DEPTH) {
+        return Err(Error::other(format!("Expression tree is too large (maximum depth {})", MAX_EXPR_DEPTH)));
+    }
+    Ok(())
+}
+
+// Whether the tree of the expression has more than depth levels. Looks no
+// deeper than that, so it can not overflow the stack on a tree too deep.
+fn deeper_than(expr: &Expr, depth: usize) -> bool {
+    if depth == 0 {
+        return true;
+    }
+    let below = |expr: &Expr| deeper_than(expr, depth - 1);
+    match expr {
+        Expr::Literal(_) | Expr::Parameter { .. } | Expr::Column { .. } | Expr::Subquery(_) | Expr::Exists(_) => false,
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } | Expr::InSubquery { expr, .. } => below(expr),
+        Expr::Binary { left, right, .. } => below(left) || below(right),
+        Expr::InList { expr, list, .. } => below(expr) || list.iter().any(below),
+        Expr::Between { expr, low, high, .. } => below(expr) || below(low) || below(high),
+        Expr::Like {
+            expr, pattern, escape, ..
+        } => below(expr) || below(pattern) || escape.as_deref().is_some_and(below),
+        Expr::Case {
+            operand,
+            branches,
+            else_expr,
+        } => {
+            operand.as_deref().is_some_and(below)
+                || branches.iter().any(|(when, then)| below(when) || below(then))
+                || else_expr.as_deref().is_some_and(below)
+        }
+        Expr::Function { args, .. } => args.iter().any(below),
+        Expr::Window { function, window } => {
+            below(function)
+                || window.partition_by.iter().any(below)
+                || window.order_by.iter().any(|term| below(&term.expr))
+                || window.frame.as_ref().is_some_and(|frame| {
+                    [&frame.start, &frame.end].into_iter().any(|bound| match bound {
+                        FrameBound::Preceding(expr) | FrameBound::Following(expr) => below(expr),
+                        _ => false,
+                    })
+                })
+        }
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED.iter().any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+fn is_column_constraint_start(word: &str) -> bool {
+    ["CONSTRAINT", "PRIMARY", "NOT", "NULL", "UNIQUE", "CHECK", "DEFAULT", "REFERENCES"]
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Ident(word) | Token::QuotedIdent(word) | Token::Parameter(word) => word.clone(),
+        Token::Integer(i) => i.to_string(),
+        Token::Real(r) => r.to_string(),
+        Token::String(s) => format!("'{}'", s),
+        Token::Symbol(s) => s.to_string(),
+    }
+}
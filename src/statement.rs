@@ -1,3 +1,5 @@
+use crate::sql::ast::Stmt;
+use crate::sql::parser::parse;
 use crate::table::row::{Row, COLUMN_EMAIL_SIZE, COLUMN_USERNAME_SIZE};
 use std::io::{Error, ErrorKind};
 use std::str::FromStr;
@@ -7,6 +9,7 @@ pub enum StatementType {
     INSERT,
     UPDATE,
     SELECT,
+    SQL,
 }
 
 impl FromStr for StatementType {
@@ -25,11 +28,23 @@ impl FromStr for StatementType {
 pub struct Statement {
     pub(crate) statement_type: StatementType,
     pub row_to_insert: Row,
+    pub(crate) sql: Option<Stmt>,
 }
 
 impl Statement {
     pub(crate) fn prepare_statement(command: &str) -> Result<Statement, Error> {
         let chunks: Vec<&str> = command.split(' ').collect();
+        if !is_legacy_command(&chunks) {
+            return Ok(Statement {
+                statement_type: StatementType::SQL,
+                row_to_insert: Row {
+                    id: 0,
+                    username: ['a'; COLUMN_USERNAME_SIZE],
+                    email: ['a'; COLUMN_EMAIL_SIZE],
+                },
+                sql: Some(parse(command)?),
+            });
+        }
         let statement_type = StatementType::from_str(chunks[0]).expect("Wrong statement");
         let mut row_to_insert = Row {
             id: 0,
@@ -72,6 +87,17 @@ impl Statement {
         Ok(Statement {
             statement_type,
             row_to_insert,
+            sql: None,
         })
     }
 }
+
+// The original commands: "insert <id> <username> <email>", a bare "select"
+// and a bare "update". Everything else is parsed as SQL.
+fn is_legacy_command(chunks: &[&str]) -> bool {
+    match chunks {
+        ["insert", id, _, _] => id.parse::<i64>().is_ok(),
+        ["select"] | ["update"] => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,355 @@
+use crate::cursor::cursor::Cursor;
+use crate::node::node::{Node, NodeType, LEAF_NODE_MAX_CELLS};
+use crate::pager::pager::TABLE_MAX_PAGES;
+use crate::table::record::{EncodedRecord, Record};
+use crate::table::row::ROW_SIZE;
+use crate::table::table::Table;
+use std::io::Error;
+use std::ptr;
+
+// A B-tree stored in the table's pager, identified by its root page. The
+// tree of the legacy rows is the one rooted at page 0, every table and index
+// created through SQL gets its own tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BTree {
+    pub root_page_num: u32,
+}
+
+impl BTree {
+    pub fn new(root_page_num: u32) -> BTree {
+        BTree { root_page_num }
+    }
+
+    // Allocates a new empty leaf page and makes it the root of a new tree.
+    pub unsafe fn create(table: &mut Table) -> Result<BTree, Error> {
+        let page_num = table.pager.get_unused_page_num();
+        if page_num >= TABLE_MAX_PAGES {
+            return Err(Error::other("Table is full"));
+        }
+        let root_node = table.pager.get_page(page_num)?;
+        Node::initialize_leaf_node(root_node);
+        Node::set_node_root(root_node, true);
+        Ok(BTree::new(page_num))
+    }
+
+    pub unsafe fn get(&self, table: &mut Table, key: u32) -> Option<Record> {
+        let mut cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        if cursor.cursor_key() != Some(key) {
+            return None;
+        }
+        Some(read_record(cursor.cursor_value()))
+    }
+
+    pub unsafe fn contains(&self, table: &mut Table, key: u32) -> bool {
+        let mut cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        cursor.cursor_key() == Some(key)
+    }
+
+    pub unsafe fn insert(&self, table: &mut Table, key: u32, record: &Record) -> Result<(), Error> {
+        let bytes = record.to_bytes()?;
+        self.ensure_pages_for_split(table)?;
+        // Splitting the root uses table.root_page_num, point it at this tree
+        // for the duration of the insert.
+        let saved_root_page_num = table.root_page_num;
+        table.root_page_num = self.root_page_num;
+        let mut cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        let result = if cursor.cursor_key() == Some(key) {
+            Err(Error::other("Error:Duplicate key"))
+        } else {
+            Node::leaf_node_insert(cursor, key, EncodedRecord(bytes)).map(|_| ())
+        };
+        table.root_page_num = saved_root_page_num;
+        result
+    }
+
+    // Overwrites the record stored under an existing key.
+    pub unsafe fn update(&self, table: &mut Table, key: u32, record: &Record) -> Result<(), Error> {
+        let bytes = record.to_bytes()?;
+        let mut cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        if cursor.cursor_key() != Some(key) {
+            return Err(Error::other("Key not found"));
+        }
+        ptr::copy(bytes.as_ptr(), cursor.cursor_value(), ROW_SIZE);
+        Ok(())
+    }
+
+    // Removes a key, returning whether it was present.
+    pub unsafe fn delete(&self, table: &mut Table, key: u32) -> bool {
+        // Unlinking an emptied leaf walks the tree from table.root_page_num.
+        let saved_root_page_num = table.root_page_num;
+        table.root_page_num = self.root_page_num;
+        let mut cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        let found = cursor.cursor_key() == Some(key);
+        if found {
+            Node::leaf_node_delete(cursor);
+        }
+        table.root_page_num = saved_root_page_num;
+        found
+    }
+
+    // The leaf holding the largest keys, reached by following right children.
+    pub unsafe fn rightmost_leaf(&self, table: &mut Table) -> u32 {
+        let mut page_num = self.root_page_num;
+        loop {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            match Node::get_node_type(node_ptr) {
+                NodeType::INTERNAL => page_num = Node::get_internal_node_right_child(node_ptr),
+                NodeType::LEAF => return page_num,
+            }
+        }
+    }
+
+    // Number of keys, summed from the cell counts of the leaves without
+    // reading any record.
+    pub unsafe fn count(&self, table: &mut Table) -> usize {
+        let mut page_num = self.root_page_num;
+        loop {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            match Node::get_node_type(node_ptr) {
+                NodeType::INTERNAL => page_num = Node::get_internal_node_child(node_ptr, 0),
+                NodeType::LEAF => break,
+            }
+        }
+        let mut count = 0;
+        loop {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            count += Node::get_leaf_node_num_cells(node_ptr) as usize;
+            page_num = Node::get_leaf_node_next_leaf(node_ptr);
+            if page_num == 0 {
+                return count;
+            }
+        }
+    }
+
+    // Every page of the tree, visited from the root like Node::print_tree.
+    pub unsafe fn pages(&self, table: &mut Table) -> Vec<u32> {
+        let mut pages = Vec::new();
+        let mut pending = vec![self.root_page_num];
+        while let Some(page_num) = pending.pop() {
+            pages.push(page_num);
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            if Node::get_node_type(node_ptr) == NodeType::INTERNAL {
+                for i in 0..Node::get_internal_node_num_cells(node_ptr) {
+                    pending.push(Node::get_internal_node_child(node_ptr, i));
+                }
+                pending.push(Node::get_internal_node_right_child(node_ptr));
+            }
+        }
+        pages
+    }
+
+    // Returns every page of the tree to the free list.
+    pub unsafe fn destroy(&self, table: &mut Table) -> Result<(), Error> {
+        for page_num in self.pages(table) {
+            table.pager.free_page(page_num)?;
+        }
+        Ok(())
+    }
+
+    pub unsafe fn scan(&self, table: &mut Table) -> TreeScan {
+        self.scan_from(table, 0)
+    }
+
+    // Starts a scan at the largest key, going down.
+    pub unsafe fn scan_reverse(&self, table: &mut Table) -> ReverseScan {
+        let mut scan = ReverseScan {
+            parents: Vec::new(),
+            page_num: 0,
+            cells_left: 0,
+        };
+        scan.descend(table, self.root_page_num);
+        scan
+    }
+
+    // Starts a scan at the first key greater than or equal to key.
+    pub unsafe fn scan_from(&self, table: &mut Table, key: u32) -> TreeScan {
+        let cursor = Cursor::find_key_in_tree(table, self.root_page_num, key);
+        TreeScan {
+            page_num: cursor.page_num,
+            cell_num: cursor.cell_num,
+            done: false,
+        }
+    }
+
+    pub unsafe fn depth(&self, table: &mut Table) -> u32 {
+        let mut depth = 1;
+        let mut page_num = self.root_page_num;
+        loop {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            match Node::get_node_type(node_ptr) {
+                NodeType::INTERNAL => {
+                    page_num = Node::get_internal_node_right_child(node_ptr);
+                    depth += 1;
+                }
+                NodeType::LEAF => return depth,
+            }
+        }
+    }
+
+    // Whether an insert could fail for want of pages: a split can allocate a
+    // new page on every level plus a new root.
+    pub unsafe fn is_full(&self, table: &mut Table) -> Result<bool, Error> {
+        let needed = self.depth(table) + 1;
+        Ok(needed > table.pager.available_pages()?)
+    }
+
+    unsafe fn ensure_pages_for_split(&self, table: &mut Table) -> Result<(), Error> {
+        if self.is_full(table)? {
+            return Err(Error::other("Table is full"));
+        }
+        Ok(())
+    }
+}
+
+// Insert position kept across the rows of one statement. Keys larger than
+// every key in the tree go straight into the rightmost leaf while it has
+// room, so a batch of ascending rowids does not search from the root for
+// each row. No internal node stores the largest key of the rightmost leaf,
+// so appending there never needs to touch the parents.
+pub struct InsertCursor {
+    tree: BTree,
+    leaf: Option<u32>,
+}
+
+impl InsertCursor {
+    pub fn new(tree: BTree) -> InsertCursor {
+        InsertCursor { tree, leaf: None }
+    }
+
+    pub unsafe fn max_key(&mut self, table: &mut Table) -> Option<u32> {
+        let (node_ptr, num_cells) = self.rightmost_leaf(table);
+        if num_cells == 0 {
+            return None;
+        }
+        Some(Node::get_leaf_node_key(node_ptr, num_cells - 1))
+    }
+
+    pub unsafe fn get(&mut self, table: &mut Table, key: u32) -> Option<Record> {
+        match self.max_key(table) {
+            Some(max_key) if key <= max_key => self.tree.get(table, key),
+            _ => None,
+        }
+    }
+
+    pub unsafe fn insert(&mut self, table: &mut Table, key: u32, record: &Record) -> Result<(), Error> {
+        let (node_ptr, num_cells) = self.rightmost_leaf(table);
+        let appends = num_cells < LEAF_NODE_MAX_CELLS
+            && (num_cells == 0 || key > Node::get_leaf_node_key(node_ptr, num_cells - 1));
+        if !appends {
+            // Splits may move the rightmost leaf, find it again next time.
+            self.leaf = None;
+            return self.tree.insert(table, key, record);
+        }
+        let cursor = Cursor {
+            page_num: self.leaf.unwrap(),
+            cell_num: num_cells,
+            end_of_table: true,
+            table,
+        };
+        Node::leaf_node_insert(cursor, key, EncodedRecord(record.to_bytes()?)).map(|_| ())
+    }
+
+    // The cached rightmost leaf and its cell count, searched from the root
+    // when there is none or the cached page stopped being the last leaf:
+    // a split gave it a next leaf, a root split made it internal, or
+    // deleting its last row unlinked it from the tree.
+    unsafe fn rightmost_leaf(&mut self, table: &mut Table) -> (*mut u8, u32) {
+        if let Some(page_num) = self.leaf {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            if Node::get_node_type(node_ptr) == NodeType::LEAF && Node::get_leaf_node_next_leaf(node_ptr) == 0 {
+                let num_cells = Node::get_leaf_node_num_cells(node_ptr);
+                if num_cells > 0 || page_num == self.tree.root_page_num {
+                    return (node_ptr, num_cells);
+                }
+            }
+        }
+        let page_num = self.tree.rightmost_leaf(table);
+        self.leaf = Some(page_num);
+        let node_ptr = table.pager.get_page(page_num).unwrap();
+        (node_ptr, Node::get_leaf_node_num_cells(node_ptr))
+    }
+}
+
+// Position of a scan over the leaves of a tree. Unlike Cursor it does not
+// borrow the table, so the caller can use the table between steps.
+pub struct TreeScan {
+    page_num: u32,
+    cell_num: u32,
+    done: bool,
+}
+
+impl TreeScan {
+    pub unsafe fn next(&mut self, table: &mut Table) -> Option<(u32, Record)> {
+        while !self.done {
+            let node_ptr = table.pager.get_page(self.page_num).unwrap();
+            if self.cell_num < Node::get_leaf_node_num_cells(node_ptr) {
+                let key = Node::get_leaf_node_key(node_ptr, self.cell_num);
+                let record = read_record(Node::get_leaf_node_value_ptr(node_ptr, self.cell_num));
+                self.cell_num += 1;
+                return Some((key, record));
+            }
+            let next_leaf = Node::get_leaf_node_next_leaf(node_ptr);
+            if next_leaf == 0 {
+                self.done = true;
+            } else {
+                self.page_num = next_leaf;
+                self.cell_num = 0;
+            }
+        }
+        None
+    }
+}
+
+// Position of a scan over the leaves of a tree from the largest key down.
+// Leaves only link to the next one, so the internal nodes above the current
+// leaf are kept with the number of their children left of it still to visit.
+pub struct ReverseScan {
+    parents: Vec<(u32, u32)>,
+    page_num: u32,
+    // The cells of the leaf not returned yet.
+    cells_left: u32,
+}
+
+impl ReverseScan {
+    pub unsafe fn next(&mut self, table: &mut Table) -> Option<(u32, Record)> {
+        loop {
+            if self.cells_left > 0 {
+                self.cells_left -= 1;
+                let node_ptr = table.pager.get_page(self.page_num).unwrap();
+                let key = Node::get_leaf_node_key(node_ptr, self.cells_left);
+                let record = read_record(Node::get_leaf_node_value_ptr(node_ptr, self.cells_left));
+                return Some((key, record));
+            }
+            let (page_num, children_left) = self.parents.pop()?;
+            if children_left > 0 {
+                self.parents.push((page_num, children_left - 1));
+                let node_ptr = table.pager.get_page(page_num).unwrap();
+                self.descend(table, Node::get_internal_node_child(node_ptr, children_left - 1));
+            }
+        }
+    }
+
+    // Goes down the right children from page to a leaf.
+    unsafe fn descend(&mut self, table: &mut Table, mut page_num: u32) {
+        loop {
+            let node_ptr = table.pager.get_page(page_num).unwrap();
+            match Node::get_node_type(node_ptr) {
+                NodeType::INTERNAL => {
+                    self.parents.push((page_num, Node::get_internal_node_num_cells(node_ptr)));
+                    page_num = Node::get_internal_node_right_child(node_ptr);
+                }
+                NodeType::LEAF => {
+                    self.page_num = page_num;
+                    self.cells_left = Node::get_leaf_node_num_cells(node_ptr);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+unsafe fn read_record(value_ptr: *const u8) -> Record {
+    let mut bytes = [0u8; ROW_SIZE];
+    ptr::copy(value_ptr, bytes.as_mut_ptr(), ROW_SIZE);
+    Record::from_bytes(&bytes)
+}
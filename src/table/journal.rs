@@ -0,0 +1,70 @@
+use crate::pager::pager::TABLE_MAX_PAGES;
+use crate::schema::catalog::Catalog;
+use crate::table::table::{Table, PAGE_SIZE};
+use std::io::Error;
+use std::ptr;
+
+// The state of the database before a statement, kept while it runs so that
+// a statement failing part way leaves no change behind. The pages loaded in
+// memory are copied, the others still hold their old content in the file as
+// nothing is written to it before the database is closed. The pages a
+// failed statement added past the end of the file are dropped again, so
+// that they are handed out as new pages, and the free list, whose head is
+// on page 1, is the one from before the statement.
+pub struct Journal {
+    pages: Vec<Option<Vec<u8>>>,
+    num_pages: u32,
+    free_list: bool,
+    num_rows: u32,
+    catalog: Catalog,
+}
+
+impl Journal {
+    pub unsafe fn begin(table: &Table) -> Journal {
+        let pages = table
+            .pager
+            .pages
+            .iter()
+            .map(|page| page.map(|page_ptr| std::slice::from_raw_parts(page_ptr, PAGE_SIZE).to_vec()))
+            .collect();
+        Journal {
+            pages,
+            num_pages: table.pager.num_pages,
+            free_list: table.pager.free_list,
+            num_rows: table.num_rows,
+            catalog: table.catalog.clone(),
+        }
+    }
+
+    pub unsafe fn rollback(self, table: &mut Table) -> Result<(), Error> {
+        for page_num in 0..TABLE_MAX_PAGES {
+            match &self.pages[page_num as usize] {
+                Some(bytes) => ptr::copy(bytes.as_ptr(), table.pager.get_page(page_num)?, PAGE_SIZE),
+                None if page_num >= self.num_pages => table.pager.uncache_page(page_num),
+                None => table.pager.reload_page(page_num)?,
+            }
+        }
+        table.pager.num_pages = self.num_pages;
+        table.pager.free_list = self.free_list;
+        table.num_rows = self.num_rows;
+        table.catalog = self.catalog;
+        Ok(())
+    }
+}
+
+// Runs f as a single statement: when it fails, every change it made is
+// undone. The statements it runs in turn, like those of triggers, are part
+// of it.
+pub unsafe fn atomically<T>(table: &mut Table, f: impl FnOnce(&mut Table) -> Result<T, Error>) -> Result<T, Error> {
+    if table.in_statement {
+        return f(table);
+    }
+    let journal = Journal::begin(table);
+    table.in_statement = true;
+    let result = f(table);
+    table.in_statement = false;
+    if result.is_err() {
+        journal.rollback(table)?;
+    }
+    result
+}
@@ -1,4 +1,9 @@
 use std::mem;
 
+pub(crate) mod btree;
+pub(crate) mod journal;
+pub(crate) mod record;
 pub mod row;
 pub mod table;
+pub(crate) mod temp_tree;
+pub mod value;
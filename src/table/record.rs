@@ -0,0 +1,114 @@
+use crate::node::node::LeafValue;
+use crate::table::row::ROW_SIZE;
+use crate::table::value::Value;
+use std::io::Error;
+use std::ptr;
+
+const TAG_NULL: u8 = 0;
+const TAG_INTEGER: u8 = 1;
+const TAG_REAL: u8 = 2;
+const TAG_TEXT: u8 = 3;
+
+// A row of a table created with CREATE TABLE. Records are stored in the same
+// fixed size leaf cells as the legacy rows, so an encoded record must fit in
+// ROW_SIZE bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub values: Vec<Value>,
+}
+
+impl Record {
+    pub fn new(values: Vec<Value>) -> Record {
+        Record { values }
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; ROW_SIZE], Error> {
+        let encoded = encode_values(&self.values);
+        if encoded.len() > ROW_SIZE {
+            return Err(Error::other("Row is too large."));
+        }
+        let mut bytes = [0u8; ROW_SIZE];
+        bytes[..encoded.len()].copy_from_slice(&encoded);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(source: &[u8]) -> Record {
+        let mut offset = 0;
+        Record {
+            values: decode_values(source, &mut offset),
+        }
+    }
+}
+
+pub(crate) struct EncodedRecord(pub [u8; ROW_SIZE]);
+
+impl LeafValue for EncodedRecord {
+    fn serialize_into(&self, destination_ptr: *mut u8) {
+        unsafe { ptr::copy(self.0.as_ptr(), destination_ptr, ROW_SIZE) }
+    }
+}
+
+// Encodes values as a count followed by tagged values. Text lengths are
+// stored in two bytes.
+pub fn encode_values(values: &[Value]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(values.len() as u16).to_be_bytes());
+    for value in values {
+        encode_value(value, &mut bytes);
+    }
+    bytes
+}
+
+pub fn encode_value(value: &Value, bytes: &mut Vec<u8>) {
+    match value {
+        Value::Null => bytes.push(TAG_NULL),
+        Value::Integer(i) => {
+            bytes.push(TAG_INTEGER);
+            bytes.extend_from_slice(&i.to_be_bytes());
+        }
+        Value::Real(r) => {
+            bytes.push(TAG_REAL);
+            bytes.extend_from_slice(&r.to_be_bytes());
+        }
+        Value::Text(s) => {
+            bytes.push(TAG_TEXT);
+            bytes.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(s.as_bytes());
+        }
+    }
+}
+
+pub fn decode_values(source: &[u8], offset: &mut usize) -> Vec<Value> {
+    let count = read_u16(source, offset) as usize;
+    (0..count).map(|_| decode_value(source, offset)).collect()
+}
+
+fn decode_value(source: &[u8], offset: &mut usize) -> Value {
+    let tag = source[*offset];
+    *offset += 1;
+    match tag {
+        TAG_INTEGER => {
+            let bytes: [u8; 8] = source[*offset..*offset + 8].try_into().unwrap();
+            *offset += 8;
+            Value::Integer(i64::from_be_bytes(bytes))
+        }
+        TAG_REAL => {
+            let bytes: [u8; 8] = source[*offset..*offset + 8].try_into().unwrap();
+            *offset += 8;
+            Value::Real(f64::from_be_bytes(bytes))
+        }
+        TAG_TEXT => {
+            let len = read_u16(source, offset) as usize;
+            let text = String::from_utf8_lossy(&source[*offset..*offset + len]).into_owned();
+            *offset += len;
+            Value::Text(text)
+        }
+        _ => Value::Null,
+    }
+}
+
+fn read_u16(source: &[u8], offset: &mut usize) -> u16 {
+    let bytes: [u8; 2] = source[*offset..*offset + 2].try_into().unwrap();
+    *offset += 2;
+    u16::from_be_bytes(bytes)
+}
@@ -1,3 +1,4 @@
+use crate::table::value::Value;
 use std::io::Write;
 use std::ptr;
 
@@ -85,6 +86,32 @@ impl Row {
             email,
         }
     }
+    // The row as the values of its id, username and email columns.
+    pub fn to_values(&self) -> Vec<Value> {
+        vec![
+            Value::Integer(self.id as i64),
+            Value::Text(remove_default_chars(&self.username).into_iter().collect()),
+            Value::Text(remove_default_chars(&self.email).into_iter().collect()),
+        ]
+    }
+
+    pub fn from_values(values: &[Value]) -> Row {
+        let mut username = ['\0'; COLUMN_USERNAME_SIZE];
+        let mut email = ['\0'; COLUMN_EMAIL_SIZE];
+        let text = |i: usize| values.get(i).map(Value::to_text).unwrap_or_default();
+        for (i, c) in text(1).chars().take(COLUMN_USERNAME_SIZE).enumerate() {
+            username[i] = c;
+        }
+        for (i, c) in text(2).chars().take(COLUMN_EMAIL_SIZE).enumerate() {
+            email[i] = c;
+        }
+        let id = match values.first() {
+            Some(Value::Integer(id)) => *id as u32,
+            _ => 0,
+        };
+        Row { id, username, email }
+    }
+
     pub fn print_row(row: Row, writer: &mut impl Write) -> std::io::Result<()> {
         writeln!(
             writer,
@@ -1,58 +1,152 @@
+use crate::executor::analyze::load_statistics;
+use crate::executor::cte::CommonTable;
+use crate::executor::function::Functions;
+use crate::executor::pragma::Pragmas;
 use crate::node::node::Node;
 use crate::pager::pager::{Pager, TABLE_MAX_PAGES};
+use crate::schema::catalog::{Catalog, SCHEMA_PAGE_NUM};
 use crate::table::row::ROW_SIZE;
+use crate::table::value::Value;
 use libm::ceil;
+use std::io::Error;
 use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const PAGE_SIZE: usize = 4096;
 
 pub const ROWS_PER_PAGE: u32 = (PAGE_SIZE as usize / ROW_SIZE) as u32;
 pub const TABLE_MAX_ROWS: u32 = ROWS_PER_PAGE * TABLE_MAX_PAGES;
 
+// The id of the next table opened.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
 pub struct Table {
+    // Tells apart the tables open in the process, statements keep what they
+    // compiled for one of them.
+    pub(crate) id: u64,
     pub num_rows: u32,
     pub pager: Pager,
     pub root_page_num: u32,
+    pub(crate) catalog: Catalog,
+    pub(crate) pragmas: Pragmas,
+    pub(crate) functions: Functions,
+    // Common table expressions of the statements being run, innermost last.
+    pub(crate) common_tables: Vec<CommonTable>,
+    // Triggers whose statements are running. A trigger does not fire again
+    // until they are done.
+    pub(crate) running_triggers: Vec<String>,
+    // How many views are being expanded, each read by the one before.
+    pub(crate) view_depth: usize,
+    // How many selects are running, each nested in the one before.
+    pub(crate) select_depth: usize,
+    // Set while a statement runs with a journal to undo it, see journal.rs.
+    pub(crate) in_statement: bool,
 }
 
 impl Table {
     pub unsafe fn db_open(filename: &str) -> Table {
-        let mut pager = Pager::open(filename).unwrap();
+        Table::open(filename).unwrap()
+    }
+
+    // Opens the database file, creating it when it does not exist.
+    pub(crate) unsafe fn open(filename: &str) -> Result<Table, Error> {
+        let mut pager = Pager::open(filename)?;
         let num_rows = (pager.file_length) / ROW_SIZE as u32;
         if (pager.num_pages == 0) {
             // New database file. Initialize page 0 as leaf node.
-            let root_node = pager.get_page(0).unwrap();
+            let root_node = pager.get_page(0)?;
             Node::initialize_leaf_node(root_node);
             Node::set_node_root(root_node, true);
         }
-        Table {
+        if pager.num_pages <= SCHEMA_PAGE_NUM {
+            Catalog::initialize(&mut pager)?;
+        }
+        let catalog = Catalog::load(&mut pager)?;
+        let mut table = Table {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             num_rows,
             pager,
             root_page_num: 0,
-        }
+            catalog,
+            pragmas: Pragmas::default(),
+            functions: Functions::default(),
+            common_tables: Vec::new(),
+            running_triggers: Vec::new(),
+            view_depth: 0,
+            select_depth: 0,
+            in_statement: false,
+        };
+        // Statistics only guide the planner, unreadable ones are left out.
+        let _ = load_statistics(&mut table);
+        Ok(table)
+    }
+
+    // A table without a schema page whose trees only live in memory, for
+    // rows materialized while a statement runs.
+    pub(crate) fn open_scratch(filename: &str) -> Result<Table, Error> {
+        Ok(Table {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            num_rows: 0,
+            pager: Pager::open(filename)?,
+            root_page_num: 0,
+            catalog: Catalog::default(),
+            pragmas: Pragmas::default(),
+            functions: Functions::default(),
+            common_tables: Vec::new(),
+            running_triggers: Vec::new(),
+            view_depth: 0,
+            select_depth: 0,
+            in_statement: false,
+        })
+    }
+
+    // Registers a scalar function callable from SQL by name. num_args is the
+    // number of arguments calls must pass, None accepts any number. A
+    // function registered under the name of another one, built-in or not,
+    // replaces it.
+    pub fn create_function(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        self.functions.register_scalar(name, num_args, function);
+    }
+
+    // Registers an aggregate function. Every group starts from the state
+    // init returns, step is called with the arguments of each row of the
+    // group and finalize turns the state into the result.
+    pub fn create_aggregate<S: 'static>(
+        &mut self,
+        name: &str,
+        num_args: Option<usize>,
+        init: impl Fn() -> S + 'static,
+        step: impl Fn(&mut S, &[Value]) -> Result<(), Error> + 'static,
+        finalize: impl Fn(S) -> Result<Value, Error> + 'static,
+    ) {
+        self.functions.register_aggregate(name, num_args, init, step, finalize);
     }
 
     pub unsafe fn db_close(mut self) {
-        self.flush_pages();
+        let _ = self.flush_pages();
         self.free_pages();
         drop(&self.pager);
         drop(self);
     }
 
-    unsafe fn flush_pages(&mut self) {
+    // Writes the cached pages back to the file.
+    pub(crate) unsafe fn flush_pages(&mut self) -> Result<(), Error> {
         for i in 0..self.pager.num_pages {
-            self.pager.flush_page(i as usize);
-        }
-        let extra_rows = (self.num_rows % ROWS_PER_PAGE);
-        if (extra_rows == 0) {
-            return;
+            self.pager.flush_page(i as usize)?;
         }
+        Ok(())
     }
 
-    pub fn free_pages(&mut self) {
-        for i in 0..self.pager.pages.len() {
-            drop(self.pager.pages[i]);
-            self.pager.pages[i] = None;
+    // Releases the cached pages, which get_page allocated as vectors of
+    // PAGE_SIZE bytes. Changes not flushed are lost.
+    pub(crate) unsafe fn free_pages(&mut self) {
+        for page_num in 0..TABLE_MAX_PAGES {
+            self.pager.uncache_page(page_num);
         }
     }
     //row_num starts from 0
@@ -0,0 +1,98 @@
+use crate::pager::temp::temp_path;
+use crate::table::btree::BTree;
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::table::value::Value;
+use std::fs;
+use std::io::Error;
+use std::ops::Range;
+use std::path::PathBuf;
+
+// Largest number of scratch tables a temp tree spreads its rows over. Their
+// pages stay in memory, this bounds what a recursion that never ends takes
+// before it fails with "Table is full".
+const MAX_SCRATCH_TABLES: usize = 100;
+
+// Rows materialized while a statement runs, like the rows of a common table
+// expression. They are stored in B-trees of scratch tables of their own, so
+// they take no pages from the database. Rows are keyed by the order they
+// were added in, starting at 1. A scratch table has the page limit of any
+// table, once its tree is full the next rows go to the tree of a new one.
+// The scratch files are removed on drop.
+#[derive(Default)]
+pub struct TempTree {
+    // In the order they were filled.
+    scratch: Vec<Scratch>,
+    len: u32,
+}
+
+struct Scratch {
+    table: Table,
+    tree: BTree,
+    path: PathBuf,
+    // Key of the first row of the tree.
+    first_key: u32,
+}
+
+impl TempTree {
+    pub fn num_rows(&self) -> u32 {
+        self.len
+    }
+
+    // Adds a row after the others.
+    pub unsafe fn push(&mut self, values: Vec<Value>) -> Result<(), Error> {
+        let key = self.len + 1;
+        let full = match self.scratch.last_mut() {
+            Some(last) => last.tree.is_full(&mut last.table)?,
+            None => true,
+        };
+        if full && self.scratch.len() < MAX_SCRATCH_TABLES {
+            self.scratch.push(Scratch::new(key)?);
+        }
+        let last = self.scratch.last_mut().unwrap();
+        last.tree.insert(&mut last.table, key, &Record::new(values))?;
+        self.len = key;
+        Ok(())
+    }
+
+    // The rows whose keys are in range, in key order.
+    pub unsafe fn rows(&mut self, keys: Range<u32>) -> Vec<Vec<Value>> {
+        let mut rows = Vec::new();
+        // The range starts in the last tree whose first key is not after it.
+        let first = self.scratch.partition_point(|scratch| scratch.first_key <= keys.start).max(1) - 1;
+        for scratch in self.scratch.iter_mut().skip(first) {
+            if scratch.first_key >= keys.end {
+                break;
+            }
+            let mut scan = scratch.tree.scan_from(&mut scratch.table, keys.start);
+            while let Some((key, record)) = scan.next(&mut scratch.table) {
+                if key >= keys.end {
+                    break;
+                }
+                rows.push(record.values);
+            }
+        }
+        rows
+    }
+}
+
+impl Scratch {
+    unsafe fn new(first_key: u32) -> Result<Scratch, Error> {
+        let path = temp_path();
+        let mut table = Table::open_scratch(path.to_str().expect("temp path is not valid UTF-8"))?;
+        let tree = BTree::create(&mut table)?;
+        Ok(Scratch {
+            table,
+            tree,
+            path,
+            first_key,
+        })
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        unsafe { self.table.free_pages() };
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,239 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// A single SQL value as stored in a record or produced by an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            Value::Real(r) => Some(*r as i64),
+            Value::Text(s) => parse_number(s).and_then(|v| v.as_i64()),
+            Value::Null => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Real(r) => Some(*r),
+            Value::Text(s) => parse_number(s).and_then(|v| v.as_f64()),
+            Value::Null => None,
+        }
+    }
+
+    // SQL truth value of the value, None for NULL. Text is true when it
+    // starts with a non zero number, as in sqlite.
+    pub fn truthy(&self) -> Option<bool> {
+        match self {
+            Value::Null => None,
+            Value::Integer(i) => Some(*i != 0),
+            Value::Real(r) => Some(*r != 0.0),
+            Value::Text(s) => Some(leading_number(s) != 0.0),
+        }
+    }
+
+    // Numeric value used by arithmetic. Text is read up to the first
+    // character that is not part of a number, as in sqlite.
+    pub fn to_numeric(&self) -> Value {
+        match self {
+            Value::Text(s) => parse_number(s).unwrap_or_else(|| {
+                let prefix = numeric_prefix(s);
+                match (prefix.parse::<i64>(), prefix.parse::<f64>()) {
+                    (Ok(i), _) => Value::Integer(i),
+                    (_, Ok(r)) => Value::Real(r),
+                    _ => Value::Integer(0),
+                }
+            }),
+            value => value.clone(),
+        }
+    }
+
+    // Text of the value used by string operators, the empty string for NULL.
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Text(s) => s.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    pub fn from_bool(value: bool) -> Value {
+        Value::Integer(value as i64)
+    }
+
+    // Total ordering used by comparisons, ORDER BY and indexes:
+    // NULL < numbers < text.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Text(_), _) => Ordering::Greater,
+            (_, Value::Text(_)) => Ordering::Less,
+            (a, b) => {
+                let a = a.as_f64().unwrap_or(0.0);
+                let b = b.as_f64().unwrap_or(0.0);
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            }
+        }
+    }
+
+    // Integral reals are stored as integers so that 1 and 1.0 hash and
+    // compare the same way.
+    pub fn normalized(self) -> Value {
+        match self {
+            Value::Real(r) if r.fract() == 0.0 && r.abs() < 9.0e15 => Value::Integer(r as i64),
+            other => other,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Integer(_) => "integer",
+            Value::Real(_) => "real",
+            Value::Text(_) => "text",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Real(r) => {
+                if r.fract() == 0.0 && r.is_finite() {
+                    write!(f, "{:.1}", r)
+                } else {
+                    write!(f, "{}", r)
+                }
+            }
+            Value::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+// Column type affinity derived from the declared type name, following the
+// sqlite rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Affinity {
+    Integer,
+    Real,
+    Text,
+    Numeric,
+    Blob,
+}
+
+impl Affinity {
+    pub fn from_type_name(type_name: Option<&str>) -> Affinity {
+        let type_name = match type_name {
+            Some(name) => name.to_uppercase(),
+            None => return Affinity::Blob,
+        };
+        if type_name.contains("INT") {
+            Affinity::Integer
+        } else if type_name.contains("CHAR") || type_name.contains("CLOB") || type_name.contains("TEXT") {
+            Affinity::Text
+        } else if type_name.contains("BLOB") || type_name.is_empty() {
+            Affinity::Blob
+        } else if type_name.contains("REAL") || type_name.contains("FLOA") || type_name.contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    pub fn apply(&self, value: Value) -> Value {
+        match (self, value) {
+            (_, Value::Null) => Value::Null,
+            (Affinity::Text, Value::Integer(i)) => Value::Text(i.to_string()),
+            (Affinity::Text, Value::Real(r)) => Value::Text(Value::Real(r).to_string()),
+            (Affinity::Integer | Affinity::Numeric, Value::Text(s)) => match parse_number(&s) {
+                Some(number) => number.normalized(),
+                None => Value::Text(s),
+            },
+            (Affinity::Integer | Affinity::Numeric, value @ Value::Real(_)) => value.normalized(),
+            (Affinity::Real, Value::Text(s)) => match parse_number(&s).and_then(|v| v.as_f64()) {
+                Some(number) => Value::Real(number),
+                None => Value::Text(s),
+            },
+            (Affinity::Real, Value::Integer(i)) => Value::Real(i as f64),
+            (_, value) => value,
+        }
+    }
+}
+
+// Parses a complete numeric literal, returning None if the text is not a number.
+pub fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(i) = text.parse::<i64>() {
+        return Some(Value::Integer(i));
+    }
+    if text.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+        if let Ok(r) = text.parse::<f64>() {
+            return Some(Value::Real(r));
+        }
+    }
+    None
+}
+
+// Numeric value of the longest numeric prefix of the text, 0 if there is none.
+fn leading_number(text: &str) -> f64 {
+    numeric_prefix(text).parse::<f64>().unwrap_or(0.0)
+}
+
+fn numeric_prefix(text: &str) -> &str {
+    let text = text.trim_start();
+    let mut end = 0;
+    for (i, _) in text.char_indices().skip(1) {
+        if text[..i].parse::<f64>().is_ok() {
+            end = i;
+        }
+    }
+    if text.parse::<f64>().is_ok() {
+        end = text.len();
+    }
+    &text[..end]
+}
@@ -0,0 +1,425 @@
+use crate::executor::aggregate::is_aggregate_query;
+use crate::executor::cte::find_common_table;
+use crate::executor::expr::{comparison_affinity, is_rowid_name, ColumnBinding, Scope};
+use crate::executor::planner::{choose_access, conjuncts, Access, PlanTable};
+use crate::executor::query::{column_name, table_bindings};
+use crate::schema::definition::TableSchema;
+use crate::sql::ast::{BinaryOp, ConflictResolution, Delete, Expr, Insert, InsertSource, Select, SelectItem, Stmt, Update};
+use crate::table::row::Row;
+use crate::table::table::Table;
+use crate::table::value::{Affinity, Value};
+use crate::vm::program::{Opcode, Program, JUMP_IF_NULL, P4};
+
+// Compiles a statement into a program for the virtual machine. Queries
+// reading a single table without grouping, sorting or limits, INSERTs of a
+// VALUES list, and UPDATEs and DELETEs without RETURNING, of a table without
+// triggers, are compiled into instructions. Anything else, or using
+// subqueries, compiles to a single Execute instruction running it the way it
+// ran before there was a machine.
+pub unsafe fn compile(table: &mut Table, stmt: Stmt, sql: &str) -> Program {
+    let program = match &stmt {
+        Stmt::Select(select) => compile_select(table, select),
+        Stmt::Insert(insert) => compile_insert(table, insert),
+        Stmt::Update(update) => compile_update(table, update),
+        Stmt::Delete(delete) => compile_delete(table, delete),
+        _ => None,
+    };
+    program.unwrap_or_else(|| {
+        let mut program = Program::new(None);
+        program.emit(Opcode::Execute, 0, 0, 0, P4::Stmt(Box::new(stmt), sql.to_string()));
+        program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+        program
+    })
+}
+
+// The legacy "select" command: every row of the legacy table.
+pub fn compile_legacy_select(table: &Table) -> Program {
+    let mut program = Program::new(Some(vec!["id".to_string(), "username".to_string(), "email".to_string()]));
+    let cursor = program.cursor();
+    let row = program.registers(3);
+    program.emit(Opcode::OpenRead, cursor, table.root_page_num as i64, 0, P4::Legacy);
+    let rewind = program.emit(Opcode::Rewind, cursor, 0, 0, P4::None);
+    let body = program.here();
+    for i in 0..3 {
+        program.emit(Opcode::Column, cursor, i, row + i, P4::None);
+    }
+    program.emit(Opcode::ResultRow, row, 3, 0, P4::None);
+    program.emit(Opcode::Next, cursor, body, 0, P4::None);
+    program.jump_here(rewind);
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    program
+}
+
+// The legacy "insert <id> <username> <email>" command.
+pub fn compile_legacy_insert(table: &Table, row: &Row) -> Program {
+    let mut program = Program::new(None);
+    let cursor = program.cursor();
+    let values = program.registers(3);
+    program.emit(Opcode::OpenWrite, cursor, table.root_page_num as i64, 0, P4::Legacy);
+    for (i, value) in row.to_values().into_iter().enumerate() {
+        load_literal(&mut program, value, values + i as i64);
+    }
+    program.emit(Opcode::Insert, cursor, values, 3, P4::None);
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    program
+}
+
+unsafe fn compile_select(table: &mut Table, select: &Select) -> Option<Program> {
+    let from = select.from.as_ref()?;
+    let simple = select.with.is_none()
+        && select.compound.is_empty()
+        && select.joins.is_empty()
+        && !select.distinct
+        && select.group_by.is_empty()
+        && select.having.is_none()
+        && select.order_by.is_empty()
+        && select.limit.is_none()
+        && select.offset.is_none()
+        && from.subquery.is_none()
+        && find_common_table(table, &from.name).is_none()
+        && !is_aggregate_query(select, &table.functions);
+    let exprs: Vec<&Expr> = select
+        .columns
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::Expr { expr, .. } => Some(expr),
+            SelectItem::Wildcard | SelectItem::TableWildcard(_) => None,
+        })
+        .chain(&select.where_clause)
+        .collect();
+    if !simple || !exprs.iter().all(|expr| compilable(expr)) {
+        return None;
+    }
+    let schema = table.catalog.table(&from.name)?.clone();
+    if exprs.iter().any(|expr| reads_rowid(&schema, expr)) {
+        return None;
+    }
+    let alias = from.alias.clone().unwrap_or_else(|| schema.name.clone());
+    // The executor reports a table.* naming another table.
+    let other_table = |item: &SelectItem| matches!(item, SelectItem::TableWildcard(name) if !name.eq_ignore_ascii_case(&alias));
+    if select.columns.iter().any(other_table) {
+        return None;
+    }
+    let mut columns = Vec::new();
+    for item in &select.columns {
+        match item {
+            SelectItem::Wildcard | SelectItem::TableWildcard(_) => columns.extend(schema.columns.iter().map(|column| column.name.clone())),
+            SelectItem::Expr { expr, alias } => columns.push(column_name(expr, alias)),
+        }
+    }
+    let mut program = Program::new(Some(columns.clone()));
+    compile_loop(
+        &mut program,
+        table,
+        Opcode::OpenRead,
+        &schema,
+        &alias,
+        &select.where_clause,
+        |program, bindings, cursor| {
+            let row = program.registers(columns.len());
+            let mut target = row;
+            for item in &select.columns {
+                match item {
+                    SelectItem::Wildcard | SelectItem::TableWildcard(_) => {
+                        for i in 0..bindings.len() {
+                            program.emit(Opcode::Column, cursor, i as i64, target, P4::None);
+                            target += 1;
+                        }
+                    }
+                    SelectItem::Expr { expr, .. } => {
+                        load(program, bindings, cursor, expr, target);
+                        target += 1;
+                    }
+                }
+            }
+            program.emit(Opcode::ResultRow, row, columns.len() as i64, 0, P4::None);
+        },
+    );
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    Some(program)
+}
+
+// All values are computed before the first row is stored, like the
+// executor does.
+fn compile_insert(table: &mut Table, insert: &Insert) -> Option<Program> {
+    let InsertSource::Values(rows) = &insert.source else { return None };
+    let schema = table.catalog.table(&insert.table)?;
+    let simple = insert.or_conflict == ConflictResolution::Abort
+        && insert.upsert.is_none()
+        && insert.returning.is_empty()
+        && table.catalog.get_writable_table(&insert.table).is_ok()
+        && !table.catalog.triggers.iter().any(|trigger| trigger.table_name.eq_ignore_ascii_case(&schema.name));
+    if !simple || !rows.iter().flatten().all(compilable) {
+        return None;
+    }
+    let columns = match &insert.columns {
+        Some(columns) if columns.iter().all(|column| schema.column_index(column).is_ok()) => columns.clone(),
+        Some(_) => return None,
+        None => schema.columns.iter().map(|column| column.name.clone()).collect(),
+    };
+    if rows.iter().any(|values| values.len() != columns.len()) {
+        return None;
+    }
+    let mut program = Program::new(None);
+    let cursor = program.cursor();
+    program.emit(
+        Opcode::OpenWrite,
+        cursor,
+        schema.root_page_num as i64,
+        0,
+        P4::Table(schema.clone(), schema.name.clone()),
+    );
+    let first = program.registers(rows.len() * columns.len());
+    for (i, expr) in rows.iter().flatten().enumerate() {
+        load(&mut program, &[], -1, expr, first + i as i64);
+    }
+    for i in 0..rows.len() {
+        let values = first + (i * columns.len()) as i64;
+        program.emit(Opcode::Insert, cursor, values, columns.len() as i64, P4::Columns(columns.clone()));
+    }
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    Some(program)
+}
+
+// The rows to change are collected into a row set first, then each of them
+// is read again and changed, like the executor does.
+unsafe fn compile_update(table: &mut Table, update: &Update) -> Option<Program> {
+    let schema = writable_table(table, &update.table, &update.returning)?;
+    let exprs = update.assignments.iter().map(|(_, expr)| expr).chain(&update.where_clause);
+    if !exprs.into_iter().all(|expr| compilable(expr) && !reads_rowid(&schema, expr))
+        || update.assignments.iter().any(|(column, _)| schema.column_index(column).is_err())
+    {
+        return None;
+    }
+    let columns: Vec<String> = update.assignments.iter().map(|(column, _)| column.clone()).collect();
+    let mut program = Program::new(None);
+    let (cursor, next) = compile_collect(&mut program, table, &schema, &update.where_clause);
+    let bindings = table_bindings(&schema, &schema.name);
+    let values = program.registers(columns.len());
+    for (i, (_, expr)) in update.assignments.iter().enumerate() {
+        load(&mut program, &bindings, cursor, expr, values + i as i64);
+    }
+    program.emit(Opcode::Update, cursor, values, columns.len() as i64, P4::Columns(columns));
+    program.emit(Opcode::Goto, 0, next, 0, P4::None);
+    program.jump_here(next);
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    Some(program)
+}
+
+unsafe fn compile_delete(table: &mut Table, delete: &Delete) -> Option<Program> {
+    let schema = writable_table(table, &delete.table, &delete.returning)?;
+    if !delete.where_clause.iter().all(|expr| compilable(expr) && !reads_rowid(&schema, expr)) {
+        return None;
+    }
+    let mut program = Program::new(None);
+    let (cursor, next) = compile_collect(&mut program, table, &schema, &delete.where_clause);
+    program.emit(Opcode::Delete, cursor, 0, 0, P4::None);
+    program.emit(Opcode::Goto, 0, next, 0, P4::None);
+    program.jump_here(next);
+    program.emit(Opcode::Halt, 0, 0, 0, P4::None);
+    Some(program)
+}
+
+// The table an UPDATE or DELETE changes, None when the statement is left to
+// the executor.
+fn writable_table(table: &Table, name: &str, returning: &[SelectItem]) -> Option<TableSchema> {
+    let schema = table.catalog.get_writable_table(name).ok()?;
+    let simple = returning.is_empty()
+        && !table.catalog.triggers.iter().any(|trigger| trigger.table_name.eq_ignore_ascii_case(&schema.name));
+    simple.then(|| schema.clone())
+}
+
+// Emits the collection of the rowids of the rows matching the WHERE clause
+// into a row set, then the start of a loop reading them back with the cursor
+// at the row of each one still there. Returns the cursor and the address of
+// the RowSetRead starting each iteration, whose jump is to be made past the
+// loop.
+unsafe fn compile_collect(
+    program: &mut Program,
+    table: &mut Table,
+    schema: &TableSchema,
+    where_clause: &Option<Expr>,
+) -> (i64, i64) {
+    let rowset = program.rowset();
+    let rowid = program.registers(1);
+    let cursor = compile_loop(
+        program,
+        table,
+        Opcode::OpenWrite,
+        schema,
+        &schema.name,
+        where_clause,
+        |program, _, cursor| {
+            program.emit(Opcode::Rowid, cursor, rowid, 0, P4::None);
+            program.emit(Opcode::RowSetAdd, rowset, rowid, 0, P4::None);
+        },
+    );
+    let next = program.emit(Opcode::RowSetRead, rowset, 0, rowid, P4::None);
+    program.emit(Opcode::SeekRowid, cursor, next, rowid, P4::None);
+    (cursor, next)
+}
+
+// Opens a cursor on a table and emits a loop over the rows matching the
+// WHERE clause, with body emitting what is done with each one. Returns the
+// cursor.
+unsafe fn compile_loop(
+    program: &mut Program,
+    table: &mut Table,
+    open: Opcode,
+    schema: &TableSchema,
+    alias: &str,
+    where_clause: &Option<Expr>,
+    body: impl FnOnce(&mut Program, &[ColumnBinding], i64),
+) -> i64 {
+    let bindings = table_bindings(schema, alias);
+    let label = if alias == schema.name {
+        schema.name.clone()
+    } else {
+        format!("{} AS {}", schema.name, alias)
+    };
+    let relation = PlanTable::stored(table, label, schema, bindings.clone());
+    let conditions = where_clause.as_ref().map(conjuncts).unwrap_or_default();
+    let access = choose_access(&table.functions, &relation, &[], &conditions, 1.0, false).access;
+
+    let cursor = program.cursor();
+    program.emit(open, cursor, schema.root_page_num as i64, 0, P4::Table(schema.clone(), alias.to_string()));
+    let first_row = match &access {
+        Access::RowidSeek(expr) => {
+            let key = program.registers(1);
+            load_compared(program, &bindings, -1, expr, Some(Affinity::Numeric), key);
+            program.emit(Opcode::SeekRowid, cursor, 0, key, P4::None)
+        }
+        Access::IndexSeek(index, exprs) => {
+            let keys = program.registers(exprs.len());
+            for (i, (name, expr)) in index.columns.iter().zip(exprs).enumerate() {
+                let affinity = schema.column_index(name).ok().map(|column| schema.columns[column].affinity);
+                load_compared(program, &bindings, -1, expr, comparison_affinity(affinity, None).1, keys + i as i64);
+            }
+            program.emit(Opcode::SeekIndex, cursor, 0, keys, P4::Index(index.clone()))
+        }
+        Access::Scan | Access::HashLookup(..) => program.emit(Opcode::Rewind, cursor, 0, 0, P4::None),
+    };
+    let loop_start = program.here();
+    // Jumps taken by rows failing the WHERE clause.
+    let mut skips = Vec::new();
+    for condition in conditions {
+        skips.push(compile_condition(program, &bindings, cursor, condition));
+    }
+    body(program, &bindings, cursor);
+    for skip in skips {
+        program.jump_here(skip);
+    }
+    program.emit(Opcode::Next, cursor, loop_start, 0, P4::None);
+    program.jump_here(first_row);
+    cursor
+}
+
+// Subqueries and window calls are left to the executor.
+fn compilable(expr: &Expr) -> bool {
+    let mut compilable = true;
+    expr.walk(&mut |expr| {
+        if matches!(
+            expr,
+            Expr::Subquery(_) | Expr::Exists(_) | Expr::InSubquery { .. } | Expr::Window { .. }
+        ) {
+            compilable = false;
+        }
+    });
+    compilable
+}
+
+// Reading the rowid by one of its names is left to the executor, the
+// machine binds the declared columns only.
+fn reads_rowid(schema: &TableSchema, expr: &Expr) -> bool {
+    let mut reads = false;
+    expr.walk(&mut |expr| {
+        if let Expr::Column { name, .. } = expr {
+            reads |= is_rowid_name(name) && schema.column_index(name).is_err();
+        }
+    });
+    reads
+}
+
+// Emits the check of a WHERE condition, returns the address of the jump
+// taken when it is not true.
+fn compile_condition(program: &mut Program, bindings: &[ColumnBinding], cursor: i64, condition: &Expr) -> i64 {
+    if let Expr::Binary { left, op, right } = condition {
+        // Jumps when the comparison is false, or NULL.
+        let opposite = match op {
+            BinaryOp::Eq => Some(Opcode::Ne),
+            BinaryOp::NotEq => Some(Opcode::Eq),
+            BinaryOp::Lt => Some(Opcode::Ge),
+            BinaryOp::LtEq => Some(Opcode::Gt),
+            BinaryOp::Gt => Some(Opcode::Le),
+            BinaryOp::GtEq => Some(Opcode::Lt),
+            _ => None,
+        };
+        if let Some(opcode) = opposite {
+            let operands = program.registers(2);
+            let scope = Scope {
+                functions: None,
+                columns: bindings,
+                values: &[],
+            };
+            let (left_affinity, right_affinity) = comparison_affinity(scope.affinity(left), scope.affinity(right));
+            load_compared(program, bindings, cursor, left, left_affinity, operands);
+            load_compared(program, bindings, cursor, right, right_affinity, operands + 1);
+            let jump = program.emit(opcode, operands, 0, operands + 1, P4::None);
+            program.instructions[jump as usize].p5 = JUMP_IF_NULL;
+            return jump;
+        }
+    }
+    let result = program.registers(1);
+    load(program, bindings, cursor, condition, result);
+    program.emit(Opcode::IfNot, result, 0, 0, P4::None)
+}
+
+// Loads an operand of a comparison with the affinity the comparison applies
+// to it, converting literals right away.
+fn load_compared(
+    program: &mut Program,
+    bindings: &[ColumnBinding],
+    cursor: i64,
+    expr: &Expr,
+    affinity: Option<Affinity>,
+    target: i64,
+) {
+    match (expr, affinity) {
+        (Expr::Literal(value), Some(affinity)) => load_literal(program, affinity.apply(value.clone()), target),
+        (_, Some(affinity)) => {
+            load(program, bindings, cursor, expr, target);
+            program.emit(Opcode::Affinity, target, 0, 0, P4::Affinity(affinity));
+        }
+        (_, None) => load(program, bindings, cursor, expr, target),
+    }
+}
+
+// Emits the computation of an expression into a register: columns of the
+// cursor's row, literals and parameters directly, anything else by
+// evaluating it.
+fn load(program: &mut Program, bindings: &[ColumnBinding], cursor: i64, expr: &Expr, target: i64) {
+    match expr {
+        Expr::Literal(value) => load_literal(program, value.clone(), target),
+        Expr::Parameter { index, .. } => {
+            program.emit(Opcode::Variable, *index as i64, target, 0, P4::None);
+        }
+        Expr::Column { table, name } if cursor >= 0 => {
+            match bindings.iter().position(|binding| binding.matches(table.as_deref(), name)) {
+                Some(i) => program.emit(Opcode::Column, cursor, i as i64, target, P4::None),
+                None => program.emit(Opcode::Expr, cursor, target, 0, P4::Expr(expr.clone())),
+            };
+        }
+        _ => {
+            program.emit(Opcode::Expr, cursor, target, 0, P4::Expr(expr.clone()));
+        }
+    }
+}
+
+fn load_literal(program: &mut Program, value: Value, target: i64) {
+    match value {
+        Value::Integer(i) => program.emit(Opcode::Integer, i, target, 0, P4::None),
+        Value::Real(_) => program.emit(Opcode::Real, 0, target, 0, P4::Value(value)),
+        Value::Text(_) => program.emit(Opcode::String8, 0, target, 0, P4::Value(value)),
+        Value::Null => program.emit(Opcode::Null, 0, target, 0, P4::None),
+    };
+}
@@ -0,0 +1,403 @@
+use crate::cursor::cursor::Cursor;
+use crate::executor::alter::{complete_row, stored_row};
+use crate::executor::dispatch::{execute_stmt, ExecuteResult, ResultSet};
+use crate::executor::dml::{build_row, delete_row, insert_row, update_row, Conflicts};
+use crate::executor::expr::{eval, is_true, ColumnBinding, Scope};
+use crate::executor::index::index_lookup;
+use crate::executor::query::table_bindings;
+use crate::node::node::Node;
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::sql::ast::ConflictResolution;
+use crate::table::btree::{BTree, InsertCursor, TreeScan};
+use crate::table::journal::atomically;
+use crate::table::row::{Row, ROW_SIZE};
+use crate::table::table::{Table, TABLE_MAX_ROWS};
+use crate::table::value::Value;
+use crate::vm::program::{Opcode, Program, JUMP_IF_NULL, P4};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::io::Error;
+use std::ptr;
+use std::vec;
+
+// Runs a program, see program.rs for what each instruction does, with the
+// values bound to its parameters. Queries return the rows of their ResultRow
+// instructions, other statements the number of rows they changed. A
+// program changing the database makes all of its changes or none.
+pub unsafe fn execute_program(table: &mut Table, program: &Program, parameters: &[Value]) -> Result<ExecuteResult, Error> {
+    let outer = table.functions.bind_parameters(parameters.to_vec());
+    let result = if program.writes() {
+        atomically(table, |table| run_program(table, program))
+    } else {
+        run_program(table, program)
+    };
+    table.functions.bind_parameters(outer);
+    result
+}
+
+unsafe fn run_program(table: &mut Table, program: &Program) -> Result<ExecuteResult, Error> {
+    let mut registers = vec![Value::Null; program.registers];
+    let mut cursors: Vec<Option<VmCursor>> = (0..program.cursors).map(|_| None).collect();
+    let mut rowsets: Vec<VecDeque<u32>> = vec![VecDeque::new(); program.rowsets];
+    let mut rows = Vec::new();
+    let mut changes = 0;
+    let mut pc = 0;
+    while let Some(instruction) = program.instructions.get(pc) {
+        pc += 1;
+        let (p1, p2, p3) = (instruction.p1, instruction.p2, instruction.p3);
+        let jump = match instruction.opcode {
+            Opcode::Halt => break,
+            Opcode::OpenRead | Opcode::OpenWrite => {
+                cursors[p1 as usize] = Some(VmCursor::open(table, &instruction.p4));
+                false
+            }
+            Opcode::Rewind => !cursor(&mut cursors, p1)?.rewind(table)?,
+            Opcode::Next => cursor(&mut cursors, p1)?.next(table)?,
+            Opcode::SeekRowid => {
+                let rowids = match registers[p3 as usize].clone().normalized() {
+                    Value::Integer(rowid) if rowid >= 0 && rowid <= u32::MAX as i64 => vec![rowid as u32],
+                    _ => Vec::new(),
+                };
+                !cursor(&mut cursors, p1)?.seek(table, rowids)?
+            }
+            Opcode::SeekIndex => {
+                let P4::Index(index) = &instruction.p4 else { unreachable!() };
+                let keys = &registers[p3 as usize..p3 as usize + index.columns.len()];
+                let mut rowids = index_lookup(table, index, keys);
+                rowids.sort_unstable();
+                !cursor(&mut cursors, p1)?.seek(table, rowids)?
+            }
+            Opcode::Column => {
+                registers[p3 as usize] = cursor(&mut cursors, p1)?.row.get(p2 as usize).cloned().unwrap_or(Value::Null);
+                false
+            }
+            Opcode::Rowid => {
+                registers[p2 as usize] = Value::Integer(cursor(&mut cursors, p1)?.rowid as i64);
+                false
+            }
+            Opcode::Integer => {
+                registers[p2 as usize] = Value::Integer(p1);
+                false
+            }
+            Opcode::Real | Opcode::String8 => {
+                let P4::Value(value) = &instruction.p4 else { unreachable!() };
+                registers[p2 as usize] = value.clone();
+                false
+            }
+            Opcode::Null => {
+                registers[p2 as usize] = Value::Null;
+                false
+            }
+            Opcode::Variable => {
+                registers[p2 as usize] = table.functions.parameter(p1 as usize);
+                false
+            }
+            Opcode::Affinity => {
+                let P4::Affinity(affinity) = &instruction.p4 else { unreachable!() };
+                registers[p1 as usize] = affinity.apply(registers[p1 as usize].clone());
+                false
+            }
+            Opcode::Expr => {
+                let P4::Expr(expr) = &instruction.p4 else { unreachable!() };
+                let value = match p1 {
+                    -1 => eval(expr, &Scope::new(&table.functions, &[], &[]))?,
+                    _ => {
+                        let cursor = cursor(&mut cursors, p1)?;
+                        eval(expr, &Scope::new(&table.functions, &cursor.bindings, &cursor.row))?
+                    }
+                };
+                registers[p2 as usize] = value;
+                false
+            }
+            Opcode::Eq | Opcode::Ne | Opcode::Lt | Opcode::Le | Opcode::Gt | Opcode::Ge => {
+                let (left, right) = (&registers[p1 as usize], &registers[p3 as usize]);
+                if left.is_null() || right.is_null() {
+                    instruction.p5 == JUMP_IF_NULL
+                } else {
+                    let ordering = left.compare(right);
+                    match instruction.opcode {
+                        Opcode::Eq => ordering == Ordering::Equal,
+                        Opcode::Ne => ordering != Ordering::Equal,
+                        Opcode::Lt => ordering == Ordering::Less,
+                        Opcode::Le => ordering != Ordering::Greater,
+                        Opcode::Gt => ordering == Ordering::Greater,
+                        _ => ordering != Ordering::Less,
+                    }
+                }
+            }
+            Opcode::IfNot => !is_true(&registers[p1 as usize]),
+            Opcode::Goto => true,
+            Opcode::RowSetAdd => {
+                if let Value::Integer(rowid) = registers[p2 as usize] {
+                    rowsets[p1 as usize].push_back(rowid as u32);
+                }
+                false
+            }
+            Opcode::RowSetRead => match rowsets[p1 as usize].pop_front() {
+                Some(rowid) => {
+                    registers[p3 as usize] = Value::Integer(rowid as i64);
+                    false
+                }
+                None => true,
+            },
+            Opcode::ResultRow => {
+                rows.push(registers[p1 as usize..(p1 + p2) as usize].to_vec());
+                false
+            }
+            Opcode::Insert => {
+                let values = registers[p2 as usize..(p2 + p3) as usize].to_vec();
+                if cursor(&mut cursors, p1)?.insert(table, &instruction.p4, values)? {
+                    changes += 1;
+                }
+                false
+            }
+            Opcode::Update => {
+                let values = registers[p2 as usize..(p2 + p3) as usize].to_vec();
+                cursor(&mut cursors, p1)?.update(table, &instruction.p4, values)?;
+                changes += 1;
+                false
+            }
+            Opcode::Delete => {
+                cursor(&mut cursors, p1)?.delete(table)?;
+                changes += 1;
+                false
+            }
+            Opcode::Execute => {
+                let P4::Stmt(stmt, sql) = &instruction.p4 else { unreachable!() };
+                return execute_stmt(table, stmt, sql);
+            }
+        };
+        if jump {
+            pc = p2 as usize;
+        }
+    }
+    Ok(match &program.columns {
+        Some(columns) => ExecuteResult::Rows(ResultSet {
+            columns: columns.clone(),
+            rows,
+        }),
+        None => ExecuteResult::Executed { changes },
+    })
+}
+
+fn cursor(cursors: &mut [Option<VmCursor>], n: i64) -> Result<&mut VmCursor, Error> {
+    cursors
+        .get_mut(n as usize)
+        .and_then(Option::as_mut)
+        .ok_or_else(|| Error::other(format!("cursor {} is not open", n)))
+}
+
+// A cursor of a running program and the row it is at.
+struct VmCursor {
+    source: Source,
+    bindings: Vec<ColumnBinding>,
+    rowid: u32,
+    row: Vec<Value>,
+}
+
+enum Source {
+    Table {
+        schema: Box<TableSchema>,
+        indexes: Vec<IndexSchema>,
+        rows: Rows,
+        writer: InsertCursor,
+    },
+    // Where the legacy cursor of cursor.rs is, it borrows the table.
+    Legacy {
+        page_num: u32,
+        cell_num: u32,
+        end_of_table: bool,
+    },
+}
+
+// The rows of a table left to read: the rest of a scan, or the rest of the
+// rows a seek found.
+enum Rows {
+    None,
+    Scan(TreeScan),
+    Rowids(vec::IntoIter<u32>),
+}
+
+impl VmCursor {
+    fn open(table: &Table, p4: &P4) -> VmCursor {
+        let source = match p4 {
+            P4::Table(schema, _) => Source::Table {
+                schema: Box::new(schema.clone()),
+                indexes: table.catalog.table_indexes(&schema.name),
+                rows: Rows::None,
+                writer: InsertCursor::new(BTree::new(schema.root_page_num)),
+            },
+            _ => Source::Legacy {
+                page_num: 0,
+                cell_num: 0,
+                end_of_table: true,
+            },
+        };
+        let bindings = match p4 {
+            P4::Table(schema, alias) => table_bindings(schema, alias),
+            _ => Vec::new(),
+        };
+        VmCursor {
+            source,
+            bindings,
+            rowid: 0,
+            row: Vec::new(),
+        }
+    }
+
+    // Moves to the first row, returns whether there is one.
+    unsafe fn rewind(&mut self, table: &mut Table) -> Result<bool, Error> {
+        match &mut self.source {
+            Source::Table { schema, rows, .. } => *rows = Rows::Scan(BTree::new(schema.root_page_num).scan(table)),
+            Source::Legacy {
+                page_num,
+                cell_num,
+                end_of_table,
+            } => {
+                let cursor = Cursor::table_start(table);
+                (*page_num, *cell_num, *end_of_table) = (cursor.page_num, cursor.cell_num, cursor.end_of_table);
+            }
+        }
+        self.read(table)
+    }
+
+    // Moves to the first of the rows having these rowids, returns whether
+    // there is one.
+    unsafe fn seek(&mut self, table: &mut Table, rowids: Vec<u32>) -> Result<bool, Error> {
+        if let Source::Table { rows, .. } = &mut self.source {
+            *rows = Rows::Rowids(rowids.into_iter());
+        }
+        self.read(table)
+    }
+
+    // Moves to the next row, returns whether there is one.
+    unsafe fn next(&mut self, table: &mut Table) -> Result<bool, Error> {
+        if let Source::Legacy {
+            page_num,
+            cell_num,
+            end_of_table,
+        } = &mut self.source
+        {
+            let mut cursor = Cursor {
+                page_num: *page_num,
+                cell_num: *cell_num,
+                end_of_table: *end_of_table,
+                table,
+            };
+            cursor.advance_cursor();
+            (*page_num, *cell_num, *end_of_table) = (cursor.page_num, cursor.cell_num, cursor.end_of_table);
+        }
+        self.read(table)
+    }
+
+    // Reads the row the cursor is at into row, returns false past the last
+    // one.
+    unsafe fn read(&mut self, table: &mut Table) -> Result<bool, Error> {
+        let row = match &mut self.source {
+            Source::Table { schema, rows, .. } => match rows {
+                Rows::None => None,
+                Rows::Scan(scan) => match scan.next(table) {
+                    Some((rowid, record)) => {
+                        let mut values = record.values;
+                        complete_row(schema, &mut values)?;
+                        Some((rowid, values))
+                    }
+                    None => None,
+                },
+                Rows::Rowids(rowids) => loop {
+                    match rowids.next() {
+                        Some(rowid) => {
+                            if let Some(values) = stored_row(table, schema, rowid)? {
+                                break Some((rowid, values));
+                            }
+                        }
+                        None => break None,
+                    }
+                },
+            },
+            Source::Legacy {
+                page_num,
+                cell_num,
+                end_of_table,
+            } => {
+                if *end_of_table {
+                    None
+                } else {
+                    let mut cursor = Cursor {
+                        page_num: *page_num,
+                        cell_num: *cell_num,
+                        end_of_table: *end_of_table,
+                        table,
+                    };
+                    let mut bytes = [0u8; ROW_SIZE];
+                    ptr::copy(cursor.cursor_value(), bytes.as_mut_ptr(), ROW_SIZE);
+                    let row = Row::deserialize_row(&bytes);
+                    Some((row.id, row.to_values()))
+                }
+            }
+        };
+        match row {
+            Some((rowid, row)) => {
+                self.rowid = rowid;
+                self.row = row;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Inserts a row, returns whether it was stored.
+    unsafe fn insert(&mut self, table: &mut Table, p4: &P4, values: Vec<Value>) -> Result<bool, Error> {
+        match &mut self.source {
+            Source::Table {
+                schema,
+                indexes,
+                writer,
+                ..
+            } => {
+                let P4::Columns(columns) = p4 else { unreachable!() };
+                let mut provided = vec![None; schema.columns.len()];
+                for (column, value) in columns.iter().zip(values) {
+                    provided[schema.column_index(column)?] = Some(value);
+                }
+                let row = build_row(&table.functions, schema, provided)?;
+                let conflicts = Conflicts {
+                    resolution: ConflictResolution::Abort,
+                    upsert: None,
+                };
+                Ok(insert_row(table, schema, indexes, writer, &conflicts, row)?.is_some())
+            }
+            Source::Legacy { .. } => {
+                if table.num_rows >= TABLE_MAX_ROWS {
+                    return Err(Error::other("Table is full"));
+                }
+                let row = Row::from_values(&values);
+                let cursor = Cursor::find_key(table, row.id);
+                Node::leaf_node_insert(cursor, row.id, row)?;
+                Ok(true)
+            }
+        }
+    }
+
+    // Sets columns of the row the cursor is at, it keeps the row as it was.
+    unsafe fn update(&mut self, table: &mut Table, p4: &P4, values: Vec<Value>) -> Result<(), Error> {
+        let Source::Table { schema, indexes, .. } = &self.source else {
+            return Err(Error::other("cannot update the legacy table"));
+        };
+        let P4::Columns(columns) = p4 else { unreachable!() };
+        let mut new_row = self.row.clone();
+        for (column, value) in columns.iter().zip(values) {
+            let i = schema.column_index(column)?;
+            new_row[i] = schema.columns[i].affinity.apply(value);
+        }
+        update_row(table, schema, indexes, self.rowid, &self.row, new_row)?;
+        Ok(())
+    }
+
+    unsafe fn delete(&mut self, table: &mut Table) -> Result<(), Error> {
+        let Source::Table { schema, indexes, .. } = &self.source else {
+            return Err(Error::other("cannot delete from the legacy table"));
+        };
+        delete_row(table, schema, indexes, self.rowid, &self.row)
+    }
+}
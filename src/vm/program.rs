@@ -0,0 +1,194 @@
+use crate::schema::definition::{IndexSchema, TableSchema};
+use crate::sql::ast::{Expr, Stmt};
+use crate::table::value::{Affinity, Value};
+
+// A statement compiled for the virtual machine of machine.rs: a list of
+// instructions reading and writing numbered registers holding values and
+// numbered cursors open on tables. Each instruction has an opcode and up to
+// five operands, p1 to p3 and p5 numbers and p4 anything else, the way
+// SQLite lays out its bytecode. EXPLAIN lists them.
+//
+//   Halt       end the program
+//   OpenRead   open cursor p1 on the table p4 rooted at page p2
+//   OpenWrite  the same, for inserting, updating and deleting rows
+//   Rewind     move cursor p1 to the first row, jump to p2 when there is none
+//   Next       move cursor p1 to the next row, jump to p2 when there is one
+//   SeekRowid  move cursor p1 to the row whose rowid is r[p3], jump to p2
+//              when there is none
+//   SeekIndex  move cursor p1 to the rows whose key in the index p4 is in
+//              the registers from p3 on, jump to p2 when there is none
+//   Column     r[p3] = column p2 of the row of cursor p1
+//   Rowid      r[p2] = the rowid of the row of cursor p1
+//   Integer    r[p2] = p1
+//   Real       r[p2] = p4
+//   String8    r[p2] = p4
+//   Null       r[p2] = NULL
+//   Variable   r[p2] = the value bound to parameter p1, NULL when there is
+//              none
+//   Affinity   apply the column affinity p4 to r[p1]
+//   Expr       r[p2] = p4 evaluated over the row of cursor p1, or without a
+//              row when p1 is -1
+//   Eq ... Ge  jump to p2 when r[p1] compares to r[p3] that way, or when
+//              either is NULL and p5 is JUMP_IF_NULL
+//   IfNot      jump to p2 unless r[p1] is true
+//   Goto       jump to p2
+//   RowSetAdd  add the rowid r[p2] to the row set p1
+//   RowSetRead r[p3] = the next rowid of the row set p1 in the order they
+//              were added, jump to p2 when there is none left
+//   ResultRow  output the p2 registers from p1 on as a row
+//   Insert     insert the p3 registers from p2 on as the columns p4 of a new
+//              row of the table of cursor p1
+//   Update     set the columns p4 of the row of cursor p1 to the p3
+//              registers from p2 on
+//   Delete     delete the row of cursor p1
+//   Execute    run the statement p4 with the tree-walking executor, for the
+//              statements the compiler does not handle
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    Halt,
+    OpenRead,
+    OpenWrite,
+    Rewind,
+    Next,
+    SeekRowid,
+    SeekIndex,
+    Column,
+    Rowid,
+    Integer,
+    Real,
+    String8,
+    Null,
+    Variable,
+    Affinity,
+    Expr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    IfNot,
+    Goto,
+    RowSetAdd,
+    RowSetRead,
+    ResultRow,
+    Insert,
+    Update,
+    Delete,
+    Execute,
+}
+
+pub const JUMP_IF_NULL: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum P4 {
+    None,
+    // The table of a cursor and the name its columns are qualified with.
+    Table(TableSchema, String),
+    // The rows of the legacy insert and select commands: id, username and
+    // email.
+    Legacy,
+    Index(IndexSchema),
+    Value(Value),
+    Affinity(Affinity),
+    Expr(Expr),
+    Columns(Vec<String>),
+    Stmt(Box<Stmt>, String),
+}
+
+impl P4 {
+    // How EXPLAIN shows the operand, None for NULL.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            P4::None | P4::Legacy => None,
+            P4::Table(schema, _) => Some(schema.name.clone()),
+            P4::Index(index) => Some(index.name.clone()),
+            P4::Value(value) => Some(value.to_string()),
+            P4::Affinity(affinity) => Some(format!("{:?}", affinity)),
+            P4::Expr(expr) => Some(expr.to_string()),
+            P4::Columns(columns) => Some(columns.join(", ")),
+            P4::Stmt(_, sql) => Some(sql.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub p1: i64,
+    pub p2: i64,
+    pub p3: i64,
+    pub p4: P4,
+    pub p5: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub registers: usize,
+    pub cursors: usize,
+    pub rowsets: usize,
+    // The names of the result columns of a query, None for a statement
+    // that changes rows.
+    pub columns: Option<Vec<String>>,
+}
+
+impl Program {
+    pub fn new(columns: Option<Vec<String>>) -> Program {
+        Program {
+            instructions: Vec::new(),
+            registers: 0,
+            cursors: 0,
+            rowsets: 0,
+            columns,
+        }
+    }
+
+    // Appends an instruction, returns its address.
+    pub fn emit(&mut self, opcode: Opcode, p1: i64, p2: i64, p3: i64, p4: P4) -> i64 {
+        self.instructions.push(Instruction {
+            opcode,
+            p1,
+            p2,
+            p3,
+            p4,
+            p5: 0,
+        });
+        self.instructions.len() as i64 - 1
+    }
+
+    // The address of the next instruction.
+    pub fn here(&self) -> i64 {
+        self.instructions.len() as i64
+    }
+
+    // Makes the jump at address go to the next instruction.
+    pub fn jump_here(&mut self, address: i64) {
+        self.instructions[address as usize].p2 = self.here();
+    }
+
+    // Allocates n consecutive registers, returns the first one.
+    pub fn registers(&mut self, n: usize) -> i64 {
+        self.registers += n;
+        (self.registers - n) as i64
+    }
+
+    // Whether running the program may change the database.
+    pub fn writes(&self) -> bool {
+        self.instructions.iter().any(|instruction| match &instruction.p4 {
+            P4::Stmt(stmt, _) => !matches!(**stmt, Stmt::Select(_) | Stmt::Explain(_) | Stmt::ExplainQueryPlan(_)),
+            _ => instruction.opcode == Opcode::OpenWrite,
+        })
+    }
+
+    pub fn cursor(&mut self) -> i64 {
+        self.cursors += 1;
+        self.cursors as i64 - 1
+    }
+
+    pub fn rowset(&mut self) -> i64 {
+        self.rowsets += 1;
+        self.rowsets as i64 - 1
+    }
+}
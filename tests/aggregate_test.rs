@@ -0,0 +1,76 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn create_sales(table: &mut Table) {
+    run(table, "CREATE TABLE sales (id INTEGER PRIMARY KEY, region TEXT, amount INTEGER)");
+    run(table, "INSERT INTO sales VALUES (1, 'north', 10)");
+    run(table, "INSERT INTO sales VALUES (2, 'south', 5)");
+    run(table, "INSERT INTO sales VALUES (3, 'north', 7)");
+    run(table, "INSERT INTO sales VALUES (4, 'east', NULL)");
+    run(table, "INSERT INTO sales VALUES (5, 'south', 20)");
+}
+
+#[test]
+fn aggregates_over_whole_table() {
+    let file_name = "aggregate_table.db";
+    let mut table = open_test(file_name);
+    create_sales(&mut table);
+    assert_eq!(run(&mut table, "SELECT COUNT(*) FROM sales"), "5\n");
+    assert_eq!(run(&mut table, "SELECT count(amount), sum(amount), min(amount), max(amount) FROM sales"), "4,42,5,20\n");
+    assert_eq!(run(&mut table, "SELECT avg(amount) FROM sales WHERE region = 'north'"), "8.5\n");
+    assert_eq!(run(&mut table, "SELECT count(*), sum(amount), avg(amount) FROM sales WHERE id > 10"), "0,NULL,NULL\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM sales WHERE amount > 6"), "3\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn group_by_and_having() {
+    let file_name = "aggregate_group.db";
+    let mut table = open_test(file_name);
+    create_sales(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT region, count(*), sum(amount) FROM sales GROUP BY region"),
+        "east,1,NULL\nnorth,2,17\nsouth,2,25\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT region FROM sales GROUP BY region HAVING sum(amount) > 20"),
+        "south\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT region, max(amount) AS top FROM sales GROUP BY region ORDER BY top DESC LIMIT 2"),
+        "south,20\nnorth,10\n"
+    );
+    assert_eq!(run(&mut table, "SELECT region FROM sales GROUP BY region ORDER BY count(*), region DESC"), "east\nsouth\nnorth\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn count_star_after_deletes() {
+    let file_name = "aggregate_count.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY)");
+    for i in 1..=30 {
+        run(&mut table, &format!("INSERT INTO t VALUES ({})", i));
+    }
+    run(&mut table, "DELETE FROM t WHERE id > 20");
+    assert_eq!(run(&mut table, "SELECT COUNT(*) FROM t"), "20\n");
+    run(&mut table, "DELETE FROM t");
+    assert_eq!(run(&mut table, "SELECT COUNT(*) FROM t"), "0\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn aggregate_misuse() {
+    let file_name = "aggregate_misuse.db";
+    let mut table = open_test(file_name);
+    create_sales(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT id FROM sales WHERE count(*) > 1"),
+        "misuse of aggregate function count()\n"
+    );
+    assert_eq!(run(&mut table, "SELECT sum(max(amount)) FROM sales"), "misuse of aggregate function sum()\n");
+    assert_eq!(run(&mut table, "SELECT sum(amount, id) FROM sales"), "wrong number of arguments to function sum()\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER)");
+    run(table, "INSERT INTO users (name, age) VALUES ('ann', 30), ('bob', 25), ('cat', 41)");
+}
+
+#[test]
+fn add_column_reads_default_in_old_rows() {
+    let file_name = "alter_add_column.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "ALTER TABLE users ADD COLUMN score INTEGER DEFAULT 7"),
+        "Executed.\n"
+    );
+    run(&mut table, "ALTER TABLE users ADD email TEXT");
+    run(&mut table, "INSERT INTO users (name, age, score, email) VALUES ('dan', 19, 3, 'd@x')");
+    assert_eq!(
+        run(&mut table, "SELECT id, name, score, email FROM users"),
+        "1,ann,7,NULL\n2,bob,7,NULL\n3,cat,7,NULL\n4,dan,3,d@x\n"
+    );
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE score = 7 AND age > 26"), "ann\ncat\n");
+    assert_eq!(run(&mut table, "SELECT sum(score) FROM users"), "24\n");
+    // Updating an old row stores it with every column.
+    run(&mut table, "UPDATE users SET email = 'b@x' WHERE name = 'bob'");
+    assert_eq!(run(&mut table, "SELECT score, email FROM users WHERE id = 2"), "7,b@x\n");
+    run(&mut table, "DELETE FROM users WHERE score = 7 AND email IS NULL");
+    assert_eq!(run(&mut table, "SELECT name FROM users"), "bob\ndan\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn add_column_joins_and_indexes_old_rows() {
+    let file_name = "alter_add_column_join.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE TABLE teams (id INTEGER PRIMARY KEY, title TEXT)");
+    run(&mut table, "INSERT INTO teams (title) VALUES ('red'), ('blue')");
+    run(&mut table, "ALTER TABLE users ADD COLUMN team INTEGER DEFAULT 2");
+    assert_eq!(
+        run(&mut table, "SELECT users.name, teams.title FROM users JOIN teams ON teams.id = users.team ORDER BY users.name"),
+        "ann,blue\nbob,blue\ncat,blue\n"
+    );
+    run(&mut table, "CREATE INDEX users_team ON users (team)");
+    assert_eq!(
+        run(&mut table, "SELECT teams.title, users.name FROM teams JOIN users ON users.team = teams.id WHERE users.age < 30"),
+        "blue,bob\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn add_column_errors() {
+    let file_name = "alter_add_column_errors.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "ALTER TABLE users ADD COLUMN age TEXT"), "duplicate column name: age\n");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE users ADD COLUMN code INTEGER PRIMARY KEY"),
+        "Cannot add a PRIMARY KEY column\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE users ADD COLUMN code TEXT UNIQUE"), "Cannot add a UNIQUE column\n");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE users ADD COLUMN code TEXT NOT NULL"),
+        "Cannot add a NOT NULL column with default value NULL\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE users ADD COLUMN code TEXT DEFAULT (upper('a'))"),
+        "Cannot add a column with non-constant default\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE missing ADD COLUMN x"), "no such table: missing\n");
+    run(&mut table, "CREATE VIEW adults AS SELECT name FROM users WHERE age >= 30");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE adults ADD COLUMN x"),
+        "cannot alter adults because it is a view\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE users ADD COLUMN code TEXT NOT NULL DEFAULT 'x'"),
+        "Executed.\n"
+    );
+    assert_eq!(run(&mut table, "SELECT code FROM users WHERE id = 1"), "x\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn rename_column() {
+    let file_name = "alter_rename_column.db";
+    let mut table = open_test(file_name);
+    run(
+        &mut table,
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, price INTEGER CHECK (price > 0), code TEXT UNIQUE)",
+    );
+    run(&mut table, "CREATE TABLE log (item_id INTEGER, old_cost INTEGER)");
+    run(&mut table, "CREATE INDEX items_price ON items (price)");
+    run(
+        &mut table,
+        "CREATE TRIGGER log_price AFTER UPDATE OF price ON items WHEN NEW.price > OLD.price BEGIN INSERT INTO log VALUES (OLD.id, OLD.price); END",
+    );
+    run(&mut table, "INSERT INTO items (price, code) VALUES (10, 'a'), (20, 'b')");
+    assert_eq!(run(&mut table, "ALTER TABLE items RENAME COLUMN price TO cost"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id, cost FROM items WHERE cost > 15"), "2,20\n");
+    assert_eq!(run(&mut table, "SELECT price FROM items"), "no such column: price\n");
+    // The CHECK constraint follows the column.
+    assert_eq!(
+        run(&mut table, "INSERT INTO items (cost, code) VALUES (0, 'c')"),
+        "CHECK constraint failed: items.cost\n"
+    );
+    // So do the UPDATE OF list, WHEN clause and statements of the trigger.
+    run(&mut table, "UPDATE items SET cost = 30 WHERE id = 1");
+    run(&mut table, "UPDATE items SET cost = 5 WHERE id = 2");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "1,10\n");
+    assert_eq!(run(&mut table, "ALTER TABLE items RENAME code TO sku"), "Executed.\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO items (cost, sku) VALUES (1, 'a')"),
+        "UNIQUE constraint failed: items.sku\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE items RENAME COLUMN cost TO sku"),
+        "duplicate column name: sku\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE items RENAME COLUMN nope TO x"), "no such column: nope\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn rename_table() {
+    let file_name = "alter_rename_table.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "PRAGMA foreign_keys = ON");
+    run(&mut table, "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT UNIQUE)");
+    run(
+        &mut table,
+        "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors (id), title TEXT)",
+    );
+    run(&mut table, "INSERT INTO authors (name) VALUES ('ann'), ('bob')");
+    run(&mut table, "INSERT INTO books (author_id, title) VALUES (1, 'x'), (2, 'y')");
+    assert_eq!(run(&mut table, "ALTER TABLE authors RENAME TO writers"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT name FROM writers"), "ann\nbob\n");
+    assert_eq!(run(&mut table, "SELECT name FROM authors"), "no such table: authors\n");
+    // The foreign key of books now refers to writers.
+    assert_eq!(
+        run(&mut table, "INSERT INTO books (author_id, title) VALUES (9, 'z')"),
+        "FOREIGN KEY constraint failed\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO writers (name) VALUES ('ann')"),
+        "UNIQUE constraint failed: writers.name\n"
+    );
+    // The old name is free again.
+    assert_eq!(run(&mut table, "CREATE TABLE authors (id INTEGER, name TEXT UNIQUE)"), "Executed.\n");
+    run(&mut table, "INSERT INTO authors VALUES (1, 'ann')");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE books RENAME TO writers"),
+        "there is already another table or index with this name: writers\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_column_rewrites_rows() {
+    let file_name = "alter_drop_column.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "ALTER TABLE users ADD COLUMN score INTEGER DEFAULT 5");
+    run(&mut table, "INSERT INTO users (name, age, score) VALUES ('dan', 19, 8)");
+    assert_eq!(run(&mut table, "ALTER TABLE users DROP COLUMN age"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM users"), "1,ann,5\n2,bob,5\n3,cat,5\n4,dan,8\n");
+    assert_eq!(run(&mut table, "SELECT age FROM users"), "no such column: age\n");
+    run(&mut table, "INSERT INTO users (name) VALUES ('eve')");
+    assert_eq!(run(&mut table, "SELECT id, name, score FROM users WHERE id = 5"), "5,eve,5\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_column_errors() {
+    let file_name = "alter_drop_column_errors.db";
+    let mut table = open_test(file_name);
+    run(
+        &mut table,
+        "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT UNIQUE, b INTEGER, c INTEGER, d INTEGER, CHECK (c > d))",
+    );
+    run(&mut table, "CREATE INDEX t_b ON t (b)");
+    run(&mut table, "CREATE TABLE single (x INTEGER)");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE t DROP COLUMN id"),
+        "cannot drop column \"id\": it is part of the PRIMARY KEY\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE t DROP COLUMN a"), "cannot drop column \"a\": it is UNIQUE\n");
+    assert_eq!(run(&mut table, "ALTER TABLE t DROP COLUMN b"), "cannot drop column \"b\": it is indexed\n");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE t DROP COLUMN c"),
+        "cannot drop column \"c\": it is used by a CHECK constraint\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE single DROP COLUMN x"),
+        "cannot drop column \"x\": no other columns exist\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE t DROP COLUMN e"), "no such column: e\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn views_and_triggers_follow_renames() {
+    let file_name = "alter_rename_references.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE TABLE log (who TEXT, what TEXT)");
+    run(&mut table, "CREATE VIEW named AS SELECT name, u.age FROM users AS u WHERE name > 'a' ORDER BY name");
+    run(
+        &mut table,
+        "CREATE VIEW counts AS SELECT (SELECT count(*) FROM users WHERE users.age > 26) AS old, \
+         (SELECT max(name) FROM log JOIN users ON who = name) AS last",
+    );
+    run(
+        &mut table,
+        "CREATE TRIGGER renamed AFTER UPDATE ON users WHEN NEW.name <> OLD.name \
+         BEGIN INSERT INTO log VALUES (OLD.name, NEW.name); UPDATE users SET age = age + 1 WHERE name = NEW.name; END",
+    );
+    run(
+        &mut table,
+        "CREATE TRIGGER audit AFTER INSERT ON log BEGIN DELETE FROM users WHERE users.name = NEW.what; END",
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE users RENAME COLUMN name TO title"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM named"), "ann,30\nbob,25\ncat,41\n");
+    run(&mut table, "UPDATE users SET title = 'amy' WHERE id = 1");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "ann,amy\n");
+    assert_eq!(run(&mut table, "SELECT id, title, age FROM users"), "2,bob,25\n3,cat,41\n");
+    assert_eq!(run(&mut table, "ALTER TABLE users RENAME TO people"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM named"), "bob,25\ncat,41\n");
+    assert_eq!(run(&mut table, "SELECT * FROM counts"), "1,NULL\n");
+    run(&mut table, "UPDATE people SET title = 'bea' WHERE id = 2");
+    assert_eq!(run(&mut table, "SELECT id, title, age FROM people"), "3,cat,41\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_column_used_by_views_and_triggers() {
+    let file_name = "alter_drop_column_references.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, n INTEGER, m INTEGER, k INTEGER, j INTEGER)");
+    run(&mut table, "CREATE TABLE log (v INTEGER)");
+    run(
+        &mut table,
+        "CREATE TRIGGER grew AFTER UPDATE ON t WHEN NEW.n > OLD.n BEGIN INSERT INTO log VALUES (1); END",
+    );
+    run(&mut table, "CREATE TRIGGER copy AFTER INSERT ON t BEGIN INSERT INTO log SELECT m FROM t; END");
+    run(&mut table, "CREATE VIEW v AS SELECT id FROM t WHERE k > 0");
+    assert_eq!(
+        run(&mut table, "ALTER TABLE t DROP COLUMN n"),
+        "cannot drop column \"n\": it is used by a trigger\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE t DROP COLUMN m"),
+        "cannot drop column \"m\": it is used by a trigger\n"
+    );
+    assert_eq!(
+        run(&mut table, "ALTER TABLE t DROP COLUMN k"),
+        "cannot drop column \"k\": it is used by a view\n"
+    );
+    assert_eq!(run(&mut table, "ALTER TABLE t DROP COLUMN j"), "Executed.\n");
+    run(&mut table, "INSERT INTO t VALUES (1, 1, 2, 3)");
+    run(&mut table, "UPDATE t SET n = 5");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "2\n1\n");
+    assert_eq!(run(&mut table, "SELECT * FROM v"), "1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn altered_schema_survives_reopen() {
+    let file_name = "alter_reopen.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "ALTER TABLE users ADD COLUMN score REAL DEFAULT 1.5");
+    run(&mut table, "ALTER TABLE users RENAME COLUMN age TO years");
+    run(&mut table, "ALTER TABLE users RENAME TO people");
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(
+        run(&mut table, "SELECT name, years, score FROM people WHERE id = 2"),
+        "bob,25,1.5\n"
+    );
+    run(&mut table, "ALTER TABLE people DROP COLUMN years");
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "SELECT * FROM people"), "1,ann,1.5\n2,bob,1.5\n3,cat,1.5\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE tasks (id INTEGER PRIMARY KEY, status TEXT, kind TEXT)");
+    run(table, "CREATE INDEX tasks_status ON tasks (status)");
+    run(table, "CREATE INDEX tasks_kind ON tasks (kind)");
+    for i in 0..16 {
+        let status = if i < 15 { "done" } else { "open" };
+        let sql = format!("INSERT INTO tasks (status, kind) VALUES ('{}', 'k{}')", status, i % 8);
+        assert_eq!(run(table, &sql), "Executed.\n");
+    }
+}
+
+#[test]
+fn analyze_stores_statistics() {
+    let file_name = "analyze_stats.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE items (id INTEGER PRIMARY KEY, color TEXT, size INTEGER)");
+    run(&mut table, "CREATE INDEX items_color_size ON items (color, size)");
+    run(
+        &mut table,
+        "INSERT INTO items (color, size) VALUES ('red', 1), ('red', 2), ('blue', 1), ('red', 1), ('green', 3), ('red', 2), ('blue', 1), ('red', NULL)",
+    );
+    assert_eq!(run(&mut table, "ANALYZE"), "Executed.\n");
+    // Keys with a NULL are not indexed.
+    assert_eq!(
+        run(&mut table, "SELECT tbl, idx, stat FROM rustql_stat"),
+        "items,NULL,8 2\nitems,items_color_size,7 3 2.33 1.75\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT idx, sample, entries FROM rustql_histogram"),
+        "items_color_size,blue,2\nitems_color_size,green,1\nitems_color_size,red,4\n"
+    );
+    // Analyzing again replaces them.
+    run(&mut table, "DELETE FROM items WHERE color = 'red'");
+    run(&mut table, "ANALYZE items_color_size");
+    assert_eq!(
+        run(&mut table, "SELECT tbl, idx, stat FROM rustql_stat"),
+        "items,NULL,3 2\nitems,items_color_size,3 3 1.50 1.50\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(*) FROM rustql_histogram"), "2\n");
+    assert_eq!(run(&mut table, "ANALYZE nowhere"), "no such table: nowhere\n");
+    run(&mut table, "DROP TABLE items");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM rustql_stat"), "0\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM rustql_histogram"), "0\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn planner_picks_the_more_selective_index() {
+    let file_name = "analyze_indexes.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    let query = "SELECT id FROM tasks WHERE status = 'done' AND kind = 'k1'";
+    // Without statistics the first index is as good as the other.
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "`--SEARCH tasks USING INDEX tasks_status (status=?)\n"
+    );
+    run(&mut table, "ANALYZE tasks");
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "`--SEARCH tasks USING INDEX tasks_kind (kind=?)\n"
+    );
+    // The histogram knows the open tasks are few.
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT id FROM tasks WHERE status = 'open' AND kind = 'k7'"),
+        "`--SEARCH tasks USING INDEX tasks_status (status=?)\n"
+    );
+    assert_eq!(run(&mut table, "SELECT id FROM tasks WHERE status = 'open' AND kind = 'k7'"), "16\n");
+    // Statistics are loaded when the database is opened again.
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "`--SEARCH tasks USING INDEX tasks_kind (kind=?)\n"
+    );
+    // Rows changed since do not make results wrong, only plans worse.
+    assert_eq!(run(&mut table, "INSERT INTO tasks (status, kind) VALUES ('done', 'k1')"), "Executed.\n");
+    assert_eq!(run(&mut table, &format!("SELECT count(*) FROM ({})", query)), "3\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn planner_joins_by_table_size() {
+    let file_name = "analyze_joins.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE TABLE kinds (name TEXT, label TEXT)");
+    run(&mut table, "INSERT INTO kinds VALUES ('k0', 'zero'), ('k1', 'one'), ('k3', 'three')");
+    let query = "SELECT count(*) FROM tasks t JOIN kinds k ON k.name = t.kind";
+    // Without statistics the tables are taken as the same size.
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "|--SCAN tasks AS t\n`--SEARCH kinds AS k USING AUTOMATIC HASH INDEX (name=?)\n"
+    );
+    run(&mut table, "ANALYZE");
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "|--SCAN kinds AS k\n`--SEARCH tasks AS t USING INDEX tasks_kind (kind=?)\n"
+    );
+    assert_eq!(run(&mut table, query), "6\n");
+    // Seeking the few tasks of each kind beats hashing the kinds, seeking
+    // the many tasks of each status does not.
+    let query = "SELECT count(*) FROM tasks t JOIN kinds k ON k.label = t.status";
+    assert_eq!(
+        run(&mut table, &format!("EXPLAIN QUERY PLAN {}", query)),
+        "|--SCAN tasks AS t\n`--SEARCH kinds AS k USING AUTOMATIC HASH INDEX (label=?)\n"
+    );
+    close_test(table, file_name);
+}
@@ -0,0 +1,37 @@
+// Helpers shared by the integration tests. Each test file uses the ones for
+// the API it tests, the rest is dead code there.
+#![allow(dead_code)]
+
+use rustql::connection::handle::Connection;
+use rustql::table::table::Table;
+use std::fs;
+
+// Opens a fresh database in file_name, removing the file of an earlier run.
+pub fn open_test(file_name: &str) -> Table {
+    let _ = fs::remove_file(file_name);
+    unsafe { Table::db_open(file_name) }
+}
+
+pub fn close_test(mut table: Table, file_name: &str) {
+    unsafe {
+        table.db_close();
+    }
+    let _ = fs::remove_file(file_name);
+}
+
+// Runs a command the way the prompt does, returns what it printed.
+pub fn run(table: &mut Table, command: &str) -> String {
+    let mut result = Vec::new();
+    rustql::run(command.to_string(), table, &mut result);
+    String::from_utf8(result).unwrap()
+}
+
+pub fn open_connection(file_name: &str) -> Connection {
+    let _ = fs::remove_file(file_name);
+    Connection::open(file_name).unwrap()
+}
+
+pub fn close_connection(connection: Connection, file_name: &str) {
+    connection.close().unwrap();
+    let _ = fs::remove_file(file_name);
+}
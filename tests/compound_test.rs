@@ -0,0 +1,99 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE cats (name TEXT, age INTEGER)");
+    run(table, "INSERT INTO cats VALUES ('tom', 3), ('kit', 1), ('max', 3)");
+    run(table, "CREATE TABLE dogs (name TEXT, age INTEGER)");
+    run(table, "INSERT INTO dogs VALUES ('rex', 5), ('max', 3), ('kit', 2), ('max', 3)");
+}
+
+#[test]
+fn union_and_union_all() {
+    let file_name = "compound_union.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM cats UNION ALL SELECT name FROM dogs"),
+        "tom\nkit\nmax\nrex\nmax\nkit\nmax\n"
+    );
+    // Duplicates are removed, 1 and 1.0 are the same value.
+    assert_eq!(run(&mut table, "SELECT name FROM cats UNION SELECT name FROM dogs"), "tom\nkit\nmax\nrex\n");
+    assert_eq!(run(&mut table, "SELECT 1 UNION SELECT 1.0 UNION SELECT NULL UNION SELECT NULL"), "1\nNULL\n");
+    assert_eq!(
+        run(&mut table, "SELECT name, age FROM cats UNION SELECT name, age FROM dogs WHERE age > 2"),
+        "tom,3\nkit,1\nmax,3\nrex,5\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn intersect_and_except() {
+    let file_name = "compound_intersect.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "SELECT name FROM dogs INTERSECT SELECT name FROM cats"), "max\nkit\n");
+    assert_eq!(run(&mut table, "SELECT name, age FROM dogs INTERSECT SELECT name, age FROM cats"), "max,3\n");
+    assert_eq!(run(&mut table, "SELECT name FROM dogs EXCEPT SELECT name FROM cats"), "rex\n");
+    assert_eq!(run(&mut table, "SELECT name, age FROM cats EXCEPT SELECT name, age FROM dogs"), "tom,3\nkit,1\n");
+    // Operators apply left to right.
+    assert_eq!(
+        run(&mut table, "SELECT name FROM cats UNION SELECT name FROM dogs EXCEPT SELECT 'max' INTERSECT SELECT name FROM dogs"),
+        "kit\nrex\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn order_by_and_limit_apply_to_the_result() {
+    let file_name = "compound_order.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name, age FROM cats UNION SELECT name, age FROM dogs ORDER BY age DESC, name"),
+        "rex,5\nmax,3\ntom,3\nkit,2\nkit,1\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name AS n FROM cats UNION ALL SELECT name FROM dogs ORDER BY n LIMIT 3 OFFSET 1"),
+        "kit\nmax\nmax\n"
+    );
+    assert_eq!(run(&mut table, "SELECT age FROM cats UNION SELECT age FROM dogs ORDER BY 1 LIMIT 2"), "1\n2\n");
+    assert_eq!(
+        run(&mut table, "SELECT name FROM cats UNION SELECT name FROM dogs ORDER BY age"),
+        "1st ORDER BY term does not match any column in the result set\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name FROM cats UNION SELECT name FROM dogs ORDER BY 2"),
+        "1st ORDER BY term out of range - should be between 1 and 1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn compound_selects_elsewhere() {
+    let file_name = "compound_elsewhere.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name, age FROM cats UNION SELECT name FROM dogs"),
+        "SELECTs to the left and right of UNION do not have the same number of result columns\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT count(*) FROM (SELECT name FROM cats UNION SELECT name FROM dogs)"),
+        "4\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name FROM dogs WHERE name IN (SELECT 'rex' UNION SELECT 'kit')"),
+        "rex\nkit\n"
+    );
+    run(&mut table, "CREATE TABLE pets (name TEXT)");
+    run(&mut table, "INSERT INTO pets SELECT name FROM cats INTERSECT SELECT name FROM dogs");
+    assert_eq!(run(&mut table, "SELECT * FROM pets"), "kit\nmax\n");
+    assert_eq!(
+        run(&mut table, "WITH young AS (SELECT name FROM cats WHERE age < 2 UNION ALL SELECT name FROM dogs WHERE age < 3) SELECT * FROM young"),
+        "kit\nkit\n"
+    );
+    close_test(table, file_name);
+}
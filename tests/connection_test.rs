@@ -0,0 +1,104 @@
+mod common;
+
+use common::{close_connection, open_connection};
+use rustql::connection::handle::{Connection, ResultSet};
+use rustql::prepared::statement::Step;
+use rustql::table::value::Value;
+use std::fs;
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+#[test]
+fn execute_and_query_return_results() {
+    let file_name = "connection_results.db";
+    let mut connection = open_connection(file_name);
+    assert_eq!(connection.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)").unwrap(), 0);
+    assert_eq!(connection.execute("INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c')").unwrap(), 3);
+    assert_eq!(connection.execute("UPDATE t SET name = upper(name) WHERE id > 1").unwrap(), 2);
+    assert_eq!(connection.execute("DELETE FROM t WHERE id = 3 RETURNING id").unwrap(), 1);
+    assert_eq!(
+        connection.query("SELECT id, name FROM t ORDER BY id").unwrap(),
+        ResultSet {
+            columns: vec!["id".to_string(), "name".to_string()],
+            rows: vec![vec![Value::Integer(1), text("a")], vec![Value::Integer(2), text("B")]],
+        }
+    );
+    let empty = connection.query("DELETE FROM t WHERE id = 5").unwrap();
+    assert!(empty.columns.is_empty() && empty.rows.is_empty());
+
+    let error = connection.execute("SELECT * FROM t").err().unwrap();
+    assert_eq!(error.to_string(), "statement returns rows, use query");
+    let error = connection.query("SELECT * FROM missing").err().unwrap();
+    assert_eq!(error.to_string(), "no such table: missing");
+    close_connection(connection, file_name);
+}
+
+#[test]
+fn dropping_a_connection_saves_the_file() {
+    let file_name = "connection_drop.db";
+    let mut connection = open_connection(file_name);
+    connection.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+    connection.execute("INSERT INTO t VALUES (1, 'kept')").unwrap();
+    drop(connection);
+
+    let mut connection = Connection::open(file_name).unwrap();
+    assert_eq!(connection.query("SELECT name FROM t").unwrap().rows, vec![vec![text("kept")]]);
+    close_connection(connection, file_name);
+}
+
+#[test]
+fn closing_a_connection_saves_the_file() {
+    let file_name = "connection_close.db";
+    let mut connection = open_connection(file_name);
+    connection.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+    connection.execute("INSERT INTO t VALUES (1, 'kept')").unwrap();
+    connection.close().unwrap();
+
+    let mut connection = Connection::open(file_name).unwrap();
+    assert_eq!(connection.query("SELECT name FROM t").unwrap().rows, vec![vec![text("kept")]]);
+    close_connection(connection, file_name);
+}
+
+// Writes to /dev/full fail as when the disk is full.
+#[cfg(target_os = "linux")]
+#[test]
+fn closing_reports_a_failed_write() {
+    let mut connection = Connection::open("/dev/full").unwrap();
+    connection.execute("CREATE TABLE t (id INTEGER PRIMARY KEY)").unwrap();
+    let error = connection.close().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::StorageFull);
+}
+
+#[test]
+fn prepared_statements_and_functions_run_on_a_connection() {
+    let file_name = "connection_prepared.db";
+    let mut connection = open_connection(file_name);
+    connection.create_function("twice", Some(1), |args| Ok(Value::Integer(args[0].as_i64().unwrap_or(0) * 2)));
+    connection.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, n INTEGER)").unwrap();
+    let mut insert = connection.prepare("INSERT INTO t VALUES (?, twice(?1))").unwrap();
+    for id in 1..=3 {
+        insert.bind_int(1, id).unwrap();
+        assert_eq!(connection.step(&mut insert).unwrap(), Step::Done);
+        insert.reset();
+    }
+    let mut select = connection.prepare("SELECT n FROM t WHERE id = :id").unwrap();
+    select.bind_named(":id", Value::Integer(3)).unwrap();
+    assert_eq!(connection.step(&mut select).unwrap(), Step::Row(vec![Value::Integer(6)]));
+    assert_eq!(connection.step(&mut select).unwrap(), Step::Done);
+
+    let mut output = Vec::new();
+    connection.run("SELECT count(*) FROM t", &mut output);
+    assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+    close_connection(connection, file_name);
+}
+
+#[test]
+fn opening_a_damaged_file_fails() {
+    let file_name = "connection_damaged.db";
+    fs::write(file_name, b"not a database").unwrap();
+    let error = Connection::open(file_name).err().unwrap();
+    assert_eq!(error.to_string(), "Db file is not whole no of page size");
+    let _ = fs::remove_file(file_name);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+#[test]
+fn not_null_rejects_missing_values() {
+    let file_name = "constraint_not_null.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)");
+    assert_eq!(run(&mut table, "INSERT INTO users (id) VALUES (1)"), "NOT NULL constraint failed: users.name\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (1, 'ann')"), "Executed.\n");
+    assert_eq!(run(&mut table, "UPDATE users SET name = NULL"), "NOT NULL constraint failed: users.name\n");
+    assert_eq!(run(&mut table, "SELECT * FROM users"), "1,ann\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn unique_rejects_duplicates_but_allows_nulls() {
+    let file_name = "constraint_unique.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE)");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (1, 'a@x')"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (2, 'a@x')"), "UNIQUE constraint failed: users.email\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (3, NULL)"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (4, NULL)"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (1, 'b@x')"), "PRIMARY KEY constraint failed: users.id\n");
+    assert_eq!(run(&mut table, "UPDATE users SET email = 'a@x' WHERE id = 3"), "UNIQUE constraint failed: users.email\n");
+    assert_eq!(run(&mut table, "UPDATE users SET email = 'c@x' WHERE id = 1"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (5, 'a@x')"), "Executed.\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn composite_unique_and_text_primary_key() {
+    let file_name = "constraint_composite.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE pairs (code TEXT PRIMARY KEY, a INTEGER, b INTEGER, UNIQUE (a, b))");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES ('x', 1, 2)"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES ('y', 1, 3)"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES ('z', 1, 2)"), "UNIQUE constraint failed: pairs.a, pairs.b\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES ('x', 5, 5)"), "UNIQUE constraint failed: pairs.code\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES (NULL, 5, 5)"), "NOT NULL constraint failed: pairs.code\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn check_and_default_constraints() {
+    let file_name = "constraint_check_default.db";
+    let mut table = open_test(file_name);
+    run(
+        &mut table,
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, qty INTEGER DEFAULT 1 CHECK (qty > 0), \
+         status TEXT DEFAULT 'new', lo INTEGER, hi INTEGER, CHECK (lo <= hi))",
+    );
+    assert_eq!(run(&mut table, "INSERT INTO items (id) VALUES (1)"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO items (id, qty) VALUES (2, 0)"), "CHECK constraint failed: items.qty\n");
+    assert_eq!(run(&mut table, "INSERT INTO items (id, qty) VALUES (2, NULL)"), "Executed.\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO items (id, lo, hi) VALUES (3, 5, 2)"),
+        "CHECK constraint failed: lo <= hi\n"
+    );
+    assert_eq!(run(&mut table, "SELECT id, qty, status FROM items"), "1,1,new\n2,NULL,new\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn constraints_survive_reopen() {
+    let file_name = "constraint_reopen.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE NOT NULL)");
+    for i in 1..=20 {
+        assert_eq!(run(&mut table, &format!("INSERT INTO users VALUES ({}, 'u{}')", i, i)), "Executed.\n");
+    }
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (21, 'u7')"), "UNIQUE constraint failed: users.email\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (21, NULL)"), "NOT NULL constraint failed: users.email\n");
+    assert_eq!(run(&mut table, "SELECT email FROM users WHERE id = 20"), "u20\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn failed_updates_and_deletes_change_nothing() {
+    let file_name = "constraint_atomic.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT UNIQUE, qty INTEGER CHECK (qty < 10))");
+    run(&mut table, "INSERT INTO t VALUES (1, 'a', 1), (2, 'b', 5), (3, 'c', 8)");
+    assert_eq!(run(&mut table, "UPDATE t SET id = id + 1"), "PRIMARY KEY constraint failed: t.id\n");
+    assert_eq!(run(&mut table, "UPDATE t SET qty = qty + 3"), "CHECK constraint failed: t.qty\n");
+    assert_eq!(run(&mut table, "UPDATE t SET name = 'z' WHERE id > 1"), "UNIQUE constraint failed: t.name\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n2,b,5\n3,c,8\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE name = 'z'"), "");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE name = 'b'"), "2\n");
+
+    run(&mut table, "CREATE TABLE child (t_id INTEGER REFERENCES t(id) ON DELETE RESTRICT)");
+    run(&mut table, "INSERT INTO child VALUES (3)");
+    assert_eq!(run(&mut table, "DELETE FROM t"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n2,b,5\n3,c,8\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE name = 'a'"), "1\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{close_test, open_test, run};
+
+#[test]
+fn with_clause() {
+    let file_name = "cte_with.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE sales (region TEXT, amount INTEGER)");
+    run(&mut table, "INSERT INTO sales VALUES ('north', 10), ('south', 30), ('north', 25), ('east', 5)");
+    assert_eq!(
+        run(
+            &mut table,
+            "WITH totals AS (SELECT region, sum(amount) AS total FROM sales GROUP BY region) SELECT region FROM totals WHERE total > 20 ORDER BY region",
+        ),
+        "north\nsouth\n"
+    );
+    // Later tables can read earlier ones, listed columns rename the results.
+    assert_eq!(
+        run(
+            &mut table,
+            "WITH totals (r, t) AS (SELECT region, sum(amount) FROM sales GROUP BY region), best AS (SELECT max(t) AS top FROM totals) SELECT totals.r FROM totals JOIN best ON totals.t = best.top",
+        ),
+        "north\n"
+    );
+    // A common table hides a stored table of the same name.
+    assert_eq!(run(&mut table, "WITH sales AS (SELECT 1 AS x) SELECT * FROM sales"), "1\n");
+    assert_eq!(
+        run(&mut table, "SELECT count(*) FROM sales WHERE amount > (WITH a AS (SELECT avg(amount) AS v FROM sales) SELECT v FROM a)"),
+        "2\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH t (a, b) AS (SELECT region FROM sales) SELECT * FROM t"),
+        "table t has 1 values for 2 columns\n"
+    );
+    // Common tables are only in scope for the select declaring them.
+    assert_eq!(run(&mut table, "SELECT * FROM (WITH t AS (SELECT 1) SELECT * FROM t), t"), "no such table: t\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn recursive_counter() {
+    let file_name = "cte_counter.db";
+    let mut table = open_test(file_name);
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE n (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 10) SELECT sum(x), count(*) FROM n"),
+        "55,10\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE fib (a, b) AS (SELECT 0, 1 UNION ALL SELECT b, a + b FROM fib WHERE b < 30) SELECT a FROM fib"),
+        "0\n1\n1\n2\n3\n5\n8\n13\n21\n"
+    );
+    // Without a reference to itself the second select runs once.
+    assert_eq!(run(&mut table, "WITH RECURSIVE t AS (SELECT 1 UNION ALL SELECT 2) SELECT * FROM t"), "1\n2\n");
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x, x FROM t) SELECT * FROM t"),
+        "SELECTs to the left and right of UNION ALL do not have the same number of result columns\n"
+    );
+    // A limit ends a recursion that would not end by itself, the rows the
+    // offset skips are still fed back.
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM t LIMIT 5) SELECT * FROM t"),
+        "1\n2\n3\n4\n5\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM t LIMIT 3 OFFSET 2) SELECT * FROM t"),
+        "3\n4\n5\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM t WHERE x < 3 LIMIT 10) SELECT * FROM t"),
+        "1\n2\n3\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM t ORDER BY x) SELECT * FROM t"),
+        "ORDER BY is not supported in recursive table t\n"
+    );
+    // Selects before the recursive one are combined first.
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE t (x) AS (SELECT 1 UNION SELECT 1 UNION ALL SELECT x + 10 FROM t WHERE x < 20) SELECT * FROM t"),
+        "1\n11\n21\n"
+    );
+    // The rows are not stored in the database, they spread over as many
+    // temp trees as they need.
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE c (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 200) SELECT count(*) FROM c"),
+        "200\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE c (x, s) AS (SELECT 1, 'row' UNION ALL SELECT x + 1, 'row ' || x FROM c WHERE x < 3000) SELECT count(*), max(x), min(s) FROM c"),
+        "3000,3000,row\n"
+    );
+    // A select reading the first rows of the table alone stops the
+    // recursion once it has them.
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE r (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM r) SELECT * FROM r LIMIT 3"),
+        "1\n2\n3\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE r (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM r) SELECT x * 2 FROM r LIMIT 2 OFFSET 3"),
+        "8\n10\n"
+    );
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE r (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM r LIMIT 5 OFFSET 1) SELECT * FROM r LIMIT 10"),
+        "2\n3\n4\n5\n6\n"
+    );
+    // Otherwise a recursion that never ends fails once the temp trees are
+    // full.
+    assert_eq!(
+        run(&mut table, "WITH RECURSIVE r (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM r) SELECT count(*) FROM r"),
+        "Table is full\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn recursive_hierarchy() {
+    let file_name = "cte_hierarchy.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE staff (id INTEGER PRIMARY KEY, name TEXT, boss INTEGER)");
+    run(
+        &mut table,
+        "INSERT INTO staff VALUES (1, 'ceo', NULL), (2, 'cto', 1), (3, 'cfo', 1), (4, 'dev', 2), (5, 'ops', 2), (6, 'intern', 4)",
+    );
+    // Rows are fed back in the order they were added, breadth first.
+    assert_eq!(
+        run(
+            &mut table,
+            "WITH RECURSIVE chart (id, name, level) AS (SELECT id, name, 0 FROM staff WHERE boss IS NULL UNION ALL SELECT staff.id, staff.name, chart.level + 1 FROM staff JOIN chart ON staff.boss = chart.id) SELECT name, level FROM chart",
+        ),
+        "ceo,0\ncto,1\ncfo,1\ndev,2\nops,2\nintern,3\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "WITH RECURSIVE up (id, boss) AS (SELECT id, boss FROM staff WHERE name = 'intern' UNION ALL SELECT staff.id, staff.boss FROM up, staff WHERE staff.id = up.boss) SELECT id FROM up",
+        ),
+        "6\n4\n2\n1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn recursive_union_discards_duplicates() {
+    let file_name = "cte_union.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE edges (a INTEGER, b INTEGER)");
+    run(&mut table, "INSERT INTO edges VALUES (1, 2), (2, 3), (3, 1), (3, 4), (5, 6)");
+    // The cycle 1 > 2 > 3 > 1 ends once no new node is reached.
+    assert_eq!(
+        run(
+            &mut table,
+            "WITH RECURSIVE reach (node) AS (SELECT 1 UNION SELECT edges.b FROM edges JOIN reach ON edges.a = reach.node) SELECT node FROM reach ORDER BY node",
+        ),
+        "1\n2\n3\n4\n"
+    );
+    assert_eq!(run(&mut table, "WITH t AS (SELECT a FROM edges UNION SELECT b FROM edges) SELECT count(*) FROM t"), "6\n");
+    assert_eq!(run(&mut table, "WITH t AS (SELECT a FROM edges UNION ALL SELECT b FROM edges) SELECT count(*) FROM t"), "10\n");
+    close_test(table, file_name);
+}
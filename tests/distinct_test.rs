@@ -0,0 +1,146 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+use rustql::table::value::Value;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE visits (id INTEGER PRIMARY KEY, page TEXT, user TEXT, ms REAL)");
+    run(
+        table,
+        "INSERT INTO visits (page, user, ms) VALUES ('home', 'ann', 10), ('docs', 'bob', 20), ('home', 'bob', 10.0), ('home', 'ann', 30), ('faq', NULL, NULL), ('faq', NULL, 5)",
+    );
+}
+
+#[test]
+fn select_distinct() {
+    let file_name = "distinct_select.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "SELECT DISTINCT page FROM visits"), "home\ndocs\nfaq\n");
+    assert_eq!(run(&mut table, "SELECT DISTINCT page, user FROM visits"), "home,ann\ndocs,bob\nhome,bob\nfaq,NULL\n");
+    // 10 and 10.0 are the same value.
+    assert_eq!(run(&mut table, "SELECT DISTINCT ms FROM visits WHERE ms <= 10"), "10.0\n5.0\n");
+    assert_eq!(run(&mut table, "SELECT ALL page FROM visits WHERE user = 'ann'"), "home\nhome\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn distinct_with_order_by_and_limit() {
+    let file_name = "distinct_order.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "SELECT DISTINCT page FROM visits ORDER BY page"), "docs\nfaq\nhome\n");
+    // LIMIT and OFFSET count distinct rows.
+    assert_eq!(run(&mut table, "SELECT DISTINCT user FROM visits ORDER BY user DESC LIMIT 2 OFFSET 1"), "ann\nNULL\n");
+    assert_eq!(run(&mut table, "SELECT DISTINCT page FROM visits ORDER BY id DESC LIMIT 2"), "faq\nhome\n");
+    assert_eq!(
+        run(&mut table, "SELECT DISTINCT v.page, u.user FROM visits v JOIN visits u ON u.page = v.page WHERE u.user IS NOT NULL"),
+        "home,ann\nhome,bob\ndocs,bob\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn distinct_over_primary_key() {
+    let file_name = "distinct_primary_key.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "SELECT DISTINCT id, page FROM visits WHERE page = 'home'"), "1,home\n3,home\n4,home\n");
+    assert_eq!(run(&mut table, "SELECT DISTINCT * FROM visits WHERE id > 4"), "5,faq,NULL,NULL\n6,faq,NULL,5.0\n");
+    run(&mut table, "CREATE TABLE pairs (a TEXT, b TEXT, c INTEGER, PRIMARY KEY (a, b))");
+    run(&mut table, "INSERT INTO pairs VALUES ('x', 'y', 1), ('x', 'z', 1), ('y', 'y', 1)");
+    assert_eq!(run(&mut table, "SELECT DISTINCT b, a FROM pairs"), "y,x\nz,x\ny,y\n");
+    assert_eq!(run(&mut table, "SELECT DISTINCT a FROM pairs"), "x\ny\n");
+    assert_eq!(run(&mut table, "SELECT DISTINCT c FROM pairs"), "1\n");
+    // A UNIQUE key is one when its columns are NOT NULL, a part of a key is
+    // not.
+    run(&mut table, "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE, nick TEXT UNIQUE)");
+    run(&mut table, "INSERT INTO users VALUES (1, 'a@x', NULL), (2, 'b@x', NULL)");
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT email FROM users"), "`--SCAN users\n");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT nick FROM users"),
+        "|--SCAN users\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    assert_eq!(run(&mut table, "SELECT DISTINCT nick FROM users"), "NULL\n");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT a FROM pairs"),
+        "|--SCAN pairs\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn distinct_aggregates() {
+    let file_name = "distinct_aggregates.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT count(DISTINCT page), count(DISTINCT user), count(user), count(*) FROM visits"),
+        "3,2,4,6\n"
+    );
+    assert_eq!(run(&mut table, "SELECT sum(DISTINCT ms), avg(DISTINCT ms), sum(ms) FROM visits"), "65.0,16.25,75.0\n");
+    assert_eq!(
+        run(&mut table, "SELECT page, count(DISTINCT user) AS users FROM visits GROUP BY page ORDER BY users DESC, page"),
+        "home,2\ndocs,1\nfaq,0\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(DISTINCT page, user) FROM visits"), "DISTINCT aggregates must have exactly one argument\n");
+    assert_eq!(
+        run(&mut table, "SELECT upper(DISTINCT page) FROM visits"),
+        "DISTINCT is not allowed in call to non-aggregate function upper()\n"
+    );
+    // Registered aggregates are only stepped once per distinct value.
+    table.create_aggregate("steps", Some(1), || 0i64, |steps, _| {
+        *steps += 1;
+        Ok(())
+    }, |steps| Ok(Value::Integer(steps)));
+    assert_eq!(run(&mut table, "SELECT steps(page), steps(DISTINCT page) FROM visits"), "6,3\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn distinct_through_index() {
+    let file_name = "distinct_index.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE hits (id INTEGER PRIMARY KEY, page TEXT NOT NULL, user TEXT NOT NULL, ms REAL)");
+    run(&mut table, "CREATE INDEX hits_page ON hits (page)");
+    run(&mut table, "CREATE INDEX hits_page_user ON hits (page, user)");
+    let rows: Vec<String> = (0..15).map(|i| format!("('p{}', 'u{}', {})", i % 5, i % 2, i)).collect();
+    run(&mut table, &format!("INSERT INTO hits (page, user, ms) VALUES {}", rows.join(", ")));
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT page FROM hits"),
+        "`--SCAN hits USING INDEX hits_page\n"
+    );
+    let distinct = |select: &str| format!("SELECT count(*), min(page), max(page) FROM ({})", select);
+    assert_eq!(run(&mut table, &distinct("SELECT DISTINCT page FROM hits")), "5,p0,p4\n");
+    assert_eq!(run(&mut table, &distinct("SELECT DISTINCT h.user, page FROM hits h")), "10,p0,p4\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM (SELECT DISTINCT page FROM hits LIMIT 3)"), "3\n");
+    run(&mut table, "DELETE FROM hits WHERE page = 'p0' OR id % 7 = 0");
+    assert_eq!(run(&mut table, &distinct("SELECT DISTINCT page FROM hits")), "4,p1,p4\n");
+    // A WHERE clause, or a column outside the index, leaves it to the hash
+    // table, and so does a prefix of an index.
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT page FROM hits WHERE ms > 3"),
+        "|--SCAN hits\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT page, ms FROM hits"),
+        "|--SCAN hits\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    run(&mut table, "CREATE TABLE pairs (a TEXT NOT NULL, b TEXT NOT NULL)");
+    run(&mut table, "CREATE INDEX pairs_ab ON pairs (a, b)");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT a FROM pairs"),
+        "|--SCAN pairs\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT DISTINCT b, a FROM pairs"),
+        "`--SCAN pairs USING INDEX pairs_ab\n"
+    );
+    // Keys are equal like values are, 10 and 10.0 are one.
+    run(&mut table, "CREATE TABLE m (v NOT NULL)");
+    run(&mut table, "CREATE INDEX m_v ON m (v)");
+    run(&mut table, "INSERT INTO m VALUES (10), (10.0), ('10'), (10)");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM (SELECT DISTINCT v FROM m)"), "2\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+use std::fs;
+
+fn fill(table: &mut Table, name: &str, rows: usize) {
+    run(table, &format!("CREATE TABLE {} (id INTEGER PRIMARY KEY, name TEXT UNIQUE, note TEXT)", name));
+    for i in 1..=rows {
+        run(table, &format!("INSERT INTO {} (name, note) VALUES ('n{}', 'some text {}')", name, i, i));
+    }
+}
+
+fn file_pages(file_name: &str) -> u64 {
+    fs::metadata(file_name).unwrap().len() / 4096
+}
+
+#[test]
+fn drop_table_removes_it() {
+    let file_name = "drop_table_basic.db";
+    let mut table = open_test(file_name);
+    fill(&mut table, "t", 3);
+    run(&mut table, "CREATE INDEX t_note ON t (note)");
+    run(&mut table, "CREATE TRIGGER t_log AFTER INSERT ON t BEGIN SELECT 1; END");
+    assert_eq!(run(&mut table, "DROP TABLE t"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "no such table: t\n");
+    assert_eq!(run(&mut table, "DROP TABLE t"), "no such table: t\n");
+    assert_eq!(run(&mut table, "DROP TABLE IF EXISTS t"), "Executed.\n");
+    // Its indexes and triggers went with it, so the names are free again.
+    fill(&mut table, "t", 2);
+    assert_eq!(run(&mut table, "CREATE INDEX t_note ON t (note)"), "Executed.\n");
+    assert_eq!(
+        run(&mut table, "CREATE TRIGGER t_log AFTER INSERT ON t BEGIN SELECT 1; END"),
+        "Executed.\n"
+    );
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE note = 'some text 2'"), "n2\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn dropped_pages_are_reused() {
+    let file_name = "drop_table_reuse.db";
+    let mut table = open_test(file_name);
+    fill(&mut table, "a", 30);
+    unsafe {
+        table.db_close();
+    }
+    let pages = file_pages(file_name);
+    let mut table = unsafe { Table::db_open(file_name) };
+    run(&mut table, "DROP TABLE a");
+    // The free list survives reopening the file.
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    fill(&mut table, "b", 30);
+    assert_eq!(run(&mut table, "SELECT count(*), max(name) FROM b"), "30,n9\n");
+    unsafe {
+        table.db_close();
+    }
+    assert_eq!(file_pages(file_name), pages);
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "SELECT note FROM b WHERE id = 17"), "some text 17\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn dropping_makes_room_in_a_full_file() {
+    let file_name = "drop_table_full.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE big (id INTEGER PRIMARY KEY, note TEXT)");
+    let mut inserted = 0;
+    while run(&mut table, "INSERT INTO big (note) VALUES ('x')") == "Executed.\n" {
+        inserted += 1;
+    }
+    assert_eq!(run(&mut table, "INSERT INTO big (note) VALUES ('x')"), "Table is full\n");
+    run(&mut table, "DROP TABLE big");
+    run(&mut table, "CREATE TABLE big (id INTEGER PRIMARY KEY, note TEXT)");
+    for _ in 0..inserted {
+        assert_eq!(run(&mut table, "INSERT INTO big (note) VALUES ('y')"), "Executed.\n");
+    }
+    assert_eq!(run(&mut table, "SELECT count(*) FROM big WHERE note = 'y'"), format!("{}\n", inserted));
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_table_with_foreign_keys() {
+    let file_name = "drop_table_foreign_keys.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "PRAGMA foreign_keys = ON");
+    run(&mut table, "CREATE TABLE parent (id INTEGER PRIMARY KEY)");
+    run(&mut table, "CREATE TABLE child (id INTEGER PRIMARY KEY, parent_id INTEGER REFERENCES parent (id))");
+    run(
+        &mut table,
+        "CREATE TABLE owned (id INTEGER PRIMARY KEY, parent_id INTEGER REFERENCES parent (id) ON DELETE CASCADE)",
+    );
+    run(&mut table, "INSERT INTO parent VALUES (1), (2)");
+    run(&mut table, "INSERT INTO owned (parent_id) VALUES (1), (2)");
+    run(&mut table, "INSERT INTO child (parent_id) VALUES (2)");
+    assert_eq!(run(&mut table, "DROP TABLE parent"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM parent"), "2\n");
+    run(&mut table, "DELETE FROM child");
+    assert_eq!(run(&mut table, "DROP TABLE parent"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM owned"), "0\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_table_errors() {
+    let file_name = "drop_table_errors.db";
+    let mut table = open_test(file_name);
+    fill(&mut table, "t", 1);
+    run(&mut table, "CREATE VIEW v AS SELECT name FROM t");
+    assert_eq!(run(&mut table, "DROP TABLE v"), "use DROP VIEW to delete view v\n");
+    assert_eq!(run(&mut table, "DROP TABLE"), "incomplete input\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{close_test, open_test, run};
+
+#[test]
+fn arithmetic_and_concatenation() {
+    let file_name = "expression_arithmetic.db";
+    let mut table = open_test(file_name);
+    assert_eq!(run(&mut table, "SELECT 1 + 2 * 3, (1 + 2) * 3, 7 / 2, 7 % 3, 7.0 / 2"), "7,9,3,1,3.5\n");
+    assert_eq!(run(&mut table, "SELECT 1 / 0, 5 % 0, 1 + NULL, -(2 - 5)"), "NULL,NULL,NULL,3\n");
+    assert_eq!(run(&mut table, "SELECT '3' + 4, 'abc' * 2, 9223372036854775807 + 1"), "7,0,9223372036854775808.0\n");
+    assert_eq!(run(&mut table, "SELECT 'a' || 'b' || 1, 'x' || NULL"), "ab1,NULL\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn in_between_and_three_valued_logic() {
+    let file_name = "expression_logic.db";
+    let mut table = open_test(file_name);
+    assert_eq!(run(&mut table, "SELECT 2 IN (1, 2), 3 NOT IN (1, 2), 3 IN (1, NULL), NULL IN (), 3 NOT IN (1, NULL)"), "1,1,NULL,0,NULL\n");
+    assert_eq!(run(&mut table, "SELECT 5 BETWEEN 1 AND 10, 5 NOT BETWEEN 1 AND 4, 5 BETWEEN NULL AND 4, 5 BETWEEN NULL AND 9"), "1,1,0,NULL\n");
+    assert_eq!(run(&mut table, "SELECT NULL AND 0, NULL OR 1, NOT NULL, NULL = NULL, NULL IS NULL"), "0,1,NULL,NULL,1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn like_glob_and_case() {
+    let file_name = "expression_like.db";
+    let mut table = open_test(file_name);
+    assert_eq!(run(&mut table, "SELECT 'Hello' LIKE 'h%o', 'Hello' LIKE 'h_llo', 'Hello' NOT LIKE '%z%'"), "1,1,1\n");
+    assert_eq!(run(&mut table, "SELECT '10%' LIKE '10!%' ESCAPE '!', '100' LIKE '10!%' ESCAPE '!'"), "1,0\n");
+    assert_eq!(run(&mut table, "SELECT 'Hello' GLOB 'H*o', 'Hello' GLOB 'h*', 'b7' GLOB '[a-c][0-9]', 'd7' GLOB '[^a-c]?'"), "1,0,1,1\n");
+    assert_eq!(run(&mut table, "SELECT CASE WHEN 1 > 2 THEN 'a' WHEN 2 > 1 THEN 'b' END, CASE 3 WHEN 1 THEN 'one' ELSE 'many' END"), "b,many\n");
+    assert_eq!(run(&mut table, "SELECT CASE NULL WHEN NULL THEN 1 ELSE 0 END, CASE WHEN 0 THEN 1 END"), "0,NULL\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn expressions_in_where_set_and_check() {
+    let file_name = "expression_statements.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price REAL, qty INTEGER CHECK (qty * price < 1000))");
+    run(&mut table, "INSERT INTO items VALUES (1, 'bolt', 0.5, 100)");
+    run(&mut table, "INSERT INTO items VALUES (2, 'nut', 0.25, 40)");
+    run(&mut table, "INSERT INTO items VALUES (3, 'gear', 12.0, 3)");
+    assert_eq!(run(&mut table, "SELECT name, price * qty AS total FROM items WHERE name LIKE '%t' ORDER BY total"), "nut,10.0\nbolt,50.0\n");
+    run(&mut table, "UPDATE items SET qty = qty * 2, name = name || '!' WHERE id BETWEEN 2 AND 3");
+    assert_eq!(run(&mut table, "SELECT id, name, qty FROM items WHERE qty % 2 = 0 AND id > 1"), "2,nut!,80\n3,gear!,6\n");
+    assert_eq!(
+        run(&mut table, "UPDATE items SET qty = qty * 100 WHERE id = 3"),
+        "CHECK constraint failed: items.qty\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn comparisons_apply_column_affinity() {
+    let file_name = "expression_affinity.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, a INTEGER, b TEXT, c)");
+    run(&mut table, "INSERT INTO t VALUES (1, 2, '2', '2')");
+    run(&mut table, "INSERT INTO t VALUES (2, 3, '10', 3)");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE a = '2'"), "1\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE b = 2"), "1\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE c = 2"), "");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE id = '2'"), "2\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE a IN ('2', '3') AND a BETWEEN '1' AND '2.5'"), "1\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE b > 9"), "");
+    assert_eq!(run(&mut table, "SELECT x.id, y.id FROM t x JOIN t y ON x.a = y.b"), "1,1\n");
+    run(&mut table, "CREATE INDEX t_a ON t (a)");
+    run(&mut table, "CREATE INDEX t_b ON t (b)");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE a = '3'"), "2\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE b = 10"), "2\n");
+    assert_eq!(run(&mut table, "SELECT x.id, y.id FROM t x JOIN t y ON x.a = y.b"), "1,1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn expression_depth_is_limited() {
+    let file_name = "expression_depth.db";
+    let mut table = open_test(file_name);
+    let too_large = "Expression tree is too large (maximum depth 1000)\n";
+    let sum = |terms: usize| format!("SELECT {}", vec!["1"; terms].join(" + "));
+    assert_eq!(run(&mut table, &sum(100)), "100\n");
+    assert_eq!(run(&mut table, &sum(1001)), too_large);
+    assert_eq!(run(&mut table, &sum(50000)), too_large);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY)");
+    run(&mut table, "INSERT INTO t VALUES (7)");
+    let ids: Vec<String> = (1..=2000).map(|id| format!("id = {}", id)).collect();
+    assert_eq!(run(&mut table, &format!("SELECT id FROM t WHERE {}", ids[..100].join(" OR "))), "7\n");
+    assert_eq!(run(&mut table, &format!("SELECT id FROM t WHERE {}", ids.join(" OR "))), too_large);
+    let nested = |depth: usize| format!("SELECT {}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert_eq!(run(&mut table, &nested(50)), "1\n");
+    assert_eq!(run(&mut table, &nested(1000)), "parser stack overflow\n");
+    assert_eq!(run(&mut table, &format!("SELECT {}1", "NOT ".repeat(1000))), "parser stack overflow\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn create_music_tables(table: &mut Table) {
+    run(table, "CREATE TABLE artist (id INTEGER PRIMARY KEY, name TEXT)");
+    run(
+        table,
+        "CREATE TABLE track (id INTEGER PRIMARY KEY, title TEXT, \
+         artist_id INTEGER REFERENCES artist(id) ON DELETE CASCADE ON UPDATE CASCADE)",
+    );
+    run(table, "INSERT INTO artist VALUES (1, 'first')");
+    run(table, "INSERT INTO artist VALUES (2, 'second')");
+    run(table, "INSERT INTO track VALUES (1, 'one', 1)");
+    run(table, "INSERT INTO track VALUES (2, 'two', 1)");
+    run(table, "INSERT INTO track VALUES (3, 'three', 2)");
+}
+
+#[test]
+fn child_rows_need_a_parent() {
+    let file_name = "foreign_key_parent.db";
+    let mut table = open_test(file_name);
+    create_music_tables(&mut table);
+    assert_eq!(run(&mut table, "INSERT INTO track VALUES (4, 'four', 9)"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "INSERT INTO track VALUES (4, 'four', NULL)"), "Executed.\n");
+    assert_eq!(run(&mut table, "UPDATE track SET artist_id = 9 WHERE id = 1"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "UPDATE track SET artist_id = 2 WHERE id = 1"), "Executed.\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn delete_and_update_cascade() {
+    let file_name = "foreign_key_cascade.db";
+    let mut table = open_test(file_name);
+    create_music_tables(&mut table);
+    assert_eq!(run(&mut table, "UPDATE artist SET id = 10 WHERE id = 1"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id, artist_id FROM track"), "1,10\n2,10\n3,2\n");
+    assert_eq!(run(&mut table, "DELETE FROM artist WHERE id = 10"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id, artist_id FROM track"), "3,2\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn restrict_and_set_null() {
+    let file_name = "foreign_key_restrict.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE team (code TEXT PRIMARY KEY)");
+    run(
+        &mut table,
+        "CREATE TABLE player (name TEXT, team TEXT, FOREIGN KEY (team) REFERENCES team ON DELETE RESTRICT ON UPDATE SET NULL)",
+    );
+    run(&mut table, "INSERT INTO team VALUES ('red')");
+    run(&mut table, "INSERT INTO team VALUES ('blue')");
+    run(&mut table, "INSERT INTO player VALUES ('ann', 'red')");
+    assert_eq!(run(&mut table, "INSERT INTO player VALUES ('bob', 'green')"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "DELETE FROM team WHERE code = 'red'"), "FOREIGN KEY constraint failed\n");
+    assert_eq!(run(&mut table, "DELETE FROM team WHERE code = 'blue'"), "Executed.\n");
+    assert_eq!(run(&mut table, "UPDATE team SET code = 'crimson'"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM player"), "ann,NULL\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn pragma_toggles_enforcement_and_check_lists_violations() {
+    let file_name = "foreign_key_pragma.db";
+    let mut table = open_test(file_name);
+    create_music_tables(&mut table);
+    assert_eq!(run(&mut table, "PRAGMA foreign_keys"), "1\n");
+    assert_eq!(run(&mut table, "PRAGMA foreign_keys = OFF"), "Executed.\n");
+    assert_eq!(run(&mut table, "PRAGMA foreign_keys"), "0\n");
+    assert_eq!(run(&mut table, "INSERT INTO track VALUES (4, 'four', 9)"), "Executed.\n");
+    assert_eq!(run(&mut table, "DELETE FROM artist WHERE id = 2"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id FROM track"), "1\n2\n3\n4\n");
+    assert_eq!(run(&mut table, "PRAGMA foreign_key_check"), "track,3,artist,0\ntrack,4,artist,0\n");
+    assert_eq!(run(&mut table, "PRAGMA foreign_key_check(artist)"), "");
+    assert_eq!(run(&mut table, "PRAGMA foreign_keys = ON"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO track VALUES (5, 'five', 9)"), "FOREIGN KEY constraint failed\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn delete_removes_rows_and_index_entries() {
+    let file_name = "foreign_key_delete.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE)");
+    for i in 1..=30 {
+        run(&mut table, &format!("INSERT INTO users VALUES ({}, 'u{}')", i, i));
+    }
+    assert_eq!(run(&mut table, "DELETE FROM users WHERE id > 3"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id FROM users"), "1\n2\n3\n");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (4, 'u10')"), "Executed.\n");
+    assert_eq!(run(&mut table, "DELETE FROM users"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id FROM users"), "");
+    assert_eq!(run(&mut table, "INSERT INTO users VALUES (1, 'u1')"), "Executed.\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::{close_test, open_test, run};
+
+#[test]
+fn string_functions() {
+    let file_name = "function_string.db";
+    let mut table = open_test(file_name);
+    assert_eq!(run(&mut table, "SELECT lower('AbC'), upper('AbC'), length('héllo'), length(1234), length(NULL)"), "abc,ABC,5,4,NULL\n");
+    assert_eq!(run(&mut table, "SELECT substr('abcdef', 2, 3), substr('abcdef', -2), substr('abcdef', 3), substr('abcdef', 4, -2)"), "bcd,ef,cdef,bc\n");
+    assert_eq!(
+        run(&mut table, "SELECT substr('hello', 9223372036854775807, 9223372036854775807), substr('hello', 2, 9223372036854775807), substr('hello', -9223372036854775808, 9223372036854775807)"),
+        ",ello,hell\n"
+    );
+    assert_eq!(run(&mut table, "SELECT trim('  x  '), ltrim('xxy', 'x'), rtrim('yxx', 'x'), replace('a-b-c', '-', '+'), instr('hello', 'll')"), "x,y,y,a+b+c,3\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn math_and_null_functions() {
+    let file_name = "function_math.db";
+    let mut table = open_test(file_name);
+    assert_eq!(run(&mut table, "SELECT abs(-3), abs(-2.5), round(2.5), round(3.14159, 2), round(-2.5)"), "3,2.5,3.0,3.14,-3.0\n");
+    assert_eq!(run(&mut table, "SELECT min(3, 1, 2), max('a', 'b'), max(1, NULL)"), "1,b,NULL\n");
+    assert_eq!(run(&mut table, "SELECT coalesce(NULL, NULL, 7), ifnull(NULL, 'x'), nullif(1, 1), nullif(1, 2), typeof(1.5)"), "7,x,NULL,1,real\n");
+    assert_eq!(run(&mut table, "SELECT nosuch(1)"), "no such function: nosuch\n");
+    assert_eq!(run(&mut table, "SELECT lower('a', 'b')"), "wrong number of arguments to function lower()\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn date_and_time_functions() {
+    let file_name = "function_date.db";
+    let mut table = open_test(file_name);
+    assert_eq!(
+        run(&mut table, "SELECT date('2024-02-28 13:45:10'), time('2024-02-28T13:45:10'), datetime('2024-02-28', '+1 day')"),
+        "2024-02-28,13:45:10,2024-02-29 00:00:00\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT datetime(1700000000, 'unixepoch'), unixepoch('2023-11-14 22:13:20'), date(2460000.5)"),
+        "2023-11-14 22:13:20,1700000000,2023-02-25\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT date('2024-01-31', '+1 month'), date('2024-05-17', 'start of month'), date('2024-05-17', 'start of year', '-1 days')"),
+        "2024-03-02,2024-05-01,2023-12-31\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT strftime('%Y/%m/%d %H:%M %j %w %s', '2024-03-01 08:05:00'), strftime('%f', '12:00:01.250')"),
+        "2024/03/01 08:05 061 5 1709280300,01.250\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT strftime('%W', '2024-01-01'), strftime('%W', '2023-01-01'), strftime('%W', '2024-12-31'), strftime('%W %w', '2024-03-03')"),
+        "01,00,53,09 0\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT date('2024-01-31', 'start of month', '-1 day', 'weekday 0'), date('2024-03-01', 'weekday 1'), datetime('2024-03-01 08:05:00', 'weekday 5')"),
+        "2023-12-31,2024-03-04,2024-03-01 08:05:00\n"
+    );
+    assert_eq!(run(&mut table, "SELECT date('2024-03-01', 'weekday 7'), date('2024-03-01', 'weekday x')"), "NULL,NULL\n");
+    assert_eq!(run(&mut table, "SELECT date('not a date'), date('2024-01-01', 'sideways'), datetime('2024-01-01 10:00+02:00')"), "NULL,NULL,2024-01-01 08:00:00\n");
+    // Moments past the years SQLite supports are NULL, however far the
+    // modifiers go.
+    assert_eq!(
+        run(&mut table, "SELECT date('2024-01-01', '+999999999999 days'), date('2024-01-01', '-9999999999999999999 seconds'), date('2024-01-01', '+9223372036854775807 months'), date('2024-01-01', '+1e300 years')"),
+        "NULL,NULL,NULL,NULL\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT date('9999-12-31', '+1 day'), date('9999-12-31', '+1 day', '-1 day'), unixepoch(1e20, 'unixepoch'), julianday(-1)"),
+        "NULL,NULL,NULL,NULL\n"
+    );
+    assert_eq!(run(&mut table, "SELECT date('9999-12-01', '+30 days'), datetime(0), julianday('2024-01-01', '+7999 years')"), "9999-12-31,NULL,NULL\n");
+    assert_eq!(run(&mut table, "SELECT length(datetime('now'))"), "19\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn functions_in_where_and_update() {
+    let file_name = "function_statements.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE events (id INTEGER PRIMARY KEY, title TEXT, at TEXT)");
+    run(&mut table, "INSERT INTO events VALUES (1, ' Launch ', '2024-01-05 10:00:00')");
+    run(&mut table, "INSERT INTO events VALUES (2, 'review', '2024-02-11 09:30:00')");
+    run(&mut table, "UPDATE events SET title = upper(trim(title)), at = datetime(at, '+7 days') WHERE id = 1");
+    assert_eq!(
+        run(&mut table, "SELECT title, date(at) FROM events WHERE strftime('%m', at) = '01' OR length(title) > 6"),
+        "LAUNCH,2024-01-12\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(*), max(length(title)) FROM events"), "2,6\n");
+    close_test(table, file_name);
+}
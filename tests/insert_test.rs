@@ -0,0 +1,124 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+#[test]
+fn multi_row_values() {
+    let file_name = "insert_multi_row.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE colors (id INTEGER PRIMARY KEY, name TEXT, hex TEXT DEFAULT '000000')");
+    run(&mut table, "INSERT INTO colors (name, hex) VALUES ('red', 'ff0000'), ('green', '00ff00'), ('blue', '0000ff')");
+    run(&mut table, "INSERT INTO colors (name) VALUES ('black'), (lower('WHITE'))");
+    assert_eq!(
+        run(&mut table, "SELECT * FROM colors"),
+        "1,red,ff0000\n2,green,00ff00\n3,blue,0000ff\n4,black,000000\n5,white,000000\n"
+    );
+    run(&mut table, "INSERT INTO colors (hex, id, name) VALUES ('808080', 10, 'grey'), ('ffff00', 7, 'yellow')");
+    assert_eq!(run(&mut table, "SELECT id, name FROM colors WHERE id > 5"), "7,yellow\n10,grey\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn multi_row_errors_store_nothing_for_bad_counts() {
+    let file_name = "insert_multi_row_errors.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE pairs (a INTEGER, b INTEGER)");
+    assert_eq!(run(&mut table, "INSERT INTO pairs VALUES (1, 2), (3)"), "1 values for 2 columns\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs (a) VALUES (1), (2, 3)"), "2 values for 1 columns\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM pairs"), "0\n");
+    assert_eq!(run(&mut table, "INSERT INTO pairs (c) VALUES (1)"), "no such column: c\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn insert_select() {
+    let file_name = "insert_select.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, total INTEGER)");
+    run(&mut table, "INSERT INTO orders (customer, total) VALUES ('ann', 10), ('bob', 25), ('ann', 5)");
+    run(&mut table, "CREATE TABLE totals (customer TEXT UNIQUE, spent INTEGER, note TEXT DEFAULT 'copied')");
+    run(
+        &mut table,
+        "INSERT INTO totals (customer, spent) SELECT customer, sum(total) FROM orders GROUP BY customer ORDER BY customer",
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM totals"), "ann,15,copied\nbob,25,copied\n");
+    // The query runs before any row is stored, so it does not see them.
+    run(&mut table, "INSERT INTO orders (customer, total) SELECT customer, total * 2 FROM orders");
+    assert_eq!(run(&mut table, "SELECT count(*), sum(total) FROM orders"), "6,120\n");
+    assert_eq!(run(&mut table, "INSERT INTO totals SELECT customer, total FROM orders"), "2 values for 3 columns\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO totals SELECT customer, total, 'dup' FROM orders WHERE id = 1"),
+        "UNIQUE constraint failed: totals.customer\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn bulk_insert_keeps_tree_order() {
+    let file_name = "insert_bulk.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE numbers (id INTEGER PRIMARY KEY, square INTEGER)");
+    let values: Vec<String> = (1..=30).map(|i| format!("({}, {})", i * 2, i * i)).collect();
+    run(&mut table, &format!("INSERT INTO numbers VALUES {}", values.join(", ")));
+    // Rowids below the largest one cannot be appended to the last leaf.
+    run(&mut table, "INSERT INTO numbers VALUES (61, 0), (1, 0), (33, 0), (62, 0)");
+    run(&mut table, "INSERT INTO numbers (square) SELECT square FROM numbers WHERE id < 10");
+    assert_eq!(run(&mut table, "SELECT count(*), max(id) FROM numbers"), "39,67\n");
+    assert_eq!(
+        run(&mut table, "SELECT id FROM numbers WHERE id < 8 OR id > 58"),
+        "1\n2\n4\n6\n60\n61\n62\n63\n64\n65\n66\n67\n"
+    );
+    // A failing statement is undone, the rows before the failing one too.
+    assert_eq!(run(&mut table, "INSERT INTO numbers VALUES (70, 0), (33, 1)"), "PRIMARY KEY constraint failed: numbers.id\n");
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "SELECT count(*), max(id) FROM numbers"), "39,67\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn failed_multi_row_insert_leaves_the_table_unchanged() {
+    let file_name = "insert_atomic.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL)");
+    run(&mut table, "CREATE INDEX t_name ON t (name)");
+    run(&mut table, "CREATE TABLE log (entry TEXT)");
+    run(&mut table, "CREATE TRIGGER t_log AFTER INSERT ON t BEGIN INSERT INTO log VALUES (NEW.name); END");
+    assert_eq!(run(&mut table, "INSERT INTO t VALUES (1, 'x')"), "Executed.\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO t VALUES (2, 'a'), (3, NULL), (4, 'c')"),
+        "NOT NULL constraint failed: t.name\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO t (name) SELECT entry || '!' FROM log UNION ALL SELECT NULL"),
+        "NOT NULL constraint failed: t.name\n"
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,x\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE name = 'a'"), "");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "x\n");
+    assert_eq!(run(&mut table, "INSERT INTO t VALUES (2, 'a'), (3, 'b')"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,x\n2,a\n3,b\n");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "x\na\nb\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn inserts_after_a_failed_statement_that_split_pages() {
+    let file_name = "insert_after_split.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, v TEXT UNIQUE)");
+    let values: Vec<String> = (0..12).map(|i| format!("('r{}')", i)).collect();
+    assert_eq!(
+        run(&mut table, &format!("INSERT INTO t (v) VALUES {}, ('r0')", values.join(", "))),
+        "UNIQUE constraint failed: t.v\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t"), "0\n");
+    // The pages the failed insert split into are handed out again.
+    assert_eq!(run(&mut table, &format!("INSERT INTO t (v) VALUES ('s0'), {}", values.join(", "))), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT count(*), min(v), max(id) FROM t"), "13,r0,13\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE v = 'r11'"), "13\n");
+    close_test(table, file_name);
+}
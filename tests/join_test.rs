@@ -0,0 +1,111 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn create_shop(table: &mut Table) {
+    run(table, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT, city TEXT)");
+    run(table, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer INTEGER, item TEXT)");
+    run(table, "INSERT INTO customers VALUES (1, 'ann', 'oslo')");
+    run(table, "INSERT INTO customers VALUES (2, 'bob', 'rome')");
+    run(table, "INSERT INTO customers VALUES (3, 'cid', 'oslo')");
+    run(table, "INSERT INTO orders VALUES (10, 2, 'pen')");
+    run(table, "INSERT INTO orders VALUES (11, 1, 'ink')");
+    run(table, "INSERT INTO orders VALUES (12, 2, 'pad')");
+    run(table, "INSERT INTO orders VALUES (13, 7, 'cap')");
+}
+
+#[test]
+fn inner_join_on_primary_key() {
+    let file_name = "join_inner.db";
+    let mut table = open_test(file_name);
+    create_shop(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT o.item, c.name FROM orders o JOIN customers c ON o.customer = c.id"),
+        "pen,bob\nink,ann\npad,bob\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT item FROM orders INNER JOIN customers ON customers.id = orders.customer WHERE city = 'oslo'"),
+        "ink\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, count(*) FROM customers JOIN orders ON customer = customers.id GROUP BY name ORDER BY 2 DESC"),
+        "bob,2\nann,1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn left_join_pads_with_nulls() {
+    let file_name = "join_left.db";
+    let mut table = open_test(file_name);
+    create_shop(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT c.name, o.item FROM customers c LEFT OUTER JOIN orders o ON o.customer = c.id"),
+        "ann,ink\nbob,pen\nbob,pad\ncid,NULL\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT c.name FROM customers c LEFT JOIN orders o ON o.customer = c.id WHERE o.id IS NULL"),
+        "cid\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT o.item, c.name FROM orders o LEFT JOIN customers c ON c.id = o.customer AND c.city = 'rome'"),
+        "pen,bob\nink,NULL\npad,bob\ncap,NULL\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn cross_joins() {
+    let file_name = "join_cross.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE a (x INTEGER)");
+    run(&mut table, "CREATE TABLE b (y TEXT)");
+    run(&mut table, "INSERT INTO a VALUES (1)");
+    run(&mut table, "INSERT INTO a VALUES (2)");
+    run(&mut table, "INSERT INTO b VALUES ('p')");
+    run(&mut table, "INSERT INTO b VALUES ('q')");
+    assert_eq!(run(&mut table, "SELECT * FROM a, b"), "1,p\n1,q\n2,p\n2,q\n");
+    assert_eq!(run(&mut table, "SELECT y, x FROM a CROSS JOIN b WHERE x > 1"), "p,2\nq,2\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM a, b, a AS c"), "8\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn equality_joins_on_indexed_and_unindexed_columns() {
+    let file_name = "join_equality.db";
+    let mut table = open_test(file_name);
+    create_shop(&mut table);
+    run(&mut table, "CREATE TABLE cities (name TEXT, country TEXT)");
+    run(&mut table, "INSERT INTO cities VALUES ('rome', 'it')");
+    run(&mut table, "INSERT INTO cities VALUES ('oslo', 'no')");
+    let query = "SELECT c.name, country FROM customers c JOIN cities ON cities.name = c.city ORDER BY c.name";
+    assert_eq!(run(&mut table, query), "ann,no\nbob,it\ncid,no\n");
+    run(&mut table, "CREATE INDEX cities_name ON cities (name)");
+    assert_eq!(run(&mut table, query), "ann,no\nbob,it\ncid,no\n");
+    assert_eq!(
+        run(&mut table, "SELECT name FROM customers JOIN cities ON country = 'no'"),
+        "ambiguous column name: name\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn qualified_wildcards() {
+    let file_name = "join_wildcards.db";
+    let mut table = open_test(file_name);
+    create_shop(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT c.* FROM orders o JOIN customers c ON o.customer = c.id WHERE o.item = 'ink'"),
+        "1,ann,oslo\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT o.*, c.name FROM orders o LEFT JOIN customers c ON o.customer = c.id WHERE o.id > 11"),
+        "12,2,pad,bob\n13,7,cap,NULL\n"
+    );
+    assert_eq!(run(&mut table, "SELECT customers.* FROM customers WHERE id = 3"), "3,cid,oslo\n");
+    // A table with an alias is only known by it.
+    assert_eq!(run(&mut table, "SELECT customers.* FROM customers c"), "no such table: customers\n");
+    assert_eq!(run(&mut table, "SELECT x.* FROM customers"), "no such table: x\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn create_scores(table: &mut Table) {
+    run(table, "CREATE TABLE scores (id INTEGER PRIMARY KEY, name TEXT, score INTEGER)");
+    run(table, "INSERT INTO scores VALUES (3, 'cy', 20)");
+    run(table, "INSERT INTO scores VALUES (1, 'al', NULL)");
+    run(table, "INSERT INTO scores VALUES (2, 'bo', 35)");
+    run(table, "INSERT INTO scores VALUES (4, 'bo', 10)");
+}
+
+#[test]
+fn order_by_primary_key_uses_tree_order() {
+    let file_name = "order_by_rowid.db";
+    let mut table = open_test(file_name);
+    create_scores(&mut table);
+    assert_eq!(run(&mut table, "SELECT id FROM scores ORDER BY id"), "1\n2\n3\n4\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores ORDER BY id DESC"), "4\n3\n2\n1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn order_by_rowid_descending_walks_every_leaf() {
+    let file_name = "order_by_rowid_desc.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE numbers (id INTEGER PRIMARY KEY, n INTEGER)");
+    for i in 1..=90 {
+        run(&mut table, &format!("INSERT INTO numbers VALUES ({}, {})", i, i % 7));
+    }
+    run(&mut table, "DELETE FROM numbers WHERE id BETWEEN 30 AND 50");
+    let expected: Vec<String> = (1..=90).rev().filter(|i| !(30..=50).contains(i)).map(|i| format!("{}\n", i)).collect();
+    assert_eq!(run(&mut table, "SELECT id FROM numbers ORDER BY id DESC"), expected.concat());
+    assert_eq!(run(&mut table, "SELECT id FROM numbers ORDER BY id DESC LIMIT 3 OFFSET 2"), "88\n87\n86\n");
+    assert_eq!(run(&mut table, "SELECT id FROM numbers WHERE n = 0 AND id < 60 ORDER BY id DESC LIMIT 2"), "56\n28\n");
+    assert_eq!(run(&mut table, "SELECT id FROM numbers WHERE id = 7 ORDER BY id DESC"), "7\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn order_by_expressions_aliases_and_positions() {
+    let file_name = "order_by_terms.db";
+    let mut table = open_test(file_name);
+    create_scores(&mut table);
+    assert_eq!(run(&mut table, "SELECT id FROM scores ORDER BY score"), "1\n4\n3\n2\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores ORDER BY name, score DESC"), "1\n2\n4\n3\n");
+    assert_eq!(run(&mut table, "SELECT name AS n, id FROM scores ORDER BY n DESC, 2 DESC"), "cy,3\nbo,4\nbo,2\nal,1\n");
+    assert_eq!(
+        run(&mut table, "SELECT id FROM scores ORDER BY 2"),
+        "1st ORDER BY term out of range - should be between 1 and 1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn limit_and_offset() {
+    let file_name = "order_by_limit.db";
+    let mut table = open_test(file_name);
+    create_scores(&mut table);
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT 2"), "1\n2\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT 2 OFFSET 3"), "4\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT 1, 2"), "2\n3\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT -1 OFFSET 2"), "3\n4\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores ORDER BY score DESC LIMIT 2"), "2\n3\n");
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT 0"), "");
+    assert_eq!(run(&mut table, "SELECT id FROM scores LIMIT 'x'"), "datatype mismatch\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn large_sorts_spill_to_temp_pages() {
+    let file_name = "order_by_spill.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE numbers (id INTEGER PRIMARY KEY, n INTEGER)");
+    for i in 1..=40 {
+        run(&mut table, &format!("INSERT INTO numbers VALUES ({}, {})", i, (i * 17) % 41));
+    }
+    let expected = run(&mut table, "SELECT n FROM numbers ORDER BY n DESC");
+    assert_eq!(run(&mut table, "PRAGMA sort_buffer_rows = 3"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT n FROM numbers ORDER BY n DESC"), expected);
+    let mut values: Vec<i64> = (1..=40).map(|i| (i * 17) % 41).collect();
+    values.sort_by(|a, b| b.cmp(a));
+    let values: Vec<String> = values.iter().map(|n| format!("{}\n", n)).collect();
+    assert_eq!(expected, values.concat());
+    assert_eq!(run(&mut table, "SELECT n FROM numbers ORDER BY n LIMIT 3 OFFSET 1"), "2\n3\n4\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT UNIQUE, city TEXT)");
+    run(table, "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, item TEXT, qty INTEGER)");
+    run(table, "CREATE INDEX orders_user ON orders (user_id)");
+    run(table, "INSERT INTO users (name, city) VALUES ('ann', 'oslo'), ('bob', 'rome'), ('cat', 'oslo')");
+    run(
+        table,
+        "INSERT INTO orders (user_id, item, qty) VALUES (1, 'pen', 2), (2, 'ink', 1), (1, 'pad', 5), (3, 'pen', 1), (2, 'cap', 3)",
+    );
+}
+
+#[test]
+fn explain_single_table_access() {
+    let file_name = "planner_single.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users"), "`--SCAN users\n");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users WHERE id = 2"),
+        "`--SEARCH users USING PRIMARY KEY (id=?)\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users u WHERE u.city = 'oslo' AND 'bob' = name"),
+        "`--SEARCH users AS u USING INDEX autoindex_users_1 (name=?)\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT item FROM orders WHERE user_id = 1 ORDER BY qty"),
+        "|--SEARCH orders USING INDEX orders_user (user_id=?)\n`--USE TEMP B-TREE FOR ORDER BY\n"
+    );
+    // OR and comparisons other than = can not seek.
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users WHERE id = 1 OR id = 2"),
+        "`--SCAN users\n"
+    );
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users WHERE id > 2"), "`--SCAN users\n");
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users WHERE id IN (1, 2)"), "`--SCAN users\n");
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT 1"), "`--SCAN CONSTANT ROW\n");
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN CREATE TABLE t (a)"), "");
+    close_test(table, file_name);
+}
+
+#[test]
+fn seeks_find_the_same_rows_as_scans() {
+    let file_name = "planner_seeks.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE id = 2"), "bob\n");
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE id = 2.0 AND city = 'rome'"), "bob\n");
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE id = 2 AND city = 'oslo'"), "");
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE id = 9"), "");
+    assert_eq!(run(&mut table, "SELECT name FROM users WHERE id = NULL"), "");
+    assert_eq!(run(&mut table, "SELECT city FROM users WHERE name = 'cat'"), "oslo\n");
+    assert_eq!(run(&mut table, "SELECT item FROM orders WHERE user_id = 1"), "pen\npad\n");
+    assert_eq!(run(&mut table, "SELECT item FROM orders WHERE user_id = 1 ORDER BY id DESC"), "pad\npen\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM orders WHERE user_id = 2"), "2\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn explain_join_order() {
+    let file_name = "planner_joins.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    // The orders are scanned and each user found by its key, whatever the
+    // order the tables are written in.
+    let plan = "|--SCAN orders AS o\n`--SEARCH users AS u USING PRIMARY KEY (id=?)\n";
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT u.name, o.item FROM users u JOIN orders o ON o.user_id = u.id"),
+        plan
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT u.name, o.item FROM orders o, users u WHERE u.id = o.user_id"),
+        plan
+    );
+    // A known user seeks both tables.
+    assert_eq!(
+        run(
+            &mut table,
+            "EXPLAIN QUERY PLAN SELECT o.item FROM orders o JOIN users u ON o.user_id = u.id WHERE u.name = 'bob'"
+        ),
+        "|--SEARCH users AS u USING INDEX autoindex_users_1 (name=?)\n`--SEARCH orders AS o USING INDEX orders_user (user_id=?)\n"
+    );
+    // Without an index the rows of a table are hashed.
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users a JOIN users b ON a.city = b.city"),
+        "|--SCAN users AS a\n`--SEARCH users AS b USING AUTOMATIC HASH INDEX (city=?)\n"
+    );
+    // A LEFT JOIN keeps the written order.
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM orders o LEFT JOIN users u ON u.id = o.user_id"),
+        "|--SCAN orders AS o\n`--SEARCH users AS u USING PRIMARY KEY (id=?)\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM users u LEFT JOIN orders o ON o.user_id = u.id"),
+        "|--SCAN users AS u\n`--SEARCH orders AS o USING INDEX orders_user (user_id=?)\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn reordered_joins_keep_their_columns() {
+    let file_name = "planner_join_rows.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT * FROM users u JOIN orders o ON o.user_id = u.id WHERE o.qty > 2 ORDER BY o.id"),
+        "1,ann,oslo,3,1,pad,5\n2,bob,rome,5,2,cap,3\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT o.item FROM orders o JOIN users u ON o.user_id = u.id WHERE u.name = 'bob' ORDER BY o.item"),
+        "cap\nink\n"
+    );
+    run(&mut table, "INSERT INTO orders (user_id, item, qty) VALUES (7, 'box', 1)");
+    assert_eq!(
+        run(&mut table, "SELECT o.item, u.name FROM orders o LEFT JOIN users u ON u.id = o.user_id WHERE o.qty = 1"),
+        "ink,bob\npen,cat\nbox,NULL\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn explain_subqueries_and_materialized_tables() {
+    let file_name = "planner_subqueries.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(
+            &mut table,
+            "EXPLAIN QUERY PLAN SELECT name, (SELECT count(*) FROM orders o WHERE o.user_id = u.id) FROM users u"
+        ),
+        "|--SCAN users AS u\n`--SCALAR SUBQUERY 1\n   `--SEARCH orders AS o USING INDEX orders_user (user_id=?)\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "EXPLAIN QUERY PLAN SELECT name FROM users WHERE id IN (SELECT user_id FROM orders WHERE item = 'pen')"
+        ),
+        "|--SCAN users\n`--LIST SUBQUERY 1\n   `--SCAN orders\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "EXPLAIN QUERY PLAN WITH big AS (SELECT * FROM orders WHERE qty > 1) SELECT DISTINCT b.item FROM big b JOIN users ON users.id = b.user_id"
+        ),
+        "|--MATERIALIZE big\n|  `--SCAN orders\n|--SCAN big AS b\n|--SEARCH users USING PRIMARY KEY (id=?)\n`--USE HASH TABLE FOR DISTINCT\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "EXPLAIN QUERY PLAN SELECT name FROM users WHERE id = 1 UNION SELECT item FROM orders ORDER BY 1"
+        ),
+        "|--COMPOUND QUERY\n|  |--LEFT-MOST SUBQUERY\n|  |  `--SEARCH users USING PRIMARY KEY (id=?)\n|  `--UNION USING HASH TABLE\n|     `--SCAN orders\n`--USE TEMP B-TREE FOR ORDER BY\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN DELETE FROM orders WHERE user_id = (SELECT id FROM users WHERE name = 'bob')"),
+        "|--SEARCH orders USING INDEX orders_user (user_id=?)\n`--SCALAR SUBQUERY 1\n   `--SEARCH users USING INDEX autoindex_users_1 (name=?)\n"
+    );
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM nowhere"), "no such table: nowhere\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn seeks_in_subqueries_and_changes() {
+    let file_name = "planner_changes.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name, (SELECT sum(qty) FROM orders o WHERE o.user_id = u.id) FROM users u"),
+        "ann,7\nbob,4\ncat,1\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name FROM users u WHERE EXISTS (SELECT 1 FROM users v WHERE v.id = u.id + 1)"),
+        "ann\nbob\n"
+    );
+    assert_eq!(run(&mut table, "UPDATE orders SET qty = qty + 10 WHERE user_id = 2"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT item, qty FROM orders WHERE user_id = 2"), "ink,11\ncap,13\n");
+    assert_eq!(
+        run(&mut table, "DELETE FROM orders WHERE user_id = (SELECT id FROM users WHERE name = 'ann') AND item = 'pen'"),
+        "Executed.\n"
+    );
+    assert_eq!(run(&mut table, "SELECT id, item FROM orders WHERE user_id = 1"), "3,pad\n");
+    assert_eq!(run(&mut table, "DELETE FROM users WHERE id = 3"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT name FROM users"), "ann\nbob\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn indexes_hold_any_number_of_equal_keys() {
+    let file_name = "planner_equal_keys.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, cat TEXT)");
+    run(&mut table, "CREATE INDEX t_cat ON t (cat)");
+    for _ in 0..39 {
+        assert_eq!(run(&mut table, "INSERT INTO t (cat) VALUES ('same')"), "Executed.\n");
+    }
+    run(&mut table, "INSERT INTO t (cat) VALUES ('other')");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT count(*) FROM t WHERE cat = 'same'"),
+        "`--SEARCH t USING INDEX t_cat (cat=?)\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t WHERE cat = 'same'"), "39\n");
+    assert_eq!(run(&mut table, "DELETE FROM t WHERE id % 3 = 0"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t WHERE cat = 'same'"), "26\n");
+    assert_eq!(run(&mut table, "UPDATE t SET cat = 'other' WHERE id < 10"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t WHERE cat = 'same'"), "20\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t WHERE cat = 'other'"), "7\n");
+    assert_eq!(run(&mut table, "INSERT INTO t (cat) VALUES ('same'), ('same')"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t WHERE cat = 'same'"), "22\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn seeks_prefer_the_index_matching_more_columns() {
+    let file_name = "planner_more_columns.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, a INTEGER, b TEXT)");
+    run(&mut table, "CREATE INDEX ta ON t (a)");
+    run(&mut table, "CREATE INDEX tab ON t (a, b)");
+    run(&mut table, "INSERT INTO t (a, b) VALUES (1, 'x'), (1, 'y'), (2, 'x')");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT id FROM t WHERE a = 1 AND b = 'x'"),
+        "`--SEARCH t USING INDEX tab (a=? AND b=?)\n"
+    );
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE a = 1 AND b = 'x'"), "1\n");
+    assert_eq!(run(&mut table, "EXPLAIN QUERY PLAN SELECT id FROM t WHERE a = 1"), "`--SEARCH t USING INDEX ta (a=?)\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::prepared::statement::{Statement, Step};
+use rustql::table::table::Table;
+use rustql::table::value::Value;
+
+fn rows(statement: &mut Statement, table: &mut Table) -> Vec<Vec<Value>> {
+    let mut rows = Vec::new();
+    while let Step::Row(row) = statement.step(table).unwrap() {
+        rows.push(row);
+    }
+    statement.reset();
+    rows
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+#[test]
+fn parameters_are_numbered_like_sqlite() {
+    let statement = rustql::prepare("SELECT ?, ?5, ?, :a, @b, :a, $c, ?2").unwrap();
+    assert_eq!(statement.parameter_count(), 9);
+    assert_eq!(statement.parameter_index(":a"), Some(7));
+    assert_eq!(statement.parameter_index("@b"), Some(8));
+    assert_eq!(statement.parameter_index("$c"), Some(9));
+    assert_eq!(statement.parameter_index(":b"), None);
+    assert_eq!(statement.parameter_name(7), Some(":a"));
+    assert_eq!(statement.parameter_name(1), None);
+
+    let error = rustql::prepare("SELECT ?0").err().unwrap();
+    assert_eq!(error.to_string(), "variable number must be between ?1 and ?32766");
+    let error = rustql::prepare("CREATE TABLE t (a INTEGER DEFAULT ?)").err().unwrap();
+    assert_eq!(error.to_string(), "parameters are not allowed in schema definitions");
+}
+
+#[test]
+fn bound_values_are_used_by_each_run() {
+    let file_name = "prepared_bind.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score REAL)");
+    let mut insert = rustql::prepare("INSERT INTO t (id, name, score) VALUES (?, :name, ?3)").unwrap();
+    for (id, name, score) in [(1, "ann", 1.5), (2, "bob", 2.5), (3, "o'hara", 3.5)] {
+        insert.bind_int(1, id).unwrap();
+        insert.bind_named(":name", text(name)).unwrap();
+        insert.bind_real(3, score).unwrap();
+        assert_eq!(insert.step(&mut table).unwrap(), Step::Done);
+        insert.reset();
+    }
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,ann,1.5\n2,bob,2.5\n3,o'hara,3.5\n");
+
+    let mut select = rustql::prepare("SELECT name FROM t WHERE id = ?").unwrap();
+    select.bind_int(1, 2).unwrap();
+    assert_eq!(rows(&mut select, &mut table), vec![vec![text("bob")]]);
+    assert_eq!(select.columns(), ["name"]);
+    select.bind_int(1, 3).unwrap();
+    assert_eq!(rows(&mut select, &mut table), vec![vec![text("o'hara")]]);
+
+    let mut lookup = rustql::prepare("SELECT id FROM t WHERE name = ?1 OR name = ?1 || 'x'").unwrap();
+    lookup.bind_text(1, "o'hara").unwrap();
+    assert_eq!(rows(&mut lookup, &mut table), vec![vec![Value::Integer(3)]]);
+    lookup.bind_text(1, "1 OR 1 = 1").unwrap();
+    assert_eq!(rows(&mut lookup, &mut table), Vec::<Vec<Value>>::new());
+
+    let error = select.bind_int(2, 0).err().unwrap();
+    assert_eq!(error.to_string(), "bind index 2 is out of range, the statement has 1 parameters");
+    assert_eq!(
+        run(&mut table, "EXPLAIN INSERT INTO t (id, name) VALUES (?, :name)"),
+        "0,OpenWrite,0,2,0,t,0\n\
+         1,Variable,1,0,0,NULL,0\n\
+         2,Variable,2,1,0,NULL,0\n\
+         3,Insert,0,0,2,id, name,0\n\
+         4,Halt,0,0,0,NULL,0\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn unbound_parameters_are_null() {
+    let file_name = "prepared_null.db";
+    let mut table = open_test(file_name);
+    let mut statement = rustql::prepare("SELECT ? IS NULL, ?2").unwrap();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![Value::Integer(1), Value::Null]]);
+    statement.bind_int(2, 7).unwrap();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![Value::Integer(1), Value::Integer(7)]]);
+    statement.clear_bindings();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![Value::Integer(1), Value::Null]]);
+    assert_eq!(run(&mut table, "SELECT ?"), "NULL\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn parameters_reach_every_part_of_a_statement() {
+    let file_name = "prepared_everywhere.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)");
+    run(&mut table, "INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')");
+
+    let mut statement = rustql::prepare(
+        "SELECT name FROM t WHERE id > (SELECT min(id) FROM t WHERE name >= ?) ORDER BY id DESC LIMIT ?",
+    )
+    .unwrap();
+    statement.bind_text(1, "b").unwrap();
+    statement.bind_int(2, 1).unwrap();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![text("d")]]);
+    statement.bind_int(2, 5).unwrap();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![text("d")], vec![text("c")]]);
+
+    let mut update = rustql::prepare("UPDATE t SET name = ? WHERE id = ? RETURNING id, name").unwrap();
+    update.bind_text(1, "z").unwrap();
+    update.bind_int(2, 4).unwrap();
+    assert_eq!(rows(&mut update, &mut table), vec![vec![Value::Integer(4), text("z")]]);
+
+    let mut delete = rustql::prepare("DELETE FROM t WHERE id IN (?, ?)").unwrap();
+    delete.bind_int(1, 1).unwrap();
+    delete.bind_int(2, 2).unwrap();
+    assert_eq!(delete.step(&mut table).unwrap(), Step::Done);
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "3,c\n4,z\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn statements_compile_again_after_a_schema_change() {
+    let file_name = "prepared_schema.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)");
+    run(&mut table, "INSERT INTO t VALUES (1, 'a'), (2, 'b')");
+    let mut statement = rustql::prepare("SELECT * FROM t WHERE id = ?").unwrap();
+    statement.bind_int(1, 2).unwrap();
+    assert_eq!(rows(&mut statement, &mut table), vec![vec![Value::Integer(2), text("b")]]);
+
+    run(&mut table, "ALTER TABLE t ADD COLUMN score INTEGER DEFAULT 5");
+    assert_eq!(
+        rows(&mut statement, &mut table),
+        vec![vec![Value::Integer(2), text("b"), Value::Integer(5)]]
+    );
+    assert_eq!(statement.columns(), ["id", "name", "score"]);
+
+    run(&mut table, "DROP TABLE t");
+    assert_eq!(statement.step(&mut table).err().unwrap().to_string(), "no such table: t");
+    close_test(table, file_name);
+}
+
+#[test]
+fn statements_compile_again_for_another_database() {
+    let (first_name, second_name) = ("prepared_first.db", "prepared_second.db");
+    let mut first = open_test(first_name);
+    let mut second = open_test(second_name);
+    // Both catalogs are at the same version.
+    run(&mut first, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)");
+    run(&mut second, "CREATE TABLE t (name TEXT, id INTEGER PRIMARY KEY, score INTEGER)");
+    run(&mut first, "INSERT INTO t VALUES (1, 'a')");
+    run(&mut second, "INSERT INTO t VALUES ('b', 1, 7)");
+    let mut statement = rustql::prepare("SELECT * FROM t WHERE id = ?").unwrap();
+    statement.bind_int(1, 1).unwrap();
+    assert_eq!(rows(&mut statement, &mut first), vec![vec![Value::Integer(1), text("a")]]);
+    assert_eq!(
+        rows(&mut statement, &mut second),
+        vec![vec![text("b"), Value::Integer(1), Value::Integer(7)]]
+    );
+    assert_eq!(statement.columns(), ["name", "id", "score"]);
+    close_test(first, first_name);
+    close_test(second, second_name);
+}
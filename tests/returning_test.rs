@@ -0,0 +1,101 @@
+mod common;
+
+use common::{close_test, open_test, run};
+
+#[test]
+fn insert_returning() {
+    let file_name = "returning_insert.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT, score REAL DEFAULT 0)");
+    assert_eq!(
+        run(&mut table, "INSERT INTO notes (body) VALUES ('first'), ('second') RETURNING id, body, score"),
+        "1,first,0.0\n2,second,0.0\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO notes VALUES (10, 'ten', '2.5') RETURNING *, upper(body) AS shout, id * 2"),
+        "10,ten,2.5,TEN,20\n"
+    );
+    run(&mut table, "CREATE TABLE copies (id INTEGER PRIMARY KEY, body TEXT)");
+    assert_eq!(
+        run(&mut table, "INSERT INTO copies (body) SELECT body FROM notes WHERE id > 1 RETURNING id"),
+        "1\n2\n"
+    );
+    // Upserts return the row as updated, ignored rows return nothing.
+    assert_eq!(
+        run(
+            &mut table,
+            "INSERT INTO notes VALUES (1, 'again', 1), (3, 'third', 1) ON CONFLICT(id) DO UPDATE SET score = score + 1 RETURNING id, body, score",
+        ),
+        "1,first,1.0\n3,third,1.0\n"
+    );
+    assert_eq!(run(&mut table, "INSERT OR IGNORE INTO notes VALUES (1, 'x', 0) RETURNING id"), "");
+    close_test(table, file_name);
+}
+
+#[test]
+fn update_returning() {
+    let file_name = "returning_update.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER CHECK (balance >= 0))");
+    run(&mut table, "INSERT INTO accounts VALUES (1, 'ann', 100), (2, 'bob', 50), (3, 'cy', 0)");
+    assert_eq!(
+        run(&mut table, "UPDATE accounts SET balance = balance + 25 WHERE balance > 0 RETURNING owner, balance"),
+        "ann,125\nbob,75\n"
+    );
+    assert_eq!(run(&mut table, "UPDATE accounts SET id = NULL WHERE owner = 'cy' RETURNING id"), "4\n");
+    assert_eq!(run(&mut table, "UPDATE accounts SET balance = 0 WHERE id = 99 RETURNING *"), "");
+    assert_eq!(
+        run(&mut table, "UPDATE accounts SET balance = balance - 100 RETURNING id"),
+        "CHECK constraint failed: accounts.balance\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn delete_returning() {
+    let file_name = "returning_delete.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE queue (id INTEGER PRIMARY KEY, job TEXT)");
+    run(&mut table, "INSERT INTO queue (job) VALUES ('a'), ('b'), ('c')");
+    assert_eq!(run(&mut table, "DELETE FROM queue WHERE id < 3 RETURNING job, id"), "a,1\nb,2\n");
+    assert_eq!(run(&mut table, "SELECT * FROM queue"), "3,c\n");
+    assert_eq!(run(&mut table, "DELETE FROM queue"), "Executed.\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn rowid_columns() {
+    let file_name = "returning_rowid.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (v TEXT)");
+    assert_eq!(run(&mut table, "INSERT INTO t (v) VALUES ('x'), ('y') RETURNING rowid, v"), "1,x\n2,y\n");
+    assert_eq!(run(&mut table, "SELECT rowid, _rowid_, OID, * FROM t"), "1,1,1,x\n2,2,2,y\n");
+    assert_eq!(run(&mut table, "SELECT t.oid + 1 FROM t WHERE rowid = 2"), "3\n");
+    assert_eq!(run(&mut table, "SELECT a.v, b.rowid FROM t a JOIN t b ON b.rowid = a.rowid + 1"), "x,2\n");
+    assert_eq!(
+        run(&mut table, "EXPLAIN QUERY PLAN SELECT v FROM t WHERE rowid = 2"),
+        "`--SEARCH t USING PRIMARY KEY (rowid=?)\n"
+    );
+    assert_eq!(run(&mut table, "UPDATE t SET v = 'z' WHERE oid = 2 RETURNING _rowid_, v"), "2,z\n");
+    assert_eq!(run(&mut table, "DELETE FROM t WHERE rowid = 1 RETURNING rowid"), "1\n");
+    assert_eq!(run(&mut table, "SELECT rowid, v FROM t"), "2,z\n");
+    // A declared column of the name hides the rowid.
+    run(&mut table, "CREATE TABLE u (oid TEXT, w TEXT)");
+    assert_eq!(run(&mut table, "INSERT INTO u VALUES ('a', 'b') RETURNING oid, rowid"), "a,1\n");
+    assert_eq!(run(&mut table, "SELECT * FROM u WHERE oid = 'a'"), "a,b\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn returning_errors() {
+    let file_name = "returning_errors.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (a INTEGER)");
+    assert_eq!(
+        run(&mut table, "INSERT INTO t VALUES (1) RETURNING count(*)"),
+        "aggregate functions are not allowed in RETURNING\n"
+    );
+    assert_eq!(run(&mut table, "INSERT INTO t VALUES (2) RETURNING b"), "no such column: b\n");
+    assert_eq!(run(&mut table, "DELETE FROM t RETURNING"), "incomplete input\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+use std::thread;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE depts (id INTEGER PRIMARY KEY, name TEXT)");
+    run(table, "INSERT INTO depts VALUES (1, 'eng'), (2, 'ops'), (3, 'hr')");
+    run(table, "CREATE TABLE staff (id INTEGER PRIMARY KEY, name TEXT, dept INTEGER, salary INTEGER)");
+    run(
+        table,
+        "INSERT INTO staff VALUES (1, 'ann', 1, 100), (2, 'bob', 1, 80), (3, 'cy', 2, 60), (4, 'di', 2, 90), (5, 'ed', NULL, 50)",
+    );
+}
+
+#[test]
+fn scalar_subqueries() {
+    let file_name = "subquery_scalar.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM staff WHERE salary > (SELECT avg(salary) FROM staff)"),
+        "ann\nbob\ndi\n"
+    );
+    assert_eq!(run(&mut table, "SELECT (SELECT max(salary) FROM staff), (SELECT name FROM depts WHERE id = 9)"), "100,NULL\n");
+    // Only the first row of a scalar subquery is used.
+    assert_eq!(run(&mut table, "SELECT (SELECT name FROM depts ORDER BY name)"), "eng\n");
+    assert_eq!(
+        run(&mut table, "SELECT (SELECT id, name FROM depts)"),
+        "sub-select returns 2 columns - expected 1\n"
+    );
+    // Columns are named after the subquery as written, views included.
+    run(&mut table, "CREATE VIEW top AS SELECT name, (SELECT max(salary) FROM staff), salary - (SELECT min(salary) FROM staff) FROM staff");
+    assert_eq!(
+        run(&mut table, "SELECT name, \"salary - (SELECT min(salary) FROM staff)\" FROM top WHERE \"(SELECT max(salary) FROM staff)\" = 100 AND name < 'c'"),
+        "ann,50\nbob,30\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn in_and_exists_subqueries() {
+    let file_name = "subquery_in_exists.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM depts WHERE id IN (SELECT dept FROM staff WHERE salary >= 90)"),
+        "eng\nops\n"
+    );
+    assert_eq!(run(&mut table, "SELECT name FROM depts WHERE id NOT IN (SELECT dept FROM staff WHERE dept IS NOT NULL)"), "hr\n");
+    // A NULL among the results makes NOT IN unknown.
+    assert_eq!(run(&mut table, "SELECT count(*) FROM depts WHERE id NOT IN (SELECT dept FROM staff)"), "0\n");
+    assert_eq!(run(&mut table, "SELECT EXISTS (SELECT * FROM staff WHERE salary > 95), NOT EXISTS (SELECT * FROM depts)"), "1,0\n");
+    assert_eq!(
+        run(&mut table, "SELECT id FROM staff WHERE dept IN (SELECT id, name FROM depts)"),
+        "sub-select returns 2 columns - expected 1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn correlated_subqueries() {
+    let file_name = "subquery_correlated.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM depts d WHERE EXISTS (SELECT * FROM staff WHERE staff.dept = d.id)"),
+        "eng\nops\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name FROM depts WHERE NOT EXISTS (SELECT * FROM staff WHERE dept = depts.id)"),
+        "hr\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, (SELECT count(*) FROM staff WHERE dept = depts.id) AS size FROM depts ORDER BY size DESC, name"),
+        "eng,2\nops,2\nhr,0\n"
+    );
+    // Earning more than the average of their own department.
+    assert_eq!(
+        run(&mut table, "SELECT s.name FROM staff s WHERE salary > (SELECT avg(salary) FROM staff WHERE dept = s.dept)"),
+        "ann\ndi\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name FROM staff s WHERE s.id IN (SELECT id FROM staff WHERE dept = s.dept AND salary < 85)"),
+        "bob\ncy\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn subqueries_in_dml() {
+    let file_name = "subquery_dml.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE TABLE totals (dept TEXT, total INTEGER)");
+    run(&mut table, "INSERT INTO totals VALUES ((SELECT name FROM depts WHERE id = 1), (SELECT sum(salary) FROM staff))");
+    assert_eq!(run(&mut table, "SELECT * FROM totals"), "eng,380\n");
+    run(&mut table, "UPDATE totals SET total = (SELECT sum(salary) FROM staff, depts WHERE staff.dept = depts.id AND depts.name = totals.dept)");
+    assert_eq!(run(&mut table, "SELECT * FROM totals"), "eng,180\n");
+    run(&mut table, "DELETE FROM staff WHERE dept NOT IN (SELECT id FROM depts) OR dept IS NULL");
+    assert_eq!(
+        run(&mut table, "DELETE FROM staff WHERE salary < (SELECT avg(salary) FROM staff s WHERE s.dept = staff.dept) RETURNING name"),
+        "bob\ncy\n"
+    );
+    assert_eq!(
+        run(&mut table, "UPDATE staff SET salary = salary + 1 RETURNING name, (SELECT name FROM depts WHERE id = staff.dept)"),
+        "ann,eng\ndi,ops\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn derived_tables() {
+    let file_name = "subquery_derived.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT dept, total FROM (SELECT dept, sum(salary) AS total FROM staff GROUP BY dept) WHERE total > 100"),
+        "1,180\n2,150\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "SELECT d.name, t.total FROM depts d JOIN (SELECT dept, sum(salary) AS total FROM staff GROUP BY dept) t ON t.dept = d.id ORDER BY t.total",
+        ),
+        "ops,150\neng,180\n"
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM (SELECT name, salary * 2 FROM staff WHERE id < 3) AS doubled"), "ann,200\nbob,160\n");
+    assert_eq!(run(&mut table, "SELECT doubled.x FROM (SELECT 1 AS x) AS doubled"), "1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn subqueries_not_allowed_in_constraints() {
+    let file_name = "subquery_constraints.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE limits (n INTEGER CHECK (n < (SELECT 10)))");
+    assert_eq!(run(&mut table, "INSERT INTO limits VALUES (1)"), "subqueries are not allowed here\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn subquery_nesting_is_limited() {
+    // A query at the limit runs on the 2 MB stack of a spawned thread.
+    let output = thread::Builder::new()
+        .stack_size(2 << 20)
+        .spawn(|| {
+            let file_name = "subquery_nesting.db";
+            let mut table = open_test(file_name);
+            run(&mut table, "CREATE TABLE t (x INTEGER)");
+            run(&mut table, "INSERT INTO t VALUES (1)");
+            let nested = |depth: usize| (1..depth).fold("SELECT x FROM t".to_string(), |inner, _| format!("SELECT max(({})) FROM t", inner));
+            let output = [run(&mut table, &nested(32)), run(&mut table, &nested(33))];
+            close_test(table, file_name);
+            output
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(output[0], "1\n");
+    assert_eq!(output[1], "too many levels of subquery nesting (maximum depth 32)\n");
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)");
+    run(table, "CREATE TABLE audit (account INTEGER, old_balance INTEGER, new_balance INTEGER)");
+    run(table, "INSERT INTO accounts (owner, balance) VALUES ('ann', 100), ('bob', 50), ('cat', 0)");
+}
+
+#[test]
+fn after_update_trigger() {
+    let file_name = "trigger_after_update.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(
+            &mut table,
+            "CREATE TRIGGER log_balance AFTER UPDATE ON accounts FOR EACH ROW BEGIN INSERT INTO audit VALUES (OLD.id, OLD.balance, NEW.balance); END"
+        ),
+        "Executed.\n"
+    );
+    run(&mut table, "UPDATE accounts SET balance = balance + 10 WHERE balance >= 50");
+    assert_eq!(run(&mut table, "SELECT * FROM audit"), "1,100,110\n2,50,60\n");
+    // Rows the update does not match fire nothing.
+    run(&mut table, "UPDATE accounts SET balance = 1 WHERE owner = 'nobody'");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM audit"), "2\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn before_and_after_insert_and_delete() {
+    let file_name = "trigger_insert_delete.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(
+        &mut table,
+        "CREATE TRIGGER opened AFTER INSERT ON accounts BEGIN INSERT INTO audit VALUES (NEW.id, NULL, NEW.balance); END",
+    );
+    run(
+        &mut table,
+        "CREATE TRIGGER closing BEFORE DELETE ON accounts BEGIN INSERT INTO audit VALUES (OLD.id, OLD.balance, NULL); END",
+    );
+    run(
+        &mut table,
+        "CREATE TRIGGER closed AFTER DELETE ON accounts BEGIN DELETE FROM audit WHERE account = OLD.id AND new_balance IS NOT NULL; END",
+    );
+    run(&mut table, "INSERT INTO accounts (owner, balance) VALUES ('dan', 5), ('eve', 7)");
+    assert_eq!(run(&mut table, "SELECT * FROM audit"), "4,NULL,5\n5,NULL,7\n");
+    run(&mut table, "DELETE FROM accounts WHERE id = 4");
+    assert_eq!(run(&mut table, "SELECT * FROM audit"), "5,NULL,7\n4,5,NULL\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn when_clause_and_update_of() {
+    let file_name = "trigger_when.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE TABLE alerts (owner TEXT, message TEXT)");
+    run(
+        &mut table,
+        "CREATE TRIGGER overdrawn AFTER UPDATE OF balance ON accounts WHEN NEW.balance < 0 BEGIN INSERT INTO alerts VALUES (NEW.owner, 'overdrawn'); END",
+    );
+    run(
+        &mut table,
+        "CREATE TRIGGER renamed AFTER UPDATE OF owner ON accounts BEGIN INSERT INTO alerts VALUES (NEW.owner, 'was ' || OLD.owner); END",
+    );
+    run(&mut table, "UPDATE accounts SET balance = balance - 60");
+    assert_eq!(run(&mut table, "SELECT * FROM alerts"), "bob,overdrawn\ncat,overdrawn\n");
+    run(&mut table, "UPDATE accounts SET owner = 'ava' WHERE id = 1");
+    assert_eq!(run(&mut table, "SELECT * FROM alerts WHERE owner = 'ava'"), "ava,was ann\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn trigger_statements_and_subqueries() {
+    let file_name = "trigger_statements.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(
+        &mut table,
+        "CREATE TRIGGER sweep AFTER DELETE ON accounts BEGIN UPDATE accounts SET balance = balance + OLD.balance WHERE id = (SELECT min(id) FROM accounts); INSERT INTO audit SELECT OLD.id, count(*), sum(balance) FROM accounts; END",
+    );
+    run(&mut table, "DELETE FROM accounts WHERE owner = 'bob'");
+    assert_eq!(run(&mut table, "SELECT balance FROM accounts WHERE id = 1"), "150\n");
+    assert_eq!(run(&mut table, "SELECT * FROM audit"), "2,2,150\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn trigger_does_not_fire_itself() {
+    let file_name = "trigger_recursion.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(
+        &mut table,
+        "CREATE TRIGGER copy AFTER INSERT ON accounts BEGIN INSERT INTO accounts (owner, balance) VALUES (NEW.owner || '2', NEW.balance); END",
+    );
+    run(&mut table, "INSERT INTO accounts (owner, balance) VALUES ('dan', 1)");
+    assert_eq!(run(&mut table, "SELECT owner FROM accounts WHERE id > 3"), "dan\ndan2\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn triggers_survive_reopen() {
+    let file_name = "trigger_reopen.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(
+        &mut table,
+        "CREATE TRIGGER log_balance AFTER UPDATE ON accounts BEGIN INSERT INTO audit VALUES (OLD.id, OLD.balance, NEW.balance); END",
+    );
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    run(&mut table, "UPDATE accounts SET balance = 0 WHERE id = 2");
+    assert_eq!(run(&mut table, "SELECT * FROM audit"), "2,50,0\n");
+    assert_eq!(run(&mut table, "DROP TRIGGER log_balance"), "Executed.\n");
+    run(&mut table, "UPDATE accounts SET balance = 1 WHERE id = 2");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM audit"), "1\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn trigger_errors() {
+    let file_name = "trigger_errors.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    let create = "CREATE TRIGGER t AFTER INSERT ON accounts BEGIN INSERT INTO audit VALUES (OLD.id, 0, 0); END";
+    assert_eq!(run(&mut table, create), "Executed.\n");
+    assert_eq!(run(&mut table, create), "trigger t already exists\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO accounts (owner) VALUES ('dan')"),
+        "no such column: OLD.id\n"
+    );
+    assert_eq!(
+        run(&mut table, "CREATE TRIGGER u AFTER DELETE ON missing BEGIN SELECT 1; END"),
+        "no such table: missing\n"
+    );
+    assert_eq!(
+        run(&mut table, "CREATE TRIGGER u AFTER UPDATE OF nope ON accounts BEGIN SELECT 1; END"),
+        "no such column: nope\n"
+    );
+    run(&mut table, "CREATE VIEW rich AS SELECT * FROM accounts WHERE balance > 10");
+    assert_eq!(
+        run(&mut table, "CREATE TRIGGER u AFTER DELETE ON rich BEGIN SELECT 1; END"),
+        "cannot create trigger on view: rich\n"
+    );
+    assert_eq!(
+        run(&mut table, "CREATE TRIGGER u AFTER DELETE ON accounts BEGIN CREATE TABLE x (a); END"),
+        "near \"CREATE\": syntax error\n"
+    );
+    assert_eq!(run(&mut table, "DROP TRIGGER nope"), "no such trigger: nope\n");
+    assert_eq!(run(&mut table, "DROP TRIGGER IF EXISTS nope"), "Executed.\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn trigger_nesting_is_limited() {
+    let file_name = "trigger_nesting.db";
+    let mut table = open_test(file_name);
+    for i in 0..18 {
+        run(&mut table, &format!("CREATE TABLE t{} (x)", i));
+    }
+    let chain = |i: usize| format!("CREATE TRIGGER r{} AFTER INSERT ON t{} BEGIN INSERT INTO t{} VALUES (NEW.x); END", i, i, i + 1);
+    for i in 0..16 {
+        run(&mut table, &chain(i));
+    }
+    assert_eq!(run(&mut table, "INSERT INTO t0 VALUES (1)"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT x FROM t16"), "1\n");
+    run(&mut table, &chain(16));
+    assert_eq!(run(&mut table, "INSERT INTO t0 VALUES (2)"), "too many levels of trigger/view nesting\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM t0"), "1\n");
+    close_test(table, file_name);
+}
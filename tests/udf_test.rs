@@ -0,0 +1,85 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+use rustql::table::value::Value;
+use std::io::{Error, ErrorKind};
+
+fn create_prices(table: &mut Table) {
+    run(table, "CREATE TABLE prices (id INTEGER PRIMARY KEY, sku TEXT, cents INTEGER)");
+    run(table, "INSERT INTO prices VALUES (1, 'a', 250)");
+    run(table, "INSERT INTO prices VALUES (2, 'b', 1999)");
+    run(table, "INSERT INTO prices VALUES (3, 'a', NULL)");
+}
+
+#[test]
+fn scalar_functions() {
+    let file_name = "udf_scalar.db";
+    let mut table = open_test(file_name);
+    create_prices(&mut table);
+    table.create_function("dollars", Some(1), |args| {
+        Ok(match &args[0] {
+            Value::Integer(cents) => Value::Text(format!("${}.{:02}", cents / 100, cents % 100)),
+            _ => Value::Null,
+        })
+    });
+    table.create_function("join_all", None, |args| {
+        Ok(Value::Text(args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join("-")))
+    });
+    assert_eq!(run(&mut table, "SELECT sku, dollars(cents) FROM prices"), "a,$2.50\nb,$19.99\na,NULL\n");
+    assert_eq!(run(&mut table, "SELECT id FROM prices WHERE DOLLARS(cents) = '$19.99'"), "2\n");
+    assert_eq!(run(&mut table, "SELECT join_all(), join_all(1, 'x', 2.5)"), ",1-x-2.5\n");
+    run(&mut table, "UPDATE prices SET sku = join_all(sku, id) WHERE id > 1");
+    assert_eq!(run(&mut table, "SELECT sku FROM prices"), "a\nb-2\na-3\n");
+    assert_eq!(run(&mut table, "SELECT dollars(1, 2)"), "wrong number of arguments to function dollars()\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn scalar_function_errors_and_overrides() {
+    let file_name = "udf_errors.db";
+    let mut table = open_test(file_name);
+    table.create_function("fail", Some(0), |_| Err(Error::new(ErrorKind::Other, "it failed")));
+    table.create_function("upper", Some(1), |args| Ok(Value::Text(format!("<{}>", args[0]))));
+    assert_eq!(run(&mut table, "SELECT fail()"), "it failed\n");
+    assert_eq!(run(&mut table, "SELECT upper('x'), lower('X')"), "<x>,x\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn aggregate_functions() {
+    let file_name = "udf_aggregate.db";
+    let mut table = open_test(file_name);
+    create_prices(&mut table);
+    table.create_aggregate(
+        "product",
+        Some(1),
+        || 1i64,
+        |product, args| {
+            if let Value::Integer(i) = args[0] {
+                *product *= i;
+            }
+            Ok(())
+        },
+        |product| Ok(Value::Integer(product)),
+    );
+    table.create_aggregate(
+        "concat_rows",
+        Some(2),
+        Vec::new,
+        |parts: &mut Vec<String>, args| {
+            parts.push(format!("{}={}", args[0], args[1]));
+            Ok(())
+        },
+        |parts| Ok(Value::Text(parts.join(";"))),
+    );
+    assert_eq!(run(&mut table, "SELECT product(id), product(cents) FROM prices"), "6,499750\n");
+    assert_eq!(
+        run(&mut table, "SELECT sku, concat_rows(id, cents) FROM prices GROUP BY sku HAVING product(id) > 1"),
+        "a,1=250;3=NULL\nb,2=1999\n"
+    );
+    assert_eq!(run(&mut table, "SELECT product(id) FROM prices WHERE id > 5"), "1\n");
+    assert_eq!(run(&mut table, "SELECT product(id, id) FROM prices"), "wrong number of arguments to function product()\n");
+    assert_eq!(run(&mut table, "SELECT id FROM prices WHERE product(id) > 1"), "misuse of aggregate function product()\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn create_stock(table: &mut Table) {
+    run(table, "CREATE TABLE stock (id INTEGER PRIMARY KEY, sku TEXT UNIQUE, qty INTEGER NOT NULL, note TEXT)");
+    run(table, "INSERT INTO stock VALUES (1, 'apple', 5, NULL), (2, 'pear', 3, NULL)");
+}
+
+#[test]
+fn on_conflict_do_update() {
+    let file_name = "upsert_do_update.db";
+    let mut table = open_test(file_name);
+    create_stock(&mut table);
+    run(
+        &mut table,
+        "INSERT INTO stock VALUES (1, 'apple', 2, 'again'), (3, 'plum', 7, NULL) \
+         ON CONFLICT(id) DO UPDATE SET qty = qty + excluded.qty, note = excluded.note",
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM stock"), "1,apple,7,again\n2,pear,3,NULL\n3,plum,7,NULL\n");
+    // A unique column works as the target as well, with the new rowid unused.
+    run(
+        &mut table,
+        "INSERT INTO stock (sku, qty) VALUES ('pear', 10) ON CONFLICT(sku) DO UPDATE SET qty = excluded.qty WHERE excluded.qty > qty",
+    );
+    run(
+        &mut table,
+        "INSERT INTO stock (sku, qty) VALUES ('plum', 1) ON CONFLICT(sku) DO UPDATE SET qty = excluded.qty WHERE excluded.qty > qty",
+    );
+    assert_eq!(run(&mut table, "SELECT id, sku, qty FROM stock"), "1,apple,7\n2,pear,10\n3,plum,7\n");
+    assert_eq!(
+        run(&mut table, "INSERT INTO stock VALUES (9, 'fig', 1, NULL) ON CONFLICT(note) DO NOTHING"),
+        "ON CONFLICT clause does not match any PRIMARY KEY or UNIQUE constraint\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO stock VALUES (2, 'fig', 1, NULL) ON CONFLICT(id) DO UPDATE SET sku = 'apple'"),
+        "UNIQUE constraint failed: stock.sku\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO stock VALUES (2, 'fig', 1, NULL) ON CONFLICT(id) DO UPDATE SET qty = excluded.missing"),
+        "no such column: excluded.missing\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn on_conflict_do_nothing() {
+    let file_name = "upsert_do_nothing.db";
+    let mut table = open_test(file_name);
+    create_stock(&mut table);
+    run(&mut table, "INSERT INTO stock VALUES (1, 'kiwi', 1, NULL), (4, 'pear', 1, NULL), (5, 'lime', 1, NULL) ON CONFLICT DO NOTHING");
+    assert_eq!(run(&mut table, "SELECT id, sku FROM stock"), "1,apple\n2,pear\n5,lime\n");
+    // Only the targeted constraint is handled, others still fail.
+    run(&mut table, "INSERT INTO stock VALUES (1, 'kiwi', 1, NULL) ON CONFLICT(id) DO NOTHING");
+    assert_eq!(
+        run(&mut table, "INSERT INTO stock VALUES (6, 'pear', 1, NULL) ON CONFLICT(id) DO NOTHING"),
+        "UNIQUE constraint failed: stock.sku\n"
+    );
+    assert_eq!(
+        run(&mut table, "INSERT INTO stock VALUES (6, 'kiwi', NULL, NULL) ON CONFLICT DO NOTHING"),
+        "NOT NULL constraint failed: stock.qty\n"
+    );
+    assert_eq!(run(&mut table, "SELECT count(*) FROM stock"), "3\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn insert_or_replace() {
+    let file_name = "upsert_or_replace.db";
+    let mut table = open_test(file_name);
+    create_stock(&mut table);
+    run(&mut table, "INSERT OR REPLACE INTO stock VALUES (1, 'apple', 9, 'replaced')");
+    // The new row collides with both existing rows, both are replaced.
+    run(&mut table, "REPLACE INTO stock VALUES (2, 'apple', 1, 'merged')");
+    assert_eq!(run(&mut table, "SELECT * FROM stock"), "2,apple,1,merged\n");
+    assert_eq!(run(&mut table, "SELECT id FROM stock WHERE sku = 'pear'"), "");
+    run(&mut table, "INSERT OR REPLACE INTO stock (sku, qty) VALUES ('apple', 4), ('kiwi', 2)");
+    assert_eq!(run(&mut table, "SELECT * FROM stock"), "3,apple,4,NULL\n4,kiwi,2,NULL\n");
+    assert_eq!(
+        run(&mut table, "INSERT OR REPLACE INTO stock VALUES (3, 'apple', NULL, NULL)"),
+        "NOT NULL constraint failed: stock.qty\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn insert_or_ignore() {
+    let file_name = "upsert_or_ignore.db";
+    let mut table = open_test(file_name);
+    create_stock(&mut table);
+    run(&mut table, "CREATE TABLE log (stock_id INTEGER REFERENCES stock(id), qty INTEGER CHECK (qty > 0))");
+    run(
+        &mut table,
+        "INSERT OR IGNORE INTO stock VALUES (1, 'kiwi', 1, NULL), (3, 'pear', 1, NULL), (4, 'lime', NULL, NULL), (5, 'fig', 1, NULL)",
+    );
+    assert_eq!(run(&mut table, "SELECT id, sku FROM stock"), "1,apple\n2,pear\n5,fig\n");
+    run(&mut table, "INSERT OR IGNORE INTO log VALUES (1, 0), (1, 2)");
+    assert_eq!(run(&mut table, "SELECT * FROM log"), "1,2\n");
+    run(&mut table, "PRAGMA foreign_keys = ON");
+    assert_eq!(run(&mut table, "INSERT OR IGNORE INTO log VALUES (7, 1)"), "FOREIGN KEY constraint failed\n");
+    close_test(table, file_name);
+}
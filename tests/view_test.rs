@@ -0,0 +1,180 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+use std::thread;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, amount INTEGER)");
+    run(
+        table,
+        "INSERT INTO orders (customer, amount) VALUES ('ann', 10), ('bob', 25), ('ann', 40), ('cat', 5)",
+    );
+}
+
+#[test]
+fn create_and_query_view() {
+    let file_name = "view_create.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "CREATE VIEW big_orders AS SELECT id, customer FROM orders WHERE amount >= 10"),
+        "Executed.\n"
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM big_orders"), "1,ann\n2,bob\n3,ann\n");
+    assert_eq!(
+        run(&mut table, "SELECT customer, count(*) FROM big_orders GROUP BY customer ORDER BY customer"),
+        "ann,2\nbob,1\n"
+    );
+    assert_eq!(run(&mut table, "SELECT b.id FROM big_orders AS b WHERE b.customer = 'bob'"), "2\n");
+    // The view reads the rows current when the query runs.
+    run(&mut table, "INSERT INTO orders (customer, amount) VALUES ('dan', 99)");
+    assert_eq!(run(&mut table, "SELECT customer FROM big_orders ORDER BY id DESC LIMIT 1"), "dan\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn view_column_names() {
+    let file_name = "view_columns.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(
+        &mut table,
+        "CREATE VIEW totals (who, total) AS SELECT customer, sum(amount) FROM orders GROUP BY customer",
+    );
+    assert_eq!(run(&mut table, "SELECT who FROM totals WHERE total > 20 ORDER BY who"), "ann\nbob\n");
+    assert_eq!(
+        run(&mut table, "SELECT o.id, t.total FROM orders o JOIN totals t ON t.who = o.customer WHERE o.id < 3"),
+        "1,50\n2,25\n"
+    );
+    assert_eq!(
+        run(&mut table, "CREATE VIEW bad (a, b) AS SELECT customer FROM orders"),
+        "expected 2 columns for 'bad' but got 1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn views_over_views() {
+    let file_name = "view_nested.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE VIEW ann_orders AS SELECT * FROM orders WHERE customer = 'ann'");
+    run(&mut table, "CREATE VIEW ann_total AS SELECT sum(amount) AS total FROM ann_orders");
+    assert_eq!(run(&mut table, "SELECT total FROM ann_total"), "50\n");
+    assert_eq!(
+        run(&mut table, "SELECT id FROM orders WHERE amount > (SELECT total FROM ann_total) - 30"),
+        "2\n3\n"
+    );
+    // A common table does not hide the tables the view reads.
+    assert_eq!(
+        run(&mut table, "WITH orders AS (SELECT 1 AS amount) SELECT * FROM ann_total, orders"),
+        "50,1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn views_survive_reopen() {
+    let file_name = "view_reopen.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE VIEW small AS SELECT customer FROM orders WHERE amount < 20");
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "SELECT * FROM small"), "ann\ncat\n");
+    assert_eq!(run(&mut table, "DROP VIEW small"), "Executed.\n");
+    unsafe {
+        table.db_close();
+    }
+    let mut table = unsafe { Table::db_open(file_name) };
+    assert_eq!(run(&mut table, "SELECT * FROM small"), "no such table: small\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn drop_view() {
+    let file_name = "view_drop.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE VIEW v AS SELECT id FROM orders");
+    assert_eq!(run(&mut table, "DROP VIEW v"), "Executed.\n");
+    assert_eq!(run(&mut table, "DROP VIEW v"), "no such view: v\n");
+    assert_eq!(run(&mut table, "DROP VIEW IF EXISTS v"), "Executed.\n");
+    assert_eq!(run(&mut table, "DROP VIEW orders"), "use DROP TABLE to delete table orders\n");
+    assert_eq!(run(&mut table, "SELECT count(*) FROM orders"), "4\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn view_errors() {
+    let file_name = "view_errors.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE VIEW v AS SELECT id FROM orders");
+    assert_eq!(run(&mut table, "CREATE VIEW v AS SELECT 1"), "view v already exists\n");
+    assert_eq!(run(&mut table, "CREATE VIEW IF NOT EXISTS v AS SELECT 1"), "Executed.\n");
+    assert_eq!(run(&mut table, "CREATE VIEW orders AS SELECT 1"), "table orders already exists\n");
+    assert_eq!(run(&mut table, "CREATE TABLE v (a)"), "view v already exists\n");
+    assert_eq!(run(&mut table, "CREATE VIEW w AS SELECT * FROM missing"), "no such table: missing\n");
+    assert_eq!(run(&mut table, "INSERT INTO v VALUES (9)"), "cannot modify v because it is a view\n");
+    assert_eq!(run(&mut table, "UPDATE v SET id = 1"), "cannot modify v because it is a view\n");
+    assert_eq!(run(&mut table, "DELETE FROM v"), "cannot modify v because it is a view\n");
+    assert_eq!(run(&mut table, "CREATE INDEX v_id ON v (id)"), "views may not be indexed\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn circular_views() {
+    let file_name = "view_circular.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    run(&mut table, "CREATE VIEW a AS SELECT * FROM orders");
+    run(&mut table, "CREATE VIEW b AS SELECT * FROM a");
+    run(&mut table, "DROP VIEW a");
+    assert_eq!(run(&mut table, "CREATE VIEW a AS SELECT * FROM b"), "no such table: a
+");
+    assert_eq!(run(&mut table, "SELECT * FROM b"), "no such table: a
+");
+    run(&mut table, "CREATE TABLE a (x)");
+    assert_eq!(run(&mut table, "SELECT * FROM b"), "");
+    close_test(table, file_name);
+}
+
+#[test]
+fn view_nesting_is_limited() {
+    let output = thread::Builder::new()
+        .stack_size(2 << 20)
+        .spawn(|| {
+            let file_name = "view_nesting.db";
+            let mut table = open_test(file_name);
+            // Built from the top, each view created over a table of the name
+            // the next one replaces, so no view is expanded deeper while the
+            // chain grows.
+            run(&mut table, "CREATE TABLE v299 (x)");
+            run(&mut table, "CREATE VIEW v300 AS SELECT * FROM v299");
+            for i in (1..300).rev() {
+                run(&mut table, &format!("DROP TABLE v{}", i));
+                run(&mut table, &format!("CREATE TABLE v{} (x)", i - 1));
+                run(&mut table, &format!("CREATE VIEW v{} AS SELECT * FROM v{}", i, i - 1));
+            }
+            run(&mut table, "INSERT INTO v0 VALUES (1)");
+            let output = [
+                run(&mut table, "SELECT * FROM v16"),
+                run(&mut table, "SELECT * FROM v17"),
+                run(&mut table, "SELECT * FROM v300"),
+                run(&mut table, "EXPLAIN QUERY PLAN SELECT * FROM v300"),
+                run(&mut table, "CREATE VIEW w AS SELECT * FROM v300"),
+            ];
+            close_test(table, file_name);
+            output
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    let too_deep = "too many levels of trigger/view nesting\n";
+    assert_eq!(output[0], "1\n");
+    assert_eq!(output[1..], [too_deep; 4]);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use common::{close_test, open_test, run};
+
+#[test]
+fn explain_lists_the_program() {
+    let file_name = "vm_explain.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score INTEGER DEFAULT 0)");
+    run(&mut table, "CREATE INDEX t_name ON t (name)");
+    assert_eq!(
+        run(&mut table, "EXPLAIN SELECT name FROM t WHERE id = 2"),
+        "0,OpenRead,0,2,0,t,0\n\
+         1,Integer,2,0,0,NULL,0\n\
+         2,SeekRowid,0,9,0,NULL,0\n\
+         3,Column,0,0,1,NULL,0\n\
+         4,Integer,2,2,0,NULL,0\n\
+         5,Ne,1,8,2,NULL,1\n\
+         6,Column,0,1,3,NULL,0\n\
+         7,ResultRow,3,1,0,NULL,0\n\
+         8,Next,0,3,0,NULL,0\n\
+         9,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN SELECT id, upper(name) FROM t WHERE name LIKE 'a%'"),
+        "0,OpenRead,0,2,0,t,0\n\
+         1,Rewind,0,8,0,NULL,0\n\
+         2,Expr,0,0,0,name LIKE 'a%',0\n\
+         3,IfNot,0,7,0,NULL,0\n\
+         4,Column,0,0,1,NULL,0\n\
+         5,Expr,0,2,0,upper(name),0\n\
+         6,ResultRow,1,2,0,NULL,0\n\
+         7,Next,0,2,0,NULL,0\n\
+         8,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN SELECT * FROM t WHERE name = 'b'"),
+        "0,OpenRead,0,2,0,t,0\n\
+         1,String8,0,0,0,b,0\n\
+         2,SeekIndex,0,11,0,t_name,0\n\
+         3,Column,0,1,1,NULL,0\n\
+         4,String8,0,2,0,b,0\n\
+         5,Ne,1,10,2,NULL,1\n\
+         6,Column,0,0,3,NULL,0\n\
+         7,Column,0,1,4,NULL,0\n\
+         8,Column,0,2,5,NULL,0\n\
+         9,ResultRow,3,3,0,NULL,0\n\
+         10,Next,0,3,0,NULL,0\n\
+         11,Halt,0,0,0,NULL,0\n"
+    );
+    // A parameter compared to an INTEGER column is made numeric.
+    assert_eq!(
+        run(&mut table, "EXPLAIN SELECT id FROM t WHERE score = ?1"),
+        "0,OpenRead,0,2,0,t,0\n\
+         1,Rewind,0,9,0,NULL,0\n\
+         2,Column,0,2,0,NULL,0\n\
+         3,Variable,1,1,0,NULL,0\n\
+         4,Affinity,1,0,0,Numeric,0\n\
+         5,Ne,0,8,1,NULL,1\n\
+         6,Column,0,0,2,NULL,0\n\
+         7,ResultRow,2,1,0,NULL,0\n\
+         8,Next,0,2,0,NULL,0\n\
+         9,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN INSERT INTO t (name) VALUES ('a'), ('b')"),
+        "0,OpenWrite,0,2,0,t,0\n\
+         1,String8,0,0,0,a,0\n\
+         2,String8,0,1,0,b,0\n\
+         3,Insert,0,0,1,name,0\n\
+         4,Insert,0,1,1,name,0\n\
+         5,Halt,0,0,0,NULL,0\n"
+    );
+    // The rows to change are collected before the first one is changed.
+    assert_eq!(
+        run(&mut table, "EXPLAIN UPDATE t SET score = score + 1 WHERE name = 'b'"),
+        "0,OpenWrite,0,2,0,t,0\n\
+         1,String8,0,1,0,b,0\n\
+         2,SeekIndex,0,9,1,t_name,0\n\
+         3,Column,0,1,2,NULL,0\n\
+         4,String8,0,3,0,b,0\n\
+         5,Ne,2,8,3,NULL,1\n\
+         6,Rowid,0,0,0,NULL,0\n\
+         7,RowSetAdd,0,0,0,NULL,0\n\
+         8,Next,0,3,0,NULL,0\n\
+         9,RowSetRead,0,14,0,NULL,0\n\
+         10,SeekRowid,0,9,0,NULL,0\n\
+         11,Expr,0,4,0,score + 1,0\n\
+         12,Update,0,4,1,score,0\n\
+         13,Goto,0,9,0,NULL,0\n\
+         14,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN DELETE FROM t WHERE score > 3"),
+        "0,OpenWrite,0,2,0,t,0\n\
+         1,Rewind,0,8,0,NULL,0\n\
+         2,Column,0,2,1,NULL,0\n\
+         3,Integer,3,2,0,NULL,0\n\
+         4,Le,1,7,2,NULL,1\n\
+         5,Rowid,0,0,0,NULL,0\n\
+         6,RowSetAdd,0,0,0,NULL,0\n\
+         7,Next,0,2,0,NULL,0\n\
+         8,RowSetRead,0,12,0,NULL,0\n\
+         9,SeekRowid,0,8,0,NULL,0\n\
+         10,Delete,0,0,0,NULL,0\n\
+         11,Goto,0,8,0,NULL,0\n\
+         12,Halt,0,0,0,NULL,0\n"
+    );
+    // Statements the compiler does not handle run the way they did before.
+    assert_eq!(
+        run(&mut table, "EXPLAIN SELECT count(*) FROM t"),
+        "0,Execute,0,0,0,SELECT count(*) FROM t,0\n1,Halt,0,0,0,NULL,0\n"
+    );
+    // Nothing is run.
+    assert_eq!(
+        run(&mut table, "EXPLAIN DELETE FROM t RETURNING id"),
+        "0,Execute,0,0,0,DELETE FROM t RETURNING id,0\n1,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "EXPLAIN CREATE TABLE u (x)"),
+        "0,Execute,0,0,0,CREATE TABLE u (x),0\n1,Halt,0,0,0,NULL,0\n"
+    );
+    assert_eq!(run(&mut table, "SELECT * FROM u"), "no such table: u\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "");
+    close_test(table, file_name);
+}
+
+#[test]
+fn compiled_statements_run_like_before() {
+    let file_name = "vm_run.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT UNIQUE, score INTEGER DEFAULT 0)");
+    run(&mut table, "CREATE INDEX t_score ON t (score)");
+    assert_eq!(
+        run(&mut table, "INSERT INTO t (name, score) VALUES ('a', 3), ('b', NULL), ('c', '3')"),
+        "Executed.\n"
+    );
+    assert_eq!(run(&mut table, "INSERT INTO t (name) VALUES ('d')"), "Executed.\n");
+    assert_eq!(run(&mut table, "INSERT INTO t VALUES (10, 'e', 1 + 1)"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,3\n2,b,NULL\n3,c,3\n4,d,0\n10,e,2\n");
+    // Rows are checked as they are stored, a failing one undoes the ones
+    // before it.
+    assert_eq!(
+        run(&mut table, "INSERT INTO t (name) VALUES ('f'), ('a')"),
+        "UNIQUE constraint failed: t.name\n"
+    );
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE id > 10"), "");
+    assert_eq!(run(&mut table, "INSERT INTO t (name, nope) VALUES ('g', 1)"), "no such column: nope\n");
+    assert_eq!(run(&mut table, "INSERT INTO t (name) VALUES ('g', 1)"), "2 values for 1 columns\n");
+    // Comparisons with NULL are not true either way.
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE score = 3"), "a\nc\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE score != 3"), "d\ne\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE score >= 2 AND score < 3"), "e\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE 2 < score"), "a\nc\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE id = 3"), "c\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE id = 30"), "");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE name = 'e'"), "e\n");
+    assert_eq!(
+        run(&mut table, "SELECT x.id, upper(x.name), score * 2 FROM t AS x WHERE x.name IN ('a', 'b') OR score IS NULL"),
+        "1,A,6\n2,B,NULL\n"
+    );
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE nope = 1"), "no such column: nope\n");
+    close_test(table, file_name);
+}
+
+#[test]
+fn compiled_updates_and_deletes_run_like_before() {
+    let file_name = "vm_update_delete.db";
+    let mut table = open_test(file_name);
+    run(&mut table, "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT UNIQUE, score INTEGER CHECK (score < 10))");
+    run(&mut table, "CREATE INDEX t_score ON t (score)");
+    run(&mut table, "INSERT INTO t VALUES (1, 'a', 1), (2, 'b', 2), (3, 'c', 2), (4, 'd', 4)");
+    assert_eq!(run(&mut table, "UPDATE t SET score = score + '1', name = upper(name) WHERE score = 2"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n2,B,3\n3,C,3\n4,d,4\n");
+    assert_eq!(run(&mut table, "SELECT name FROM t WHERE score = 3"), "B\nC\n");
+    // A row moved to another rowid is changed once.
+    assert_eq!(run(&mut table, "UPDATE t SET id = id + 10 WHERE id > 2"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT id, name FROM t"), "1,a\n2,B\n13,C\n14,d\n");
+    // A failing row undoes the rows changed before it.
+    assert_eq!(run(&mut table, "UPDATE t SET score = score * 3"), "CHECK constraint failed: t.score\n");
+    assert_eq!(run(&mut table, "UPDATE t SET name = 'a' WHERE id > 1"), "UNIQUE constraint failed: t.name\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n2,B,3\n13,C,3\n14,d,4\n");
+    assert_eq!(run(&mut table, "DELETE FROM t WHERE score = 3"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n14,d,4\n");
+    assert_eq!(run(&mut table, "SELECT id FROM t WHERE name = 'B'"), "");
+    assert_eq!(run(&mut table, "DELETE FROM t WHERE id = 14"), "Executed.\n");
+    assert_eq!(run(&mut table, "DELETE FROM t WHERE id = 14"), "Executed.\n");
+    assert_eq!(run(&mut table, "SELECT * FROM t"), "1,a,1\n");
+    assert_eq!(run(&mut table, "UPDATE t SET nope = 1"), "no such column: nope\n");
+    close_test(table, file_name);
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{close_test, open_test, run};
+use rustql::table::table::Table;
+
+fn setup(table: &mut Table) {
+    run(table, "CREATE TABLE scores (id INTEGER PRIMARY KEY, team TEXT, name TEXT, points INTEGER)");
+    run(
+        table,
+        "INSERT INTO scores (team, name, points) VALUES ('red', 'ann', 10), ('red', 'bob', 30), ('blue', 'cat', 20), ('red', 'dan', 30), ('blue', 'eve', 5)",
+    );
+}
+
+#[test]
+fn ranking_functions() {
+    let file_name = "window_ranking.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(
+            &mut table,
+            "SELECT name, row_number() OVER (PARTITION BY team ORDER BY points DESC), rank() OVER (PARTITION BY team ORDER BY points DESC), dense_rank() OVER (PARTITION BY team ORDER BY points DESC) FROM scores"
+        ),
+        "ann,3,3,2\nbob,1,1,1\ncat,1,1,1\ndan,2,1,1\neve,2,2,2\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, row_number() OVER (ORDER BY id) FROM scores WHERE team = 'blue'"),
+        "cat,1\neve,2\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn lag_and_lead() {
+    let file_name = "window_lag_lead.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name, lag(points) OVER (ORDER BY id), lead(points, 2, 0) OVER (ORDER BY id) FROM scores"),
+        "ann,NULL,20\nbob,10,30\ncat,30,5\ndan,20,0\neve,30,0\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, lag(name, 1, '-') OVER (PARTITION BY team ORDER BY id) FROM scores"),
+        "ann,-\nbob,ann\ncat,-\ndan,bob\neve,cat\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn first_and_last_value() {
+    let file_name = "window_first_last.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(
+            &mut table,
+            "SELECT name, first_value(name) OVER (PARTITION BY team ORDER BY points), last_value(name) OVER (PARTITION BY team ORDER BY points ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) FROM scores"
+        ),
+        "ann,ann,dan\nbob,ann,dan\ncat,eve,cat\ndan,ann,dan\neve,eve,cat\n"
+    );
+    // By default the frame ends at the last row with the same ORDER BY values.
+    assert_eq!(
+        run(&mut table, "SELECT name, last_value(name) OVER (ORDER BY points) FROM scores"),
+        "ann,ann\nbob,dan\ncat,cat\ndan,dan\neve,eve\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn aggregate_window_functions() {
+    let file_name = "window_aggregate.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT id, sum(points) OVER (ORDER BY id) FROM scores"),
+        "1,10\n2,40\n3,60\n4,90\n5,95\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, sum(points) OVER (ORDER BY points) FROM scores"),
+        "ann,15\nbob,95\ncat,35\ndan,95\neve,5\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, count(*) OVER (PARTITION BY team), max(points) OVER () FROM scores"),
+        "ann,3,30\nbob,3,30\ncat,2,30\ndan,3,30\neve,2,30\n"
+    );
+    assert_eq!(
+        run(
+            &mut table,
+            "SELECT id, sum(points) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM scores"
+        ),
+        "1,40\n2,60\n3,80\n4,55\n5,35\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT id, avg(points) OVER (ORDER BY id ROWS 1 PRECEDING) FROM scores WHERE id < 4"),
+        "1,10.0\n2,20.0\n3,25.0\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn window_in_order_by() {
+    let file_name = "window_order_by.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM scores ORDER BY row_number() OVER (ORDER BY points DESC) LIMIT 3"),
+        "bob\ndan\ncat\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT name, rank() OVER (ORDER BY points) AS r FROM scores ORDER BY r DESC, name"),
+        "bob,4\ndan,4\ncat,3\nann,2\neve,1\n"
+    );
+    close_test(table, file_name);
+}
+
+#[test]
+fn window_errors() {
+    let file_name = "window_errors.db";
+    let mut table = open_test(file_name);
+    setup(&mut table);
+    assert_eq!(
+        run(&mut table, "SELECT name FROM scores WHERE row_number() OVER () > 1"),
+        "misuse of window function row_number()\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT upper(name) OVER () FROM scores"),
+        "upper() may not be used as a window function\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT count(*), row_number() OVER () FROM scores"),
+        "window functions are not supported in aggregate queries\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT sum(points) OVER (ROWS -1 PRECEDING) FROM scores"),
+        "frame starting offset must be a non-negative integer\n"
+    );
+    assert_eq!(
+        run(&mut table, "SELECT rank(points) OVER () FROM scores"),
+        "wrong number of arguments to function rank()\n"
+    );
+    close_test(table, file_name);
+}